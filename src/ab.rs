@@ -0,0 +1,274 @@
+//! `ahc ab`, which runs the solvers of two commits over the same seeds as `ahc test --at` does
+//! and compares them seed by seed.
+
+use crate::config::Config;
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::pahcer;
+use crate::runner::{self, RunOptions, TestRun};
+use crate::tune::stats::wilcoxon_signed_rank;
+use crate::tune::Objective;
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tracing::info;
+
+#[derive(Args)]
+pub(crate) struct AbArgs {
+    /// Commit of the solver A, e.g. a tag of the best submission
+    commit_a: String,
+    /// Commit of the solver B
+    commit_b: String,
+    #[command(flatten)]
+    options: RunOptions,
+}
+
+/// What `ahc ab --output json` prints.
+#[derive(Serialize, Debug, PartialEq)]
+struct Comparison {
+    a: Side,
+    b: Side,
+    /// Seeds where B did better than A
+    wins: usize,
+    /// Seeds where B did worse than A
+    losses: usize,
+    ties: usize,
+    /// Two-sided p-value of a Wilcoxon signed-rank test over the per-seed scores, small when the
+    /// difference is unlikely to be noise
+    p_value: f64,
+    cases: Vec<CaseComparison>,
+}
+
+/// p-values below which a difference is reported as significant
+const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Side {
+    commit: String,
+    result_file: PathBuf,
+    average_score: f64,
+    /// Time of the slowest case in seconds
+    max_execution_time: f64,
+    mean_execution_time: f64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct CaseComparison {
+    seed: u64,
+    score_a: u64,
+    score_b: u64,
+    /// Change of B from A relative to A, better when positive whatever the objective
+    change: Option<f64>,
+}
+
+pub(crate) fn ab(args: AbArgs, config: Config, output: Output) -> Result<()> {
+    let mut sides = vec![];
+    for (label, commit) in [("A", &args.commit_a), ("B", &args.commit_b)] {
+        info!("{}", msg!("ab.running", label, commit));
+        let run = TestRun {
+            at: Some(commit),
+            ..TestRun::default()
+        };
+        let summary = runner::run_test(&args.options, run, &config, output)?;
+        let result = pahcer::read_result(&summary.result_file)?;
+        let side = Side {
+            commit: commit.clone(),
+            result_file: summary.result_file,
+            average_score: summary.average_score,
+            max_execution_time: result.max_execution_time,
+            mean_execution_time: result.mean_execution_time(),
+        };
+        sides.push((side, result));
+    }
+    let objective = config.objective();
+    let (b, result_b) = sides.pop().expect("two sides");
+    let (a, result_a) = sides.pop().expect("two sides");
+    let comparison = compare(a, &result_a, b, &result_b, objective);
+
+    if output.is_json() {
+        print_json(&comparison)?;
+    } else {
+        print!("{}", format_comparison(&comparison));
+    }
+    Ok(())
+}
+
+fn compare(
+    a: Side,
+    result_a: &pahcer::ExecResult,
+    b: Side,
+    result_b: &pahcer::ExecResult,
+    objective: Objective,
+) -> Comparison {
+    let scores_b = result_b
+        .cases
+        .iter()
+        .map(|case| (case.seed, case.score))
+        .collect::<BTreeMap<_, _>>();
+    let mut comparison = Comparison {
+        a,
+        b,
+        wins: 0,
+        losses: 0,
+        ties: 0,
+        p_value: 1.0,
+        cases: vec![],
+    };
+    for case in &result_a.cases {
+        let Some(&score_b) = scores_b.get(&case.seed) else {
+            continue;
+        };
+        let (score_a, score_b_f) = (case.score as f64, score_b as f64);
+        if objective.is_better(score_b_f, score_a) {
+            comparison.wins += 1;
+        } else if objective.is_better(score_a, score_b_f) {
+            comparison.losses += 1;
+        } else {
+            comparison.ties += 1;
+        }
+        let change = (case.score > 0).then(|| {
+            let change = (score_b_f - score_a) / score_a;
+            match objective {
+                Objective::Max => change,
+                Objective::Min => -change,
+            }
+        });
+        comparison.cases.push(CaseComparison {
+            seed: case.seed,
+            score_a: case.score,
+            score_b,
+            change,
+        });
+    }
+    let differences = comparison
+        .cases
+        .iter()
+        .map(|case| case.score_b as f64 - case.score_a as f64)
+        .collect::<Vec<_>>();
+    comparison.p_value = wilcoxon_signed_rank(&differences);
+    comparison
+}
+
+fn format_comparison(comparison: &Comparison) -> String {
+    let change = |change: Option<f64>| {
+        change.map_or("-".to_string(), |change| format!("{:+.2}%", change * 100.0))
+    };
+    let mut output = format!("{:>6} {:>12} {:>12} {:>9}\n", "seed", "A", "B", "change");
+    for case in &comparison.cases {
+        output.push_str(&format!(
+            "{:>6} {:>12} {:>12} {:>9}\n",
+            format!("{:04}", case.seed),
+            case.score_a,
+            case.score_b,
+            change(case.change)
+        ));
+    }
+    for (label, side) in [("A", &comparison.a), ("B", &comparison.b)] {
+        output.push_str(&format!(
+            "{} {}: {:.2}, max {:.3}s, mean {:.3}s\n",
+            label,
+            side.commit,
+            side.average_score,
+            side.max_execution_time,
+            side.mean_execution_time
+        ));
+    }
+    output.push_str(&format!(
+        "B wins {}, loses {}, ties {}\n",
+        comparison.wins, comparison.losses, comparison.ties
+    ));
+    let verdict = if comparison.p_value < SIGNIFICANCE_LEVEL {
+        "significant"
+    } else {
+        "possibly noise"
+    };
+    output.push_str(&format!(
+        "p = {:.4} (Wilcoxon signed-rank), {}\n",
+        comparison.p_value, verdict
+    ));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(scores: &[(u64, u64)]) -> pahcer::ExecResult {
+        serde_json::from_value(serde_json::json!({
+            "case_count": scores.len(),
+            "total_score": scores.iter().map(|(_, score)| score).sum::<u64>(),
+            "cases": scores
+                .iter()
+                .map(|(seed, score)| serde_json::json!({"seed": seed, "score": score}))
+                .collect::<Vec<_>>(),
+        }))
+        .unwrap()
+    }
+
+    fn side(commit: &str) -> Side {
+        Side {
+            commit: commit.to_string(),
+            result_file: PathBuf::new(),
+            average_score: 0.0,
+            max_execution_time: 0.0,
+            mean_execution_time: 0.0,
+        }
+    }
+
+    #[test]
+    fn counts_wins_and_losses_by_the_objective() {
+        let result_a = result(&[(0, 100), (1, 200), (2, 0), (3, 50)]);
+        let result_b = result(&[(0, 110), (1, 100), (2, 0)]);
+
+        let comparison = compare(side("a"), &result_a, side("b"), &result_b, Objective::Max);
+        assert_eq!(
+            (comparison.wins, comparison.losses, comparison.ties),
+            (1, 1, 1)
+        );
+        let changes = comparison
+            .cases
+            .iter()
+            .map(|case| case.change.map(|change| (change * 100.0).round()))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, vec![Some(10.0), Some(-50.0), None]);
+
+        let comparison = compare(side("a"), &result_a, side("b"), &result_b, Objective::Min);
+        assert_eq!(
+            (comparison.wins, comparison.losses, comparison.ties),
+            (1, 1, 1)
+        );
+        assert_eq!(comparison.cases[1].change, Some(0.5));
+        assert!(comparison.p_value > 0.5);
+    }
+
+    #[test]
+    fn tells_consistent_gains_from_noise() {
+        let result_a = result(&(0..20).map(|seed| (seed, 100)).collect::<Vec<_>>());
+        let result_b = result(&(0..20).map(|seed| (seed, 101 + seed)).collect::<Vec<_>>());
+
+        let comparison = compare(side("a"), &result_a, side("b"), &result_b, Objective::Max);
+        assert!(comparison.p_value < 0.001);
+        assert!(format_comparison(&comparison).ends_with("(Wilcoxon signed-rank), significant\n"));
+
+        let comparison = compare(side("a"), &result_a, side("b"), &result_a, Objective::Max);
+        assert_eq!(comparison.p_value, 1.0);
+        assert!(format_comparison(&comparison).ends_with("possibly noise\n"));
+    }
+
+    #[test]
+    fn shows_the_times_of_each_side() {
+        let b = Side {
+            max_execution_time: 1.98,
+            mean_execution_time: 1.5,
+            ..side("b")
+        };
+        let result_a = result(&[(0, 100)]);
+        let comparison = compare(side("a"), &result_a, b, &result_a, Objective::Max);
+
+        let text = format_comparison(&comparison);
+        assert!(text.contains("A a: 0.00, max 0.000s, mean 0.000s\n"));
+        assert!(text.contains("B b: 0.00, max 1.980s, mean 1.500s\n"));
+    }
+}
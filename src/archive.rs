@@ -0,0 +1,230 @@
+//! `ahc archive`, which packs what is worth keeping after a contest into a `.tar.gz`: the
+//! project config, the solution, the pahcer results, the outputs, the tune studies and the
+//! journal of commits. `ahc restore` unpacks it again for analysis or a writeup.
+
+use crate::config::Config;
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::tar;
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Tune studies, the journal and other state of `ahc`
+const STATE_DIR: &str = ".ahc";
+
+#[derive(Args)]
+pub(crate) struct ArchiveArgs {
+    /// Archive to write, `<contest>.tar.gz` by default
+    #[arg(short = 'o', long)]
+    output_file: Option<PathBuf>,
+    /// Leave out the inputs, which `ahc download` can regenerate
+    #[arg(long)]
+    strip_inputs: bool,
+    /// Also pack these files or directories, e.g. notes on the statement
+    #[arg(long)]
+    include: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub(crate) struct RestoreArgs {
+    archive: PathBuf,
+    /// Directory to unpack into
+    #[arg(short = 'C', long, default_value = ".")]
+    dir: PathBuf,
+    /// Overwrite files which already exist
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct ArchiveSummary {
+    archive: PathBuf,
+    files: Vec<PathBuf>,
+    bytes: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct RestoreSummary {
+    dir: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+pub(crate) fn archive(
+    args: ArchiveArgs,
+    config: Config,
+    config_file_name: &str,
+    output: Output,
+) -> Result<()> {
+    let archive_path = args
+        .output_file
+        .unwrap_or_else(|| PathBuf::from(format!("{}.tar.gz", config.general.name)));
+
+    let source = config.source.clone().unwrap_or_default();
+    let mut roots = vec![
+        PathBuf::from(config_file_name),
+        source.path,
+        config.paths.results_dir.clone(),
+        config.paths.outputs_dir.clone(),
+        PathBuf::from(STATE_DIR),
+    ];
+    if !args.strip_inputs {
+        roots.push(config.paths.inputs_dir.clone());
+    }
+    roots.extend(args.include);
+    let files = collect_files(&roots, &archive_path)?;
+    if files.is_empty() {
+        return Err(anyhow!(msg!("archive.empty")));
+    }
+
+    let file = std::fs::File::create(&archive_path)
+        .context(format!("Failed to create file: {}", archive_path.display()))?;
+    let mut builder = tar::Builder::new(file);
+    for path in &files {
+        let content =
+            std::fs::read(path).context(format!("Failed to read file: {}", path.display()))?;
+        let name = path
+            .to_str()
+            .ok_or_else(|| anyhow!("Path is not valid UTF-8: {}", path.display()))?;
+        // Archives use forward slashes whatever the platform
+        builder.append_file(&name.replace('\\', "/"), &content)?;
+    }
+    builder
+        .finish()?
+        .sync_all()
+        .context(format!("Failed to write file: {}", archive_path.display()))?;
+    let bytes = std::fs::metadata(&archive_path)?.len();
+
+    info!(
+        "{}",
+        msg!(
+            "archive.created",
+            files.len(),
+            archive_path.display(),
+            bytes
+        )
+    );
+    if output.is_json() {
+        print_json(&ArchiveSummary {
+            archive: archive_path,
+            files,
+            bytes,
+        })?;
+    }
+    Ok(())
+}
+
+/// The files under `roots` which exist, relative like the roots, without duplicates. Build
+/// directories and the archive being written are left out.
+fn collect_files(roots: &[PathBuf], archive_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = BTreeSet::new();
+    let mut pending = roots.iter().map(|root| normalize(root)).collect::<Vec<_>>();
+    while let Some(path) = pending.pop() {
+        if path.is_dir() {
+            if path.file_name().is_some_and(|name| name == "target") {
+                continue;
+            }
+            for entry in std::fs::read_dir(&path)
+                .context(format!("Failed to read directory: {}", path.display()))?
+            {
+                pending.push(entry?.path());
+            }
+        } else if path.is_file() && path != normalize(archive_path) {
+            files.insert(path);
+        }
+    }
+    Ok(files.into_iter().collect())
+}
+
+/// `path` without leading `./`, so that the same file is not packed twice.
+fn normalize(path: &Path) -> PathBuf {
+    path.components().filter(|c| c.as_os_str() != ".").collect()
+}
+
+pub(crate) fn restore(args: RestoreArgs, output: Output) -> Result<()> {
+    let file = std::fs::File::open(&args.archive)
+        .context(format!("Failed to open file: {}", args.archive.display()))?;
+    let entries = tar::read_entries(std::io::BufReader::new(file)).context(format!(
+        "Failed to read archive: {}",
+        args.archive.display()
+    ))?;
+
+    if !args.force {
+        let existing = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                tar::Entry::File { path, .. } if args.dir.join(path).exists() => Some(path),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if let Some(path) = existing.first() {
+            return Err(anyhow!(msg!(
+                "restore.exists",
+                args.dir.join(path).display(),
+                existing.len()
+            )));
+        }
+    }
+
+    let mut files = vec![];
+    for entry in entries {
+        match entry {
+            tar::Entry::Dir { path } => {
+                let path = args.dir.join(path);
+                std::fs::create_dir_all(&path)
+                    .context(format!("Failed to create directory: {}", path.display()))?;
+            }
+            tar::Entry::File { path, content, .. } => {
+                let target = args.dir.join(&path);
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)
+                        .context(format!("Failed to create directory: {}", parent.display()))?;
+                }
+                std::fs::write(&target, content)
+                    .context(format!("Failed to write file: {}", target.display()))?;
+                files.push(path);
+            }
+        }
+    }
+
+    info!("{}", msg!("restore.done", files.len(), args.dir.display()));
+    if output.is_json() {
+        print_json(&RestoreSummary {
+            dir: args.dir,
+            files,
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn collects_each_file_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("tools/out")).unwrap();
+        fs::create_dir_all(root.join("tools/target/release")).unwrap();
+        fs::write(root.join("tools/out/0000.txt"), "1").unwrap();
+        fs::write(root.join("tools/target/release/vis"), "").unwrap();
+        fs::write(root.join("ahc001.tar.gz"), "").unwrap();
+
+        let files = collect_files(
+            &[
+                root.join("tools"),
+                root.join("./tools/out"),
+                root.join("missing"),
+                root.to_path_buf(),
+            ],
+            &root.join("ahc001.tar.gz"),
+        )
+        .unwrap();
+        assert_eq!(files, vec![root.join("tools/out/0000.txt")]);
+    }
+}
@@ -0,0 +1,215 @@
+//! `ahc batch`, which splits the seeds of a run across workers spawned by a command of the user,
+//! e.g. on cloud VMs or in containers, and merges the results the workers leave into one.
+
+use crate::config::Config;
+use crate::error::ErrorKind;
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::results;
+use crate::runner::{self, runs, Partition, PartitionStrategy, SeedArgs};
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
+use tracing::{debug, info, warn};
+
+const BATCH_DIR: &str = ".ahc/batch";
+
+#[derive(Args)]
+pub(crate) struct BatchArgs {
+    /// Number of workers, overriding [batch] workers
+    #[arg(short, long)]
+    workers: Option<usize>,
+    #[command(flatten)]
+    seeds: SeedArgs,
+}
+
+/// `[batch]`, how `ahc batch` spawns its workers.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct BatchConfig {
+    /// Command running a worker, with `{worker}` replaced by its number from 0, `{seeds}` by its
+    /// seeds such as `0,4,8` and `{result}` by the file it must leave its result in, e.g.
+    /// `["sh", "-c", "ssh vm{worker} ./run.sh {seeds} > {result}"]`
+    pub(crate) command: Vec<String>,
+    /// Number of workers the seeds are split across
+    #[serde(default = "default_workers")]
+    pub(crate) workers: usize,
+    /// How the seeds are split across the workers: `round-robin` or `hash`, as `--shard-by`
+    #[serde(default)]
+    pub(crate) partition: PartitionStrategy,
+}
+
+fn default_workers() -> usize {
+    4
+}
+
+/// What `ahc batch --output json` prints.
+#[derive(Serialize, Debug)]
+struct BatchSummary {
+    result_file: PathBuf,
+    case_count: usize,
+    average_score: f64,
+    workers: usize,
+}
+
+pub(crate) fn batch(args: BatchArgs, config: Config, output: Output) -> Result<()> {
+    let batch_config = config
+        .batch
+        .as_ref()
+        .ok_or_else(|| ErrorKind::Config.error(msg!("batch.no_config")))?;
+    if batch_config.command.is_empty() {
+        return Err(ErrorKind::Config.error("[batch] command is empty"));
+    }
+    let workers = args.workers.unwrap_or(batch_config.workers);
+    if workers == 0 {
+        return Err(anyhow!("workers must be at least 1"));
+    }
+    let paths = &config.paths;
+    let (seeds, _) = runner::choose_seeds(&args.seeds, &paths.inputs_dir)?;
+    let shares = split_seeds(&seeds, batch_config.partition, workers);
+
+    let start = SystemTime::now();
+    let run_id = runs::new_run_id(start, &paths.results_dir, &paths.outputs_dir);
+    let work_dir = Path::new(BATCH_DIR).join(&run_id);
+    std::fs::create_dir_all(&work_dir).context(format!(
+        "Failed to create directory: {}",
+        work_dir.display()
+    ))?;
+    info!("{}", msg!("batch.start", seeds.len(), shares.len()));
+
+    let mut children = vec![];
+    for (worker, share) in shares.iter().enumerate() {
+        let result = work_dir.join(format!("worker_{}.json", worker));
+        let command = expand(&batch_config.command, worker, share, &result);
+        let mut child = Command::new(&command[0]);
+        child.args(&command[1..]);
+        if output.is_json() {
+            // Keep stdout for the summary
+            child.stdout(Stdio::from(std::io::stderr()));
+        }
+        debug!("Running {:?}", child);
+        let child = child
+            .spawn()
+            .context(format!("Failed to run {}", command[0]))?;
+        children.push((worker, child, result));
+    }
+
+    let mut worker_results = vec![];
+    let mut failed = vec![];
+    for (worker, mut child, result) in children {
+        let finished = child
+            .wait()
+            .map_err(anyhow::Error::from)
+            .and_then(|status| {
+                if !status.success() {
+                    return Err(anyhow!("exited with {}", status));
+                }
+                results::read_result_json(&result)
+            });
+        match finished {
+            Ok(result) => worker_results.push(result),
+            Err(e) => {
+                warn!(
+                    "{}",
+                    msg!("batch.worker_failed", worker, format!("{:#}", e))
+                );
+                failed.push(worker);
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(anyhow!(msg!(
+            "batch.failed",
+            failed.len(),
+            work_dir.display()
+        )));
+    }
+
+    let mut merged = results::merge(&worker_results)?;
+    let partition = Partition {
+        strategy: batch_config.partition,
+        count: workers,
+        index: None,
+    };
+    merged["partition"] = serde_json::to_value(partition)?;
+    std::fs::create_dir_all(&paths.results_dir).context(format!(
+        "Failed to create directory: {}",
+        paths.results_dir.display()
+    ))?;
+    let result_file = paths.results_dir.join(runs::result_file_name(&run_id));
+    std::fs::write(&result_file, serde_json::to_string_pretty(&merged)?)
+        .context(format!("Failed to write file: {}", result_file.display()))?;
+    let _ = std::fs::remove_dir_all(&work_dir);
+
+    let result = crate::pahcer::read_result(&result_file)?;
+    let average_score = result.average_score();
+    info!(
+        "{}",
+        msg!(
+            "batch.done",
+            format!("{:.2}", average_score),
+            result.case_count,
+            result_file.display()
+        )
+    );
+    if output.is_json() {
+        print_json(&BatchSummary {
+            result_file,
+            case_count: result.case_count,
+            average_score,
+            workers: shares.len(),
+        })?;
+    }
+    Ok(())
+}
+
+/// Splits the seeds across the workers as `--shard` would, so that a worker runs the same seeds
+/// as `ahc test --shard <worker>/<workers>`, leaving out workers without any.
+fn split_seeds(seeds: &[u64], strategy: PartitionStrategy, workers: usize) -> Vec<Vec<u64>> {
+    runner::partition(seeds, strategy, workers)
+        .into_iter()
+        .filter(|share| !share.is_empty())
+        .collect()
+}
+
+fn expand(command: &[String], worker: usize, seeds: &[u64], result: &Path) -> Vec<String> {
+    let seeds = seeds
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    command
+        .iter()
+        .map(|arg| {
+            arg.replace("{worker}", &worker.to_string())
+                .replace("{seeds}", &seeds)
+                .replace("{result}", &result.display().to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deals_seeds_to_workers_in_turn() {
+        assert_eq!(
+            split_seeds(&[0, 1, 2, 3, 4], PartitionStrategy::RoundRobin, 2),
+            vec![vec![0, 2, 4], vec![1, 3]]
+        );
+        assert_eq!(
+            split_seeds(&[7], PartitionStrategy::RoundRobin, 3),
+            vec![vec![7]]
+        );
+
+        let command =
+            ["run", "--worker={worker}", "--seeds", "{seeds}", "{result}"].map(String::from);
+        assert_eq!(
+            expand(&command, 1, &[1, 3], Path::new("w1.json")),
+            vec!["run", "--worker=1", "--seeds", "1,3", "w1.json"]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! `ahc case`, which prints the input or output of a seed, or with `--copy` puts it on the
+//! clipboard for pasting into the web visualizer.
+
+use crate::clipboard;
+use crate::config::Config;
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::runner::runs;
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use serde_json::json;
+use std::path::PathBuf;
+use tracing::info;
+
+#[derive(Args)]
+pub(crate) struct CaseArgs {
+    seed: u64,
+    /// Which file of the seed
+    #[arg(value_enum, default_value_t = File::Input)]
+    file: File,
+    /// Put the file on the clipboard instead of printing it
+    #[arg(long)]
+    copy: bool,
+    /// Take the output from this run of `ahc test`, `latest` for the newest
+    #[arg(long, value_name = "RUN_ID")]
+    run: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum File {
+    Input,
+    Output,
+}
+
+pub(crate) fn case(args: CaseArgs, config: Config, output: Output) -> Result<()> {
+    let path = case_path(&config, args.seed, args.file, args.run.as_deref())?;
+    let content = std::fs::read_to_string(&path)
+        .context(format!("Failed to read file: {}", path.display()))?;
+
+    if args.copy {
+        clipboard::copy(&content, config.clipboard.as_ref())?;
+        info!("{}", msg!("clipboard.copied", path.display()));
+    } else if !output.is_json() {
+        print!("{}", content);
+    }
+    if output.is_json() {
+        print_json(&json!({
+            "seed": args.seed,
+            "file": args.file,
+            "path": path,
+            "copied": args.copy,
+            "content": (!args.copy).then_some(&content),
+        }))?;
+    }
+    Ok(())
+}
+
+/// Where pahcer and the local tools keep the file of `seed`, e.g. `tools/in/0042.txt`, or
+/// `ahc test` the output of a run. Without a run, outputs are those of pahcer or else of the
+/// latest run of `ahc test`.
+fn case_path(config: &Config, seed: u64, file: File, run: Option<&str>) -> Result<PathBuf> {
+    let name = format!("{:04}.txt", seed);
+    let outputs_dir = &config.paths.outputs_dir;
+    Ok(match (file, run) {
+        (File::Input, _) => config.paths.inputs_dir.join(name),
+        (File::Output, Some(run)) => runs::run_dir(outputs_dir, run)?.join(name),
+        (File::Output, None) if !outputs_dir.join(&name).exists() => {
+            match runs::run_dir(outputs_dir, runs::LATEST) {
+                Ok(dir) => dir.join(name),
+                Err(_) => outputs_dir.join(name),
+            }
+        }
+        (File::Output, None) => outputs_dir.join(name),
+    })
+}
@@ -0,0 +1,108 @@
+//! Putting text on the system clipboard through the platform's clipboard command, for the
+//! `--copy` flags of `ahc case` and `ahc source`.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+/// `[clipboard]`, only needed when none of the usual clipboard commands is installed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct ClipboardConfig {
+    /// Command reading the text to copy from stdin, e.g. `["xclip", "-selection", "clipboard"]`
+    pub(crate) command: Vec<String>,
+}
+
+/// Clipboard commands to try in order on this platform.
+fn default_commands() -> Vec<Vec<&'static str>> {
+    if cfg!(target_os = "macos") {
+        vec![vec!["pbcopy"]]
+    } else if cfg!(windows) {
+        vec![vec!["clip"]]
+    } else {
+        let mut commands = vec![];
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            commands.push(vec!["wl-copy"]);
+        }
+        commands.push(vec!["xclip", "-selection", "clipboard"]);
+        commands.push(vec!["xsel", "--clipboard", "--input"]);
+        // Under WSL the Windows clipboard is reachable through clip.exe
+        commands.push(vec!["clip.exe"]);
+        commands
+    }
+}
+
+/// Copies `text` with the configured command, or the first of the usual ones which is installed.
+pub(crate) fn copy(text: &str, config: Option<&ClipboardConfig>) -> Result<()> {
+    if let Some(config) = config {
+        let command = config
+            .command
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if command.is_empty() {
+            return Err(anyhow!("[clipboard] command is empty"));
+        }
+        return run(&command, text)?.ok_or_else(|| anyhow!("{} not found", command[0]));
+    }
+    for command in default_commands() {
+        if run(&command, text)?.is_some() {
+            return Ok(());
+        }
+    }
+    Err(anyhow!(
+        "No clipboard command found, install one or set [clipboard] command"
+    ))
+}
+
+/// Runs `command` with `text` on stdin, returning `None` if it is not installed.
+fn run(command: &[&str], text: &str) -> Result<Option<()>> {
+    let mut child = match Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).context(format!("Failed to run {}", command[0])),
+    };
+    debug!("Copying {} bytes with {}", text.len(), command[0]);
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(text.as_bytes())
+        .context(format!("Failed to write to {}", command[0]))?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("{} exited with {}", command[0], status));
+    }
+    Ok(Some(()))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_with_the_configured_command() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("clipboard.txt");
+        let config = ClipboardConfig {
+            command: vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                format!("cat > {}", path.display()),
+            ],
+        };
+        copy("1 2 3\n", Some(&config)).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1 2 3\n");
+
+        let missing = ClipboardConfig {
+            command: vec!["ahc-no-such-clipboard".to_string()],
+        };
+        assert!(copy("x", Some(&missing)).is_err());
+    }
+}
@@ -1,47 +1,141 @@
-use crate::pahcer::ExecResult;
-use crate::Config;
+//! `ahc commit`, which prefixes commit messages with the average score of the latest pahcer run.
+
+use crate::config::Config;
+use crate::error::ErrorKind;
+use crate::journal::{Journal, Operation};
+use crate::messages::msg;
+use crate::notify::{Event, Notification, Notifier, Severity, Source};
+use crate::output::{print_json, Output};
+use crate::pahcer::{self, ExecResult};
 use anyhow::{anyhow, Context, Result};
 use clap::Args;
-use git2::Repository;
+use git2::{Oid, Repository};
+use serde::Serialize;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tracing::info;
 
 #[derive(Args)]
 pub(crate) struct CommitArgs {
+    pub(crate) message: String,
+    /// Also tag the new commit, e.g. `--tag best`. `ahc undo` deletes the tag again
+    #[arg(long)]
+    pub(crate) tag: Option<String>,
+}
+
+/// What `ahc commit --output json` prints.
+#[derive(Serialize, Debug)]
+pub(crate) struct CommitSummary {
+    commit: String,
     message: String,
+    files: Vec<PathBuf>,
+    result_file: Option<PathBuf>,
+    case_count: Option<usize>,
+    average_score: Option<f64>,
+    max_execution_time: Option<f64>,
+    tag: Option<String>,
+}
+
+pub(crate) fn commit(args: CommitArgs, config: Config, output: Output) -> Result<()> {
+    if let Some(summary) = commit_staged_changes(&args, &config)? {
+        if output.is_json() {
+            print_json(&summary)?;
+        }
+    }
+    Ok(())
 }
 
-pub(crate) fn commit(args: CommitArgs, _config: Config) -> Result<()> {
+/// Commits the staged changes, prefixing the message with the score of a staged pahcer result.
+/// Returns `None` if the user declined to commit without one.
+pub(crate) fn commit_staged_changes(
+    args: &CommitArgs,
+    config: &Config,
+) -> Result<Option<CommitSummary>> {
     if args.message.is_empty() {
-        return Err(anyhow!("Commit message is empty"));
+        return Err(anyhow!(msg!("commit.empty_message")));
     }
 
+    let notifier = Notifier::new(config.notify.as_ref(), Source::Commit)?;
     let repo = Repository::open_from_env().context("Failed to open git repository")?;
     let updated_file_paths = list_updated_files(&repo)?;
 
     if updated_file_paths.is_empty() {
-        return Err(anyhow!("Nothing to commit"));
+        return Err(ErrorKind::NothingToCommit.error(msg!("commit.nothing")));
     }
 
-    let result_file_paths = filter_and_sort_result_files(&updated_file_paths);
+    let result_file_paths =
+        filter_and_sort_result_files(&updated_file_paths, &config.paths.results_dir);
 
-    if result_file_paths.is_empty() {
-        // Ask if the user wants to commit anyway
+    let summary = if result_file_paths.is_empty() {
+        // Ask if the user wants to commit anyway, on stderr to keep JSON output clean
         let mut input = String::new();
-        print!("No result files found. Commit anyway? [y/N]: ");
-        std::io::stdout().flush()?;
+        eprint!("{}", msg!("commit.no_results"));
+        std::io::stderr().flush()?;
         std::io::stdin().read_line(&mut input)?;
         if input.trim().to_lowercase() != "y" {
-            return Ok(());
+            return Ok(None);
         }
         let message = args.message.to_string();
-        return commit_staged(&repo, &message);
-    }
-
-    let result = read_exec_result(&repo, result_file_paths)?;
-    let commit_message = build_commit_message(&args, &result);
+        let commit = commit_staged(&repo, &message)?;
+        CommitSummary {
+            commit: commit.to_string(),
+            message,
+            files: updated_file_paths.clone(),
+            result_file: None,
+            case_count: None,
+            average_score: None,
+            max_execution_time: None,
+            tag: args.tag.clone(),
+        }
+    } else {
+        let result_file = result_file_paths[0].clone();
+        let result = read_exec_result(&repo, result_file_paths)?;
+        let with_time = config
+            .test
+            .as_ref()
+            .is_some_and(|test| test.time_limit_ms.is_some());
+        let message = build_commit_message(&args.message, &result, with_time);
+        let commit = commit_staged(&repo, &message)?;
+        CommitSummary {
+            commit: commit.to_string(),
+            message,
+            files: updated_file_paths.clone(),
+            result_file: Some(result_file),
+            case_count: Some(result.case_count),
+            average_score: Some(result.average_score()),
+            max_execution_time: Some(result.max_execution_time),
+            tag: args.tag.clone(),
+        }
+    };
 
-    commit_staged(&repo, &commit_message)
+    let commit = repo.find_commit(Oid::from_str(&summary.commit)?)?;
+    if let Some(tag) = &summary.tag {
+        repo.tag_lightweight(tag, commit.as_object(), false)
+            .context(format!("Failed to create tag {}", tag))?;
+    }
+    Journal::open().append(Operation::Commit {
+        commit: summary.commit.clone(),
+        parent: commit.parent_id(0)?.to_string(),
+        message: summary.message.clone(),
+        tag: summary.tag.clone(),
+        result_file: summary.result_file.clone(),
+        average_score: summary.average_score,
+    })?;
+
+    info!(
+        "{}",
+        msg!("commit.done", &summary.commit[..7], summary.message)
+    );
+    notifier.send(&Notification {
+        event: Event::Committed,
+        severity: Severity::Info,
+        title: format!("Committed {}", &summary.commit[..7]),
+        body: summary.message.clone(),
+        chart: None,
+        details: None,
+        summary: serde_json::to_value(&summary).ok(),
+    });
+    Ok(Some(summary))
 }
 
 fn list_updated_files(repo: &Repository) -> Result<Vec<PathBuf>> {
@@ -69,24 +163,34 @@ fn list_updated_files(repo: &Repository) -> Result<Vec<PathBuf>> {
     Ok(updated_file_paths)
 }
 
-fn filter_and_sort_result_files(updated_file_paths: &[PathBuf]) -> Vec<&PathBuf> {
+fn filter_and_sort_result_files<'a>(
+    updated_file_paths: &'a [PathBuf],
+    results_dir: &Path,
+) -> Vec<&'a PathBuf> {
     let re = regex::Regex::new(r"result_[0-9]{8}_[0-9]{6}\.json").unwrap();
     let mut result_file_paths = updated_file_paths
         .iter()
         .filter(|path| re.is_match(path.file_name().unwrap().to_str().unwrap()))
         .collect::<Vec<_>>();
-    result_file_paths.sort_by(|a, b| b.file_name().unwrap().cmp(a.file_name().unwrap()));
-    
+    // Prefer result files in the configured results directory, newest first
+    result_file_paths.sort_by(|a, b| {
+        let a_in_dir = a.starts_with(results_dir);
+        let b_in_dir = b.starts_with(results_dir);
+        b_in_dir
+            .cmp(&a_in_dir)
+            .then_with(|| b.file_name().unwrap().cmp(a.file_name().unwrap()))
+    });
+
     result_file_paths
 }
 
-fn commit_staged(repo: &Repository, message: &str) -> Result<()> {
+fn commit_staged(repo: &Repository, message: &str) -> Result<Oid> {
     let mut index = repo.index()?;
     let tree_id = index.write_tree()?;
     let tree = repo.find_tree(tree_id)?;
     let signature = repo.signature()?;
     let parent_commit = repo.head()?.peel_to_commit()?;
-    repo.commit(
+    let commit = repo.commit(
         Some("HEAD"),
         &signature,
         &signature,
@@ -94,20 +198,27 @@ fn commit_staged(repo: &Repository, message: &str) -> Result<()> {
         &tree,
         &[&parent_commit],
     )?;
-    Ok(())
+    Ok(commit)
 }
 
 fn read_exec_result(repo: &Repository, result_file_paths: Vec<&PathBuf>) -> Result<ExecResult> {
     let latest_file_path = repo.workdir().unwrap().join(result_file_paths[0]);
-    let mut file = std::fs::File::open(&latest_file_path)?;
-    let result: ExecResult = serde_json::from_reader(&mut file)?;
-    Ok(result)
+    pahcer::read_result(&latest_file_path)
 }
 
-fn build_commit_message(args: &CommitArgs, result: &ExecResult) -> String {
-    let avg_score = result.total_score as f64 / result.case_count as f64;
-    let commit_message = format!("({:.2}) {}", avg_score, args.message);
-    commit_message
+/// Prefixes `message` with the average score of `result`, e.g. `(5.00) Tune the schedule`, the
+/// time of its slowest case `with_time`, and the solver which scored it for results of
+/// `ahc test --solver`, e.g. `(5.00, 1.980s, sa) ...`.
+pub fn build_commit_message(message: &str, result: &ExecResult, with_time: bool) -> String {
+    let mut stats = vec![format!("{:.2}", result.average_score())];
+    // Results without times, such as those of older runs, leave it out
+    if with_time && result.max_execution_time > 0.0 {
+        stats.push(format!("{:.3}s", result.max_execution_time));
+    }
+    if let Some(solver) = &result.solver {
+        stats.push(solver.clone());
+    }
+    format!("({}) {}", stats.join(", "), message)
 }
 
 #[cfg(test)]
@@ -180,23 +291,46 @@ mod tests {
         ];
         let expected = vec![&updated_files[1], &updated_files[0]];
 
-        let result_files = filter_and_sort_result_files(&updated_files);
+        let result_files = filter_and_sort_result_files(&updated_files, Path::new("pahcer/json"));
+
+        assert_eq!(result_files, expected);
+    }
+
+    #[test]
+    fn test_filter_result_files_prefers_results_dir() {
+        let updated_files = vec![
+            PathBuf::from("results/result_20210901_123456.json"),
+            PathBuf::from("other/result_20210902_000000.json"),
+        ];
+        let expected = vec![&updated_files[0], &updated_files[1]];
+
+        let result_files = filter_and_sort_result_files(&updated_files, Path::new("results"));
 
         assert_eq!(result_files, expected);
     }
 
     #[test]
     fn test_build_commit_message() {
-        let args = CommitArgs {
-            message: "Test commit message".to_string(),
-        };
-        let result = ExecResult {
+        let mut result = ExecResult {
             case_count: 2,
             total_score: 10,
+            max_execution_time: 0.0,
+            solver: None,
+            cases: vec![],
         };
 
-        let commit_message = build_commit_message(&args, &result);
+        let commit_message = build_commit_message("Test commit message", &result, true);
 
         assert_eq!(commit_message, "(5.00) Test commit message");
+
+        result.solver = Some("sa".to_string());
+        let commit_message = build_commit_message("Test commit message", &result, true);
+        assert_eq!(commit_message, "(5.00, sa) Test commit message");
+
+        result.max_execution_time = 1.98;
+        let commit_message = build_commit_message("Test commit message", &result, true);
+        assert_eq!(commit_message, "(5.00, 1.980s, sa) Test commit message");
+        let commit_message = build_commit_message("Test commit message", &result, false);
+        assert_eq!(commit_message, "(5.00, sa) Test commit message");
     }
-}
\ No newline at end of file
+}
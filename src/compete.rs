@@ -0,0 +1,102 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+const WORKSPACE_FILE_NAME: &str = "compete.toml";
+
+/// A contest package of a cargo-compete workspace.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Package {
+    pub(crate) dir: PathBuf,
+    /// Package name, which cargo-compete takes from the contest id
+    pub(crate) name: String,
+    /// Problem URL of the package's first binary
+    pub(crate) problem_url: Option<String>,
+}
+
+/// Finds the cargo-compete contest package containing `start`, which is `None` outside of a
+/// cargo-compete workspace and in the workspace root itself.
+pub(crate) fn find_package(start: &Path) -> Result<Option<Package>> {
+    let mut package = None;
+    for dir in start.ancestors() {
+        if package.is_none() {
+            package = read_package(dir)?;
+        }
+        if dir.join(WORKSPACE_FILE_NAME).exists() {
+            return Ok(package);
+        }
+    }
+    Ok(None)
+}
+
+/// The cargo-compete package whose manifest is in `dir`, if any.
+pub(crate) fn read_package(dir: &Path) -> Result<Option<Package>> {
+    let path = dir.join("Cargo.toml");
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)
+        .context(format!("Failed to read manifest: {}", path.display()))?;
+    let manifest: toml::Table = toml::from_str(&content)
+        .context(format!("Failed to parse manifest: {}", path.display()))?;
+    let Some(package) = manifest.get("package").and_then(toml::Value::as_table) else {
+        return Ok(None);
+    };
+    let Some(metadata) = package
+        .get("metadata")
+        .and_then(|metadata| metadata.get("cargo-compete"))
+    else {
+        return Ok(None);
+    };
+    let problem_url = metadata
+        .get("bin")
+        .and_then(toml::Value::as_table)
+        .and_then(|bins| bins.values().find_map(|bin| bin.get("problem")))
+        .and_then(toml::Value::as_str)
+        .map(str::to_string);
+    Ok(Some(Package {
+        dir: dir.to_path_buf(),
+        name: package
+            .get("name")
+            .and_then(toml::Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        problem_url,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn finds_the_package_within_a_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let package_dir = dir.path().join("ahc030");
+        fs::create_dir_all(package_dir.join("src/bin")).unwrap();
+        fs::write(dir.path().join("compete.toml"), "").unwrap();
+        fs::write(
+            package_dir.join("Cargo.toml"),
+            r#"
+            [package]
+            name = "ahc030"
+
+            [package.metadata.cargo-compete.bin]
+            ahc030-a = { alias = "a", problem = "https://atcoder.jp/contests/ahc030/tasks/ahc030_a" }
+            "#,
+        )
+        .unwrap();
+
+        let package = find_package(&package_dir.join("src/bin")).unwrap().unwrap();
+        assert_eq!(package.dir, package_dir);
+        assert_eq!(package.name, "ahc030");
+        assert_eq!(
+            package.problem_url.as_deref(),
+            Some("https://atcoder.jp/contests/ahc030/tasks/ahc030_a")
+        );
+        assert_eq!(find_package(dir.path()).unwrap(), None);
+
+        fs::remove_file(dir.path().join("compete.toml")).unwrap();
+        assert_eq!(find_package(&package_dir).unwrap(), None);
+    }
+}
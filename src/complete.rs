@@ -0,0 +1,252 @@
+//! Shell completion: `ahc completions <shell>` prints a script which asks the hidden
+//! `ahc __complete` for the candidates of the word under the cursor, so that values such as
+//! study names come from the project itself.
+
+use crate::config::{self, Config};
+use crate::plugin;
+use crate::runner::{build, runs};
+use crate::tune;
+use crate::workspace;
+use crate::Cli;
+use anyhow::Result;
+use clap::{Arg, Args, Command, CommandFactory, ValueEnum};
+use std::path::Path;
+
+const BASH_SCRIPT: &str = r#"_ahc() {
+    local IFS=$'\n'
+    COMPREPLY=($(ahc __complete -- "${COMP_WORDS[@]:1:COMP_CWORD}" 2>/dev/null))
+    if [[ ${#COMPREPLY[@]} -eq 0 ]]; then
+        compopt -o default
+    fi
+}
+complete -F _ahc ahc
+"#;
+
+const ZSH_SCRIPT: &str = r#"#compdef ahc
+_ahc() {
+    local -a candidates
+    candidates=("${(@f)$(ahc __complete -- "${(@)words[2,CURRENT]}" 2>/dev/null)}")
+    if [[ -n ${candidates[1]} ]]; then
+        compadd -a candidates
+    else
+        _files
+    fi
+}
+compdef _ahc ahc
+"#;
+
+const FISH_SCRIPT: &str = r#"complete -c ahc -f -a '(ahc __complete -- (commandline -opc)[2..-1] (commandline -ct) 2>/dev/null)'
+"#;
+
+#[derive(Args)]
+pub(crate) struct CompletionsArgs {
+    /// Shell to print the completion script for, e.g. `ahc completions bash >> ~/.bashrc`
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+#[derive(Args)]
+pub(crate) struct CompleteArgs {
+    /// Words of the command line after `ahc`, the last one being completed
+    #[arg(num_args = 0.., trailing_var_arg = true, allow_hyphen_values = true)]
+    words: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+pub(crate) fn completions(args: CompletionsArgs) {
+    let script = match args.shell {
+        Shell::Bash => BASH_SCRIPT,
+        Shell::Zsh => ZSH_SCRIPT,
+        Shell::Fish => FISH_SCRIPT,
+    };
+    print!("{}", script);
+}
+
+/// Prints the candidates for the last word, one per line. Printing none lets the shell fall
+/// back to completing file names.
+pub(crate) fn complete(args: CompleteArgs, config_file_name: &str) -> Result<()> {
+    // Completing must not fail on a broken config, it just has fewer candidates
+    let config = Path::new(config_file_name)
+        .exists()
+        .then(|| config::load_effective_config(config_file_name).ok())
+        .flatten()
+        .and_then(|effective| effective.to_config().ok())
+        .map(|(config, _)| config);
+    let mut command = Cli::command();
+    command.build();
+    for candidate in candidates(&command, &args.words, config.as_ref(), config_file_name) {
+        println!("{}", candidate);
+    }
+    Ok(())
+}
+
+fn candidates(
+    root: &Command,
+    words: &[String],
+    config: Option<&Config>,
+    config_file_name: &str,
+) -> Vec<String> {
+    let Some((current, before)) = words.split_last() else {
+        return names(root);
+    };
+
+    let mut command = root;
+    let mut pending: Option<&Arg> = None;
+    for word in before {
+        if pending.take().is_some() {
+            continue;
+        }
+        if let Some(long) = word.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (long, None),
+            };
+            let arg = command
+                .get_arguments()
+                .find(|arg| arg.get_long() == Some(name));
+            pending = arg.filter(|arg| takes_value(arg) && value.is_none());
+        } else if let Some(shorts) = word.strip_prefix('-').filter(|s| !s.is_empty()) {
+            // Only the last of grouped short flags, as in `-vj 4`, can take a value
+            let last = shorts.chars().last();
+            let arg = command
+                .get_arguments()
+                .find(|arg| arg.get_short().is_some() && arg.get_short() == last);
+            pending = arg.filter(|arg| takes_value(arg));
+        } else if let Some(subcommand) = command.find_subcommand(word) {
+            command = subcommand;
+        }
+    }
+
+    let mut candidates = match pending {
+        Some(arg) => values(arg, config, config_file_name),
+        None if current.starts_with('-') => flags(command),
+        None if std::ptr::eq(command, root) => {
+            let mut names = names(command);
+            names.extend(plugin::names());
+            names
+        }
+        None => names(command),
+    };
+    candidates.retain(|candidate| candidate.starts_with(current.as_str()));
+    candidates
+}
+
+fn takes_value(arg: &Arg) -> bool {
+    arg.get_num_args().is_some_and(|range| range.takes_values())
+}
+
+fn names(command: &Command) -> Vec<String> {
+    command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set())
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect()
+}
+
+fn flags(command: &Command) -> Vec<String> {
+    command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set() && !arg.is_positional())
+        .flat_map(|arg| {
+            let long = arg.get_long().map(|long| format!("--{}", long));
+            let short = arg.get_short().map(|short| format!("-{}", short));
+            long.into_iter().chain(short)
+        })
+        .collect()
+}
+
+/// Values of an option, from its possible values or, for the options naming things of the
+/// project, from the project.
+fn values(arg: &Arg, config: Option<&Config>, config_file_name: &str) -> Vec<String> {
+    let possible = arg.get_possible_values();
+    if !possible.is_empty() {
+        return possible
+            .iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name().to_string())
+            .collect();
+    }
+    match arg.get_id().as_str() {
+        "study" => tune::study_names(),
+        "contest" => workspace::names(config_file_name),
+        "class" => config
+            .and_then(|config| config.tune.as_ref())
+            .and_then(|tune| tune.classes.as_ref())
+            .map(|classes| {
+                (0..=classes.bounds.len())
+                    .map(|class| class.to_string())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        "solver" => config
+            .and_then(|config| config.solver.as_ref())
+            .map(|solvers| solvers.keys().cloned().collect())
+            .unwrap_or_default(),
+        "profile" => build::profile_names(
+            config
+                .and_then(|config| config.test.as_ref())
+                .and_then(|test| test.profiles.as_ref()),
+        ),
+        "run" => {
+            let outputs_dir = config.map_or_else(
+                || Path::new("tools/out").to_path_buf(),
+                |config| config.paths.outputs_dir.clone(),
+            );
+            let mut runs = runs::list_runs(&outputs_dir).unwrap_or_default();
+            runs.push(runs::LATEST.to_string());
+            runs
+        }
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complete_words(words: &[&str]) -> Vec<String> {
+        let mut command = Cli::command();
+        command.build();
+        let words = words
+            .iter()
+            .map(|word| word.to_string())
+            .collect::<Vec<_>>();
+        candidates(&command, &words, None, crate::DEFAULT_CONFIG_FILE_NAME)
+    }
+
+    #[test]
+    fn completes_subcommands_and_hides_the_backend() {
+        let candidates = complete_words(&["t"]);
+        assert_eq!(candidates, vec!["test", "tune"]);
+        assert!(!complete_words(&[""]).contains(&"__complete".to_string()));
+        assert_eq!(complete_words(&["tune", "ex"]), vec!["export"]);
+    }
+
+    #[test]
+    fn completes_flags_including_global_ones() {
+        let candidates = complete_words(&["tune", "--st"]);
+        assert_eq!(candidates, vec!["--study", "--strategy"]);
+        assert!(complete_words(&["download", "--"]).contains(&"--verbose".to_string()));
+    }
+
+    #[test]
+    fn completes_option_values() {
+        assert_eq!(
+            complete_words(&["tune", "--strategy", "r"]),
+            vec!["random", "race"]
+        );
+        assert_eq!(complete_words(&["--output", ""]), vec!["text", "json"]);
+        assert!(complete_words(&["download", "--url", ""]).is_empty());
+        // A value given with `=` is not waiting for the next word
+        assert_eq!(
+            complete_words(&["tune", "--strategy=tpe", "ex"]),
+            vec!["export"]
+        );
+    }
+}
@@ -0,0 +1,794 @@
+//! Layered configuration: defaults, the global config, the project's `ahc_tools.toml` and
+//! `AHC_*` environment variables.
+
+mod diagnostics;
+mod infer;
+
+use crate::batch::BatchConfig;
+use crate::clipboard::ClipboardConfig;
+use crate::dotenv;
+use crate::download::DownloadConfig;
+use crate::error::{ErrorKind, ResultExt};
+use crate::http::HttpConfig;
+use crate::messages::msg;
+use crate::notify::NotifyConfig;
+use crate::output::{print_json, Output};
+use crate::overlay::OverlayConfig;
+use crate::problem::ProblemConfig;
+use crate::remote::RemoteConfig;
+use crate::run::RunConfig;
+use crate::runner::{SolverConfig, TestConfig};
+use crate::source::SourceConfig;
+use crate::sweep::SweepConfig;
+use crate::tune::{Objective, TuneConfig};
+use crate::watch::WatchConfig;
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, Subcommand, ValueEnum};
+use diagnostics::Diagnostic;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+const GLOBAL_CONFIG_FILE_NAME: &str = "config.toml";
+const ENV_PREFIX: &str = "AHC_";
+const ENV_SEPARATOR: &str = "__";
+
+#[derive(Args)]
+pub(crate) struct ConfigArgs {
+    #[command(subcommand)]
+    command: ConfigCommands,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the project configuration
+    Show {
+        /// Print the merged configuration with the source of each key
+        #[arg(long)]
+        effective: bool,
+    },
+    /// Open the configuration in $VISUAL or $EDITOR
+    Edit {
+        /// Edit the global configuration instead of the project one
+        #[arg(long)]
+        global: bool,
+    },
+}
+
+/// The project configuration. The sections of the subcommands are internal.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Config {
+    pub general: General,
+    #[serde(default)]
+    pub paths: Paths,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) tune: Option<TuneConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) notify: Option<NotifyConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) overlay: Option<OverlayConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) source: Option<SourceConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) clipboard: Option<ClipboardConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) run: Option<RunConfig>,
+    /// `[test]`, also read from `[runner]`
+    #[serde(default, alias = "runner", skip_serializing_if = "Option::is_none")]
+    pub(crate) test: Option<TestConfig>,
+    /// `[solver.<name>]`, the variants of the solver by name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) solver: Option<BTreeMap<String, SolverConfig>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sweep: Option<SweepConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) watch: Option<WatchConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) remote: Option<RemoteConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) batch: Option<BatchConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) download: Option<DownloadConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) http: Option<HttpConfig>,
+    /// `[problem]`, written by `ahc problem` from the problem page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) problem: Option<ProblemConfig>,
+}
+
+impl Config {
+    /// Whether higher or lower scores are better: `[tune] objective`, else the one `ahc problem`
+    /// read from the scoring section, else higher.
+    pub(crate) fn objective(&self) -> Objective {
+        match (&self.tune, &self.problem) {
+            (Some(tune), _) => tune.objective,
+            (None, Some(problem)) => problem.objective.unwrap_or_default(),
+            (None, None) => Objective::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct General {
+    /// Contest id, e.g. `ahc030`
+    pub name: String,
+    pub problem_url: String,
+    /// Language of the problem page
+    #[serde(default)]
+    pub lang: Lang,
+    /// Language of the messages of `ahc`, the locale's when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_lang: Option<Lang>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    Ja,
+    En,
+}
+
+impl Lang {
+    /// Value of the `lang` query of AtCoder pages.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Lang::Ja => "ja",
+            Lang::En => "en",
+        }
+    }
+
+    /// Text of the link to the local tools on the problem page.
+    pub fn tool_link_text(&self) -> &'static str {
+        match self {
+            Lang::Ja => "ローカル版",
+            Lang::En => "Local version",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Paths {
+    pub tools_dir: PathBuf,
+    pub inputs_dir: PathBuf,
+    pub outputs_dir: PathBuf,
+    /// Directory of the pahcer result files
+    pub results_dir: PathBuf,
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Paths {
+            tools_dir: PathBuf::from("tools"),
+            inputs_dir: PathBuf::from("tools/in"),
+            outputs_dir: PathBuf::from("tools/out"),
+            results_dir: PathBuf::from("pahcer/json"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Source {
+    Default,
+    Global(PathBuf),
+    Project(PathBuf),
+    Env(String),
+    DotEnv(String),
+    /// Guessed from the named surroundings, without a project config
+    Inferred(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::Global(path) => write!(f, "global ({})", path.display()),
+            Source::Project(path) => write!(f, "project ({})", path.display()),
+            Source::Env(name) => write!(f, "env ({})", name),
+            Source::DotEnv(name) => write!(f, ".env ({})", name),
+            Source::Inferred(from) => write!(f, "inferred ({})", from),
+        }
+    }
+}
+
+/// Configuration merged from every layer, keeping track of which layer set each key.
+#[derive(Debug, Default)]
+pub(crate) struct EffectiveConfig {
+    pub(crate) table: toml::Table,
+    pub(crate) provenance: BTreeMap<String, Source>,
+}
+
+impl EffectiveConfig {
+    pub(crate) fn merge(&mut self, table: toml::Table, source: &Source) {
+        merge_table(&mut self.table, table, "", source, &mut self.provenance);
+    }
+
+    pub(crate) fn to_config(&self) -> Result<(Config, Vec<Diagnostic>)> {
+        let mut ignored_keys = vec![];
+        let config = serde_ignored::deserialize(toml::Value::Table(self.table.clone()), |path| {
+            ignored_keys.push(path.to_string())
+        })
+        .map_err(|e| ErrorKind::Config.error(format!("Failed to parse config file: {}", e)))?;
+        let diagnostics =
+            diagnostics::collect(ignored_keys, &self.provenance, diagnostics::DEPRECATED_KEYS);
+        Ok((config, diagnostics))
+    }
+}
+
+fn merge_table(
+    dst: &mut toml::Table,
+    src: toml::Table,
+    prefix: &str,
+    source: &Source,
+    provenance: &mut BTreeMap<String, Source>,
+) {
+    for (key, value) in src {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match (dst.get_mut(&key), value) {
+            (Some(toml::Value::Table(dst_table)), toml::Value::Table(src_table)) => {
+                merge_table(dst_table, src_table, &path, source, provenance);
+            }
+            (_, toml::Value::Table(src_table)) => {
+                let mut new_table = toml::Table::new();
+                merge_table(&mut new_table, src_table, &path, source, provenance);
+                dst.insert(key, toml::Value::Table(new_table));
+            }
+            (_, value) => {
+                provenance.insert(path, source.clone());
+                dst.insert(key, value);
+            }
+        }
+    }
+}
+
+pub(crate) fn config(args: ConfigArgs, file_name: &str, output: Output) -> Result<()> {
+    match args.command {
+        ConfigCommands::Show { effective } => {
+            if effective {
+                let effective = load_effective_config(file_name)?;
+                if let Ok((_, diagnostics)) = effective.to_config() {
+                    diagnostics::report(&diagnostics);
+                }
+                if output.is_json() {
+                    print_json(&effective_config_json(&effective))?;
+                } else {
+                    print!("{}", format_effective_config(&effective));
+                }
+            } else if output.is_json() {
+                print_json(&read_table(Path::new(file_name))?)?;
+            } else {
+                let content = std::fs::read_to_string(file_name)
+                    .map_err(|e| anyhow!("Failed to read config file: {}", e))?;
+                print!("{}", content);
+            }
+        }
+        ConfigCommands::Edit { global } => {
+            let path = edit(global, file_name)?;
+            if output.is_json() {
+                print_json(&json!({ "path": path, "valid": true }))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the path of the edited configuration.
+fn edit(global: bool, file_name: &str) -> Result<PathBuf> {
+    let path = if global {
+        global_config_path().ok_or_else(|| anyhow!("Failed to locate global config directory"))?
+    } else {
+        PathBuf::from(file_name)
+    };
+
+    if !path.exists() {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+        std::fs::write(&path, config_template(global)).context(format!(
+            "Failed to write config to file: {}",
+            path.display()
+        ))?;
+        info!("{}", msg!("config.created", path.display()));
+    }
+
+    open_editor(&path)?;
+
+    read_table(&path)?;
+    if !global || Path::new(file_name).exists() {
+        load_config(file_name)?;
+    }
+    info!("{}", msg!("config.valid", path.display()));
+    Ok(path)
+}
+
+fn open_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| anyhow!("Editor command is empty"))?;
+
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .context(format!("Failed to launch editor: {}", editor))?;
+    if !status.success() {
+        return Err(anyhow!("Editor exited with status: {}", status));
+    }
+    Ok(())
+}
+
+fn config_template(global: bool) -> String {
+    let mut output = String::from(if global {
+        "# ahc-tools global configuration, shared by every project.\n"
+    } else {
+        "# ahc-tools project configuration.\n"
+    });
+    output.push_str("# Uncomment and edit the keys you want to change.\n");
+
+    for (section, value) in default_table() {
+        let toml::Value::Table(table) = value else {
+            continue;
+        };
+        output.push_str(&format!("\n[{}]\n", section));
+        if section == "general" && !global {
+            output.push_str("name = \"ahc001\"\n");
+            output.push_str(
+                "problem_url = \"https://atcoder.jp/contests/ahc001/tasks/ahc001_a?lang=ja\"\n",
+            );
+        }
+        for (key, value) in table {
+            output.push_str(&format!("# {} = {}\n", key, value));
+        }
+    }
+    output
+}
+
+/// Loads the configuration merged from the defaults, the global config, the project config
+/// `file_name` and `AHC_*` environment variables, reporting unknown and deprecated keys.
+/// Without the project config, the contest is inferred from the git remote or directory name.
+/// `.env` files are only loaded by [`crate::run_command`].
+pub fn load_config(file_name: &str) -> Result<Config> {
+    let effective = load_effective_config(file_name)?;
+    let (config, diagnostics) = effective.to_config()?;
+    diagnostics::report(&diagnostics);
+    if let Some(source @ Source::Inferred(_)) = effective.provenance.get("general.name") {
+        info!(
+            "{}",
+            msg!("config.inferred", file_name, config.general.name, source)
+        );
+    }
+    Ok(config)
+}
+
+pub(crate) fn load_effective_config(file_name: &str) -> Result<EffectiveConfig> {
+    let mut effective = EffectiveConfig::default();
+    effective.merge(default_table(), &Source::Default);
+
+    if let Some(path) = global_config_path() {
+        if path.exists() {
+            let table = read_table(&path)?;
+            effective.merge(table, &Source::Global(path));
+        }
+    }
+
+    let path = PathBuf::from(file_name);
+    let exists = path.exists();
+    if exists {
+        let table = read_table(&path)?;
+        effective.merge(table, &Source::Project(path));
+    }
+
+    for (name, table) in env_tables(std::env::vars()) {
+        let source = if dotenv::is_loaded_from_dotenv(&name) {
+            Source::DotEnv(name)
+        } else {
+            Source::Env(name)
+        };
+        effective.merge(table, &source);
+    }
+
+    if !exists {
+        infer::fill(&mut effective, &std::env::current_dir()?, file_name)?;
+    }
+    follow_download_dir(&mut effective);
+    follow_problem(&mut effective);
+
+    Ok(effective)
+}
+
+/// Moves the paths of the tools, inputs and outputs left at their defaults under
+/// `[download] output_dir`, where `ahc download` puts them.
+fn follow_download_dir(effective: &mut EffectiveConfig) {
+    let Some(output_dir) = effective
+        .table
+        .get("download")
+        .and_then(|download| download.get("output_dir"))
+        .and_then(|dir| dir.as_str())
+        .map(PathBuf::from)
+    else {
+        return;
+    };
+    let source = effective.provenance["download.output_dir"].clone();
+    let defaults = Paths::default();
+    let Some(toml::Value::Table(paths)) = effective.table.get_mut("paths") else {
+        return;
+    };
+    for (key, default) in [
+        ("tools_dir", defaults.tools_dir),
+        ("inputs_dir", defaults.inputs_dir),
+        ("outputs_dir", defaults.outputs_dir),
+    ] {
+        let path = format!("paths.{}", key);
+        if effective.provenance.get(&path) == Some(&Source::Default) {
+            let dir = output_dir.join(default).to_string_lossy().to_string();
+            paths.insert(key.to_string(), toml::Value::String(dir));
+            effective.provenance.insert(path, source.clone());
+        }
+    }
+}
+
+/// Fills `[test] time_limit_ms` and `[tune] objective` left unset in existing sections with
+/// what `ahc problem` read from the problem page into `[problem]`.
+fn follow_problem(effective: &mut EffectiveConfig) {
+    let Some(toml::Value::Table(problem)) = effective.table.get("problem").cloned() else {
+        return;
+    };
+    for (key, section, section_key) in [
+        ("time_limit_ms", "test", "time_limit_ms"),
+        ("objective", "tune", "objective"),
+    ] {
+        let Some(value) = problem.get(key) else {
+            continue;
+        };
+        let path = format!("{}.{}", section, section_key);
+        if effective.provenance.contains_key(&path) {
+            continue;
+        }
+        let Some(toml::Value::Table(table)) = effective.table.get_mut(section) else {
+            continue;
+        };
+        table.insert(section_key.to_string(), value.clone());
+        let source = effective.provenance[&format!("problem.{}", key)].clone();
+        effective.provenance.insert(path, source);
+    }
+}
+
+fn default_table() -> toml::Table {
+    let mut general = toml::Table::new();
+    general.insert(
+        "lang".to_string(),
+        toml::Value::String(Lang::default().as_str().to_string()),
+    );
+
+    let mut table = toml::Table::new();
+    table.insert("general".to_string(), toml::Value::Table(general));
+    table.insert(
+        "paths".to_string(),
+        toml::Value::try_from(Paths::default()).expect("default paths must serialize"),
+    );
+    table
+}
+
+pub(crate) fn global_config_path() -> Option<PathBuf> {
+    if let Ok(dir) = std::env::var("AHC_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join(GLOBAL_CONFIG_FILE_NAME));
+    }
+    let config_home = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => PathBuf::from(std::env::var("HOME").ok()?).join(".config"),
+    };
+    Some(config_home.join("ahc-tools").join(GLOBAL_CONFIG_FILE_NAME))
+}
+
+fn read_table(path: &Path) -> Result<toml::Table> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| ErrorKind::Config.error(format!("Failed to read config file: {}", e)))?;
+    toml::from_str(&content)
+        .context(format!("Failed to parse config file: {}", path.display()))
+        .kind(ErrorKind::Config)
+}
+
+/// Converts variables such as `AHC_GENERAL__PROBLEM_URL` into single-key tables.
+fn env_tables<I>(vars: I) -> Vec<(String, toml::Table)>
+where
+    I: Iterator<Item = (String, String)>,
+{
+    let mut tables = vec![];
+    for (name, raw) in vars {
+        let Some(rest) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let segments = rest
+            .split(ENV_SEPARATOR)
+            .map(|s| s.to_lowercase())
+            .collect::<Vec<_>>();
+        if segments.len() < 2 || segments.iter().any(|s| s.is_empty()) {
+            continue;
+        }
+
+        let mut value = parse_env_value(&raw);
+        for segment in segments.iter().skip(1).rev() {
+            let mut table = toml::Table::new();
+            table.insert(segment.clone(), value);
+            value = toml::Value::Table(table);
+        }
+        let mut table = toml::Table::new();
+        table.insert(segments[0].clone(), value);
+        tables.push((name, table));
+    }
+    tables.sort_by(|a, b| a.0.cmp(&b.0));
+    tables
+}
+
+fn parse_env_value(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+fn format_effective_config(effective: &EffectiveConfig) -> String {
+    let mut output = String::new();
+    format_table(&effective.table, "", &effective.provenance, &mut output);
+    output
+}
+
+/// Every key of the effective configuration, by its dotted path, with its value and source.
+fn effective_config_json(effective: &EffectiveConfig) -> serde_json::Value {
+    fn collect(
+        table: &toml::Table,
+        prefix: &str,
+        provenance: &BTreeMap<String, Source>,
+        keys: &mut serde_json::Map<String, serde_json::Value>,
+    ) {
+        for (key, value) in table {
+            let path = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            match value {
+                toml::Value::Table(sub_table) => collect(sub_table, &path, provenance, keys),
+                value => {
+                    let source = provenance.get(&path).map(|s| s.to_string());
+                    keys.insert(path, json!({ "value": value, "source": source }));
+                }
+            }
+        }
+    }
+
+    let mut keys = serde_json::Map::new();
+    collect(&effective.table, "", &effective.provenance, &mut keys);
+    serde_json::Value::Object(keys)
+}
+
+fn format_table(
+    table: &toml::Table,
+    prefix: &str,
+    provenance: &BTreeMap<String, Source>,
+    output: &mut String,
+) {
+    let join = |key: &str| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        }
+    };
+
+    for (key, value) in table.iter().filter(|(_, v)| !v.is_table()) {
+        let source = provenance
+            .get(&join(key))
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+        output.push_str(&format!("{} = {}  # {}\n", key, value, source));
+    }
+    for (key, value) in table {
+        if let toml::Value::Table(sub_table) = value {
+            let path = join(key);
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str(&format!("[{}]\n", path));
+            format_table(sub_table, &path, provenance, output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(content: &str) -> toml::Table {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn merge_tracks_provenance() {
+        let global = Source::Global(PathBuf::from("global.toml"));
+        let project = Source::Project(PathBuf::from("ahc_tools.toml"));
+        let mut effective = EffectiveConfig::default();
+        effective.merge(
+            table("[general]\nname = \"global\"\nproblem_url = \"https://example.net\""),
+            &global,
+        );
+        effective.merge(table("[general]\nname = \"ahc001\""), &project);
+
+        let (config, _) = effective.to_config().unwrap();
+        assert_eq!(config.general.name, "ahc001");
+        assert_eq!(config.general.lang, Lang::Ja);
+        assert_eq!(config.paths.tools_dir, PathBuf::from("tools"));
+        assert_eq!(config.general.problem_url, "https://example.net");
+        assert_eq!(effective.provenance["general.name"], project);
+        assert_eq!(effective.provenance["general.problem_url"], global);
+    }
+
+    #[test]
+    fn paths_left_unset_follow_the_download_dir() {
+        let project = Source::Project(PathBuf::from("ahc_tools.toml"));
+        let mut effective = EffectiveConfig::default();
+        effective.merge(default_table(), &Source::Default);
+        effective.merge(
+            table(
+                "[general]\nname = \"ahc001\"\nproblem_url = \"https://example.net\"\n\
+                 [paths]\noutputs_dir = \"out\"\n[download]\noutput_dir = \"contest\"",
+            ),
+            &project,
+        );
+        follow_download_dir(&mut effective);
+
+        let (config, _) = effective.to_config().unwrap();
+        assert_eq!(config.paths.tools_dir, PathBuf::from("contest/tools"));
+        assert_eq!(config.paths.inputs_dir, PathBuf::from("contest/tools/in"));
+        assert_eq!(config.paths.outputs_dir, PathBuf::from("out"));
+        assert_eq!(effective.provenance["paths.tools_dir"], project);
+    }
+
+    #[test]
+    fn unset_limits_follow_the_problem() {
+        let project = Source::Project(PathBuf::from("ahc_tools.toml"));
+        let mut effective = EffectiveConfig::default();
+        effective.merge(default_table(), &Source::Default);
+        effective.merge(
+            table(
+                "[general]\nname = \"ahc001\"\nproblem_url = \"https://example.net\"\n\
+                 [test]\ncommand = [\"./a.out\"]\n\
+                 [problem]\ntime_limit_ms = 2000\nobjective = \"min\"",
+            ),
+            &project,
+        );
+        follow_problem(&mut effective);
+
+        let (config, _) = effective.to_config().unwrap();
+        assert!(config.tune.is_none());
+        assert_eq!(config.objective(), Objective::Min);
+        assert_eq!(config.test.unwrap().time_limit_ms, Some(2000));
+        assert_eq!(effective.provenance["test.time_limit_ms"], project);
+
+        effective.merge(
+            table("[test]\ntime_limit_ms = 3000"),
+            &Source::Env("AHC_TEST__TIME_LIMIT_MS".to_string()),
+        );
+        follow_problem(&mut effective);
+        let (config, _) = effective.to_config().unwrap();
+        assert_eq!(config.test.unwrap().time_limit_ms, Some(3000));
+    }
+
+    #[test]
+    fn env_tables_parse_nested_keys() {
+        let vars = vec![
+            ("AHC_GENERAL__NAME".to_string(), "ahc002".to_string()),
+            ("AHC_TEST__THREADS".to_string(), "4".to_string()),
+            ("AHC_CONFIG_HOME".to_string(), "/tmp".to_string()),
+            ("PATH".to_string(), "/usr/bin".to_string()),
+        ];
+
+        let tables = env_tables(vars.into_iter());
+
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].0, "AHC_GENERAL__NAME");
+        assert_eq!(tables[0].1["general"]["name"].as_str(), Some("ahc002"));
+        assert_eq!(tables[1].1["test"]["threads"].as_integer(), Some(4));
+    }
+
+    #[test]
+    fn format_effective_config_annotates_sources() {
+        let mut effective = EffectiveConfig::default();
+        effective.merge(
+            table("[general]\nname = \"ahc001\""),
+            &Source::Project(PathBuf::from("ahc_tools.toml")),
+        );
+        effective.merge(
+            table("[general]\nproblem_url = \"https://example.net\""),
+            &Source::Env("AHC_GENERAL__PROBLEM_URL".to_string()),
+        );
+
+        let output = format_effective_config(&effective);
+
+        assert_eq!(
+            output,
+            "[general]\n\
+             name = \"ahc001\"  # project (ahc_tools.toml)\n\
+             problem_url = \"https://example.net\"  # env (AHC_GENERAL__PROBLEM_URL)\n"
+        );
+    }
+
+    #[test]
+    fn effective_config_json_flattens_keys() {
+        let mut effective = EffectiveConfig::default();
+        effective.merge(
+            table("[general]\nname = \"ahc001\"\n[tune.params]\nk = 3"),
+            &Source::Project(PathBuf::from("ahc_tools.toml")),
+        );
+
+        let json = effective_config_json(&effective);
+
+        assert_eq!(json["general.name"]["value"], "ahc001");
+        assert_eq!(json["general.name"]["source"], "project (ahc_tools.toml)");
+        assert_eq!(json["tune.params.k"]["value"], 3);
+    }
+
+    #[test]
+    fn config_templates_are_valid() {
+        let global: toml::Table = toml::from_str(&config_template(true)).unwrap();
+        assert!(global["paths"].as_table().unwrap().is_empty());
+
+        let mut effective = EffectiveConfig::default();
+        effective.merge(default_table(), &Source::Default);
+        effective.merge(
+            toml::from_str(&config_template(false)).unwrap(),
+            &Source::Project(PathBuf::from(crate::DEFAULT_CONFIG_FILE_NAME)),
+        );
+        let (config, _) = effective.to_config().unwrap();
+        assert_eq!(config.general.name, "ahc001");
+    }
+
+    #[test]
+    fn to_config_reports_unknown_keys() {
+        let project = Source::Project(PathBuf::from("ahc_tools.toml"));
+        let mut effective = EffectiveConfig::default();
+        effective.merge(
+            table("[general]\nname = \"ahc001\"\nproblem_url = \"https://example.net\"\nnmae = \"typo\""),
+            &project,
+        );
+
+        let (_, diagnostics) = effective.to_config().unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].key, "general.nmae");
+        assert_eq!(diagnostics[0].source, Some(project));
+    }
+
+    #[test]
+    fn reads_runner_as_test() {
+        let mut effective = EffectiveConfig::default();
+        effective.merge(
+            table("[general]\nname = \"ahc001\"\nproblem_url = \"https://example.net\"\n[runner]\ncommand = [\"./a.out\"]\nscore_from_stderr = true"),
+            &Source::Project(PathBuf::from("ahc_tools.toml")),
+        );
+
+        let (config, diagnostics) = effective.to_config().unwrap();
+
+        assert!(config.test.is_some_and(|test| test.score_from_stderr));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].key, "runner");
+        assert_eq!(
+            diagnostics::format(&diagnostics),
+            "Warning: 1 problem(s) found in configuration:\n \
+             - deprecated key `runner` (use `test` instead) in project (ahc_tools.toml)\n"
+        );
+    }
+}
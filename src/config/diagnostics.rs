@@ -0,0 +1,145 @@
+use super::Source;
+use crate::messages::msg;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::warn;
+
+/// Keys which are still accepted but will be removed, with the key to use instead.
+pub(crate) const DEPRECATED_KEYS: &[(&str, &str)] = &[
+    // Renamed when ahc test took over from the runner, still read through a serde alias
+    ("runner", "test"),
+];
+
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DiagnosticKind {
+    Unknown,
+    /// A deprecated key, with the key to use instead
+    Deprecated(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Diagnostic {
+    pub(crate) kind: DiagnosticKind,
+    pub(crate) key: String,
+    pub(crate) source: Option<Source>,
+}
+
+pub(crate) fn collect(
+    ignored_keys: Vec<String>,
+    provenance: &BTreeMap<String, Source>,
+    deprecated_keys: &[(&str, &str)],
+) -> Vec<Diagnostic> {
+    let source_of = |key: &str| {
+        provenance
+            .iter()
+            .find(|(path, _)| *path == key || path.starts_with(&format!("{}.", key)))
+            .map(|(_, source)| source.clone())
+    };
+
+    let mut diagnostics = vec![];
+    for (key, replacement) in deprecated_keys {
+        if let Some(source) = source_of(key) {
+            diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::Deprecated(replacement.to_string()),
+                key: key.to_string(),
+                source: Some(source),
+            });
+        }
+    }
+    for key in ignored_keys {
+        if deprecated_keys
+            .iter()
+            .any(|(deprecated, _)| *deprecated == key)
+        {
+            continue;
+        }
+        diagnostics.push(Diagnostic {
+            kind: DiagnosticKind::Unknown,
+            source: source_of(&key),
+            key,
+        });
+    }
+    diagnostics
+}
+
+pub(crate) fn format(diagnostics: &[Diagnostic]) -> String {
+    let mut output = format!("{}\n", msg!("config.problems", diagnostics.len()));
+    for diagnostic in diagnostics {
+        let message = match &diagnostic.kind {
+            DiagnosticKind::Unknown => msg!("config.unknown_key", diagnostic.key),
+            DiagnosticKind::Deprecated(replacement) => {
+                msg!("config.deprecated_key", diagnostic.key, replacement)
+            }
+        };
+        match &diagnostic.source {
+            Some(source) => output.push_str(&format!(
+                " - {}\n",
+                msg!("config.in_source", message, source)
+            )),
+            None => output.push_str(&format!(" - {}\n", message)),
+        }
+    }
+    output
+}
+
+/// Prints the warnings at most once per invocation, however many times the config is loaded.
+pub(crate) fn report(diagnostics: &[Diagnostic]) {
+    if diagnostics.is_empty() || REPORTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    warn!("{}", format(diagnostics).trim_end());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn collect_unknown_and_deprecated_keys() {
+        let project = Source::Project(PathBuf::from("ahc_tools.toml"));
+        let mut provenance = BTreeMap::new();
+        provenance.insert("general.name".to_string(), project.clone());
+        provenance.insert("general.old_key".to_string(), project.clone());
+        provenance.insert("typo.key".to_string(), project.clone());
+
+        let diagnostics = collect(
+            vec!["general.old_key".to_string(), "typo".to_string()],
+            &provenance,
+            &[("general.old_key", "general.new_key")],
+        );
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic {
+                    kind: DiagnosticKind::Deprecated("general.new_key".to_string()),
+                    key: "general.old_key".to_string(),
+                    source: Some(project.clone()),
+                },
+                Diagnostic {
+                    kind: DiagnosticKind::Unknown,
+                    key: "typo".to_string(),
+                    source: Some(project),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn format_lists_every_diagnostic() {
+        let diagnostics = vec![Diagnostic {
+            kind: DiagnosticKind::Unknown,
+            key: "general.nmae".to_string(),
+            source: Some(Source::Env("AHC_GENERAL__NMAE".to_string())),
+        }];
+
+        assert_eq!(
+            format(&diagnostics),
+            "Warning: 1 problem(s) found in configuration:\n \
+             - unknown key `general.nmae` in env (AHC_GENERAL__NMAE)\n"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Settings guessed from the surroundings when there is no project config, so that `ahc download`
+//! works in a fresh clone of a contest repository.
+
+use super::{EffectiveConfig, Lang, Source};
+use crate::error::ErrorKind;
+use crate::init::build_default_problem_url;
+use anyhow::Result;
+use clap::ValueEnum;
+use git2::Repository;
+use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Fills `general.name` and `general.problem_url` from the contest id in the name of the git
+/// remote `origin` or of `dir`, unless another layer already set them.
+pub(crate) fn fill(effective: &mut EffectiveConfig, dir: &Path, file_name: &str) -> Result<()> {
+    let general = effective.table.get("general").and_then(|g| g.as_table());
+    let name = general.and_then(|g| g.get("name")).and_then(|v| v.as_str());
+    let has_url = general.is_some_and(|g| g.contains_key("problem_url"));
+    let (name, from) = match name {
+        Some(name) => (name.to_string(), None),
+        None => {
+            let (name, from) = infer_name(dir).ok_or_else(|| {
+                ErrorKind::Config.error(format!(
+                    "Failed to read config file: {} does not exist, and no contest name could be \
+                     inferred from the git remote or directory name. Run `ahc init` to create it",
+                    file_name
+                ))
+            })?;
+            (name, Some(from))
+        }
+    };
+
+    let mut inferred = toml::Table::new();
+    if from.is_some() {
+        inferred.insert("name".to_string(), toml::Value::String(name.clone()));
+    }
+    if !has_url {
+        let lang = general
+            .and_then(|g| g.get("lang"))
+            .and_then(|v| v.as_str())
+            .and_then(|lang| Lang::from_str(lang, true).ok())
+            .unwrap_or_default();
+        let problem_url = build_default_problem_url(&name, lang)?;
+        inferred.insert("problem_url".to_string(), toml::Value::String(problem_url));
+    }
+    if inferred.is_empty() {
+        return Ok(());
+    }
+    let mut table = toml::Table::new();
+    table.insert("general".to_string(), toml::Value::Table(inferred));
+    let from = from.unwrap_or_else(|| "general.name".to_string());
+    effective.merge(table, &Source::Inferred(from));
+    Ok(())
+}
+
+/// The contest id and where it was found.
+fn infer_name(dir: &Path) -> Option<(String, String)> {
+    let remote = Repository::discover(dir).ok().and_then(|repo| {
+        let remote = repo.find_remote("origin").ok()?;
+        remote.url().map(str::to_string)
+    });
+    if let Some(name) = remote
+        .as_deref()
+        .and_then(repository_name)
+        .and_then(contest_id)
+    {
+        return Some((name, "git remote origin".to_string()));
+    }
+    let dir_name = dir.file_name()?.to_str()?;
+    contest_id(dir_name).map(|name| (name, format!("directory {}", dir_name)))
+}
+
+/// The last path segment of a remote URL such as `git@github.com:user/ahc030.git`.
+fn repository_name(url: &str) -> Option<&str> {
+    let url = url.trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    url.rsplit(['/', ':'])
+        .next()
+        .filter(|name| !name.is_empty())
+}
+
+/// A contest id such as `ahc030` in a name like `AHC030-solutions`.
+fn contest_id(name: &str) -> Option<String> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)(?:^|[^a-z0-9])([a-z]{3}\d{3})(?:$|[^0-9])").expect("valid regex")
+    });
+    pattern
+        .captures(name)
+        .map(|captures| captures[1].to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_contest_ids_in_names() {
+        assert_eq!(contest_id("ahc030"), Some("ahc030".to_string()));
+        assert_eq!(contest_id("AHC030-solutions"), Some("ahc030".to_string()));
+        assert_eq!(contest_id("my_ahc012_a"), Some("ahc012".to_string()));
+        assert_eq!(contest_id("ahc0301"), None);
+        assert_eq!(contest_id("xahc030"), None);
+        assert_eq!(contest_id("solutions"), None);
+    }
+
+    #[test]
+    fn takes_repository_names_from_remote_urls() {
+        assert_eq!(
+            repository_name("git@github.com:user/ahc030.git"),
+            Some("ahc030")
+        );
+        assert_eq!(
+            repository_name("https://github.com/user/AHC031-rust/"),
+            Some("AHC031-rust")
+        );
+    }
+
+    #[test]
+    fn fills_name_and_url_from_the_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().join("ahc040");
+        std::fs::create_dir(&dir).unwrap();
+        let mut effective = EffectiveConfig::default();
+        effective.merge(super::super::default_table(), &Source::Default);
+
+        fill(&mut effective, &dir, "ahc_tools.toml").unwrap();
+        let (config, _) = effective.to_config().unwrap();
+        assert_eq!(config.general.name, "ahc040");
+        assert_eq!(
+            config.general.problem_url,
+            "https://atcoder.jp/contests/ahc040/tasks/ahc040_a?lang=ja"
+        );
+        assert_eq!(
+            effective.provenance.get("general.name"),
+            Some(&Source::Inferred("directory ahc040".to_string()))
+        );
+    }
+}
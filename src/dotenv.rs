@@ -0,0 +1,122 @@
+use crate::error::{ErrorKind, ResultExt};
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub(crate) const DOTENV_FILE_NAME: &str = ".env";
+
+static LOADED_KEYS: OnceLock<Vec<String>> = OnceLock::new();
+
+pub(crate) fn dotenv_path(config_file_name: &str) -> PathBuf {
+    Path::new(config_file_name)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(DOTENV_FILE_NAME)
+}
+
+/// Loads variables from the `.env` file into the process environment.
+/// Variables which are already set take precedence over the file.
+pub(crate) fn load(path: &Path) -> Result<()> {
+    let mut loaded = vec![];
+    if path.exists() {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read env file: {}", path.display()))
+            .kind(ErrorKind::Config)?;
+        for (key, value) in parse(&content).kind(ErrorKind::Config)? {
+            if std::env::var_os(&key).is_none() {
+                std::env::set_var(&key, value);
+                loaded.push(key);
+            }
+        }
+    }
+    let _ = LOADED_KEYS.set(loaded);
+    Ok(())
+}
+
+pub(crate) fn is_loaded_from_dotenv(key: &str) -> bool {
+    LOADED_KEYS
+        .get()
+        .is_some_and(|keys| keys.iter().any(|k| k == key))
+}
+
+fn parse(content: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid line {} in env file: {}", i + 1, line))?;
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(anyhow!(
+                "Invalid key on line {} in env file: {}",
+                i + 1,
+                key
+            ));
+        }
+        vars.push((key.to_string(), unquote(value.trim())));
+    }
+    Ok(vars)
+}
+
+fn unquote(value: &str) -> String {
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return value[1..value.len() - 1].to_string();
+        }
+    }
+    match value.split_once(" #") {
+        Some((value, _comment)) => value.trim_end().to_string(),
+        None => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_env_file() {
+        let content = r#"
+# comment
+AHC_GENERAL__NAME=ahc001
+export AHC_SESSION="secret value"
+SINGLE='quoted # not a comment'
+TRAILING=value # comment
+EMPTY=
+"#;
+
+        let vars = parse(content).unwrap();
+
+        assert_eq!(
+            vars,
+            vec![
+                ("AHC_GENERAL__NAME".to_string(), "ahc001".to_string()),
+                ("AHC_SESSION".to_string(), "secret value".to_string()),
+                ("SINGLE".to_string(), "quoted # not a comment".to_string()),
+                ("TRAILING".to_string(), "value".to_string()),
+                ("EMPTY".to_string(), "".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_rejects_invalid_lines() {
+        let result = parse("NOT A VARIABLE");
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("line 1"));
+    }
+
+    #[test]
+    fn dotenv_path_is_next_to_config_file() {
+        assert_eq!(dotenv_path("ahc_tools.toml"), PathBuf::from(".env"));
+        assert_eq!(
+            dotenv_path("contest/ahc_tools.toml"),
+            PathBuf::from("contest/.env")
+        );
+    }
+}
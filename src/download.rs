@@ -1,116 +1,1392 @@
-use crate::Config;
+//! Downloading the local tools of a problem.
+
+mod cache;
+mod filter;
+mod links;
+mod manifest;
+mod validators;
+mod webvis;
+
+use crate::config::{Config, Lang};
+use crate::error::{ErrorKind, ResultExt};
+use crate::http;
+use crate::journal::{Journal, Operation};
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::runner;
+use crate::session;
+use crate::sha256;
+use crate::tar;
 use anyhow::{anyhow, Context, Result};
 use bytes::Bytes;
+use cache::Partial;
 use clap::Args;
-use std::io::Cursor;
+pub use filter::PathFilter;
+use links::ArchiveLink;
+use manifest::{Changes, Manifest, OtherArchive, MANIFEST_PATH};
+use regex::Regex;
+use reqwest::header::{HeaderMap, CONTENT_RANGE, IF_RANGE, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Cursor, IsTerminal, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, info, trace, warn};
+use url::Url;
+use validators::Validators;
 use zip::ZipArchive;
 
+const ARCHIVE_TOOLS_DIR: &str = "tools";
+/// File of the seeds `gen` makes inputs of, in the tools directory
+const SEEDS_FILE: &str = "seeds.txt";
+/// Directory of the tools the web visualizer is saved in
+const WEBVIS_DIR: &str = "webvis";
+
 #[derive(Args)]
 pub(crate) struct DownloadArgs {
+    /// Directory to extract the whole archive into, overriding [download] output_dir
     #[arg(short, long)]
     output_path: Option<String>,
     #[arg(short, long)]
     url: Option<String>,
     #[arg(short, long)]
     zip_url: Option<String>,
+    /// Number of times a failed request is tried again, overriding [download] retries
+    #[arg(long)]
+    retries: Option<u32>,
+    /// Check the tools against the checksums recorded when they were downloaded, without
+    /// downloading anything
+    #[arg(long, conflicts_with_all = ["output_path", "url", "zip_url"])]
+    verify: bool,
+    /// Extract only the files matching this glob, e.g. `in/**`, overriding [download] only. May
+    /// be given more than once
+    #[arg(long, value_name = "GLOB")]
+    only: Vec<String>,
+    /// Drop this many leading directories from the paths in the archive, overriding [download]
+    /// strip_components
+    #[arg(long, value_name = "N")]
+    strip_components: Option<usize>,
+    /// Overwrite files edited since they were extracted, or not from the archive
+    #[arg(long, conflicts_with = "skip_existing")]
+    force: bool,
+    /// Keep files edited since they were extracted, or not from the archive, and extract the
+    /// others
+    #[arg(long)]
+    skip_existing: bool,
+    /// Build the tools after extracting them, only the binaries given if any, e.g.
+    /// `--build gen,vis`
+    #[arg(long, value_name = "BIN", num_args = 0.., value_delimiter = ',')]
+    build: Option<Vec<String>>,
+    /// Make the inputs of seeds 0 to COUNT - 1 with `gen` of the tools after extracting them,
+    /// overriding [download] gen
+    #[arg(long, value_name = "COUNT")]
+    gen: Option<u64>,
+    /// Save the web visualizer linked from the problem page into the `webvis` directory of the
+    /// tools
+    #[arg(long, conflicts_with = "zip_url")]
+    webvis: bool,
+    /// Print the files of the archive and their sizes without extracting anything
+    #[arg(long, conflicts_with = "verify")]
+    list: bool,
+    /// Download the tools of the N-th link found, counted from 1, instead of all the archives
+    /// the page links
+    #[arg(long, value_name = "N", conflicts_with = "zip_url")]
+    index: Option<usize>,
+    /// Only consider the links matching this regular expression
+    #[arg(long = "match", value_name = "PATTERN", conflicts_with = "zip_url")]
+    pattern: Option<Regex>,
+    /// Extract the archive kept in .ahc/cache by an earlier download, of --zip-url if given, else
+    /// the latest, without connecting
+    #[arg(long, conflicts_with_all = ["url", "webvis", "index", "pattern", "verify"])]
+    offline: bool,
 }
 
-pub(crate) fn download(args: DownloadArgs, config: Config) -> Result<()> {
-    let zip_url = if let Some(zip_url) = args.zip_url {
-        zip_url
-    } else {
-        let url = if let Some(url) = args.url {
-            url
+/// `[download]`, how `ahc download` fetches the tools.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct DownloadConfig {
+    /// Number of times a request failing in a way which may pass, such as a server error right
+    /// after the contest starts, is tried again
+    #[serde(default = "default_retries")]
+    pub(crate) retries: u32,
+    /// Delay before the first retry, doubling with each one after it
+    #[serde(default = "default_retry_backoff_ms")]
+    pub(crate) retry_backoff_ms: u64,
+    /// Texts of the link to the tools, tried in order until one is found on the page. By default
+    /// that of the language of the page, then that of the other
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) link_texts: Vec<String>,
+    /// CSS selector of the link to the tools, for pages where it has no usual text. The texts
+    /// are then only checked if `link_texts` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) link_selector: Option<String>,
+    /// Directory the archive is extracted into instead of the project directory. The paths of
+    /// the tools, inputs and outputs left unset follow it, e.g. `<output_dir>/tools/in`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) output_dir: Option<PathBuf>,
+    /// Number of leading directories dropped from the paths in the archive, e.g. 1 for archives
+    /// nesting everything under a directory named after the contest
+    #[serde(default)]
+    pub(crate) strip_components: usize,
+    /// Build the tools with `cargo build --release` after extracting them
+    #[serde(default)]
+    pub(crate) build: bool,
+    /// Binaries of the tools to build, e.g. `["gen", "vis"]`, all of them if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) build_bins: Vec<String>,
+    /// Number of inputs to make with `gen` after extracting the tools, from seed 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) gen: Option<u64>,
+    /// Save the web visualizer linked from the problem page for use offline
+    #[serde(default)]
+    pub(crate) webvis: bool,
+    /// Globs of the files to extract, e.g. `["in/**"]`, all of them if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) only: Vec<String>,
+    /// Largest size in MiB of a file of the archive once decompressed
+    #[serde(default = "default_max_file_mb")]
+    pub(crate) max_file_mb: u64,
+    /// Largest size in MiB of all the files of the archive once decompressed
+    #[serde(default = "default_max_total_mb")]
+    pub(crate) max_total_mb: u64,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        DownloadConfig {
+            retries: default_retries(),
+            retry_backoff_ms: default_retry_backoff_ms(),
+            link_texts: vec![],
+            link_selector: None,
+            output_dir: None,
+            strip_components: 0,
+            build: false,
+            build_bins: vec![],
+            gen: None,
+            webvis: false,
+            only: vec![],
+            max_file_mb: default_max_file_mb(),
+            max_total_mb: default_max_total_mb(),
+        }
+    }
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_max_file_mb() -> u64 {
+    SizeLimits::default().max_file_size >> 20
+}
+
+fn default_max_total_mb() -> u64 {
+    SizeLimits::default().max_total_size >> 20
+}
+
+impl DownloadConfig {
+    pub(crate) fn retry(&self) -> http::Retry {
+        http::Retry {
+            retries: self.retries,
+            backoff: Duration::from_millis(self.retry_backoff_ms),
+        }
+    }
+
+    fn size_limits(&self) -> SizeLimits {
+        SizeLimits {
+            max_file_size: self.max_file_mb << 20,
+            max_total_size: self.max_total_mb << 20,
+        }
+    }
+
+    fn link_query(&self, lang: Lang) -> LinkQuery {
+        let texts = if !self.link_texts.is_empty() {
+            self.link_texts.clone()
+        } else if self.link_selector.is_some() {
+            vec![]
         } else {
-            config.general.problem_url
+            let other = if lang == Lang::Ja { Lang::En } else { Lang::Ja };
+            vec![
+                lang.tool_link_text().to_string(),
+                other.tool_link_text().to_string(),
+            ]
         };
+        LinkQuery {
+            selector: self.link_selector.clone().unwrap_or("a".to_string()),
+            texts,
+        }
+    }
+}
+
+/// How the links to the tools are told from the others on a problem page: elements matching
+/// `selector` with one of `texts`, or any of them without texts.
+#[derive(Debug, Clone, PartialEq)]
+struct LinkQuery {
+    selector: String,
+    texts: Vec<String>,
+}
+
+/// What `ahc download --output json` prints.
+#[derive(Serialize, Debug)]
+struct DownloadSummary {
+    /// Problem page the links were looked for on, unless `--zip-url` was given
+    problem_url: Option<String>,
+    links: Vec<String>,
+    zip_url: String,
+    files: Vec<PathBuf>,
+    /// The archive was the one downloaded last, and was not extracted again
+    unchanged: bool,
+    /// SHA-256 of the archive, unless it was unchanged
+    sha256: Option<String>,
+    /// Binaries of the tools built after extracting them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    built: Vec<PathBuf>,
+    /// Number of inputs made with `gen`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generated: Option<u64>,
+    /// Files of the web visualizer saved
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    webvis: Vec<PathBuf>,
+    /// The other archives of the problem page, e.g. a tester, extracted next to the tools
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    others: Vec<OtherArchive>,
+}
+
+/// What `ahc download --list --output json` prints.
+#[derive(Serialize, Debug)]
+struct ListSummary {
+    zip_url: String,
+    entries: Vec<ArchiveEntry>,
+    /// Size of the files once extracted
+    total_size: u64,
+}
+
+/// A file or directory in an archive.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ArchiveEntry {
+    pub path: PathBuf,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+/// What `ahc download --verify --output json` prints.
+#[derive(Serialize, Debug)]
+struct VerifySummary {
+    zip_url: String,
+    sha256: String,
+    files: usize,
+    #[serde(flatten)]
+    changes: Changes,
+}
 
-        let html = fetch_html(&url)?;
-        find_tool_url(&html)?
+/// The archives `ahc download` fetches: those a problem page links, or the one given.
+#[derive(Debug, Default)]
+struct ProblemLinks {
+    /// Problem page the links were looked for on, unless the archive was given
+    problem_url: Option<String>,
+    /// Links to tools found on the page
+    links: Vec<String>,
+    /// Archive of the tools
+    zip_url: String,
+    /// The other archives of the page, extracted next to the tools
+    others: Vec<ArchiveLink>,
+    /// Web visualizer to save, unless it is one of `others`
+    webvis_url: Option<String>,
+}
+
+pub(crate) fn download(args: DownloadArgs, config: Config, output: Output) -> Result<()> {
+    if args.verify {
+        return verify(output);
+    }
+    let download_config = download_config(&args, &config);
+    let retry = download_config.retry();
+    let previous = Manifest::read(Path::new(MANIFEST_PATH))?;
+    let options = extract_options(&args, &download_config, previous.as_ref())?;
+    let links = problem_links(&args, &config, &download_config)?;
+    if args.list {
+        return list(&links.zip_url, retry, args.offline, &options, output);
+    }
+
+    // The validators of the archive are kept where it is extracted to
+    let target_dir = args
+        .output_path
+        .as_deref()
+        .map_or(config.paths.tools_dir.clone(), PathBuf::from);
+    let tools_dir = match args.output_path.as_deref() {
+        Some(output_path) => Path::new(output_path).join(ARCHIVE_TOOLS_DIR),
+        None => config.paths.tools_dir.clone(),
     };
+    let previous_others = previous.map(|manifest| manifest.others).unwrap_or_default();
+    let Some((cursor, validators)) = fetch_tools(&links.zip_url, &target_dir, retry, args.offline)?
+    else {
+        info!("{}", msg!("download.unchanged", target_dir.display()));
+        let others = extract_others(&links.others, &tools_dir, retry, &options, &previous_others)?;
+        if others != previous_others {
+            if let Some(mut manifest) = Manifest::read(Path::new(MANIFEST_PATH))? {
+                manifest.others = others.clone();
+                manifest.write(Path::new(MANIFEST_PATH))?;
+            }
+        }
+        if output.is_json() {
+            print_json(&DownloadSummary {
+                problem_url: links.problem_url,
+                links: links.links,
+                zip_url: links.zip_url,
+                files: vec![],
+                unchanged: true,
+                sha256: None,
+                built: vec![],
+                generated: None,
+                webvis: vec![],
+                others,
+            })?;
+        }
+        return Ok(());
+    };
+    let archive = cursor.get_ref().clone();
+    let output_dir = match (&args.output_path, &download_config.output_dir) {
+        (Some(output_path), _) => Some(PathBuf::from(output_path)),
+        (None, output_dir) => output_dir.clone(),
+    };
+    let files = extract_tools(
+        cursor,
+        &links.zip_url,
+        output_dir.as_deref(),
+        // The whole archive goes into --output-path, not just the tools
+        args.output_path
+            .is_none()
+            .then_some(config.paths.tools_dir.as_path()),
+        &options,
+    )?;
+    let mut manifest = Manifest::new(&links.zip_url, &archive, &files)?;
+    manifest.others = extract_others(&links.others, &tools_dir, retry, &options, &previous_others)?;
+    manifest.write(Path::new(MANIFEST_PATH))?;
+    info!("{}", msg!("download.manifest", files.len(), MANIFEST_PATH));
+    // Only a full extraction may be skipped next time
+    match validators {
+        Some(validators) if options.only.is_empty() => validators::write(&target_dir, &validators)?,
+        _ => validators::remove(&target_dir),
+    }
+
+    let built = if download_config.build {
+        info!("{}", msg!("download.building", tools_dir.display()));
+        runner::build_tools(&tools_dir, &download_config.build_bins)?
+    } else {
+        vec![]
+    };
+    if let Some(count) = download_config.gen {
+        generate_inputs(&tools_dir, count)?;
+    }
+    let webvis = match &links.webvis_url {
+        Some(webvis_url) => {
+            let dir = tools_dir.join(WEBVIS_DIR);
+            let files = webvis::save(webvis_url, &dir, retry)?;
+            info!("{}", msg!("download.webvis", webvis_url, dir.display()));
+            files
+        }
+        None => vec![],
+    };
+
+    Journal::open().append(Operation::Download {
+        zip_url: links.zip_url.clone(),
+        files: files.len(),
+    })?;
+
+    if output.is_json() {
+        print_json(&DownloadSummary {
+            problem_url: links.problem_url,
+            links: links.links,
+            zip_url: links.zip_url,
+            files,
+            unchanged: false,
+            sha256: Some(manifest.sha256),
+            built,
+            generated: download_config.gen,
+            webvis,
+            others: manifest.others,
+        })?;
+    }
+    Ok(())
+}
+
+/// `[download]` with the options of the command line applied.
+fn download_config(args: &DownloadArgs, config: &Config) -> DownloadConfig {
+    let mut download_config = config.download.clone().unwrap_or_default();
+    if let Some(retries) = args.retries {
+        download_config.retries = retries;
+    }
+    if !args.only.is_empty() {
+        download_config.only = args.only.clone();
+    }
+    if let Some(bins) = &args.build {
+        download_config.build = true;
+        download_config.build_bins = bins.clone();
+    }
+    if args.webvis {
+        download_config.webvis = true;
+    }
+    if args.gen.is_some() {
+        download_config.gen = args.gen;
+    }
+    if let Some(strip_components) = args.strip_components {
+        download_config.strip_components = strip_components;
+    }
+    download_config
+}
 
-    let cursor = fetch_zip(&zip_url)?;
-    let output_path = args.output_path.as_deref().unwrap_or(".");
+/// How the archives are extracted, sparing the files as the `previous` download left them.
+fn extract_options(
+    args: &DownloadArgs,
+    download_config: &DownloadConfig,
+    previous: Option<&Manifest>,
+) -> Result<ExtractOptions> {
+    let existing = match (args.force, args.skip_existing) {
+        (true, _) => Existing::Overwrite,
+        (_, true) => Existing::Skip,
+        _ => Existing::Refuse,
+    };
+    let pristine = previous
+        .iter()
+        .flat_map(|manifest| manifest.all_files())
+        .map(|file| (file.path.clone(), file.sha256.clone()))
+        .collect();
+    Ok(ExtractOptions {
+        only: PathFilter::new(&download_config.only).kind(ErrorKind::Config)?,
+        strip_components: download_config.strip_components,
+        existing,
+        pristine,
+        limits: download_config.size_limits(),
+    })
+}
+
+/// The archives to download: the one of `--zip-url`, or the latest cached when offline, else
+/// those the problem page links.
+fn problem_links(
+    args: &DownloadArgs,
+    config: &Config,
+    download_config: &DownloadConfig,
+) -> Result<ProblemLinks> {
+    if args.offline {
+        let zip_url = match &args.zip_url {
+            Some(zip_url) => zip_url.clone(),
+            None => cache::latest(Path::new(cache::CACHE_DIR))?
+                .ok_or_else(|| anyhow!(msg!("download.nothing_cached", cache::CACHE_DIR)))?,
+        };
+        return Ok(ProblemLinks {
+            zip_url,
+            ..ProblemLinks::default()
+        });
+    }
+    if let Some(zip_url) = &args.zip_url {
+        debug!("Using the archive given by --zip-url");
+        return Ok(ProblemLinks {
+            zip_url: zip_url.clone(),
+            ..ProblemLinks::default()
+        });
+    }
+
+    let url = args.url.as_ref().unwrap_or(&config.general.problem_url);
+    let url = localize_url(url, config.general.lang);
+    let query = download_config.link_query(config.general.lang);
+    debug!("Fetching problem page {}", url);
+    let html = fetch_html(&url, download_config.retry())?;
+    let links = tool_links(&url, &html, &query)?;
+    // Only the archive chosen by --index or --match is downloaded
+    let (zip_url, others) = if args.index.is_some() || args.pattern.is_some() {
+        let zip_url = choose_tool_url(&links, args.index, args.pattern.as_ref())?;
+        (zip_url, vec![])
+    } else if links.is_empty() {
+        (single_tool_url(&links)?, vec![])
+    } else {
+        links::split(&url, &html, &links)?
+    };
+    // A visualizer published as an archive is extracted with the others instead
+    let webvis_url = match download_config.webvis {
+        true => webvis_link(&url, &html)?
+            .filter(|webvis_url| !others.iter().any(|other| &other.url == webvis_url)),
+        false => None,
+    };
+    Ok(ProblemLinks {
+        problem_url: Some(url),
+        links,
+        zip_url,
+        others,
+        webvis_url,
+    })
+}
+
+/// Prints the files of the archive at `zip_url` which `options` would extract, for
+/// `ahc download --list`.
+fn list(
+    zip_url: &str,
+    retry: http::Retry,
+    offline: bool,
+    options: &ExtractOptions,
+    output: Output,
+) -> Result<()> {
+    // The contents are needed even if the archive did not change
+    let (cursor, _) = load_archive(zip_url, retry, None, offline)?
+        .ok_or_else(|| anyhow!("No archive at {}", zip_url))?;
+    let kind = ArchiveKind::detect(zip_url, cursor.get_ref());
+    let entries = list_archive(cursor, kind, &options.limits)?
+        .into_iter()
+        .filter_map(|entry| {
+            let path = strip_components(&entry.path, options.strip_components)?;
+            options
+                .only
+                .matches(&path)
+                .then_some(ArchiveEntry { path, ..entry })
+        })
+        .collect::<Vec<_>>();
+    let total_size = entries.iter().map(|entry| entry.size).sum();
+    if output.is_json() {
+        print_json(&ListSummary {
+            zip_url: zip_url.to_string(),
+            entries,
+            total_size,
+        })?;
+    } else {
+        print!("{}", format_listing(&entries));
+    }
+    Ok(())
+}
+
+/// The archive of the tools at `zip_url`, or `None` if it did not change since it was extracted
+/// into `target_dir`. Without the archive in the cache, it is downloaded even if unchanged to
+/// keep it there.
+fn fetch_tools(
+    zip_url: &str,
+    target_dir: &Path,
+    retry: http::Retry,
+    offline: bool,
+) -> Result<Option<(Cursor<Bytes>, Option<Validators>)>> {
+    let cached = validators::read(target_dir, zip_url).filter(|_| {
+        matches!(
+            cache::get(Path::new(cache::CACHE_DIR), zip_url),
+            Ok(Some(_))
+        )
+    });
+    load_archive(zip_url, retry, cached.as_ref(), offline)
+}
+
+/// Extracts the archive of the tools at `zip_url` into `output_dir`, the current directory if
+/// `None`, its tools into `tools_dir` if given. Returns the files extracted.
+fn extract_tools(
+    cursor: Cursor<Bytes>,
+    zip_url: &str,
+    output_dir: Option<&Path>,
+    tools_dir: Option<&Path>,
+    options: &ExtractOptions,
+) -> Result<Vec<PathBuf>> {
+    let kind = ArchiveKind::detect(zip_url, cursor.get_ref());
+    let output_dir = output_dir.map_or(".".into(), Path::to_string_lossy);
+    extract_archive(cursor, kind, &output_dir, tools_dir, options)
+}
+
+/// Extracts `others`, the archives of the problem page besides the tools, each into its directory
+/// in `tools_dir`. Those unchanged since the last download are skipped, their records in
+/// `previous` kept.
+fn extract_others(
+    others: &[ArchiveLink],
+    tools_dir: &Path,
+    retry: http::Retry,
+    options: &ExtractOptions,
+    previous: &[OtherArchive],
+) -> Result<Vec<OtherArchive>> {
+    // --only and --strip-components are about the tools
+    let options = ExtractOptions {
+        only: PathFilter::default(),
+        strip_components: 0,
+        ..options.clone()
+    };
+    let mut extracted = vec![];
+    for other in others {
+        let dir = tools_dir.join(&other.dir);
+        let previous = previous
+            .iter()
+            .find(|previous| previous.zip_url == other.url);
+        let cached = validators::read(&dir, &other.url).filter(|_| {
+            previous.is_some()
+                && matches!(
+                    cache::get(Path::new(cache::CACHE_DIR), &other.url),
+                    Ok(Some(_))
+                )
+        });
+        let Some((cursor, validators)) = load_archive(&other.url, retry, cached.as_ref(), false)?
+        else {
+            info!("{}", msg!("download.unchanged", dir.display()));
+            extracted.extend(previous.cloned());
+            continue;
+        };
+        info!("{}", msg!("download.other", other.url, dir.display()));
+        let archive = cursor.get_ref().clone();
+        let kind = ArchiveKind::detect(&other.url, &archive);
+        let files = extract_archive(cursor, kind, &dir.to_string_lossy(), None, &options)?;
+        match validators {
+            Some(validators) => validators::write(&dir, &validators)?,
+            None => validators::remove(&dir),
+        }
+        extracted.push(OtherArchive::new(&other.url, other.role, &archive, &files)?);
+    }
+    Ok(extracted)
+}
 
-    unzip_file(cursor, output_path)?;
+/// Writes the seeds 0 to `count - 1` to `seeds.txt` of the tools and makes their inputs in the
+/// `in` directory of the tools with `gen`, building it first if needed.
+fn generate_inputs(tools_dir: &Path, count: u64) -> Result<()> {
+    let seeds = (0..count)
+        .map(|seed| format!("{}\n", seed))
+        .collect::<String>();
+    let seeds_path = tools_dir.join(SEEDS_FILE);
+    std::fs::write(&seeds_path, seeds)
+        .context(format!("Failed to write file: {}", seeds_path.display()))?;
+    let gen = runner::tool(tools_dir, "gen")?;
+    info!(
+        "{}",
+        msg!("download.generating", count, tools_dir.join("in").display())
+    );
+    let gen = std::path::absolute(&gen)?;
+    let status = std::process::Command::new(&gen)
+        .arg(SEEDS_FILE)
+        .current_dir(tools_dir)
+        .status()
+        .context(format!("Failed to run {}", gen.display()))?;
+    if !status.success() {
+        return Err(anyhow!("{} exited with {}", gen.display(), status));
+    }
+    Ok(())
+}
 
+/// Checks the files of the last download against their checksums, failing if any changed.
+fn verify(output: Output) -> Result<()> {
+    let manifest = Manifest::read(Path::new(MANIFEST_PATH))?
+        .ok_or_else(|| anyhow!(msg!("download.no_manifest", MANIFEST_PATH)))?;
+    let changes = manifest.verify();
+    for path in &changes.modified {
+        warn!("{}", msg!("download.modified", path.display()));
+    }
+    for path in &changes.missing {
+        warn!("{}", msg!("download.missing", path.display()));
+    }
+    let changed = changes.modified.len() + changes.missing.len();
+    if output.is_json() {
+        print_json(&VerifySummary {
+            zip_url: manifest.zip_url.clone(),
+            sha256: manifest.sha256.clone(),
+            files: manifest.all_files().count(),
+            changes,
+        })?;
+    }
+    if changed > 0 {
+        return Err(anyhow!(msg!("download.changed", changed)));
+    }
+    info!(
+        "{}",
+        msg!(
+            "download.verified",
+            manifest.all_files().count(),
+            manifest.zip_url
+        )
+    );
     Ok(())
 }
 
-fn fetch_html(url: &String) -> Result<String> {
-    let html = reqwest::blocking::get(url)
-        .context(format!("Failed to fetch HTML from URL: {}", url))?
-        .text()
-        .context("Failed to get HTML text")?;
-    Ok(html)
+/// Finds the URL of the tools archive on the problem page at `problem_url`.
+pub fn fetch_tool_url(problem_url: &str, lang: Lang) -> Result<String> {
+    let config = DownloadConfig::default();
+    let url = localize_url(problem_url, lang);
+    let links = fetch_tool_links(&url, &config.link_query(lang), config.retry())?;
+    single_tool_url(&links)
 }
 
-fn find_tool_url(html: &str) -> Result<String> {
+/// Finds the links to the tools on the problem page at `url`, made absolute.
+fn fetch_tool_links(url: &str, query: &LinkQuery, retry: http::Retry) -> Result<Vec<String>> {
+    debug!("Fetching problem page {}", url);
+    let html = fetch_html(url, retry)?;
+    tool_links(url, &html, query)
+}
+
+/// Finds the links to the tools in `html` of the problem page at `url`, made absolute.
+fn tool_links(url: &str, html: &str, query: &LinkQuery) -> Result<Vec<String>> {
+    let links = find_links(html, query)?
+        .iter()
+        .map(|href| resolve_link(url, href))
+        .collect::<Result<Vec<_>>>()?;
+    log_tool_links(&links);
+    Ok(links)
+}
+
+/// The web visualizer linked from `html` of the problem page at `url`, if any.
+fn webvis_link(url: &str, html: &str) -> Result<Option<String>> {
+    let query = LinkQuery {
+        selector: "a".to_string(),
+        texts: webvis::LINK_TEXTS.map(String::from).to_vec(),
+    };
+    match find_links(html, &query)?.first() {
+        Some(href) => Ok(Some(resolve_link(url, href)?)),
+        None => {
+            warn!("{}", msg!("download.no_webvis", url));
+            Ok(None)
+        }
+    }
+}
+
+/// The URL a link `href` on the page at `page_url` points to.
+fn resolve_link(page_url: &str, href: &str) -> Result<String> {
+    let base = Url::parse(page_url).context(format!("Invalid problem URL: {}", page_url))?;
+    let url = base
+        .join(href)
+        .context(format!("Invalid tool link {} on {}", href, page_url))?;
+    Ok(url.into())
+}
+
+pub(crate) fn fetch_html(url: &str, retry: http::Retry) -> Result<String> {
+    http::retry(retry, url, || {
+        http::block_on(async {
+            let request = session::authenticate(http::client().get(url), url);
+            let response = request.send().await?;
+            // AtCoder redirects pages needing a login to the login page
+            if response.url().path() == "/login" && response.url().as_str() != url {
+                warn!("{}", msg!("download.login_required", url));
+            }
+            response.error_for_status()?.text().await
+        })
+    })
+    .context(format!("Failed to fetch HTML from URL: {}", url))
+    .kind(ErrorKind::Network)
+}
+
+/// Sets the language of an AtCoder page URL. Only AtCoder pages are switched between
+/// languages; other URLs are returned as is.
+pub fn localize_url(url: &str, lang: Lang) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    if parsed.host_str() != Some("atcoder.jp") {
+        return url.to_string();
+    }
+
+    let pairs = parsed
+        .query_pairs()
+        .filter(|(key, _)| key != "lang")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+    parsed
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("lang", lang.as_str());
+    parsed.into()
+}
+
+/// Finds the link to the local tools in a problem page, which must have exactly one.
+pub fn find_tool_url(html: &str, lang: Lang) -> Result<String> {
+    single_tool_url(&find_tool_links(html, lang)?)
+}
+
+/// Finds every link to the local tools in a problem page.
+pub fn find_tool_links(html: &str, lang: Lang) -> Result<Vec<String>> {
+    let links = find_links(html, &DownloadConfig::default().link_query(lang))?;
+    log_tool_links(&links);
+    Ok(links)
+}
+
+fn find_links(html: &str, query: &LinkQuery) -> Result<Vec<String>> {
     let document = scraper::Html::parse_document(html);
-    let selector =
-        scraper::Selector::parse("a").map_err(|_| anyhow!("Failed to parse selector: a"))?;
+    let selector = scraper::Selector::parse(&query.selector).map_err(|_| {
+        ErrorKind::Config.error(format!("Failed to parse selector: {}", query.selector))
+    })?;
+    let links = document
+        .select(&selector)
+        .map(|element| {
+            let text = element.text().collect::<String>();
+            (text.trim().to_string(), element.value().attr("href"))
+        })
+        .collect::<Vec<_>>();
+    // Without texts, every link the selector matches is one
+    let texts = match query.texts.is_empty() {
+        true => vec![None],
+        false => query.texts.iter().map(|text| Some(text.as_str())).collect(),
+    };
     let mut tools = vec![];
-    for element in document.select(&selector) {
-        if element.text().any(|text| text.contains("ローカル版")) {
-            if let Some(href) = element.value().attr("href") {
-                tools.push(href);
+    for link_text in texts {
+        for (text, href) in &links {
+            if link_text.is_some_and(|link_text| !text.contains(link_text)) {
+                trace!("Skipping link {:?} to {:?}", text, href);
+                continue;
+            }
+            match href {
+                Some(href) => {
+                    let by = link_text.unwrap_or(&query.selector);
+                    debug!("Link {:?} to {} matches {:?}", text, href, by);
+                    tools.push(href.to_string());
+                }
+                None => debug!("Link {:?} matches but has no href", text),
             }
         }
+        if !tools.is_empty() {
+            break;
+        }
     }
 
-    eprintln!("Found {} tool links:", tools.len());
-    for tool in &tools {
-        eprintln!(" - {}", tool);
+    Ok(tools)
+}
+
+fn log_tool_links(links: &[String]) {
+    info!("{}", msg!("download.links", links.len()));
+    for link in links {
+        info!(" - {}", link);
     }
+}
 
-    if tools.len() != 1 {
-        return Err(anyhow!("Found {} tool links, expected 1", tools.len()));
+/// The link to download the tools of: the one `index` says among those matching `pattern`, the
+/// only one, or the one the user chooses when several are left and stdin is a terminal.
+fn choose_tool_url(
+    links: &[String],
+    index: Option<usize>,
+    pattern: Option<&Regex>,
+) -> Result<String> {
+    let links = links
+        .iter()
+        .filter(|link| pattern.is_none_or(|pattern| pattern.is_match(link)))
+        .cloned()
+        .collect::<Vec<_>>();
+    if let Some(pattern) = pattern {
+        debug!("{} links match {}", links.len(), pattern);
     }
-    Ok(tools[0].into())
+    if let Some(index) = index {
+        return index
+            .checked_sub(1)
+            .and_then(|i| links.get(i))
+            .cloned()
+            .ok_or_else(|| anyhow!(msg!("download.bad_index", index, links.len())));
+    }
+    if links.len() > 1 && std::io::stdin().is_terminal() {
+        return prompt_tool_url(&links);
+    }
+    single_tool_url(&links)
 }
 
-fn fetch_zip(zip_url: &String) -> Result<Cursor<Bytes>> {
-    eprintln!("Downloading tools from: {}", zip_url);
-    let zip_bytes = reqwest::blocking::get(zip_url)
-        .context(format!("Failed to fetch zip file from URL: {}", zip_url))?
-        .bytes()?;
-    let cursor = Cursor::new(zip_bytes);
+/// Asks on stderr which of `links` to download, keeping JSON output clean.
+fn prompt_tool_url(links: &[String]) -> Result<String> {
+    for (i, link) in links.iter().enumerate() {
+        eprintln!("  [{}] {}", i + 1, link);
+    }
+    loop {
+        eprint!("{}", msg!("download.choose", links.len()));
+        std::io::stderr().flush()?;
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input)? == 0 {
+            return Err(anyhow!(msg!("download.not_one_link", links.len())));
+        }
+        if let Ok(i) = input.trim().parse::<usize>() {
+            if (1..=links.len()).contains(&i) {
+                return Ok(links[i - 1].clone());
+            }
+        }
+    }
+}
+
+fn single_tool_url(links: &[String]) -> Result<String> {
+    if links.len() != 1 {
+        return Err(anyhow!(msg!("download.not_one_link", links.len())));
+    }
+    Ok(links[0].clone())
+}
+
+/// Downloads the tools archive.
+pub fn fetch_zip(zip_url: &str) -> Result<Cursor<Bytes>> {
+    let retry = DownloadConfig::default().retry();
+    let fetched = fetch_archive(zip_url, retry, None, Path::new(cache::CACHE_DIR))?;
+    let (cursor, _) = fetched.ok_or_else(|| anyhow!("No archive at {}", zip_url))?;
     Ok(cursor)
 }
 
-fn unzip_file<R>(data: R, output_path: &str) -> Result<()>
-where
-    R: std::io::Read + std::io::Seek,
-{
-    eprintln!("Unzipping tools to: {}", output_path);
-    // unzip file
+/// Downloads the tools archive with its validators, or `None` if it is still the one `cached`
+/// describes. A download cut short is resumed with a `Range` request, by the next attempt or
+/// from what is kept in `partial_dir` by the next run, as long as the server tells the archive
+/// did not change in between.
+fn fetch_archive(
+    zip_url: &str,
+    retry: http::Retry,
+    cached: Option<&Validators>,
+    partial_dir: &Path,
+) -> Result<Option<(Cursor<Bytes>, Option<Validators>)>> {
+    info!("{}", msg!("download.fetching", zip_url));
+    let mut partial = cache::read_partial(partial_dir, zip_url).unwrap_or_default();
+    // Size of the whole archive, as the server gave it
+    let mut total = None;
+    let fetched = http::retry(retry, zip_url, || {
+        http::block_on(async {
+            let mut request = session::authenticate(http::client().get(zip_url), zip_url);
+            if let Some(cached) = cached {
+                request = cached.condition(request);
+            }
+            let if_range = partial.validators.as_ref().and_then(Validators::if_range);
+            if let (false, Some(if_range)) = (partial.body.is_empty(), if_range) {
+                info!("{}", msg!("download.resuming", partial.body.len()));
+                request = request
+                    .header(RANGE, format!("bytes={}-", partial.body.len()))
+                    .header(IF_RANGE, if_range);
+            }
+            let response = request.send().await?;
+            if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+                return Ok(None);
+            }
+            let mut response = response.error_for_status()?;
+            let validators = Validators::from_headers(zip_url, response.headers());
+            match content_range(response.headers()) {
+                Some((start, range_total)) if response.status() == StatusCode::PARTIAL_CONTENT => {
+                    partial.body.truncate(start as usize);
+                    total = range_total;
+                }
+                _ => {
+                    // The server sent the whole archive again
+                    partial = Partial {
+                        body: vec![],
+                        validators: validators.clone(),
+                    };
+                    total = response.content_length();
+                }
+            }
+            while let Some(chunk) = response.chunk().await? {
+                partial.body.extend_from_slice(&chunk);
+            }
+            Ok(Some(validators.or(partial.validators.clone())))
+        })
+    })
+    .context(format!("Failed to fetch zip file from URL: {}", zip_url))
+    .kind(ErrorKind::Network);
+    let validators = match fetched {
+        Ok(Some(validators)) => validators,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            if let Err(write_error) = cache::write_partial(partial_dir, zip_url, &partial) {
+                warn!("{:#}", write_error);
+            }
+            return Err(e);
+        }
+    };
+    cache::remove_partial(partial_dir, zip_url);
+    let size = partial.body.len() as u64;
+    if let Some(total) = total.filter(|total| *total != size) {
+        return Err(ErrorKind::Network.error(msg!("download.incomplete", zip_url, size, total)));
+    }
+    Ok(Some((Cursor::new(Bytes::from(partial.body)), validators)))
+}
+
+/// The start of the part and the size of the whole of a `206 Partial Content` response, e.g. of
+/// `Content-Range: bytes 100-999/1000`. The size is `None` if the server does not know it.
+fn content_range(headers: &HeaderMap) -> Option<(u64, Option<u64>)> {
+    let range = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = range.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.parse().ok()?, total.parse().ok()))
+}
+
+/// The archive of `zip_url` from the cache when `offline`, else as [`fetch_archive`] downloads
+/// it, then kept in the cache.
+fn load_archive(
+    zip_url: &str,
+    retry: http::Retry,
+    cached: Option<&Validators>,
+    offline: bool,
+) -> Result<Option<(Cursor<Bytes>, Option<Validators>)>> {
+    let dir = Path::new(cache::CACHE_DIR);
+    if offline {
+        let data = cache::get(dir, zip_url)?
+            .ok_or_else(|| anyhow!(msg!("download.not_cached", zip_url, dir.display())))?;
+        info!("{}", msg!("download.from_cache", zip_url));
+        return Ok(Some((Cursor::new(Bytes::from(data)), None)));
+    }
+    let fetched = fetch_archive(zip_url, retry, cached, dir)?;
+    if let Some((cursor, _)) = &fetched {
+        let path = cache::put(dir, zip_url, cursor.get_ref())?;
+        debug!("Kept the archive in {}", path.display());
+    }
+    Ok(fetched)
+}
+
+/// Format of a tools archive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveKind {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// The format of the archive at `url` starting with `head`, told by its magic number, else by
+    /// the extension of the URL. Archives of neither are taken for zips.
+    pub fn detect(url: &str, head: &[u8]) -> Self {
+        if head.starts_with(b"PK\x03\x04") {
+            return ArchiveKind::Zip;
+        }
+        if head.starts_with(&[0x1f, 0x8b]) {
+            return ArchiveKind::TarGz;
+        }
+        let path = Url::parse(url).map_or(url.to_string(), |url| url.path().to_string());
+        if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+            ArchiveKind::TarGz
+        } else {
+            ArchiveKind::Zip
+        }
+    }
+}
+
+/// An entry read from an archive: a directory, or a file with its content and Unix mode if any.
+struct Item {
+    path: PathBuf,
+    content: Option<Vec<u8>>,
+    mode: Option<u32>,
+}
+
+/// Bounds on the decompressed sizes of the files of an archive, so that a malformed or malicious
+/// one fails instead of filling the memory and the disk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SizeLimits {
+    pub max_file_size: u64,
+    pub max_total_size: u64,
+}
+
+impl Default for SizeLimits {
+    fn default() -> Self {
+        SizeLimits {
+            max_file_size: 256 << 20,
+            max_total_size: 1 << 30,
+        }
+    }
+}
+
+impl SizeLimits {
+    /// Counts a file of `size` bytes into `total`, failing if either goes over its limit.
+    fn check(&self, path: &Path, size: u64, total: &mut u64) -> Result<()> {
+        if size > self.max_file_size {
+            return Err(anyhow!(msg!(
+                "download.file_too_large",
+                path.display(),
+                self.max_file_size
+            )));
+        }
+        *total += size;
+        if *total > self.max_total_size {
+            return Err(anyhow!(msg!("download.too_large", self.max_total_size)));
+        }
+        Ok(())
+    }
+}
+
+fn read_zip<R: Read + Seek>(data: R, limits: &SizeLimits) -> Result<Vec<Item>> {
     let mut zip = ZipArchive::new(data).context("Failed to parse zip file")?;
+    let mut items = vec![];
+    let mut total = 0;
     for i in 0..zip.len() {
         let mut file = zip
             .by_index(i)
             .context(format!("Failed to get file by index: {}", i))?;
-
-        let file_path = match file.enclosed_name() {
-            None => continue,
-            Some(path) => path,
+        let Some(path) = file.enclosed_name() else {
+            continue;
+        };
+        let content = if file.is_dir() {
+            None
+        } else {
+            // The size in the header may lie, so reading stops just past the limit
+            let mut content = vec![];
+            (&mut file)
+                .take(limits.max_file_size + 1)
+                .read_to_end(&mut content)
+                .context(format!("Failed to read {:?} from the archive", path))?;
+            limits.check(&path, content.len() as u64, &mut total)?;
+            Some(content)
         };
-        let out_path = std::path::Path::new(output_path).join(file_path);
+        items.push(Item {
+            path,
+            content,
+            mode: file.unix_mode(),
+        });
+    }
+    Ok(items)
+}
 
-        if file.is_dir() {
-            std::fs::create_dir_all(out_path).context(format!(
-                "Failed to create directory: {:?}",
-                file.enclosed_name().unwrap()
-            ))?;
+fn read_tar<R: Read>(data: R, limits: &SizeLimits) -> Result<Vec<Item>> {
+    let mut total = 0;
+    let entries = tar::read_entries_within(data, |path, size| limits.check(path, size, &mut total))
+        .context("Failed to parse tar.gz file")?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            tar::Entry::File {
+                path,
+                content,
+                mode,
+            } => Item {
+                path: without_cur_dir(&path),
+                content: Some(content),
+                mode,
+            },
+            tar::Entry::Dir { path } => Item {
+                path: without_cur_dir(&path),
+                content: None,
+                mode: None,
+            },
+        })
+        .collect())
+}
+
+fn read_items<R: Read + Seek>(
+    data: R,
+    kind: ArchiveKind,
+    limits: &SizeLimits,
+) -> Result<Vec<Item>> {
+    match kind {
+        ArchiveKind::Zip => read_zip(data, limits),
+        ArchiveKind::TarGz => read_tar(data, limits),
+    }
+}
+
+/// The files and directories of an archive of `kind`, in the order they are stored.
+pub fn list_archive<R>(data: R, kind: ArchiveKind, limits: &SizeLimits) -> Result<Vec<ArchiveEntry>>
+where
+    R: Read + Seek,
+{
+    Ok(read_items(data, kind, limits)?
+        .into_iter()
+        .map(|item| ArchiveEntry {
+            size: item
+                .content
+                .as_ref()
+                .map_or(0, |content| content.len() as u64),
+            is_dir: item.content.is_none(),
+            path: item.path,
+        })
+        .collect())
+}
+
+/// The entries of an archive sorted by path, one per line with the size of the files, and the
+/// total.
+fn format_listing(entries: &[ArchiveEntry]) -> String {
+    let mut entries = entries.iter().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut text = String::new();
+    for entry in &entries {
+        if entry.is_dir {
+            text += &format!("{:>10}  {}/\n", "", entry.path.display());
         } else {
-            let mut output_file = std::fs::File::create(out_path).context(format!(
-                "Failed to create file: {:?}",
-                file.enclosed_name().unwrap()
-            ))?;
-            std::io::copy(&mut file, &mut output_file)
-                .context(format!("Failed to copy file: {:?}", output_file))?;
+            text += &format!("{:>10}  {}\n", entry.size, entry.path.display());
         }
     }
-    Ok(())
+    let files = entries.iter().filter(|entry| !entry.is_dir).count();
+    let total = entries.iter().map(|entry| entry.size).sum::<u64>();
+    text += &msg!("download.listed", total, files);
+    text += "\n";
+    text
+}
+
+/// What is done with a file about to be overwritten by one of different content.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Existing {
+    /// Fail before extracting anything, listing the files
+    #[default]
+    Refuse,
+    Overwrite,
+    /// Keep the file and extract the others
+    Skip,
+}
+
+/// How an archive is extracted.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    /// Files to extract. Directories are only created for them
+    pub only: PathFilter,
+    /// Number of leading directories dropped from the paths, before `only` is matched. Entries
+    /// with no more are skipped
+    pub strip_components: usize,
+    pub existing: Existing,
+    /// SHA-256 of files as extracted last time. They are overwritten as long as they still have
+    /// it, since they were not edited since
+    pub pristine: HashMap<PathBuf, String>,
+    pub limits: SizeLimits,
+}
+
+/// Extracts an archive of `kind` into `output_path`, moving its `tools` directory to `tools_dir`
+/// if given. Returns the paths of the extracted files.
+pub fn extract_archive<R>(
+    data: R,
+    kind: ArchiveKind,
+    output_path: &str,
+    tools_dir: Option<&Path>,
+    options: &ExtractOptions,
+) -> Result<Vec<PathBuf>>
+where
+    R: Read + Seek,
+{
+    let items = read_items(data, kind, &options.limits)?;
+    extract_items(items, output_path, tools_dir, options)
+}
+
+/// Extracts a zip into `output_path`, moving its `tools` directory to `tools_dir` if given.
+/// Returns the paths of the extracted files.
+pub fn unzip_file<R>(
+    data: R,
+    output_path: &str,
+    tools_dir: Option<&Path>,
+    options: &ExtractOptions,
+) -> Result<Vec<PathBuf>>
+where
+    R: Read + Seek,
+{
+    extract_items(
+        read_zip(data, &options.limits)?,
+        output_path,
+        tools_dir,
+        options,
+    )
+}
+
+/// Extracts a `.tar.gz` like [`unzip_file`] does a zip.
+pub fn untar_file<R>(
+    data: R,
+    output_path: &str,
+    tools_dir: Option<&Path>,
+    options: &ExtractOptions,
+) -> Result<Vec<PathBuf>>
+where
+    R: Read,
+{
+    extract_items(
+        read_tar(data, &options.limits)?,
+        output_path,
+        tools_dir,
+        options,
+    )
+}
+
+fn extract_items(
+    items: Vec<Item>,
+    output_path: &str,
+    tools_dir: Option<&Path>,
+    options: &ExtractOptions,
+) -> Result<Vec<PathBuf>> {
+    log_extracting(output_path, tools_dir);
+    let items = items
+        .into_iter()
+        .filter_map(|item| {
+            let path = strip_components(&item.path, options.strip_components)?;
+            Some(Item { path, ..item })
+        })
+        .filter(|item| {
+            let keep = options.only.is_empty()
+                || (item.content.is_some() && options.only.matches(&item.path));
+            if !keep {
+                trace!("Skipping {}", item.path.display());
+            }
+            keep
+        })
+        .map(|item| {
+            let out_path = resolve_output_path(Path::new(output_path), tools_dir, &item.path);
+            (out_path, item)
+        })
+        .collect::<Vec<_>>();
+
+    // Everything is checked before anything is written
+    let conflicts = items
+        .iter()
+        .filter_map(|(out_path, item)| {
+            let content = item.content.as_ref()?;
+            overwrites(out_path, content, &options.pristine).then_some(out_path.clone())
+        })
+        .collect::<Vec<_>>();
+    if !conflicts.is_empty() && options.existing == Existing::Refuse {
+        let listing = conflicts
+            .iter()
+            .map(|path| format!("\n  {}", path.display()))
+            .collect::<String>();
+        return Err(anyhow!(
+            "{}{}",
+            msg!("download.conflicts", conflicts.len()),
+            listing
+        ));
+    }
+
+    let mut files = vec![];
+    for (out_path, item) in items {
+        let Some(content) = item.content else {
+            std::fs::create_dir_all(&out_path)
+                .context(format!("Failed to create directory: {:?}", item.path))?;
+            continue;
+        };
+        if options.existing == Existing::Skip && conflicts.contains(&out_path) {
+            info!("{}", msg!("download.kept", out_path.display()));
+            continue;
+        }
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {:?}", parent))?;
+        }
+        std::fs::write(&out_path, content)
+            .context(format!("Failed to create file: {:?}", item.path))?;
+        #[cfg(unix)]
+        if let Some(mode) = item.mode {
+            set_mode(&out_path, mode)?;
+        }
+        files.push(out_path);
+    }
+    Ok(files)
+}
+
+/// Whether writing `content` to `path` loses a file which differs from it and was not extracted
+/// as is last time.
+fn overwrites(path: &Path, content: &[u8], pristine: &HashMap<PathBuf, String>) -> bool {
+    let Ok(existing) = std::fs::read(path) else {
+        return false;
+    };
+    if existing == content {
+        return false;
+    }
+    pristine.get(path) != Some(&sha256::hex_digest(&existing))
+}
+
+/// Applies the permission bits of an entry, so that prebuilt binaries and scripts stay
+/// executable.
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o777)).context(format!(
+        "Failed to set the permissions of {}",
+        path.display()
+    ))
+}
+
+/// `path` without its first `count` components, or `None` if nothing is left.
+fn strip_components(path: &Path, count: usize) -> Option<PathBuf> {
+    let stripped = path.components().skip(count).collect::<PathBuf>();
+    (!stripped.as_os_str().is_empty()).then_some(stripped)
+}
+
+/// `path` without `.` components: archives made by `tar -C dir .` name their entries
+/// `./tools/...`.
+fn without_cur_dir(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
+
+fn log_extracting(output_path: &str, tools_dir: Option<&Path>) {
+    match tools_dir {
+        Some(tools_dir) => info!("{}", msg!("download.unzipping", tools_dir.display())),
+        None => info!("{}", msg!("download.unzipping", output_path)),
+    }
+}
+
+fn resolve_output_path(output_path: &Path, tools_dir: Option<&Path>, file_path: &Path) -> PathBuf {
+    if let Some(tools_dir) = tools_dir {
+        if let Ok(rest) = file_path.strip_prefix(ARCHIVE_TOOLS_DIR) {
+            return tools_dir.join(rest);
+        }
+    }
+    output_path.join(file_path)
 }
 
 #[cfg(test)]
@@ -129,19 +1405,169 @@ mod tests {
             .with_header("content-type", "text/html")
             .with_body("content")
             .create();
-        let html = fetch_html(&server.url()).unwrap();
+        let html = fetch_html(&server.url(), DownloadConfig::default().retry()).unwrap();
         assert_eq!(html, "content");
         mock.assert();
     }
 
+    #[test]
+    fn test_fetch_relative_tool_links() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("GET", "/tasks/ahc001_a")
+            .with_body(r#"<a href="/tools/ahc001.zip">ローカル版</a>"#)
+            .create();
+        let url = format!("{}/tasks/ahc001_a", server.url());
+        let config = DownloadConfig::default();
+        let links = fetch_tool_links(&url, &config.link_query(Lang::Ja), config.retry()).unwrap();
+        assert_eq!(links, vec![format!("{}/tools/ahc001.zip", server.url())]);
+        mock.assert();
+
+        assert_eq!(
+            resolve_link(
+                "https://atcoder.jp/contests/ahc001/tasks/ahc001_a",
+                "../tools.zip"
+            )
+            .unwrap(),
+            "https://atcoder.jp/contests/ahc001/tools.zip"
+        );
+        assert_eq!(
+            resolve_link("https://atcoder.jp/", "https://img.atcoder.jp/tools.zip").unwrap(),
+            "https://img.atcoder.jp/tools.zip"
+        );
+    }
+
+    #[test]
+    fn test_fetch_archive_resumes_a_partial_download() {
+        let mut server = mockito::Server::new();
+        let url = format!("{}/tools.zip", server.url());
+        let dir = tempdir().unwrap();
+        let retry = http::Retry {
+            retries: 0,
+            backoff: Duration::ZERO,
+        };
+        let partial = Partial {
+            body: b"PK\x03\x04".to_vec(),
+            validators: Some(Validators {
+                zip_url: url.clone(),
+                etag: Some("\"v1\"".to_string()),
+                last_modified: None,
+            }),
+        };
+        cache::write_partial(dir.path(), &url, &partial).unwrap();
+        let rest = server
+            .mock("GET", "/tools.zip")
+            .match_header("range", "bytes=4-")
+            .match_header("if-range", "\"v1\"")
+            .with_status(206)
+            .with_header("content-range", "bytes 4-9/10")
+            .with_body("rest..")
+            .create();
+
+        let (cursor, validators) = fetch_archive(&url, retry, None, dir.path())
+            .unwrap()
+            .unwrap();
+        assert_eq!(cursor.get_ref().as_ref(), b"PK\x03\x04rest..");
+        assert_eq!(validators, partial.validators);
+        assert_eq!(cache::read_partial(dir.path(), &url), None);
+        rest.assert();
+
+        // A changed archive is sent whole and replaces what was received
+        cache::write_partial(dir.path(), &url, &partial).unwrap();
+        server.reset();
+        let whole = server
+            .mock("GET", "/tools.zip")
+            .with_header("etag", "\"v2\"")
+            .with_body("PK\x03\x04v2")
+            .create();
+        let (cursor, _) = fetch_archive(&url, retry, None, dir.path())
+            .unwrap()
+            .unwrap();
+        assert_eq!(cursor.get_ref().as_ref(), b"PK\x03\x04v2");
+        whole.assert();
+
+        assert_eq!(
+            content_range(&HeaderMap::from_iter([(
+                CONTENT_RANGE,
+                "bytes 100-999/*".parse().unwrap()
+            )])),
+            Some((100, None))
+        );
+    }
+
     #[test]
     fn test_find_tool_url() {
         // read file from test directory
         let html = include_str!("tests/fixtures/atcoder_mock.html");
-        let url = find_tool_url(html).unwrap();
+        let url = find_tool_url(html, Lang::Ja).unwrap();
         assert_eq!(url, "https://example.net/tools.zip");
     }
 
+    #[test]
+    fn test_find_tool_url_in_english() {
+        let html = r#"<a href="https://example.net/ja.zip">ローカル版</a>
+            <a href="https://example.net/en.zip">Local version</a>"#;
+        let url = find_tool_url(html, Lang::En).unwrap();
+        assert_eq!(url, "https://example.net/en.zip");
+    }
+
+    #[test]
+    fn test_find_links_by_configured_text_or_selector() {
+        let html = r#"<a href="/ja.zip">ローカル版</a>
+            <div class="tools"><a href="/custom.zip">Tester</a></div>"#;
+        // An English page without an English link still has the Japanese one
+        let query = DownloadConfig::default().link_query(Lang::En);
+        assert_eq!(query.texts, vec!["Local version", "ローカル版"]);
+        assert_eq!(find_links(html, &query).unwrap(), vec!["/ja.zip"]);
+
+        let config: DownloadConfig = toml::from_str("link_texts = [\"Tester\"]").unwrap();
+        assert_eq!(
+            find_links(html, &config.link_query(Lang::Ja)).unwrap(),
+            vec!["/custom.zip"]
+        );
+        let config: DownloadConfig = toml::from_str("link_selector = \".tools a\"").unwrap();
+        assert_eq!(
+            find_links(html, &config.link_query(Lang::Ja)).unwrap(),
+            vec!["/custom.zip"]
+        );
+        let config: DownloadConfig = toml::from_str("link_selector = \"[[\"").unwrap();
+        assert!(find_links(html, &config.link_query(Lang::Ja)).is_err());
+    }
+
+    #[test]
+    fn test_choose_tool_url() {
+        let links = vec![
+            "https://example.net/tools_windows.zip".to_string(),
+            "https://example.net/tools.zip".to_string(),
+        ];
+        assert_eq!(
+            choose_tool_url(&links, Some(2), None).unwrap(),
+            "https://example.net/tools.zip"
+        );
+        let pattern = Regex::new("windows").unwrap();
+        assert_eq!(
+            choose_tool_url(&links, None, Some(&pattern)).unwrap(),
+            "https://example.net/tools_windows.zip"
+        );
+        assert!(choose_tool_url(&links, Some(2), Some(&pattern)).is_err());
+        assert!(choose_tool_url(&links, Some(0), None).is_err());
+    }
+
+    #[test]
+    fn test_localize_url() {
+        assert_eq!(
+            localize_url(
+                "https://atcoder.jp/contests/ahc001/tasks/ahc001_a?lang=ja",
+                Lang::En
+            ),
+            "https://atcoder.jp/contests/ahc001/tasks/ahc001_a?lang=en"
+        );
+        assert_eq!(
+            localize_url("http://127.0.0.1:1234/", Lang::En),
+            "http://127.0.0.1:1234/"
+        );
+    }
+
     #[test]
     fn test_unzip_file() {
         let data = include_bytes!("tests/fixtures/test_archive.zip");
@@ -149,7 +1575,7 @@ mod tests {
         let dir = tempdir().unwrap();
         let output_path = dir.path().to_str().unwrap();
 
-        unzip_file(cursor, output_path).unwrap();
+        unzip_file(cursor, output_path, None, &ExtractOptions::default()).unwrap();
 
         let file_path = dir.path().join("tools/mock.txt");
         assert!(file_path.exists());
@@ -166,4 +1592,338 @@ mod tests {
         file.read_to_string(&mut contents).unwrap();
         assert_eq!(contents, "1000\n");
     }
+
+    #[test]
+    fn test_unzip_file_into_tools_dir() {
+        let data = include_bytes!("tests/fixtures/test_archive.zip");
+        let cursor = Cursor::new(data.as_ref());
+        let dir = tempdir().unwrap();
+        let tools_dir = dir.path().join("my_tools");
+
+        let files = unzip_file(cursor, ".", Some(&tools_dir), &ExtractOptions::default()).unwrap();
+
+        assert!(files.contains(&tools_dir.join("mock.txt")));
+        assert!(tools_dir.join("mock.txt").exists());
+        assert!(tools_dir.join("in/0000.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unzip_keeps_unix_modes() {
+        use std::io::Write;
+        use std::os::unix::fs::PermissionsExt;
+        use zip::write::SimpleFileOptions;
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        let options = SimpleFileOptions::default();
+        zip.start_file("tools/tester", options.unix_permissions(0o755))
+            .unwrap();
+        zip.write_all(b"#!/bin/sh\n").unwrap();
+        zip.start_file("tools/README.md", options.unix_permissions(0o644))
+            .unwrap();
+        let data = zip.finish().unwrap().into_inner();
+        let dir = tempdir().unwrap();
+
+        unzip_file(
+            Cursor::new(data),
+            dir.path().to_str().unwrap(),
+            None,
+            &ExtractOptions::default(),
+        )
+        .unwrap();
+
+        let mode = |name: &str| {
+            let metadata = std::fs::metadata(dir.path().join(name)).unwrap();
+            metadata.permissions().mode() & 0o777
+        };
+        assert_eq!(mode("tools/tester"), 0o755);
+        assert_eq!(mode("tools/README.md"), 0o644);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_untar_keeps_unix_modes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut builder = tar::Builder::new(vec![]);
+        builder
+            .append_file_with_mode("tools/tester", b"#!/bin/sh\n", 0o755)
+            .unwrap();
+        builder
+            .append_file("tools/README.md", b"# tools\n")
+            .unwrap();
+        let data = builder.finish().unwrap();
+        let dir = tempdir().unwrap();
+
+        untar_file(
+            data.as_slice(),
+            dir.path().to_str().unwrap(),
+            None,
+            &ExtractOptions::default(),
+        )
+        .unwrap();
+
+        let mode = |name: &str| {
+            let metadata = std::fs::metadata(dir.path().join(name)).unwrap();
+            metadata.permissions().mode() & 0o777
+        };
+        assert_eq!(mode("tools/tester"), 0o755);
+        assert_eq!(mode("tools/README.md"), 0o644);
+    }
+
+    #[test]
+    fn test_unzip_only_matching_files() {
+        let data = include_bytes!("tests/fixtures/test_archive.zip");
+        let dir = tempdir().unwrap();
+        let tools_dir = dir.path().join("my_tools");
+        let options = ExtractOptions {
+            only: PathFilter::new(&["in/**".to_string()]).unwrap(),
+            ..ExtractOptions::default()
+        };
+
+        let files =
+            unzip_file(Cursor::new(data.as_ref()), ".", Some(&tools_dir), &options).unwrap();
+
+        assert!(files.contains(&tools_dir.join("in/0000.txt")));
+        assert!(files
+            .iter()
+            .all(|file| file.starts_with(tools_dir.join("in"))));
+        assert!(!tools_dir.join("mock.txt").exists());
+    }
+
+    #[test]
+    fn test_unzip_refuses_to_overwrite_edited_files() {
+        let data = include_bytes!("tests/fixtures/test_archive.zip");
+        let dir = tempdir().unwrap();
+        let tools_dir = dir.path().join("my_tools");
+        let unzip = |options: &ExtractOptions| {
+            unzip_file(Cursor::new(data.as_ref()), ".", Some(&tools_dir), options)
+        };
+        let files = unzip(&ExtractOptions::default()).unwrap();
+        // The same files again are no conflict
+        assert_eq!(unzip(&ExtractOptions::default()).unwrap(), files);
+
+        let mock = tools_dir.join("mock.txt");
+        std::fs::write(&mock, "edited\n").unwrap();
+        std::fs::remove_file(tools_dir.join("in/0000.txt")).unwrap();
+        let error = unzip(&ExtractOptions::default()).unwrap_err();
+        assert!(error.to_string().contains("mock.txt"));
+        assert!(!tools_dir.join("in/0000.txt").exists());
+
+        let skipped = unzip(&ExtractOptions {
+            existing: Existing::Skip,
+            ..ExtractOptions::default()
+        })
+        .unwrap();
+        assert!(!skipped.contains(&mock));
+        assert_eq!(std::fs::read_to_string(&mock).unwrap(), "edited\n");
+        assert!(tools_dir.join("in/0000.txt").exists());
+
+        // A file still as extracted last time is overwritten
+        let pristine = HashMap::from([(mock.clone(), sha256::hex_digest(b"edited\n"))]);
+        unzip(&ExtractOptions {
+            pristine,
+            ..ExtractOptions::default()
+        })
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(&mock).unwrap(), "content\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_generate_inputs() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let release = dir.path().join("target/release");
+        std::fs::create_dir_all(&release).unwrap();
+        // Makes an input of each seed holding the seed
+        let gen = release.join("gen");
+        std::fs::write(
+            &gen,
+            "#!/bin/sh\nmkdir -p in\nwhile read s; do echo $s > in/$(printf %04d $s).txt; done < \"$1\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&gen, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        generate_inputs(dir.path(), 3).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(SEEDS_FILE)).unwrap(),
+            "0\n1\n2\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("in/0002.txt")).unwrap(),
+            "2\n"
+        );
+    }
+
+    #[test]
+    fn test_untar_file_stripping_components() {
+        let mut builder = tar::Builder::new(vec![]);
+        builder
+            .append_file("ahc001/tools/in/0000.txt", b"1000\n")
+            .unwrap();
+        builder
+            .append_file("ahc001/README.md", b"# ahc001\n")
+            .unwrap();
+        let data = builder.finish().unwrap();
+        let dir = tempdir().unwrap();
+        let tools_dir = dir.path().join("my_tools");
+        let output_path = dir.path().to_str().unwrap();
+        let options = ExtractOptions {
+            strip_components: 1,
+            ..ExtractOptions::default()
+        };
+
+        let files = untar_file(data.as_slice(), output_path, Some(&tools_dir), &options).unwrap();
+
+        assert_eq!(
+            files,
+            vec![tools_dir.join("in/0000.txt"), dir.path().join("README.md")]
+        );
+        assert_eq!(strip_components(Path::new("ahc001/tools"), 2), None);
+    }
+
+    #[test]
+    fn test_untar_file_into_tools_dir() {
+        let mut builder = tar::Builder::new(vec![]);
+        builder
+            .append_file("./tools/mock.txt", b"content\n")
+            .unwrap();
+        builder.append_file("tools/in/0000.txt", b"1000\n").unwrap();
+        let data = builder.finish().unwrap();
+        assert_eq!(
+            ArchiveKind::detect("https://example.net/tools", &data),
+            ArchiveKind::TarGz
+        );
+        let dir = tempdir().unwrap();
+        let tools_dir = dir.path().join("my_tools");
+
+        let files = extract_archive(
+            Cursor::new(data),
+            ArchiveKind::TarGz,
+            ".",
+            Some(&tools_dir),
+            &ExtractOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            files,
+            vec![tools_dir.join("mock.txt"), tools_dir.join("in/0000.txt")]
+        );
+        assert_eq!(
+            std::fs::read_to_string(tools_dir.join("mock.txt")).unwrap(),
+            "content\n"
+        );
+    }
+
+    #[test]
+    fn test_detect_archive_kind() {
+        let zip = include_bytes!("tests/fixtures/test_archive.zip");
+        assert_eq!(
+            ArchiveKind::detect("https://example.net/tools.tar.gz", zip),
+            ArchiveKind::Zip
+        );
+        assert_eq!(
+            ArchiveKind::detect("https://example.net/tools.tgz?v=2", b""),
+            ArchiveKind::TarGz
+        );
+        assert_eq!(
+            ArchiveKind::detect("https://example.net/tools", b""),
+            ArchiveKind::Zip
+        );
+    }
+
+    #[test]
+    fn test_list_archive() {
+        let data = include_bytes!("tests/fixtures/test_archive.zip");
+        let entries = list_archive(
+            Cursor::new(data.as_ref()),
+            ArchiveKind::Zip,
+            &SizeLimits::default(),
+        )
+        .unwrap();
+        assert!(entries.contains(&ArchiveEntry {
+            path: PathBuf::from("tools/mock.txt"),
+            size: 8,
+            is_dir: false,
+        }));
+
+        let mut builder = tar::Builder::new(vec![]);
+        builder.append_file("tools/in/0001.txt", b"20\n").unwrap();
+        builder.append_file("tools/in/0000.txt", b"1000\n").unwrap();
+        let data = builder.finish().unwrap();
+        let entries = list_archive(
+            Cursor::new(data),
+            ArchiveKind::TarGz,
+            &SizeLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            format_listing(&entries),
+            format!(
+                "         5  tools/in/0000.txt\n         3  tools/in/0001.txt\n{}\n",
+                msg!("download.listed", 8, 2)
+            )
+        );
+    }
+
+    #[test]
+    fn test_extraction_stops_at_the_size_limits() {
+        let data = include_bytes!("tests/fixtures/test_archive.zip");
+        let dir = tempdir().unwrap();
+        let tools_dir = dir.path().join("my_tools");
+        let options = ExtractOptions {
+            limits: SizeLimits {
+                max_file_size: 4,
+                ..SizeLimits::default()
+            },
+            ..ExtractOptions::default()
+        };
+        assert!(unzip_file(Cursor::new(data.as_ref()), ".", Some(&tools_dir), &options).is_err());
+        // Nothing is written from an archive over the limits
+        assert!(!tools_dir.exists());
+
+        let mut builder = tar::Builder::new(vec![]);
+        builder
+            .append_file("tools/in/0000.txt", &[b'0'; 600])
+            .unwrap();
+        builder
+            .append_file("tools/in/0001.txt", &[b'1'; 600])
+            .unwrap();
+        let data = builder.finish().unwrap();
+        let limits = SizeLimits {
+            max_file_size: 1000,
+            max_total_size: 1000,
+        };
+        assert!(list_archive(Cursor::new(data.as_slice()), ArchiveKind::TarGz, &limits).is_err());
+        let limits = SizeLimits {
+            max_total_size: 1200,
+            ..limits
+        };
+        assert!(list_archive(Cursor::new(data), ArchiveKind::TarGz, &limits).is_ok());
+    }
+
+    #[test]
+    fn test_resolve_output_path() {
+        let tools_dir = Path::new("work/tools");
+        assert_eq!(
+            resolve_output_path(
+                Path::new("."),
+                Some(tools_dir),
+                Path::new("tools/in/0000.txt")
+            ),
+            PathBuf::from("work/tools/in/0000.txt")
+        );
+        assert_eq!(
+            resolve_output_path(Path::new("."), Some(tools_dir), Path::new("README.md")),
+            PathBuf::from("./README.md")
+        );
+        assert_eq!(
+            resolve_output_path(Path::new("out"), None, Path::new("tools/in/0000.txt")),
+            PathBuf::from("out/tools/in/0000.txt")
+        );
+    }
 }
@@ -0,0 +1,195 @@
+//! Archives downloaded before, kept in `.ahc/cache/archives` by URL so that `ahc download
+//! --offline` can extract them again without a connection, e.g. on a second machine. Downloads
+//! cut short are kept there too, to be resumed.
+
+use super::validators::Validators;
+use crate::sha256;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub(super) const CACHE_DIR: &str = ".ahc/cache/archives";
+const INDEX_FILE: &str = "index.json";
+
+/// The archives of the cache by URL, and the URL downloaded last.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Index {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latest: Option<String>,
+    /// File names of the archives in the cache directory
+    #[serde(default)]
+    archives: BTreeMap<String, String>,
+}
+
+impl Index {
+    fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join(INDEX_FILE);
+        if !path.exists() {
+            return Ok(Index::default());
+        }
+        let text = std::fs::read_to_string(&path)
+            .context(format!("Failed to read file: {}", path.display()))?;
+        serde_json::from_str(&text).context(format!("Failed to parse {}", path.display()))
+    }
+}
+
+/// Keeps `data`, the archive of `zip_url`, replacing the one kept for it before.
+pub(super) fn put(dir: &Path, zip_url: &str, data: &[u8]) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .context(format!("Failed to create directory: {}", dir.display()))?;
+    let mut index = Index::read(dir)?;
+    let name = file_name(zip_url);
+    let path = dir.join(&name);
+    std::fs::write(&path, data).context(format!("Failed to write file: {}", path.display()))?;
+    index.archives.insert(zip_url.to_string(), name);
+    index.latest = Some(zip_url.to_string());
+    let index_path = dir.join(INDEX_FILE);
+    std::fs::write(&index_path, serde_json::to_string_pretty(&index)?)
+        .context(format!("Failed to write file: {}", index_path.display()))?;
+    Ok(path)
+}
+
+/// The archive of `zip_url` kept in `dir`, if any.
+pub(super) fn get(dir: &Path, zip_url: &str) -> Result<Option<Vec<u8>>> {
+    let index = Index::read(dir)?;
+    let Some(name) = index.archives.get(zip_url) else {
+        return Ok(None);
+    };
+    let path = dir.join(name);
+    if !path.exists() {
+        return Ok(None);
+    }
+    std::fs::read(&path)
+        .context(format!("Failed to read file: {}", path.display()))
+        .map(Some)
+}
+
+/// The URL of the archive kept last, if any.
+pub(super) fn latest(dir: &Path) -> Result<Option<String>> {
+    Ok(Index::read(dir)?.latest)
+}
+
+/// The start of an archive whose download was cut short, with the validators telling whether the
+/// rest the server sends is still of the same archive.
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct Partial {
+    pub(super) body: Vec<u8>,
+    pub(super) validators: Option<Validators>,
+}
+
+/// The partial download of `zip_url` kept in `dir`, if it can be resumed.
+pub(super) fn read_partial(dir: &Path, zip_url: &str) -> Option<Partial> {
+    let path = dir.join(format!("{}.part", file_name(zip_url)));
+    let text = std::fs::read_to_string(path.with_extension("part.json")).ok()?;
+    let validators: Validators = serde_json::from_str(&text).ok()?;
+    if validators.zip_url != zip_url {
+        return None;
+    }
+    Some(Partial {
+        body: std::fs::read(&path).ok()?,
+        validators: Some(validators),
+    })
+}
+
+/// Keeps `partial` to resume the download of `zip_url` later, unless nothing tells whether the
+/// archive changes in between.
+pub(super) fn write_partial(dir: &Path, zip_url: &str, partial: &Partial) -> Result<()> {
+    let Some(validators) = partial
+        .validators
+        .as_ref()
+        .filter(|v| v.if_range().is_some())
+    else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(dir)
+        .context(format!("Failed to create directory: {}", dir.display()))?;
+    let path = dir.join(format!("{}.part", file_name(zip_url)));
+    std::fs::write(&path, &partial.body)
+        .context(format!("Failed to write file: {}", path.display()))?;
+    let validators_path = path.with_extension("part.json");
+    std::fs::write(&validators_path, serde_json::to_string_pretty(validators)?).context(format!(
+        "Failed to write file: {}",
+        validators_path.display()
+    ))
+}
+
+pub(super) fn remove_partial(dir: &Path, zip_url: &str) {
+    let path = dir.join(format!("{}.part", file_name(zip_url)));
+    let _ = std::fs::remove_file(path.with_extension("part.json"));
+    let _ = std::fs::remove_file(path);
+}
+
+/// A name unique to `zip_url` keeping the name of its file, e.g. `3f2a…-ahc001.zip`.
+fn file_name(zip_url: &str) -> String {
+    let base = zip_url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|base| !base.is_empty())
+        .unwrap_or("archive");
+    let base = base
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    format!("{}-{}", &sha256::hex_digest(zip_url.as_bytes())[..16], base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_archives_by_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = "https://example.net/tools/ahc001.zip?v=2";
+        assert_eq!(get(dir.path(), url).unwrap(), None);
+        assert_eq!(latest(dir.path()).unwrap(), None);
+
+        let path = put(dir.path(), url, b"first").unwrap();
+        assert!(path.to_string_lossy().ends_with("-ahc001.zip"));
+        put(dir.path(), "https://example.net/other.zip", b"other").unwrap();
+        put(dir.path(), url, b"second").unwrap();
+
+        assert_eq!(get(dir.path(), url).unwrap(), Some(b"second".to_vec()));
+        assert_eq!(
+            get(dir.path(), "https://example.net/other.zip").unwrap(),
+            Some(b"other".to_vec())
+        );
+        assert_eq!(latest(dir.path()).unwrap(), Some(url.to_string()));
+    }
+
+    #[test]
+    fn keeps_partial_downloads_which_can_be_resumed() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = "https://example.net/tools.zip";
+        let validators = |etag: &str| Validators {
+            zip_url: url.to_string(),
+            etag: Some(etag.to_string()),
+            last_modified: None,
+        };
+        let partial = Partial {
+            body: b"PK\x03".to_vec(),
+            validators: Some(validators("\"v1\"")),
+        };
+        write_partial(dir.path(), url, &partial).unwrap();
+        assert_eq!(read_partial(dir.path(), url), Some(partial));
+        assert_eq!(
+            read_partial(dir.path(), "https://example.net/other.zip"),
+            None
+        );
+        remove_partial(dir.path(), url);
+        assert_eq!(read_partial(dir.path(), url), None);
+
+        // A weak ETag cannot tell the rest is of the same archive
+        let partial = Partial {
+            body: b"PK".to_vec(),
+            validators: Some(validators("W/\"v1\"")),
+        };
+        write_partial(dir.path(), url, &partial).unwrap();
+        assert_eq!(read_partial(dir.path(), url), None);
+    }
+}
@@ -0,0 +1,90 @@
+//! Globs choosing the files of the archive to extract, e.g. `in/**` to take only the inputs on a
+//! machine where the tools are already built.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::path::Path;
+
+use super::ARCHIVE_TOOLS_DIR;
+
+/// Paths matching any of a list of globs, or every path for an empty list. In a glob `*` and `?`
+/// match within a path component and `**` across components. A path matches also without the
+/// `tools/` directory the archive keeps the tools in, so `in/**` takes `tools/in/0000.txt`.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    patterns: Vec<Regex>,
+}
+
+impl PathFilter {
+    pub fn new(globs: &[String]) -> Result<Self> {
+        let patterns = globs
+            .iter()
+            .map(|glob| {
+                Regex::new(&glob_to_regex(glob)).context(format!("Invalid pattern: {}", glob))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(PathFilter { patterns })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        if self.patterns.is_empty() {
+            return true;
+        }
+        let candidates = [Some(path), path.strip_prefix(ARCHIVE_TOOLS_DIR).ok()];
+        candidates.into_iter().flatten().any(|path| {
+            let path = path.to_string_lossy().replace('\\', "/");
+            self.patterns.iter().any(|pattern| pattern.is_match(&path))
+        })
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directory at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex += "(?:.*/)?";
+                } else {
+                    regex += ".*";
+                }
+            }
+            '*' => regex += "[^/]*",
+            '?' => regex += "[^/]",
+            c => regex += &regex::escape(&c.to_string()),
+        }
+    }
+    regex += "$";
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_paths_with_or_without_the_tools_directory() {
+        let filter = PathFilter::new(&["in/**".to_string(), "src/bin/vis.rs".to_string()]).unwrap();
+        assert!(filter.matches(Path::new("tools/in/0000.txt")));
+        assert!(filter.matches(Path::new("in/0000.txt")));
+        assert!(filter.matches(Path::new("tools/src/bin/vis.rs")));
+        assert!(!filter.matches(Path::new("tools/src/bin/tester.rs")));
+        assert!(!filter.matches(Path::new("tools/inputs.txt")));
+
+        let filter = PathFilter::new(&["**/*.rs".to_string(), "?.txt".to_string()]).unwrap();
+        assert!(filter.matches(Path::new("tools/src/lib.rs")));
+        assert!(filter.matches(Path::new("main.rs")));
+        assert!(filter.matches(Path::new("a.txt")));
+        assert!(!filter.matches(Path::new("in/a.txt")));
+
+        assert!(PathFilter::default().matches(Path::new("anything")));
+    }
+}
@@ -0,0 +1,194 @@
+//! The archives linked from a problem page besides the tools, e.g. a tester or a visualizer
+//! published as a zip of their own, told apart by the text of their links.
+
+use super::{resolve_link, WEBVIS_DIR};
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use url::Url;
+
+/// What an archive linked from a problem page holds.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum ArchiveRole {
+    Tools,
+    Tester,
+    Webvis,
+    /// Anything else, e.g. prebuilt binaries for Windows, which is not downloaded
+    Other,
+}
+
+impl ArchiveRole {
+    /// The role the text of a link to an archive says, e.g. テスター or "Web version".
+    fn of(text: &str) -> Self {
+        let said = |pattern: &str| {
+            Regex::new(pattern)
+                .expect("the role regex is valid")
+                .is_match(text)
+        };
+        if said(r"(?i)web\s*版|web version|visuali[sz]er|ビジュアライザ") {
+            ArchiveRole::Webvis
+        } else if said(r"(?i)tester|テスター|judge|ジャッジ") {
+            ArchiveRole::Tester
+        } else if said(r"(?i)windows|binary|バイナリ") {
+            ArchiveRole::Other
+        } else {
+            ArchiveRole::Tools
+        }
+    }
+}
+
+/// A link to an archive, made absolute.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct ArchiveLink {
+    pub(super) url: String,
+    pub(super) role: ArchiveRole,
+    /// Directory of the tools it is extracted into
+    pub(super) dir: PathBuf,
+}
+
+/// Splits the archives linked from `html` of the page at `url` into the tools and the others to
+/// extract next to them. The tools are the first of `tool_links` whose text names nothing else;
+/// the rest of them go into directories named after their files. Of the links not to the tools,
+/// only those to an archive of a tester or a visualizer are kept.
+pub(super) fn split(
+    url: &str,
+    html: &str,
+    tool_links: &[String],
+) -> Result<(String, Vec<ArchiveLink>)> {
+    let mut archives = vec![];
+    for (text, href) in anchors(html) {
+        let link = resolve_link(url, &href)?;
+        if archives.iter().any(|(archive, _)| archive == &link) {
+            continue;
+        }
+        let role = match (tool_links.contains(&link), ArchiveRole::of(&text)) {
+            (true, ArchiveRole::Other) => ArchiveRole::Tools,
+            (true, role) => role,
+            (false, role @ (ArchiveRole::Tester | ArchiveRole::Webvis)) if is_archive(&link) => {
+                role
+            }
+            _ => continue,
+        };
+        archives.push((link, role));
+    }
+    // The tool links are found by text, but may be elements other than anchors
+    for link in tool_links {
+        if !archives.iter().any(|(archive, _)| archive == link) {
+            archives.push((link.clone(), ArchiveRole::Tools));
+        }
+    }
+
+    let tools = archives
+        .iter()
+        .position(|(_, role)| *role == ArchiveRole::Tools)
+        .or_else(|| {
+            archives
+                .iter()
+                .position(|(link, _)| tool_links.contains(link))
+        })
+        .map(|i| archives.remove(i).0)
+        .unwrap_or_else(|| tool_links[0].clone());
+    let mut others: Vec<ArchiveLink> = vec![];
+    for (url, role) in archives {
+        let dir = match role {
+            ArchiveRole::Tester => PathBuf::from("tester"),
+            ArchiveRole::Webvis => PathBuf::from(WEBVIS_DIR),
+            _ => PathBuf::from(stem(&url)),
+        };
+        // A second archive of the same role goes by its name
+        let dir = match others.iter().any(|other| other.dir == dir) {
+            true => dir.join(stem(&url)),
+            false => dir,
+        };
+        others.push(ArchiveLink { url, role, dir });
+    }
+    Ok((tools, others))
+}
+
+/// The texts and targets of the anchors of `html`.
+fn anchors(html: &str) -> Vec<(String, String)> {
+    let document = scraper::Html::parse_document(html);
+    let selector = scraper::Selector::parse("a[href]").expect("the anchor selector is valid");
+    document
+        .select(&selector)
+        .filter_map(|element| {
+            let href = element.value().attr("href")?;
+            let text = element.text().collect::<String>();
+            Some((text.trim().to_string(), href.to_string()))
+        })
+        .collect()
+}
+
+fn is_archive(url: &str) -> bool {
+    let path = Url::parse(url).map_or(url.to_string(), |url| url.path().to_string());
+    [".zip", ".tar.gz", ".tgz"]
+        .iter()
+        .any(|extension| path.ends_with(extension))
+}
+
+/// The name of the file at `url` without its extensions, e.g. `tools_windows`.
+fn stem(url: &str) -> String {
+    let path = Url::parse(url).map_or(url.to_string(), |url| url.path().to_string());
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let stem = name.split('.').next().unwrap_or_default();
+    match stem.is_empty() {
+        true => "archive".to_string(),
+        false => stem.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_the_archives_apart_by_their_links() {
+        let html = r#"
+            <a href="/tools.zip">ローカル版</a>
+            <a href="/tools_windows.zip">Windows 用のコンパイル済みバイナリ</a>
+            <a href="/tester.tar.gz">テスター</a>
+            <a href="/vis.zip">Web版 (ダウンロード)</a>
+            <a href="/vis.html">Web版</a>
+            <a href="/tools.zip">ローカル版</a>"#;
+        let base = "https://example.net/tasks/a";
+        let tool_links = vec!["https://example.net/tools.zip".to_string()];
+        let (tools, others) = split(base, html, &tool_links).unwrap();
+        assert_eq!(tools, "https://example.net/tools.zip");
+        assert_eq!(
+            others,
+            vec![
+                ArchiveLink {
+                    url: "https://example.net/tester.tar.gz".to_string(),
+                    role: ArchiveRole::Tester,
+                    dir: PathBuf::from("tester"),
+                },
+                ArchiveLink {
+                    url: "https://example.net/vis.zip".to_string(),
+                    role: ArchiveRole::Webvis,
+                    dir: PathBuf::from("webvis"),
+                },
+            ]
+        );
+
+        // Several links to tools: the first is taken, the others go by their names
+        let html = r#"<a href="/a/tools.zip">Local version</a>
+            <a href="/b/tools.zip">Local version (tester)</a>
+            <a href="/c/tester.zip">Local version, another tester</a>
+            <a href="/d/extra.zip">Local version</a>"#;
+        let tool_links = ["a/tools.zip", "b/tools.zip", "c/tester.zip", "d/extra.zip"]
+            .map(|path| format!("https://example.net/{}", path))
+            .to_vec();
+        let (tools, others) = split(base, html, &tool_links).unwrap();
+        assert_eq!(tools, "https://example.net/a/tools.zip");
+        assert_eq!(
+            others.iter().map(|other| &other.dir).collect::<Vec<_>>(),
+            vec![
+                &PathBuf::from("tester"),
+                &PathBuf::from("tester/tester"),
+                &PathBuf::from("extra"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! `.ahc/tools_manifest.json`, what `ahc download` extracted last: the archive, its checksum and
+//! those of its files, so that `ahc download --verify` tells whether the tools are still the
+//! official ones.
+
+use super::links::ArchiveRole;
+use crate::sha256;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub(super) const MANIFEST_PATH: &str = ".ahc/tools_manifest.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) struct Manifest {
+    pub(super) zip_url: String,
+    /// SHA-256 of the archive
+    pub(super) sha256: String,
+    pub(super) files: Vec<ManifestFile>,
+    /// The other archives of the problem page, extracted next to the tools
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(super) others: Vec<OtherArchive>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) struct OtherArchive {
+    pub(super) zip_url: String,
+    pub(super) role: ArchiveRole,
+    pub(super) sha256: String,
+    pub(super) files: Vec<ManifestFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) struct ManifestFile {
+    pub(super) path: PathBuf,
+    pub(super) size: u64,
+    pub(super) sha256: String,
+}
+
+/// Files which are no longer as extracted.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub(super) struct Changes {
+    pub(super) modified: Vec<PathBuf>,
+    pub(super) missing: Vec<PathBuf>,
+}
+
+impl Manifest {
+    /// The manifest of `files` just extracted from `archive`.
+    pub(super) fn new(zip_url: &str, archive: &[u8], files: &[PathBuf]) -> Result<Self> {
+        Ok(Manifest {
+            zip_url: zip_url.to_string(),
+            sha256: sha256::hex_digest(archive),
+            files: checksums(files)?,
+            others: vec![],
+        })
+    }
+
+    /// Every file extracted, those of the other archives included.
+    pub(super) fn all_files(&self) -> impl Iterator<Item = &ManifestFile> {
+        self.files
+            .iter()
+            .chain(self.others.iter().flat_map(|other| &other.files))
+    }
+
+    /// The manifest at `path`, or `None` if nothing was downloaded yet.
+    pub(super) fn read(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(path)
+            .context(format!("Failed to read file: {}", path.display()))?;
+        let manifest = serde_json::from_str(&text)
+            .context(format!("Failed to parse manifest: {}", path.display()))?;
+        Ok(Some(manifest))
+    }
+
+    pub(super) fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .context(format!("Failed to write file: {}", path.display()))
+    }
+
+    /// Compares the files on disk with their checksums.
+    pub(super) fn verify(&self) -> Changes {
+        let mut changes = Changes::default();
+        for file in self.all_files() {
+            match std::fs::read(&file.path) {
+                Ok(content) => {
+                    if sha256::hex_digest(&content) != file.sha256 {
+                        changes.modified.push(file.path.clone());
+                    }
+                }
+                Err(_) => changes.missing.push(file.path.clone()),
+            }
+        }
+        changes
+    }
+}
+
+impl OtherArchive {
+    /// The record of `files` just extracted from `archive`, of `zip_url`.
+    pub(super) fn new(
+        zip_url: &str,
+        role: ArchiveRole,
+        archive: &[u8],
+        files: &[PathBuf],
+    ) -> Result<Self> {
+        Ok(OtherArchive {
+            zip_url: zip_url.to_string(),
+            role,
+            sha256: sha256::hex_digest(archive),
+            files: checksums(files)?,
+        })
+    }
+}
+
+fn checksums(files: &[PathBuf]) -> Result<Vec<ManifestFile>> {
+    files
+        .iter()
+        .map(|path| {
+            let content =
+                std::fs::read(path).context(format!("Failed to read file: {}", path.display()))?;
+            Ok(ManifestFile {
+                path: path.clone(),
+                size: content.len() as u64,
+                sha256: sha256::hex_digest(&content),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_files_changed_since_the_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+        std::fs::write(&a, "1000\n").unwrap();
+        std::fs::write(&b, "2000\n").unwrap();
+        let manifest = Manifest::new(
+            "https://example.net/tools.zip",
+            b"zip",
+            &[a.clone(), b.clone()],
+        )
+        .unwrap();
+        assert_eq!(manifest.files[0].size, 5);
+
+        let path = dir.path().join(MANIFEST_PATH);
+        manifest.write(&path).unwrap();
+        let manifest = Manifest::read(&path).unwrap().unwrap();
+        assert_eq!(manifest.verify(), Changes::default());
+
+        std::fs::write(&a, "1001\n").unwrap();
+        std::fs::remove_file(&b).unwrap();
+        assert_eq!(
+            manifest.verify(),
+            Changes {
+                modified: vec![a],
+                missing: vec![b],
+            }
+        );
+        assert_eq!(Manifest::read(&dir.path().join("none.json")).unwrap(), None);
+    }
+}
@@ -0,0 +1,105 @@
+//! The `ETag` and `Last-Modified` of the archive downloaded last, kept next to the tools so that
+//! `ahc download` asks the server whether the archive changed and skips it if not.
+
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const VALIDATORS_FILE: &str = ".download.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) struct Validators {
+    pub(super) zip_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) last_modified: Option<String>,
+}
+
+impl Validators {
+    /// The validators the server sent with the archive of `zip_url`, if any.
+    pub(super) fn from_headers(zip_url: &str, headers: &HeaderMap) -> Option<Self> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+        (etag.is_some() || last_modified.is_some()).then(|| Validators {
+            zip_url: zip_url.to_string(),
+            etag,
+            last_modified,
+        })
+    }
+
+    /// The value of `If-Range` resuming a download of the archive these validators are of, if
+    /// one is strong enough: an ETag, unless weak, else the date of last change.
+    pub(super) fn if_range(&self) -> Option<&str> {
+        match (&self.etag, &self.last_modified) {
+            (Some(etag), _) if !etag.starts_with("W/") => Some(etag),
+            (_, Some(last_modified)) => Some(last_modified),
+            _ => None,
+        }
+    }
+
+    /// Makes `request` conditional, so that the server answers 304 if the archive is unchanged.
+    pub(super) fn condition(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+/// The validators kept in `dir` for the archive of `zip_url`, if the tools there came from it.
+pub(super) fn read(dir: &Path, zip_url: &str) -> Option<Validators> {
+    let text = std::fs::read_to_string(dir.join(VALIDATORS_FILE)).ok()?;
+    let validators: Validators = serde_json::from_str(&text).ok()?;
+    (validators.zip_url == zip_url).then_some(validators)
+}
+
+pub(super) fn write(dir: &Path, validators: &Validators) -> Result<()> {
+    let path = dir.join(VALIDATORS_FILE);
+    std::fs::create_dir_all(dir)
+        .context(format!("Failed to create directory: {}", dir.display()))?;
+    std::fs::write(&path, serde_json::to_string_pretty(validators)?)
+        .context(format!("Failed to write file: {}", path.display()))
+}
+
+/// Forgets the validators in `dir`, e.g. when the server sent none for a new archive.
+pub(super) fn remove(dir: &Path) {
+    let _ = std::fs::remove_file(dir.join(VALIDATORS_FILE));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn keeps_the_validators_of_an_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(Validators::from_headers("u", &headers), None);
+        headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+        let validators =
+            Validators::from_headers("https://example.net/tools.zip", &headers).unwrap();
+        assert_eq!(validators.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(validators.last_modified, None);
+
+        write(dir.path(), &validators).unwrap();
+        assert_eq!(
+            read(dir.path(), "https://example.net/tools.zip"),
+            Some(validators)
+        );
+        assert_eq!(read(dir.path(), "https://example.net/other.zip"), None);
+        remove(dir.path());
+        assert_eq!(read(dir.path(), "https://example.net/tools.zip"), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Saving the web visualizer linked from the problem page, with the scripts, styles, images and
+//! WebAssembly it loads, so that it works without a connection.
+
+use crate::error::{ErrorKind, ResultExt};
+use crate::http;
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+use url::Url;
+
+/// Texts of the link to the web visualizer on Japanese and English problem pages
+pub(super) const LINK_TEXTS: [&str; 2] = ["Web版", "Web version"];
+
+/// Saves the page at `page_url` and its assets into `dir`, keeping their paths relative to the
+/// page so that its relative links still work. Assets elsewhere, e.g. on a CDN, are left to be
+/// loaded online. Returns the paths of the saved files.
+pub(super) fn save(page_url: &str, dir: &Path, retry: http::Retry) -> Result<Vec<PathBuf>> {
+    let page = Url::parse(page_url).context(format!("Invalid URL: {}", page_url))?;
+    let html = fetch(&page, retry)?;
+    let mut pending = asset_urls(&page, &String::from_utf8_lossy(&html));
+    let mut seen = BTreeSet::from([page.clone()]);
+    let mut files = vec![write(dir, &page, &page, &html)?];
+    while let Some(url) = pending.pop() {
+        if !seen.insert(url.clone()) {
+            continue;
+        }
+        if local_path(&page, &url).is_none() {
+            debug!("Leaving {} online", url);
+            continue;
+        }
+        let content = fetch(&url, retry)?;
+        if url.path().ends_with(".js") {
+            pending.extend(wasm_urls(&url, &String::from_utf8_lossy(&content)));
+        }
+        files.push(write(dir, &page, &url, &content)?);
+    }
+    Ok(files)
+}
+
+fn fetch(url: &Url, retry: http::Retry) -> Result<Bytes> {
+    http::retry(retry, url.as_str(), || {
+        http::block_on(async {
+            let response = http::client().get(url.clone()).send().await?;
+            response.error_for_status()?.bytes().await
+        })
+    })
+    .context(format!("Failed to fetch {}", url))
+    .kind(ErrorKind::Network)
+}
+
+fn write(dir: &Path, page: &Url, url: &Url, content: &[u8]) -> Result<PathBuf> {
+    let path = dir.join(local_path(page, url).ok_or_else(|| anyhow!("{} is not local", url))?);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .context(format!("Failed to create directory: {}", parent.display()))?;
+    }
+    std::fs::write(&path, content).context(format!("Failed to write file: {}", path.display()))?;
+    Ok(path)
+}
+
+/// The path of `url` relative to the directory of `page`, if it is in it. The page itself is
+/// `index.html` unless it has a name.
+fn local_path(page: &Url, url: &Url) -> Option<PathBuf> {
+    if url.origin() != page.origin() {
+        return None;
+    }
+    let base = &page.path()[..page.path().rfind('/')? + 1];
+    let rest = url.path().strip_prefix(base)?;
+    let rest = if rest.is_empty() { "index.html" } else { rest };
+    let path = PathBuf::from(rest);
+    path.components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)))
+        .then_some(path)
+}
+
+/// The scripts, styles and images a page loads.
+fn asset_urls(page: &Url, html: &str) -> Vec<Url> {
+    let document = scraper::Html::parse_document(html);
+    let selector = scraper::Selector::parse("script[src], link[href], img[src]")
+        .expect("the asset selector is valid");
+    document
+        .select(&selector)
+        .filter_map(|element| {
+            let value = element.value();
+            value.attr("src").or_else(|| value.attr("href"))
+        })
+        .filter_map(|link| page.join(link).ok())
+        .collect()
+}
+
+/// The WebAssembly modules a script loads, e.g. by
+/// `new URL('vis_bg.wasm', import.meta.url)` in the glue of wasm-bindgen.
+fn wasm_urls(script: &Url, js: &str) -> Vec<Url> {
+    let regex = Regex::new(r#"["']([^"'\s]+\.wasm)["']"#).expect("the wasm regex is valid");
+    regex
+        .captures_iter(js)
+        .filter_map(|captures| script.join(&captures[1]).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_the_page_and_its_assets() {
+        let mut server = mockito::Server::new();
+        let page = server
+            .mock("GET", "/ahc001/vis.html")
+            .with_body(
+                r#"<html><head><link rel="stylesheet" href="style.css">
+                <script type="module" src="./pkg/vis.js"></script>
+                <script src="https://cdn.example.net/lib.js"></script></head></html>"#,
+            )
+            .create();
+        let style = server
+            .mock("GET", "/ahc001/style.css")
+            .with_body("body {}")
+            .create();
+        let script = server
+            .mock("GET", "/ahc001/pkg/vis.js")
+            .with_body("input = new URL('vis_bg.wasm', import.meta.url);")
+            .create();
+        let wasm = server
+            .mock("GET", "/ahc001/pkg/vis_bg.wasm")
+            .with_body("\0asm")
+            .create();
+        let dir = tempfile::tempdir().unwrap();
+        let retry = http::Retry {
+            retries: 0,
+            backoff: std::time::Duration::ZERO,
+        };
+
+        let url = format!("{}/ahc001/vis.html", server.url());
+        let mut files = save(&url, dir.path(), retry).unwrap();
+        files.sort();
+
+        let expected = ["pkg/vis.js", "pkg/vis_bg.wasm", "style.css", "vis.html"];
+        assert_eq!(files, expected.map(|path| dir.path().join(path)).to_vec());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("pkg/vis_bg.wasm")).unwrap(),
+            "\0asm"
+        );
+        for mock in [page, style, script, wasm] {
+            mock.assert();
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Kinds of failures, which `ahc` exits with distinct codes for so that scripts and CI can tell
+//! them apart. Errors are still [`anyhow::Error`]s; a kind is attached with [`ErrorKind::error`]
+//! or [`ResultExt::kind`] and found again with [`ErrorKind::of`].
+
+use std::fmt;
+
+/// Exit code of failures without a more specific kind
+pub const EXIT_FAILURE: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The configuration is missing, unreadable or invalid
+    Config,
+    /// A request to AtCoder or another service failed
+    Network,
+    /// There are no changes to commit
+    NothingToCommit,
+    /// The score got worse than the reference and the command stopped
+    Regression,
+}
+
+impl ErrorKind {
+    /// Exit code of the kind. 2 is left to command line usage errors, which clap reports.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Config => 3,
+            ErrorKind::Network => 4,
+            ErrorKind::NothingToCommit => 5,
+            ErrorKind::Regression => 6,
+        }
+    }
+
+    /// The kind `ahc` exits with `code` for, e.g. of an `ahc` run on another machine.
+    pub fn from_exit_code(code: i32) -> Option<ErrorKind> {
+        [
+            ErrorKind::Config,
+            ErrorKind::Network,
+            ErrorKind::NothingToCommit,
+            ErrorKind::Regression,
+        ]
+        .into_iter()
+        .find(|kind| kind.exit_code() == code)
+    }
+
+    /// A new error of this kind.
+    pub fn error(self, message: impl fmt::Display) -> anyhow::Error {
+        anyhow::Error::new(Error {
+            kind: self,
+            inner: anyhow::Error::msg(message.to_string()),
+        })
+    }
+
+    /// The kind of the innermost typed error of `error`, if any. Failed HTTP requests are
+    /// network errors even where they were not marked so.
+    pub fn of(error: &anyhow::Error) -> Option<ErrorKind> {
+        let mut kind = None;
+        for cause in error.chain() {
+            // The chain skips the top of the wrapped error, so look into it separately
+            if let Some(typed) = cause.downcast_ref::<Error>() {
+                return Some(ErrorKind::of(&typed.inner).unwrap_or(typed.kind));
+            }
+            if cause.is::<reqwest::Error>() {
+                kind = Some(ErrorKind::Network);
+            }
+        }
+        kind
+    }
+}
+
+/// Exit code for an error returned by [`crate::run_command`].
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    ErrorKind::of(error).map_or(EXIT_FAILURE, ErrorKind::exit_code)
+}
+
+/// An error marked with its kind. It reads as the error it wraps, so marking does not change
+/// messages.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    inner: anyhow::Error,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.inner)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.inner.chain().nth(1)
+    }
+}
+
+/// Marks the error of a result with a kind, like [`anyhow::Context`] adds a message.
+pub trait ResultExt<T> {
+    fn kind(self, kind: ErrorKind) -> anyhow::Result<T>;
+}
+
+impl<T, E> ResultExt<T> for Result<T, E>
+where
+    E: Into<anyhow::Error>,
+{
+    fn kind(self, kind: ErrorKind) -> anyhow::Result<T> {
+        self.map_err(|e| {
+            anyhow::Error::new(Error {
+                kind,
+                inner: e.into(),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context};
+
+    #[test]
+    fn kinds_survive_context_without_changing_messages() {
+        let error = Err::<(), _>(anyhow!("Failed to read config file: missing"))
+            .context("Failed to parse")
+            .kind(ErrorKind::Config)
+            .context("Failed to load")
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&error), Some(ErrorKind::Config));
+        assert_eq!(exit_code(&error), 3);
+        assert_eq!(
+            format!("{:#}", error),
+            "Failed to load: Failed to parse: Failed to read config file: missing"
+        );
+    }
+
+    #[test]
+    fn the_innermost_kind_wins() {
+        let error = Err::<(), _>(ErrorKind::NothingToCommit.error("Nothing to commit"))
+            .kind(ErrorKind::Config)
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&error), Some(ErrorKind::NothingToCommit));
+        assert_eq!(exit_code(&anyhow!("plain")), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn kinds_are_found_by_their_exit_codes() {
+        assert_eq!(ErrorKind::from_exit_code(6), Some(ErrorKind::Regression));
+        assert_eq!(ErrorKind::from_exit_code(EXIT_FAILURE), None);
+    }
+}
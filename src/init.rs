@@ -1,44 +1,80 @@
-use crate::{Config, General};
+use crate::compete;
+use crate::config::{Config, General, Lang, Paths};
+use crate::messages::msg;
+use crate::output::{print_json, Output};
 use anyhow::{anyhow, Context, Result};
 use clap::Args;
-use colored::Colorize;
+use serde_json::json;
+use tracing::info;
 use url::Url;
 
 #[derive(Args)]
 pub(crate) struct InitArgs {
-    name: String,
+    /// Contest id, taken from the package inside a cargo-compete workspace if omitted
+    name: Option<String>,
     #[arg(short, long)]
     force: bool,
+    #[arg(short, long, value_enum, default_value_t = Lang::Ja)]
+    lang: Lang,
 }
 
-pub(crate) fn init(args: InitArgs, file_name: &str) -> Result<()> {
+pub(crate) fn init(args: InitArgs, file_name: &str, output: Output) -> Result<()> {
     let path = std::path::Path::new(&file_name);
     if !args.force && path.exists() {
-        return Err(anyhow!(
-            "{} already exists. Use --force to overwrite",
-            file_name
-        ));
+        return Err(anyhow!(msg!("init.exists", file_name)));
     }
 
+    let package = compete::find_package(&std::env::current_dir()?)?;
+    let name = match (args.name, &package) {
+        (Some(name), _) => name,
+        (None, Some(package)) => package.name.clone(),
+        (None, None) => return Err(anyhow!(msg!("init.name_required"))),
+    };
+    let problem_url = match package.and_then(|package| package.problem_url) {
+        Some(problem_url) => with_lang(&problem_url, args.lang)?,
+        None => build_default_problem_url(&name, args.lang)?,
+    };
+
     let config = Config {
         general: General {
-            name: args.name.clone(),
-            problem_url: build_default_problem_url(&args.name)?,
+            name: name.clone(),
+            problem_url,
+            lang: args.lang,
+            message_lang: None,
         },
+        paths: Paths::default(),
+        tune: None,
+        notify: None,
+        overlay: None,
+        source: None,
+        clipboard: None,
+        run: None,
+        test: None,
+        solver: None,
+        sweep: None,
+        watch: None,
+        remote: None,
+        batch: None,
+        download: None,
+        http: None,
+        problem: None,
     };
     let config_str = toml::to_string(&config)
         .context(format!("Failed to serialize config to TOML: {:?}", config))?;
 
     std::fs::write(path, config_str)
         .context(format!("Failed to write config to file: {}", file_name))?;
-    eprintln!(
-        "{}",
-        format!("Initialized project with name: {}", args.name).green()
-    );
+    info!("{}", msg!("init.done", name));
+    if output.is_json() {
+        print_json(&json!({
+            "config_file": file_name,
+            "general": config.general,
+        }))?;
+    }
     Ok(())
 }
 
-fn build_default_problem_url(name: &String) -> Result<String> {
+pub(crate) fn build_default_problem_url(name: &String, lang: Lang) -> Result<String> {
     let base_url = "https://atcoder.jp/contests";
     let mut url = Url::parse(base_url).context(anyhow!("Failed to parse URL: {}", base_url))?;
 
@@ -47,8 +83,24 @@ fn build_default_problem_url(name: &String) -> Result<String> {
         .push(name)
         .push("tasks")
         .push(format!("{}_a", name).as_str());
-    url.query_pairs_mut().append_pair("lang", "ja");
+    url.query_pairs_mut().append_pair("lang", lang.as_str());
+
+    Ok(url.into())
+}
 
+/// Sets the `lang` query of a problem URL, replacing any existing one.
+fn with_lang(problem_url: &str, lang: Lang) -> Result<String> {
+    let mut url =
+        Url::parse(problem_url).context(format!("Failed to parse URL: {}", problem_url))?;
+    let pairs = url
+        .query_pairs()
+        .filter(|(key, _)| key != "lang")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("lang", lang.as_str());
     Ok(url.into())
 }
 
@@ -64,11 +116,12 @@ mod tests {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join(DEFAULT_CONFIG_FILE_NAME);
         let args = InitArgs {
-            name: "test_project".to_string(),
+            name: Some("test_project".to_string()),
             force: false,
+            lang: Lang::Ja,
         };
 
-        init(args, file_path.to_str().unwrap()).unwrap();
+        init(args, file_path.to_str().unwrap(), Output::Text).unwrap();
 
         assert!(file_path.exists());
         let content = fs::read_to_string(file_path).unwrap();
@@ -82,11 +135,12 @@ mod tests {
         fs::write(&file_path, "existing content").unwrap();
 
         let args = InitArgs {
-            name: "new_project".to_string(),
+            name: Some("new_project".to_string()),
             force: true,
+            lang: Lang::Ja,
         };
 
-        init(args, file_path.to_str().unwrap()).unwrap();
+        init(args, file_path.to_str().unwrap(), Output::Text).unwrap();
 
         let content = fs::read_to_string(file_path).unwrap();
         assert!(content.contains("new_project"));
@@ -100,11 +154,12 @@ mod tests {
         fs::write(&file_path, "existing content").unwrap();
 
         let args = InitArgs {
-            name: "new_project".to_string(),
+            name: Some("new_project".to_string()),
             force: false,
+            lang: Lang::Ja,
         };
 
-        let result = init(args, file_path.to_str().unwrap());
+        let result = init(args, file_path.to_str().unwrap(), Output::Text);
         assert!(result.is_err());
         let error_message = result.unwrap_err().to_string();
         assert!(error_message.contains("already exists"));
@@ -112,10 +167,32 @@ mod tests {
 
     #[test]
     fn build_default_url() {
-        let url = build_default_problem_url(&"ahc001".to_string()).unwrap();
+        let url = build_default_problem_url(&"ahc001".to_string(), Lang::Ja).unwrap();
         assert_eq!(
             url,
             "https://atcoder.jp/contests/ahc001/tasks/ahc001_a?lang=ja"
         );
     }
+
+    #[test]
+    fn build_default_url_in_english() {
+        let url = build_default_problem_url(&"ahc001".to_string(), Lang::En).unwrap();
+        assert_eq!(
+            url,
+            "https://atcoder.jp/contests/ahc001/tasks/ahc001_a?lang=en"
+        );
+    }
+
+    #[test]
+    fn replaces_lang_of_problem_url() {
+        let url = with_lang(
+            "https://atcoder.jp/contests/ahc030/tasks/ahc030_a?lang=ja",
+            Lang::En,
+        )
+        .unwrap();
+        assert_eq!(
+            url,
+            "https://atcoder.jp/contests/ahc030/tasks/ahc030_a?lang=en"
+        );
+    }
 }
@@ -0,0 +1,173 @@
+//! A log of what `ahc` did in the project, one JSON object per line in `.ahc/journal.jsonl`, so
+//! that `ahc undo` can take back the last commit and `ahc status` can tell what happened last.
+
+use crate::notify::format_timestamp;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const JOURNAL_PATH: &str = ".ahc/journal.jsonl";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Entry {
+    pub(crate) time: String,
+    #[serde(flatten)]
+    pub(crate) operation: Operation,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub(crate) enum Operation {
+    /// `ahc commit` created `commit` on top of `parent`, tagging it with `tag` if given
+    Commit {
+        commit: String,
+        parent: String,
+        message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+        /// The pahcer result whose score the message carries
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        result_file: Option<PathBuf>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        average_score: Option<f64>,
+    },
+    /// `ahc undo` took back the commit
+    Undo { commit: String },
+    /// `ahc download` unpacked `files` files of the tools
+    Download { zip_url: String, files: usize },
+    /// `ahc tune` ran the study, which has `trials` trials in all now
+    Tune {
+        study: String,
+        trials: usize,
+        best: Option<f64>,
+    },
+}
+
+pub(crate) struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    /// The journal of the project in the current directory.
+    pub(crate) fn open() -> Self {
+        Journal::at(Path::new(JOURNAL_PATH))
+    }
+
+    fn at(path: &Path) -> Self {
+        Journal {
+            path: path.to_path_buf(),
+        }
+    }
+
+    pub(crate) fn load(&self) -> Result<Vec<Entry>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let content = std::fs::read_to_string(&self.path)
+            .context(format!("Failed to read journal: {}", self.path.display()))?;
+        let mut entries = vec![];
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(line).context(format!(
+                "Failed to parse line {} of {}",
+                i + 1,
+                self.path.display()
+            ))?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    pub(crate) fn append(&self, operation: Operation) -> Result<()> {
+        if let Some(dir) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .context(format!("Failed to create directory: {}", dir.display()))?;
+        }
+        let entry = Entry {
+            time: format_timestamp(SystemTime::now()),
+            operation,
+        };
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context(format!("Failed to open journal: {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+}
+
+/// The latest operation which was not undone yet. Undoing repeatedly walks further back.
+pub(crate) fn last_undoable(entries: &[Entry]) -> Option<&Entry> {
+    let mut done = vec![];
+    for entry in entries {
+        match &entry.operation {
+            Operation::Commit { .. } => done.push(entry),
+            Operation::Undo { commit } => {
+                if let Some(i) = done.iter().rposition(
+                    |e| matches!(&e.operation, Operation::Commit { commit: c, .. } if c == commit),
+                ) {
+                    done.remove(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    done.pop()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(id: &str) -> Operation {
+        Operation::Commit {
+            commit: id.to_string(),
+            parent: format!("{}^", id),
+            message: "message".to_string(),
+            tag: None,
+            result_file: None,
+            average_score: None,
+        }
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal = Journal::at(&temp_dir.path().join(".ahc/journal.jsonl"));
+        assert!(journal.load().unwrap().is_empty());
+
+        journal.append(commit("a")).unwrap();
+        journal
+            .append(Operation::Undo {
+                commit: "a".to_string(),
+            })
+            .unwrap();
+        let entries = journal.load().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].operation, commit("a"));
+    }
+
+    #[test]
+    fn undoing_walks_back_through_commits() {
+        let entry = |operation| Entry {
+            time: String::new(),
+            operation,
+        };
+        let undo = |id: &str| {
+            entry(Operation::Undo {
+                commit: id.to_string(),
+            })
+        };
+        let mut entries = vec![entry(commit("a")), entry(commit("b"))];
+        assert_eq!(last_undoable(&entries).unwrap().operation, commit("b"));
+        entries.push(undo("b"));
+        assert_eq!(last_undoable(&entries).unwrap().operation, commit("a"));
+        entries.push(undo("a"));
+        assert_eq!(last_undoable(&entries), None);
+    }
+}
@@ -0,0 +1,285 @@
+//! Tools for AtCoder Heuristic Contest projects, behind the `ahc` command.
+//!
+//! Besides [`run_command`], which runs a parsed command line as the binary does, the building
+//! blocks are public for scripts and other front-ends:
+//!
+//! - [`config`] loads the layered project configuration
+//! - [`download`] finds and unpacks a problem's local tools
+//! - [`pahcer`] reads the results of pahcer runs
+//! - [`commit`] builds commit messages from those results
+//! - [`error`] tells the kinds of failures apart, as `ahc`'s exit codes do
+
+mod ab;
+mod archive;
+mod batch;
+mod case;
+mod clipboard;
+pub mod commit;
+mod compete;
+mod complete;
+pub mod config;
+mod dotenv;
+pub mod download;
+pub mod error;
+mod http;
+mod init;
+mod journal;
+mod logging;
+mod login;
+mod messages;
+mod notify;
+mod output;
+mod overlay;
+pub mod pahcer;
+mod plugin;
+mod problem;
+mod remote;
+mod results;
+mod run;
+mod runner;
+mod session;
+mod sha256;
+mod source;
+mod status;
+mod sweep;
+mod tar;
+mod tune;
+mod undo;
+mod watch;
+mod workspace;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use config::load_config;
+use messages::msg;
+use output::Output;
+
+/// Name of the project configuration file
+pub const DEFAULT_CONFIG_FILE_NAME: &str = "ahc_tools.toml";
+
+/// Runs a parsed command line.
+pub fn run_command(cli: Cli) -> Result<()> {
+    logging::init(cli.verbose, cli.quiet, cli.log_file.as_deref())?;
+
+    let config_file_name = cli
+        .config_file_name
+        .as_deref()
+        .unwrap_or(DEFAULT_CONFIG_FILE_NAME);
+
+    // Run in the contest named by --contest, else from the contest package when invoked
+    // elsewhere in a cargo-compete workspace, else in the contest chosen with `ahc workspace switch`
+    let current_dir = std::env::current_dir()?;
+    let completing = matches!(
+        cli.command,
+        Commands::Completions(_) | Commands::Complete(_)
+    );
+    if let (Some(contest), false) = (cli.contest.as_deref(), completing) {
+        if let Some((name, dir)) =
+            workspace::contest_dir(&current_dir, config_file_name, Some(contest))?
+        {
+            if dir != current_dir {
+                std::env::set_current_dir(&dir)?;
+                tracing::info!("{}", msg!("workspace.using", name, dir.display()));
+            }
+        }
+    } else if !std::path::Path::new(config_file_name).exists() {
+        if let Some(package) = compete::find_package(&current_dir)? {
+            if package.dir != current_dir {
+                std::env::set_current_dir(&package.dir)?;
+                tracing::info!("{}", msg!("compete.using_package", package.dir.display()));
+            }
+        } else if !completing && !matches!(cli.command, Commands::Init(_) | Commands::Workspace(_))
+        {
+            if let Some((name, dir)) = workspace::contest_dir(&current_dir, config_file_name, None)?
+            {
+                std::env::set_current_dir(&dir)?;
+                tracing::info!("{}", msg!("workspace.using", name, dir.display()));
+            }
+        }
+    }
+
+    dotenv::load(&dotenv::dotenv_path(config_file_name))?;
+    messages::init(config_file_name);
+
+    // Load config file except for init command
+    let config = match cli.command {
+        Commands::Init(_)
+        | Commands::Config(_)
+        | Commands::Completions(_)
+        | Commands::Complete(_)
+        | Commands::Undo(_)
+        | Commands::Restore(_)
+        | Commands::Workspace(_)
+        | Commands::External(_) => None,
+        // Only for [http], which a login outside of a project goes without
+        Commands::Login(_) => load_config(config_file_name).ok(),
+        _ => Some(load_config(config_file_name)?),
+    };
+    if let Some(http) = config.as_ref().and_then(|config| config.http.as_ref()) {
+        http::configure(http)?;
+    }
+
+    let output = cli.output;
+    match cli.command {
+        Commands::Init(args) => {
+            init::init(args, config_file_name, output)?;
+        }
+        Commands::Config(args) => {
+            config::config(args, config_file_name, output)?;
+        }
+        Commands::Download(args) => {
+            download::download(args, config.unwrap(), output)?;
+        }
+        Commands::Problem(args) => {
+            problem::problem(args, config.unwrap(), config_file_name, output)?;
+        }
+        Commands::Commit(args) => {
+            commit::commit(args, config.unwrap(), output)?;
+        }
+        Commands::Run(args) => {
+            run::run(args, config.unwrap(), output)?;
+        }
+        Commands::Test(args) => {
+            runner::test(args, config.unwrap(), output)?;
+        }
+        Commands::Ab(args) => {
+            ab::ab(args, config.unwrap(), output)?;
+        }
+        Commands::Sweep(args) => {
+            sweep::sweep(args, config.unwrap(), output)?;
+        }
+        Commands::Watch(args) => {
+            watch::watch(args, config.unwrap(), output)?;
+        }
+        Commands::Remote(args) => {
+            remote::remote(args, config.unwrap(), output)?;
+        }
+        Commands::Batch(args) => {
+            batch::batch(args, config.unwrap(), output)?;
+        }
+        Commands::Results(args) => {
+            results::results(args, config.unwrap(), output)?;
+        }
+        Commands::Tune(args) => {
+            tune::tune(args, config.unwrap(), output)?;
+        }
+        Commands::Case(args) => {
+            case::case(args, config.unwrap(), output)?;
+        }
+        Commands::Source(args) => {
+            source::source(args, config.unwrap(), output)?;
+        }
+        Commands::Workspace(args) => {
+            workspace::workspace(args, config_file_name, output)?;
+        }
+        Commands::Status(args) => {
+            status::status(args, config.unwrap(), output)?;
+        }
+        Commands::Login(args) => {
+            login::login(args, output)?;
+        }
+        Commands::Undo(args) => {
+            undo::undo(args, output)?;
+        }
+        Commands::Archive(args) => {
+            archive::archive(args, config.unwrap(), config_file_name, output)?;
+        }
+        Commands::Restore(args) => {
+            archive::restore(args, output)?;
+        }
+        Commands::Completions(args) => {
+            complete::completions(args);
+        }
+        Commands::Complete(args) => {
+            complete::complete(args, config_file_name)?;
+        }
+        Commands::External(args) => {
+            plugin::run(args, config_file_name, output)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The message printed for an error of [`run_command`], in the language of `ahc`'s messages.
+pub fn error_message(error: &anyhow::Error) -> String {
+    msg!("cli.error", error)
+}
+
+/// The command line of `ahc`.
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+    #[arg(short, long)]
+    config_file_name: Option<String>,
+    /// Run in this contest of the workspace, see `ahc workspace list`
+    #[arg(long, global = true)]
+    contest: Option<String>,
+    /// Print more details of what the command does, -vv for even more
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Only print warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Also append the log, with timestamps and debug details, to this file
+    #[arg(long, global = true)]
+    log_file: Option<std::path::PathBuf>,
+    /// Print the results of the command as JSON, for jq and scripts
+    #[arg(long, value_enum, global = true, default_value_t = Output::Text)]
+    output: Output,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    Init(init::InitArgs),
+    Download(download::DownloadArgs),
+    /// Save the problem statement as Markdown in .ahc/problem.md, in each language of the page,
+    /// and its limits and scoring in [problem] of the config
+    Problem(problem::ProblemArgs),
+    Commit(commit::CommitArgs),
+    Config(config::ConfigArgs),
+    /// Run pahcer and print the average score, committing the result with --commit
+    Run(run::RunArgs),
+    /// Run the solver on every input in parallel and score it with the tools, without pahcer
+    Test(runner::TestArgs),
+    /// Run the solvers of two commits on the same seeds and compare them seed by seed
+    Ab(ab::AbArgs),
+    /// Run the solver for every combination of the values of [sweep.params] and tabulate them
+    Sweep(sweep::SweepArgs),
+    /// Run a few seeds whenever the source changes and show how the average score moved
+    Watch(watch::WatchArgs),
+    /// Run a command of ahc on the host of [remote], e.g. `ahc remote test`, copying the project
+    /// there and the results back
+    Remote(remote::RemoteArgs),
+    /// Split the seeds across workers spawned by [batch] command and merge their results
+    Batch(batch::BatchArgs),
+    /// Work with result files, e.g. merge those of several machines
+    Results(results::ResultsArgs),
+    Tune(tune::TuneArgs),
+    /// Print the input or output of a seed, or copy it with --copy
+    Case(case::CaseArgs),
+    /// Print the source to submit, or copy it with --copy
+    Source(source::SourceArgs),
+    /// Show the latest and best runs, what ahc did last and what to do next
+    Status(status::StatusArgs),
+    /// Take back the last commit made by `ahc commit`, keeping its changes staged
+    Undo(undo::UndoArgs),
+    /// Log in to AtCoder and keep the session, sent when fetching pages of contests needing a
+    /// login
+    Login(login::LoginArgs),
+    /// Pack the results, outputs, tune studies and journal into a .tar.gz
+    Archive(archive::ArchiveArgs),
+    /// Unpack an archive made by `ahc archive`
+    Restore(archive::RestoreArgs),
+    /// List the contests of a repository holding several, and choose one
+    Workspace(workspace::WorkspaceArgs),
+    /// Print a completion script for bash, zsh or fish
+    Completions(complete::CompletionsArgs),
+    #[command(name = "__complete", hide = true)]
+    Complete(complete::CompleteArgs),
+    /// Runs `ahc-<name>` from PATH for any other command
+    #[command(external_subcommand)]
+    External(Vec<String>),
+}
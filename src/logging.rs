@@ -0,0 +1,202 @@
+//! Logging through `tracing`: messages go to stderr at the verbosity chosen by `-v`/`-q`, and
+//! with every detail to the `--log-file` if there is one.
+
+use crate::notify::format_timestamp;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// Prefix of the targets of this crate's events; those of dependencies only show at `-vv`.
+const CRATE_TARGET: &str = "ahc_tools";
+
+/// Installs the logger for the rest of the process. `verbose` counts the `-v` flags.
+pub(crate) fn init(verbose: u8, quiet: bool, log_file: Option<&Path>) -> Result<()> {
+    let level = stderr_level(verbose, quiet);
+    let file = log_file
+        .map(|path| {
+            File::options()
+                .create(true)
+                .append(true)
+                .open(path)
+                .context(format!("Failed to open log file: {}", path.display()))
+        })
+        .transpose()?
+        .map(Mutex::new);
+    let logger = Logger {
+        level,
+        file,
+        next_span: AtomicU64::new(1),
+    };
+    // Already set when commands run more than once in a process, as in tests
+    let _ = tracing::subscriber::set_global_default(logger);
+    Ok(())
+}
+
+fn stderr_level(verbose: u8, quiet: bool) -> Level {
+    match (quiet, verbose) {
+        (true, _) => Level::WARN,
+        (false, 0) => Level::INFO,
+        (false, 1) => Level::DEBUG,
+        (false, _) => Level::TRACE,
+    }
+}
+
+struct Logger {
+    /// Most verbose level printed to stderr
+    level: Level,
+    /// Log file, which records down to `DEBUG` whatever the verbosity
+    file: Option<Mutex<File>>,
+    next_span: AtomicU64,
+}
+
+impl Logger {
+    fn file_level(&self) -> Option<Level> {
+        self.file.as_ref().map(|_| self.level.max(Level::DEBUG))
+    }
+
+    fn shows(level: Level, metadata: &Metadata) -> bool {
+        *metadata.level() <= level
+            && (level == Level::TRACE || metadata.target().starts_with(CRATE_TARGET))
+    }
+}
+
+impl Subscriber for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        Logger::shows(self.level, metadata)
+            || self
+                .file_level()
+                .is_some_and(|level| Logger::shows(level, metadata))
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::from_level(
+            self.file_level()
+                .map_or(self.level, |level| level.max(self.level)),
+        ))
+    }
+
+    fn new_span(&self, _span: &Attributes) -> Id {
+        Id::from_u64(self.next_span.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &Id, _values: &Record) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event) {
+        let metadata = event.metadata();
+        let mut message = Message::default();
+        event.record(&mut message);
+
+        if Logger::shows(self.level, metadata) {
+            let line = match *metadata.level() {
+                Level::ERROR => message.text.red().to_string(),
+                Level::WARN => message.text.yellow().to_string(),
+                Level::INFO => message.text.clone(),
+                _ => format!("[{}] {}", metadata.level(), message.text)
+                    .dimmed()
+                    .to_string(),
+            };
+            eprintln!("{}", line);
+        }
+        if let (Some(file), Some(level)) = (&self.file, self.file_level()) {
+            if Logger::shows(level, metadata) {
+                let mut file = file.lock().unwrap();
+                let _ = writeln!(
+                    file,
+                    "{} {:5} {}: {}",
+                    format_timestamp(SystemTime::now()),
+                    metadata.level(),
+                    metadata.target(),
+                    message.text
+                );
+            }
+        }
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+/// The message of an event followed by its other fields as `key=value`.
+#[derive(Default)]
+struct Message {
+    text: String,
+}
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.text);
+            let _ = write!(self.text, "{:?}{}", value, fields);
+        } else {
+            let _ = write!(self.text, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.record_debug(field, &format_args!("{}", value));
+        } else {
+            let _ = write!(self.text, " {}={}", field.name(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbosity_flags_select_the_level() {
+        assert_eq!(stderr_level(0, true), Level::WARN);
+        assert_eq!(stderr_level(0, false), Level::INFO);
+        assert_eq!(stderr_level(1, false), Level::DEBUG);
+        assert_eq!(stderr_level(3, false), Level::TRACE);
+    }
+
+    #[test]
+    fn dependencies_only_show_at_trace() {
+        assert!(!Logger::shows(Level::INFO, &OURS));
+        assert!(Logger::shows(Level::DEBUG, &OURS));
+        assert!(!Logger::shows(Level::DEBUG, &THEIRS));
+        assert!(Logger::shows(Level::TRACE, &THEIRS));
+    }
+
+    static OURS: Metadata<'static> = tracing::metadata!(
+        name: "event",
+        target: "ahc_tools::download",
+        level: Level::DEBUG,
+        fields: &[],
+        callsite: &Callsite,
+        kind: tracing::metadata::Kind::EVENT,
+    );
+    static THEIRS: Metadata<'static> = tracing::metadata!(
+        name: "event",
+        target: "hyper::proto",
+        level: Level::DEBUG,
+        fields: &[],
+        callsite: &Callsite,
+        kind: tracing::metadata::Kind::EVENT,
+    );
+
+    struct Callsite;
+
+    impl tracing::Callsite for Callsite {
+        fn set_interest(&self, _: tracing::subscriber::Interest) {}
+        fn metadata(&self) -> &Metadata<'_> {
+            &OURS
+        }
+    }
+}
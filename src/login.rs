@@ -0,0 +1,297 @@
+//! `ahc login`, which logs in to AtCoder with a password or takes the session cookie of a logged-in
+//! browser, checks it with AtCoder and keeps it for the commands fetching its pages.
+
+use crate::error::{ErrorKind, ResultExt};
+use crate::http;
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::session::{self, Session, Store};
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use regex::Regex;
+use reqwest::header::{HeaderMap, COOKIE, LOCATION, SET_COOKIE};
+use serde::Serialize;
+use std::io::{IsTerminal, Write};
+use tracing::info;
+
+const ATCODER_URL: &str = "https://atcoder.jp";
+
+#[derive(Args)]
+pub(crate) struct LoginArgs {
+    /// AtCoder user name, asked for if not given. The password is asked for, or read from stdin
+    /// when it is not a terminal
+    #[arg(short, long)]
+    username: Option<String>,
+    /// Take the REVEL_SESSION cookie of a browser logged in to AtCoder instead of a password,
+    /// asked for or read from stdin
+    #[arg(long, conflicts_with = "username")]
+    session: bool,
+    /// Check that the stored session is still valid instead of logging in
+    #[arg(long, conflicts_with_all = ["username", "session"])]
+    check: bool,
+    /// Forget the stored session
+    #[arg(long, conflicts_with_all = ["username", "session", "check"])]
+    logout: bool,
+    /// Keep the session in session.json next to the global config, readable by the user only,
+    /// instead of the system keyring, e.g. where there is none
+    #[arg(long, conflicts_with_all = ["check", "logout"])]
+    file: bool,
+}
+
+/// What `ahc login --output json` prints.
+#[derive(Serialize, Debug)]
+struct LoginSummary {
+    logged_in: bool,
+    username: Option<String>,
+    /// Where the session is kept
+    store: Option<Store>,
+}
+
+pub(crate) fn login(args: LoginArgs, output: Output) -> Result<()> {
+    let (username, store) = if args.logout {
+        session::forget()?;
+        info!("{}", msg!("login.logged_out"));
+        (None, None)
+    } else if args.check {
+        let Some((session, store)) = session::load()? else {
+            return Err(anyhow!(msg!("login.not_logged_in")));
+        };
+        let username = logged_in_user(ATCODER_URL, &session.revel_session)?
+            .ok_or_else(|| anyhow!(msg!("login.expired")))?;
+        info!("{}", msg!("login.logged_in", username));
+        (Some(username), Some(store))
+    } else {
+        let revel_session = if args.session {
+            let cookie = ask(&msg!("login.ask_session"), true)?;
+            cookie
+                .trim()
+                .trim_start_matches("REVEL_SESSION=")
+                .to_string()
+        } else {
+            let username = match args.username {
+                Some(username) => username,
+                None if std::io::stdin().is_terminal() => ask(&msg!("login.ask_username"), false)?,
+                None => return Err(anyhow!(msg!("login.no_username"))),
+            };
+            let password = ask(&msg!("login.ask_password"), true)?;
+            log_in(ATCODER_URL, &username, &password)?
+        };
+        let username = logged_in_user(ATCODER_URL, &revel_session)?
+            .ok_or_else(|| anyhow!(msg!("login.invalid_session")))?;
+        let session = Session {
+            revel_session,
+            username: Some(username.clone()),
+        };
+        let store = if args.file {
+            Store::File
+        } else {
+            Store::Keyring
+        };
+        session::save(&session, store)?;
+        info!("{}", msg!("login.logged_in", username));
+        match store {
+            Store::Keyring => info!("{}", msg!("login.saved_keyring")),
+            Store::File => info!("{}", msg!("login.saved_file")),
+        }
+        (Some(username), Some(store))
+    };
+
+    if output.is_json() {
+        print_json(&LoginSummary {
+            logged_in: username.is_some(),
+            username,
+            store,
+        })?;
+    }
+    Ok(())
+}
+
+/// Reads a line, asking for it with `prompt` on a terminal and hiding what is typed if `hidden`.
+fn ask(prompt: &str, hidden: bool) -> Result<String> {
+    let stdin = std::io::stdin();
+    let terminal = stdin.is_terminal();
+    if terminal {
+        eprint!("{}", prompt);
+        std::io::stderr().flush()?;
+    }
+    let mut line = String::new();
+    if terminal && hidden {
+        without_echo(|| stdin.read_line(&mut line))?;
+        eprintln!();
+    } else {
+        stdin.read_line(&mut line)?;
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Runs `read` with the echo of the terminal turned off, so that a password does not show.
+#[cfg(unix)]
+fn without_echo<T>(read: impl FnOnce() -> std::io::Result<T>) -> std::io::Result<T> {
+    use std::os::fd::AsRawFd;
+
+    let fd = std::io::stdin().as_raw_fd();
+    let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+        return read();
+    }
+    let original = termios;
+    termios.c_lflag &= !libc::ECHO;
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) };
+    let result = read();
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    result
+}
+
+#[cfg(not(unix))]
+fn without_echo<T>(read: impl FnOnce() -> std::io::Result<T>) -> std::io::Result<T> {
+    read()
+}
+
+/// Logs in to AtCoder at `base` with the form of its login page, returning the session cookie.
+fn log_in(base: &str, username: &str, password: &str) -> Result<String> {
+    let url = format!("{}/login", base);
+    let client = http::client_without_redirects();
+    let (page, cookie) = http::block_on(async {
+        let response = client.get(&url).send().await?.error_for_status()?;
+        let cookie = session_cookie(response.headers());
+        Ok::<_, reqwest::Error>((response.text().await?, cookie))
+    })
+    .context(format!("Failed to fetch {}", url))
+    .kind(ErrorKind::Network)?;
+    let csrf_token = csrf_token(&page).ok_or_else(|| anyhow!(msg!("login.no_form", url)))?;
+
+    let form = [
+        ("username", username),
+        ("password", password),
+        ("csrf_token", csrf_token.as_str()),
+    ];
+    let response = http::block_on(async {
+        let mut request = client.post(&url).form(&form);
+        if let Some(cookie) = &cookie {
+            request = request.header(COOKIE, format!("REVEL_SESSION={}", cookie));
+        }
+        request.send().await?.error_for_status()
+    })
+    .context(format!("Failed to log in at {}", url))
+    .kind(ErrorKind::Network)?;
+    // AtCoder sends a failed login back to the login page
+    let location = response
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .unwrap_or_default();
+    if !response.status().is_redirection() || location.ends_with("/login") {
+        return Err(anyhow!(msg!("login.failed", username)));
+    }
+    session_cookie(response.headers())
+        .or(cookie)
+        .ok_or_else(|| anyhow!(msg!("login.failed", username)))
+}
+
+/// The user logged in to AtCoder at `base` with the session cookie `revel_session`, if any.
+fn logged_in_user(base: &str, revel_session: &str) -> Result<Option<String>> {
+    let url = format!("{}/home", base);
+    let page = http::block_on(async {
+        http::client()
+            .get(&url)
+            .header(COOKIE, format!("REVEL_SESSION={}", revel_session))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    })
+    .context(format!("Failed to fetch {}", url))
+    .kind(ErrorKind::Network)?;
+    let regex = Regex::new(r#"userScreenName\s*=\s*"([^"]*)""#).expect("the user regex is valid");
+    Ok(regex
+        .captures(&page)
+        .map(|captures| captures[1].to_string())
+        .filter(|username| !username.is_empty()))
+}
+
+/// The value of the `REVEL_SESSION` cookie a response sets, if any.
+fn session_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.strip_prefix("REVEL_SESSION="))
+        .map(|value| value.split(';').next().unwrap_or_default().to_string())
+        .next_back()
+}
+
+fn csrf_token(page: &str) -> Option<String> {
+    let document = scraper::Html::parse_document(page);
+    let selector = scraper::Selector::parse(r#"input[name="csrf_token"]"#)
+        .expect("the token selector is valid");
+    let token = document.select(&selector).next()?.value().attr("value")?;
+    Some(token.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    #[test]
+    fn logs_in_with_the_form() {
+        let mut server = mockito::Server::new();
+        let page = server
+            .mock("GET", "/login")
+            .with_header("set-cookie", "REVEL_SESSION=anonymous; Path=/; HttpOnly")
+            .with_body(r#"<form><input type="hidden" name="csrf_token" value="t0k3n="></form>"#)
+            .create();
+        let accepted = server
+            .mock("POST", "/login")
+            .match_header("cookie", "REVEL_SESSION=anonymous")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("username".to_string(), "alice".to_string()),
+                Matcher::UrlEncoded("password".to_string(), "secret".to_string()),
+                Matcher::UrlEncoded("csrf_token".to_string(), "t0k3n=".to_string()),
+            ]))
+            .with_status(302)
+            .with_header("location", "/home")
+            .with_header("set-cookie", "REVEL_SESSION=alice%00; Path=/; HttpOnly")
+            .create();
+        let rejected = server
+            .mock("POST", "/login")
+            .match_body(Matcher::UrlEncoded(
+                "password".to_string(),
+                "wrong".to_string(),
+            ))
+            .with_status(302)
+            .with_header("location", "/login")
+            .create();
+
+        assert_eq!(
+            log_in(&server.url(), "alice", "secret").unwrap(),
+            "alice%00"
+        );
+        assert!(log_in(&server.url(), "alice", "wrong").is_err());
+        page.expect(2).assert();
+        accepted.assert();
+        rejected.assert();
+    }
+
+    #[test]
+    fn finds_the_user_of_a_session() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("GET", "/home")
+            .match_header("cookie", "REVEL_SESSION=alice%00")
+            .with_body(r#"<script>var userScreenName = "alice";</script>"#)
+            .create();
+        server
+            .mock("GET", "/home")
+            .match_header("cookie", "REVEL_SESSION=expired")
+            .with_body(r#"<script>var userScreenName = "";</script>"#)
+            .create();
+
+        assert_eq!(
+            logged_in_user(&server.url(), "alice%00").unwrap(),
+            Some("alice".to_string())
+        );
+        assert_eq!(logged_in_user(&server.url(), "expired").unwrap(), None);
+    }
+}
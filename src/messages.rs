@@ -0,0 +1,1273 @@
+//! The catalog of user-facing messages in English and Japanese. The language is
+//! `[general] message_lang`, or the locale's when it is not set.
+
+use crate::config::{self, Lang};
+use clap::ValueEnum;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Messages by key, in English and Japanese. `{}` takes the next argument and `{0}` a given one,
+/// so that a translation can reorder them.
+const CATALOG: &[(&str, &str, &str)] = &[
+    ("cli.error", "Error: {}", "エラー: {}"),
+    (
+        "compete.using_package",
+        "Using cargo-compete package {}",
+        "cargo-compete のパッケージ {} を使います",
+    ),
+    (
+        "config.problems",
+        "Warning: {} problem(s) found in configuration:",
+        "警告: 設定に {} 件の問題があります:",
+    ),
+    ("config.unknown_key", "unknown key `{}`", "不明なキー `{}`"),
+    (
+        "config.deprecated_key",
+        "deprecated key `{}` (use `{}` instead)",
+        "非推奨のキー `{}` (代わりに `{}` を使ってください)",
+    ),
+    ("config.in_source", "{} in {}", "{} ({})"),
+    (
+        "config.created",
+        "Created {} from template",
+        "テンプレートから {} を作成しました",
+    ),
+    ("config.valid", "{} is valid", "{} は有効です"),
+    (
+        "config.inferred",
+        "No {} found, using contest {} {}",
+        "{} がないため、コンテスト {} を使います {}",
+    ),
+    (
+        "init.exists",
+        "{} already exists. Use --force to overwrite",
+        "{} は既にあります。上書きするには --force を付けてください",
+    ),
+    (
+        "init.name_required",
+        "Contest name is required outside of a cargo-compete package",
+        "cargo-compete のパッケージの外ではコンテスト名が必要です",
+    ),
+    (
+        "init.done",
+        "Initialized project with name: {}",
+        "プロジェクト {} を初期化しました",
+    ),
+    (
+        "download.links",
+        "Found {} tool links:",
+        "ツールのリンクが {} 件見つかりました:",
+    ),
+    (
+        "download.not_one_link",
+        "Found {} tool links, expected 1. Choose one with --index or --match",
+        "ツールのリンクが {} 件見つかりました。1 件のはずです。--index か --match で選んでください",
+    ),
+    (
+        "download.bad_index",
+        "There is no tool link {}: found {}",
+        "ツールのリンク {} はありません: {} 件見つかりました",
+    ),
+    (
+        "download.choose",
+        "Which tools to download? [1-{}]: ",
+        "どのツールをダウンロードしますか? [1-{}]: ",
+    ),
+    (
+        "download.listed",
+        "{} bytes in {} files",
+        "{} バイト、{} ファイル",
+    ),
+    (
+        "download.login_required",
+        "{} needs a login to AtCoder. Log in with ahc login, or again if the session expired",
+        "{} の取得には AtCoder へのログインが必要です。ahc login でログインしてください。期限切れならもう一度ログインしてください",
+    ),
+    (
+        "download.fetching",
+        "Downloading tools from: {}",
+        "ツールをダウンロードしています: {}",
+    ),
+    (
+        "download.unchanged",
+        "The tools in {} are up to date",
+        "{} のツールは最新です",
+    ),
+    (
+        "download.other",
+        "Extracting {} into {}",
+        "{} を {} に展開しています",
+    ),
+    (
+        "download.unzipping",
+        "Extracting tools to: {}",
+        "ツールを展開しています: {}",
+    ),
+    (
+        "download.resuming",
+        "Resuming the download after the {} bytes already received",
+        "受信済みの {} バイトの続きからダウンロードを再開します",
+    ),
+    (
+        "download.incomplete",
+        "The archive from {} is incomplete: received {} bytes of {}",
+        "{0} のアーカイブが不完全です: {2} バイト中 {1} バイトしか受信していません",
+    ),
+    (
+        "download.from_cache",
+        "Using the archive of {} kept in the cache",
+        "キャッシュにある {} のアーカイブを使います",
+    ),
+    (
+        "download.not_cached",
+        "No archive of {} in {}. Download it once with a connection first",
+        "{} のアーカイブが {} にありません。先に一度ネットワークに接続してダウンロードしてください",
+    ),
+    (
+        "download.nothing_cached",
+        "No archive in {}. Download the tools once with a connection first",
+        "{} にアーカイブがありません。先に一度ネットワークに接続してツールをダウンロードしてください",
+    ),
+    (
+        "download.file_too_large",
+        "{} in the archive is over the limit of {} bytes once decompressed. Raise [download] max_file_mb if the archive is trusted",
+        "アーカイブ内の {} は展開すると上限の {} バイトを超えます。信頼できるアーカイブなら [download] max_file_mb を増やしてください",
+    ),
+    (
+        "download.too_large",
+        "The archive is over the limit of {} bytes once decompressed. Raise [download] max_total_mb if the archive is trusted",
+        "アーカイブは展開すると上限の {} バイトを超えます。信頼できるアーカイブなら [download] max_total_mb を増やしてください",
+    ),
+    (
+        "download.conflicts",
+        "{} files would be overwritten. Run again with --force to overwrite them or --skip-existing to keep them:",
+        "{} 個のファイルが上書きされます。上書きするには --force、残すには --skip-existing を付けて実行してください:",
+    ),
+    (
+        "download.kept",
+        "Kept {}",
+        "{} を残しました",
+    ),
+    (
+        "download.building",
+        "Building the tools in {}",
+        "{} のツールをビルドしています",
+    ),
+    (
+        "download.generating",
+        "Making {} inputs in {}",
+        "{} 個の入力を {} に生成しています",
+    ),
+    (
+        "download.webvis",
+        "Saved the web visualizer {} to {}",
+        "Web 版ビジュアライザ {} を {} に保存しました",
+    ),
+    (
+        "download.no_webvis",
+        "No link to the web visualizer on {}",
+        "{} に Web 版ビジュアライザへのリンクがありません",
+    ),
+    (
+        "download.manifest",
+        "Recorded the checksums of {} files in {}",
+        "{} 個のファイルのチェックサムを {} に記録しました",
+    ),
+    (
+        "download.no_manifest",
+        "No tools were downloaded yet: {} does not exist",
+        "ツールはまだダウンロードされていません: {} がありません",
+    ),
+    (
+        "download.verified",
+        "The {} files of the tools are as downloaded from {}",
+        "ツールの {} 個のファイルは {} からダウンロードしたままです",
+    ),
+    (
+        "download.modified",
+        "Modified since the download: {}",
+        "ダウンロード後に変更されています: {}",
+    ),
+    (
+        "download.missing",
+        "Missing since the download: {}",
+        "ダウンロード後に削除されています: {}",
+    ),
+    (
+        "download.changed",
+        "{} files of the tools changed since the download",
+        "ツールの {} 個のファイルがダウンロード後に変更されています",
+    ),
+    (
+        "problem.saved",
+        "Saved the statement to {}",
+        "問題文を {} に保存しました",
+    ),
+    (
+        "problem.configured",
+        "Recorded the limits and scoring of the problem in [problem] of {}",
+        "問題の制限と得点を {} の [problem] に記録しました",
+    ),
+    (
+        "problem.no_statement",
+        "No statement found on the page, expected an element with id task-statement",
+        "ページに問題文がありません。id が task-statement の要素が必要です",
+    ),
+    (
+        "login.ask_username",
+        "AtCoder user name: ",
+        "AtCoder のユーザー名: ",
+    ),
+    ("login.ask_password", "Password: ", "パスワード: "),
+    (
+        "login.ask_session",
+        "REVEL_SESSION cookie of a browser logged in to AtCoder: ",
+        "AtCoder にログインしたブラウザの REVEL_SESSION クッキー: ",
+    ),
+    (
+        "login.no_username",
+        "Give the AtCoder user name with --username",
+        "AtCoder のユーザー名を --username で指定してください",
+    ),
+    (
+        "login.no_form",
+        "No login form found at {}",
+        "{} にログインフォームがありません",
+    ),
+    (
+        "login.failed",
+        "Failed to log in to AtCoder as {}. Check the user name and password",
+        "{} として AtCoder にログインできませんでした。ユーザー名とパスワードを確認してください",
+    ),
+    (
+        "login.invalid_session",
+        "AtCoder does not know the session. Copy the REVEL_SESSION cookie again from a browser logged in to AtCoder",
+        "AtCoder がこのセッションを認識しません。ログインしたブラウザから REVEL_SESSION クッキーをもう一度コピーしてください",
+    ),
+    (
+        "login.logged_in",
+        "Logged in to AtCoder as {}",
+        "{} として AtCoder にログインしています",
+    ),
+    (
+        "login.saved_keyring",
+        "Saved the session in the system keyring",
+        "セッションをシステムのキーリングに保存しました",
+    ),
+    (
+        "login.saved_file",
+        "Saved the session in session.json next to the global config",
+        "セッションをグローバル設定と同じディレクトリの session.json に保存しました",
+    ),
+    (
+        "login.no_keyring",
+        "Failed to keep the session in the system keyring (security on macOS, secret-tool elsewhere). Run again with --file to keep it in a file instead",
+        "セッションをシステムのキーリング (macOS では security、それ以外では secret-tool) に保存できませんでした。代わりにファイルに保存するには --file を付けて実行してください",
+    ),
+    (
+        "login.not_logged_in",
+        "Not logged in to AtCoder. Log in with ahc login",
+        "AtCoder にログインしていません。ahc login でログインしてください",
+    ),
+    (
+        "login.expired",
+        "The stored AtCoder session expired. Log in again with ahc login",
+        "保存された AtCoder のセッションは期限切れです。ahc login でもう一度ログインしてください",
+    ),
+    (
+        "login.logged_out",
+        "Forgot the AtCoder session",
+        "AtCoder のセッションを削除しました",
+    ),
+    (
+        "http.retrying",
+        "Request to {} failed: {}. Retrying in {}s ({}/{})",
+        "{} へのリクエストが失敗しました: {}。{} 秒後に再試行します ({}/{})",
+    ),
+    (
+        "commit.empty_message",
+        "Commit message is empty",
+        "コミットメッセージが空です",
+    ),
+    (
+        "commit.nothing",
+        "Nothing to commit",
+        "コミットする変更がありません",
+    ),
+    (
+        "commit.no_results",
+        "No result files found. Commit anyway? [y/N]: ",
+        "結果ファイルがありません。このままコミットしますか? [y/N]: ",
+    ),
+    (
+        "commit.done",
+        "Committed {}: {}",
+        "{} をコミットしました: {}",
+    ),
+    (
+        "clipboard.copied",
+        "Copied {} to the clipboard",
+        "{} をクリップボードにコピーしました",
+    ),
+    (
+        "archive.empty",
+        "Nothing to archive, no results, outputs or tune studies found",
+        "アーカイブするものがありません。結果、出力、チューニングのスタディが見つかりません",
+    ),
+    (
+        "archive.created",
+        "Archived {} files into {} ({} bytes)",
+        "{1} に {0} 個のファイルをアーカイブしました ({2} バイト)",
+    ),
+    (
+        "batch.no_config",
+        "No [batch] section found in config file, set command = [...] to spawn a worker",
+        "設定ファイルに [batch] セクションがありません。ワーカーを起動する command = [...] を設定してください",
+    ),
+    (
+        "batch.start",
+        "Running {} seeds on {} workers",
+        "{} 個のシードを {} 個のワーカーで実行します",
+    ),
+    (
+        "batch.worker_failed",
+        "Worker {} failed: {}",
+        "ワーカー {} が失敗しました: {}",
+    ),
+    (
+        "batch.failed",
+        "{} workers failed, the results of the others are in {}",
+        "{} 個のワーカーが失敗しました。他のワーカーの結果は {} にあります",
+    ),
+    (
+        "batch.done",
+        "Average score {} over {} cases ({})",
+        "平均スコア {} ({} ケース, {})",
+    ),
+    (
+        "restore.exists",
+        "{} already exists ({} files in all), use --force to overwrite",
+        "{} は既に存在します (全 {} ファイル)。上書きするには --force を指定してください",
+    ),
+    (
+        "restore.done",
+        "Restored {} files into {}",
+        "{1} に {0} 個のファイルを復元しました",
+    ),
+    (
+        "remote.no_config",
+        "No [remote] section found in config file, set host = \"...\" to run on another machine",
+        "設定ファイルに [remote] セクションがありません。別のマシンで実行するには host = \"...\" を設定してください",
+    ),
+    (
+        "remote.pushing",
+        "Copying the project to {}:{}",
+        "プロジェクトを {}:{} にコピーしています",
+    ),
+    ("remote.running", "Running {} on {}", "{1} で {0} を実行しています"),
+    (
+        "remote.pulling",
+        "Copying the results back from {}",
+        "{} から結果をコピーしています",
+    ),
+    (
+        "remote.failed",
+        "ahc on {} exited with {}",
+        "{} の ahc が {} で終了しました",
+    ),
+    (
+        "results.merged",
+        "Merged {} results into {}: average score {} over {} cases",
+        "{} 個の結果を {} にまとめました: 平均スコア {} ({} ケース)",
+    ),
+    ("run.failed", "{} exited with {}", "{} が {} で終了しました"),
+    (
+        "run.no_result",
+        "{} finished without writing a result to {}",
+        "{} は {} に結果を書かずに終了しました",
+    ),
+    (
+        "run.done",
+        "Average score {} over {} cases ({})",
+        "平均スコア {} ({} ケース, {})",
+    ),
+    (
+        "test.no_config",
+        "No [test] section in the config, set [test] command to the solver",
+        "設定に [test] セクションがありません。[test] command にソルバーを設定してください",
+    ),
+    (
+        "test.no_inputs",
+        "No inputs found in {}, run `ahc download` first",
+        "{} に入力がありません。先に `ahc download` を実行してください",
+    ),
+    (
+        "test.no_tools",
+        "No tools found in {}, run `ahc download` first",
+        "{} にツールがありません。先に `ahc download` を実行してください",
+    ),
+    (
+        "test.building_tool",
+        "Building {} of the tools",
+        "ツールの {} をビルドしています",
+    ),
+    (
+        "test.tools_build_failed",
+        "Failed to build the tools in {}: cargo exited with {}",
+        "{} のツールのビルドに失敗しました: cargo が {} で終了しました",
+    ),
+    (
+        "test.unknown_solver",
+        "No solver {} in the config, the solvers are: {}",
+        "設定にソルバー {} がありません。ソルバー: {}",
+    ),
+    (
+        "test.solver",
+        "Running solver {}",
+        "ソルバー {} を実行します",
+    ),
+    (
+        "test.unknown_profile",
+        "No build profile {}, the profiles are: {}",
+        "ビルドプロファイル {} がありません。プロファイル: {}",
+    ),
+    (
+        "test.profile",
+        "Building the solver with profile {}",
+        "プロファイル {} でソルバーをビルドします",
+    ),
+    (
+        "test.cached",
+        "Running the solver built before at this commit: {}",
+        "このコミットでビルド済みのソルバーを実行します: {}",
+    ),
+    (
+        "test.at_no_binary",
+        "--at runs a binary which the build makes, but the solver command runs {}",
+        "--at はビルドで作られるバイナリを実行しますが、ソルバーのコマンドは {} を実行します",
+    ),
+    (
+        "test.at_no_build",
+        "--at needs [test] build or a build profile to build the solver",
+        "--at でソルバーをビルドするには [test] build かビルドプロファイルが必要です",
+    ),
+    (
+        "test.checkout",
+        "Building the solver of {} in a checkout",
+        "{} のソルバーをチェックアウトしてビルドします",
+    ),
+    (
+        "test.time",
+        "Max time {}s (seed {}), mean {}s",
+        "最大時間 {}s (シード {}), 平均 {}s",
+    ),
+    (
+        "test.near_time_limit",
+        "Max time {}s is {}% of the time limit {}s",
+        "最大時間 {}s は制限時間 {2}s の {1}% です",
+    ),
+    (
+        "test.regressions",
+        "Seeds which dropped the most from {}:",
+        "{} からスコアが大きく下がったシード:",
+    ),
+    (
+        "test.container",
+        "Building and running the solver in a container of {}",
+        "{} のコンテナでソルバーをビルド・実行します",
+    ),
+    (
+        "test.no_affinity",
+        "[test] cpus is ignored, as cores can only be chosen on Linux",
+        "コアの指定は Linux でのみ可能なため、[test] cpus は無視されます",
+    ),
+    (
+        "test.resuming",
+        "Resuming run {} with {} of {} seeds finished",
+        "実行 {} を再開します ({} / {} シード完了済み)",
+    ),
+    (
+        "test.score_noise",
+        "Over {} runs, the score of a seed varies by {}% on average (standard deviation)",
+        "{} 回の実行で、シードごとのスコアは平均 {}% ばらつきました (標準偏差)",
+    ),
+    (
+        "test.start",
+        "Running {} cases with {} jobs",
+        "{} ケースを {} 並列で実行します",
+    ),
+    ("test.case_failed", "Seed {}: {}", "シード {}: {}"),
+    ("test.tle", "TLE on {} seeds: {}", "{} シードで TLE: {}"),
+    (
+        "test.memory",
+        "Peak memory {} MB on seed {}",
+        "最大メモリ {} MB (シード {})",
+    ),
+    (
+        "test.baseline",
+        "Stopping early if significantly worse than {}",
+        "{} より有意に悪ければ途中で打ち切ります",
+    ),
+    (
+        "test.no_baseline",
+        "No baseline for early stopping, no result in {} yet",
+        "{} に結果がまだないため、途中打ち切りの基準がありません",
+    ),
+    (
+        "test.early_stopped",
+        "Stopped early after {} cases, {}% from the baseline on average (p = {})",
+        "{} ケースで打ち切りました。基準から平均 {}% (p = {})",
+    ),
+    (
+        "test.outputs",
+        "Outputs of run {} are in {}",
+        "実行 {} の出力は {} にあります",
+    ),
+    (
+        "test.done",
+        "Average score {} over {} cases, {} failed ({})",
+        "平均スコア {} ({} ケース, 失敗 {}, {})",
+    ),
+    (
+        "test.no_repeats",
+        "repeats must be at least 1",
+        "repeats は 1 以上にしてください",
+    ),
+    (
+        "test.no_jobs",
+        "jobs must be at least 1",
+        "jobs は 1 以上にしてください",
+    ),
+    (
+        "test.no_seeds",
+        "No seeds chosen",
+        "シードが 1 つも選ばれていません",
+    ),
+    (
+        "test.no_cases",
+        "No cases finished, so there is no score to report",
+        "終了したケースがないため、スコアを出せません",
+    ),
+    (
+        "test.invalid_seeds",
+        "Invalid seeds {}, expected e.g. 0..100 or 3,5,8",
+        "不正なシード {} です。0..100 や 3,5,8 のように指定してください",
+    ),
+    (
+        "test.invalid_seed",
+        "Invalid seed {}",
+        "不正なシード {} です",
+    ),
+    (
+        "test.invalid_shard",
+        "Invalid shard {}, expected e.g. 0/4",
+        "不正なシャード {} です。0/4 のように指定してください",
+    ),
+    (
+        "test.shard_out_of_range",
+        "Shard {} is not one of {} shards numbered from 0",
+        "シャード {} は 0 から番号の付いた {} 個のシャードのどれでもありません",
+    ),
+    (
+        "test.no_input_for_seed",
+        "No input for seed {}",
+        "シード {} の入力がありません",
+    ),
+    (
+        "test.tool_not_built",
+        "cargo did not build {}",
+        "cargo が {} をビルドしませんでした",
+    ),
+    (
+        "test.build_failed",
+        "Build failed: {} exited with {}",
+        "ビルドに失敗しました: {} が {} で終了しました",
+    ),
+    (
+        "test.not_produced",
+        "The build at {} did not produce {}",
+        "{} のビルドで {} が作られませんでした",
+    ),
+    (
+        "test.no_workdir",
+        "The git repository has no working directory",
+        "git リポジトリに作業ディレクトリがありません",
+    ),
+    (
+        "test.container_failed",
+        "Failed to start a container of {}: {} exited with {}",
+        "{} のコンテナの起動に失敗しました: {} が {} で終了しました",
+    ),
+    (
+        "test.run_finished",
+        "Run {} already finished",
+        "実行 {} は既に終了しています",
+    ),
+    (
+        "test.run_not_unfinished",
+        "No unfinished run {} in {}",
+        "{1} に未完了の実行 {0} がありません",
+    ),
+    (
+        "test.no_unfinished_run",
+        "No unfinished run in {}",
+        "{} に未完了の実行がありません",
+    ),
+    (
+        "test.no_run",
+        "No run {} in {}",
+        "{1} に実行 {0} がありません",
+    ),
+    (
+        "test.tester_failed",
+        "Tester exited with {}",
+        "テスターが {} で終了しました",
+    ),
+    (
+        "test.solver_failed",
+        "Solver exited with {}",
+        "ソルバーが {} で終了しました",
+    ),
+    (
+        "test.time_limit_exceeded",
+        "Time limit exceeded ({}s)",
+        "実行時間制限を超えました ({}s)",
+    ),
+    (
+        "ab.running",
+        "Running solver {} of {}",
+        "{1} のソルバー {0} を実行します",
+    ),
+    (
+        "sweep.no_config",
+        "No [sweep.params] in the config, set the values of each parameter, e.g. T0 = [1.0, 2.0]",
+        "設定に [sweep.params] がありません。各パラメータの値を設定してください (例: T0 = [1.0, 2.0])",
+    ),
+    (
+        "sweep.no_values",
+        "Every parameter of [sweep.params] needs a list of values",
+        "[sweep.params] の各パラメータには値のリストが必要です",
+    ),
+    (
+        "sweep.combination",
+        "Combination {}/{}: {}",
+        "組み合わせ {}/{}: {}",
+    ),
+    ("sweep.best", "Best: {}", "最良: {}"),
+    (
+        "watch.start",
+        "Watching {} for changes, Ctrl-C to stop",
+        "{} の変更を監視します。Ctrl-C で終了します",
+    ),
+    (
+        "watch.waiting",
+        "Waiting for changes",
+        "変更を待っています",
+    ),
+    (
+        "watch.first",
+        "Average score {}",
+        "平均スコア {}",
+    ),
+    (
+        "watch.delta",
+        "Average score {} ({}) since the previous run",
+        "平均スコア {} (前回の実行から {})",
+    ),
+    (
+        "workspace.switched",
+        "Switched to contest {}",
+        "コンテスト {} に切り替えました",
+    ),
+    (
+        "workspace.unknown",
+        "No contest {} found under {}",
+        "{1} の下にコンテスト {0} がありません",
+    ),
+    (
+        "workspace.using",
+        "Using contest {} in {}",
+        "{1} のコンテスト {0} を使います",
+    ),
+    (
+        "status.hint_download",
+        "Download the local tools with `ahc download`",
+        "`ahc download` でローカルツールをダウンロードしましょう",
+    ),
+    (
+        "status.hint_no_runs",
+        "No pahcer results in {} yet",
+        "{} に pahcer の結果がまだありません",
+    ),
+    (
+        "status.hint_commit_best",
+        "Best run {} ({}) is not committed yet",
+        "最良の実行 {} ({}) がまだコミットされていません",
+    ),
+    (
+        "status.hint_ended",
+        "The contest has ended",
+        "コンテストは終了しました",
+    ),
+    (
+        "status.hint_ending",
+        "The contest ends in {}",
+        "コンテスト終了まであと {} です",
+    ),
+    (
+        "undo.nothing",
+        "Nothing to undo",
+        "取り消せる操作がありません",
+    ),
+    (
+        "undo.head_moved",
+        "HEAD moved from {} to {} since ahc committed it, undo it with git instead",
+        "ahc のコミット {} から HEAD が {} に移動しています。git で取り消してください",
+    ),
+    (
+        "undo.tag_deleted",
+        "Deleted tag {}",
+        "タグ {} を削除しました",
+    ),
+    (
+        "undo.done",
+        "Undid commit {}: {}, its changes are still staged",
+        "コミット {} を取り消しました: {}。変更はステージされたままです",
+    ),
+    (
+        "notify.failed",
+        "Failed to send notification to {}: {}",
+        "{} への通知に失敗しました: {}",
+    ),
+    (
+        "overlay.failed",
+        "Failed to write overlay: {}",
+        "オーバーレイを書き込めませんでした: {}",
+    ),
+    (
+        "plugin.unknown",
+        "Unknown command {}, and no {} found on PATH",
+        "不明なコマンド {} です。PATH に {} もありません",
+    ),
+    (
+        "tune.no_section",
+        "No [tune] section found in config file",
+        "設定ファイルに [tune] セクションがありません",
+    ),
+    (
+        "tune.discarding",
+        "Discarding {} interrupted trial(s) of study {}, pass --resume to finish them",
+        "スタディ {1} の中断された試行 {0} 件を破棄します。続けるには --resume を付けてください",
+    ),
+    (
+        "tune.params_file_jobs",
+        "params_via = \"file\" runs one trial at a time, set jobs to 1",
+        "params_via = \"file\" では試行を 1 つずつ実行します。jobs を 1 にしてください",
+    ),
+    (
+        "tune.resuming",
+        "Resuming study {}",
+        "スタディ {} を再開します",
+    ),
+    (
+        "tune.start",
+        "Tuning study {} over seeds {}..{}",
+        "スタディ {} をシード {}..{} で調整します",
+    ),
+    (
+        "tune.holding_out",
+        "Holding out {} seed(s) for validation",
+        "検証用にシード {} 個を取り分けます",
+    ),
+    (
+        "tune.class",
+        "Tuning class {} ({} seed(s))",
+        "クラス {} を調整します (シード {} 個)",
+    ),
+    (
+        "tune.validating_class",
+        "Validating class {} on {} held-out seed(s)",
+        "クラス {} を取り分けたシード {} 個で検証します",
+    ),
+    (
+        "tune.validating",
+        "Validating on {} held-out seed(s)",
+        "取り分けたシード {} 個で検証します",
+    ),
+    (
+        "tune.best",
+        "Best trial #{}: {} ({})",
+        "最良の試行 #{}: {} ({})",
+    ),
+    (
+        "tune.no_trials",
+        "No trials recorded",
+        "記録された試行はありません",
+    ),
+    (
+        "tune.out_of_time",
+        "Time budget exhausted",
+        "制限時間を使い切りました",
+    ),
+    (
+        "tune.resuming_trial",
+        "Resuming trial after {} seed(s) ({})",
+        "シード {} 個まで終えた試行を再開します ({})",
+    ),
+    (
+        "tune.resuming_race",
+        "Resuming the race of {} candidate(s)",
+        "候補 {} 個のレースを再開します",
+    ),
+    (
+        "tune.racing",
+        "Racing {} candidate(s)",
+        "候補 {} 個でレースします",
+    ),
+    (
+        "tune.race_step",
+        "After {} seed(s): eliminated {}, {} candidate(s) left",
+        "シード {} 個の後: {} 個を除外、残り {} 個",
+    ),
+    ("tune.trial", "Trial #{}: {} ({})", "試行 #{}: {} ({})"),
+    (
+        "tune.trial_pruned",
+        "Trial #{}: pruned after {} seed(s) at {} ({})",
+        "試行 #{}: シード {} 個で打ち切り、{} ({})",
+    ),
+    (
+        "tune.failed_cases",
+        "  {} case(s) failed, e.g. seed {}: {}",
+        "  {} ケースが失敗しました。例えばシード {}: {}",
+    ),
+    (
+        "tune.stopped_cases",
+        "  {} case(s) stopped early behind the best schedule, their scores projected",
+        "  {} ケースが最良のスケジュールに遅れて打ち切られ、スコアは予測値です",
+    ),
+    (
+        "tune.plots_written",
+        "Wrote plots of {} trial(s) to {}",
+        "試行 {} 件のプロットを {} に書き込みました",
+    ),
+    (
+        "tune.params_written",
+        "Wrote tuned parameters to {}",
+        "調整したパラメータを {} に書き込みました",
+    ),
+    (
+        "tune.truncated_trial",
+        "Dropping truncated trial at the end of {}",
+        "{} の末尾の途切れた試行を削除します",
+    ),
+    (
+        "tune.no_run_to_resume",
+        "Study {} has no run to resume",
+        "スタディ {} に再開できる実行がありません",
+    ),
+    (
+        "tune.params_changed",
+        "The parameters of study {} changed since the run started, tune into a new study instead",
+        "スタディ {} のパラメータが実行の開始後に変わりました。新しいスタディで調整してください",
+    ),
+    (
+        "tune.no_seeds",
+        "No seeds to evaluate: start_seed={} end_seed={}",
+        "評価するシードがありません: start_seed={} end_seed={}",
+    ),
+    (
+        "tune.no_jobs",
+        "jobs must be at least 1",
+        "jobs は 1 以上にしてください",
+    ),
+    (
+        "tune.invalid_duration",
+        "Invalid duration: {}",
+        "不正な時間です: {}",
+    ),
+    (
+        "tune.duration_unit",
+        "Invalid duration unit in {}, use s, m or h",
+        "{} の時間の単位が不正です。s, m, h のいずれかを使ってください",
+    ),
+    (
+        "tune.no_params",
+        "No parameters defined in [tune.params]",
+        "[tune.params] にパラメータが定義されていません",
+    ),
+    (
+        "tune.param_env",
+        "Invalid environment variable name for parameter {}: {}",
+        "パラメータ {} の環境変数名が不正です: {}",
+    ),
+    (
+        "tune.param_env_taken",
+        "Parameter {} uses the same environment variable as another parameter: {}",
+        "パラメータ {} は他のパラメータと同じ環境変数を使っています: {}",
+    ),
+    (
+        "tune.param_arg",
+        "Argument for parameter {} must contain {}: {}",
+        "パラメータ {} の引数には {} が必要です: {}",
+    ),
+    (
+        "tune.param_no_values",
+        "No values given for parameter {}",
+        "パラメータ {} に値が指定されていません",
+    ),
+    (
+        "tune.param_range",
+        "Invalid range for parameter {}: [{}, {}]",
+        "パラメータ {} の範囲が不正です: [{}, {}]",
+    ),
+    (
+        "tune.param_log",
+        "Log scale parameter {} needs a positive lower bound",
+        "対数スケールのパラメータ {} には正の下限が必要です",
+    ),
+    (
+        "tune.param_no_int",
+        "Integer parameter {} has no integer in [{}, {}]",
+        "整数パラメータ {} の範囲 [{}, {}] に整数がありません",
+    ),
+    (
+        "tune.param_twice",
+        "Parameter {} is set in both [tune.params] and [tune.annealing]",
+        "パラメータ {} が [tune.params] と [tune.annealing] の両方に設定されています",
+    ),
+    (
+        "tune.grid_no_values",
+        "Grid search needs a list of values for parameter {}",
+        "グリッドサーチにはパラメータ {} の値のリストが必要です",
+    ),
+    (
+        "tune.no_command",
+        "[tune] command is empty",
+        "[tune] command が空です",
+    ),
+    (
+        "tune.unknown_placeholder",
+        "Unknown placeholder {} in [tune] command: {}",
+        "[tune] command に不明なプレースホルダ {} があります: {}",
+    ),
+    (
+        "tune.no_score_group",
+        "Score regex must have a named group `score`",
+        "スコアの正規表現には名前付きグループ `score` が必要です",
+    ),
+    (
+        "tune.command_failed",
+        "Command exited with failure",
+        "コマンドが失敗しました",
+    ),
+    (
+        "tune.command_exited",
+        "Command exited with status: {}",
+        "コマンドがステータス {} で終了しました",
+    ),
+    (
+        "tune.no_score",
+        "Score not found in output",
+        "出力にスコアが見つかりません",
+    ),
+    (
+        "tune.no_baseline",
+        "[tune] baseline is required with aggregate = \"relative\"",
+        "aggregate = \"relative\" には [tune] baseline が必要です",
+    ),
+    (
+        "tune.baseline_missing",
+        "The baseline has no score for {} seed(s), e.g. seed {}",
+        "ベースラインにシード {} 個のスコアがありません。例えばシード {}",
+    ),
+    (
+        "tune.time_penalty",
+        "[tune.time_penalty] time_limit and threshold must be positive",
+        "[tune.time_penalty] の time_limit と threshold は正の値にしてください",
+    ),
+    (
+        "tune.race_first_test",
+        "[tune.race] first_test must be at least 2",
+        "[tune.race] first_test は 2 以上にしてください",
+    ),
+    (
+        "tune.race_confidence",
+        "[tune.race] confidence must be at least 0.5 and below 1",
+        "[tune.race] confidence は 0.5 以上 1 未満にしてください",
+    ),
+    (
+        "tune.pruning_rungs",
+        "[tune.pruning] rungs must be non-empty and strictly increasing",
+        "[tune.pruning] rungs は空でない狭義単調増加の列にしてください",
+    ),
+    (
+        "tune.pruning_keep",
+        "[tune.pruning] keep must be in (0, 1]",
+        "[tune.pruning] keep は 0 より大きく 1 以下にしてください",
+    ),
+    (
+        "tune.annealing_time_limit",
+        "[tune.annealing] time_limit must be positive",
+        "[tune.annealing] time_limit は正の値にしてください",
+    ),
+    (
+        "tune.annealing_cutoffs",
+        "[tune.annealing] cutoffs must be increasing fractions between 0 and 1",
+        "[tune.annealing] cutoffs は 0 と 1 の間の増加する割合にしてください",
+    ),
+    (
+        "tune.annealing_margin",
+        "[tune.annealing] margin must not be negative",
+        "[tune.annealing] margin は負にできません",
+    ),
+    (
+        "tune.annealing_temp",
+        "[tune.annealing] {} must be a positive range [low, high]",
+        "[tune.annealing] {} は正の範囲 [low, high] にしてください",
+    ),
+    (
+        "tune.no_progress_groups",
+        "Progress regex must have named groups `time` and `score`",
+        "進捗の正規表現には名前付きグループ `time` と `score` が必要です",
+    ),
+    (
+        "tune.validation_fraction",
+        "[tune.validation] fraction must be between 0 and 1",
+        "[tune.validation] fraction は 0 と 1 の間にしてください",
+    ),
+    (
+        "tune.validation_top",
+        "[tune.validation] top must be at least 1",
+        "[tune.validation] top は 1 以上にしてください",
+    ),
+    (
+        "tune.validation_max_gap",
+        "[tune.validation] max_gap must not be negative",
+        "[tune.validation] max_gap は負にできません",
+    ),
+    (
+        "tune.classes_empty",
+        "[tune.classes] bounds must not be empty",
+        "[tune.classes] bounds は空にできません",
+    ),
+    (
+        "tune.classes_order",
+        "[tune.classes] bounds must be strictly increasing",
+        "[tune.classes] bounds は狭義単調増加にしてください",
+    ),
+    (
+        "tune.no_class_token",
+        "Input file {} has no token at index {}",
+        "入力ファイル {} にインデックス {} のトークンがありません",
+    ),
+    (
+        "tune.nothing_to_plot",
+        "Study {} has no trials to plot",
+        "スタディ {} にプロットする試行がありません",
+    ),
+    (
+        "tune.no_finished_trials",
+        "Study {} has no finished trials",
+        "スタディ {} に完了した試行がありません",
+    ),
+    (
+        "tune.mixed_param",
+        "Parameter {} mixes strings and numbers, which has no Rust type",
+        "パラメータ {} には文字列と数値が混在しており、Rust の型にできません",
+    ),
+    (
+        "tune.param_not_in_class",
+        "Parameter {} was not tuned for class {}, the parameters differ between classes",
+        "パラメータ {} はクラス {} で調整されていません。クラスごとにパラメータが異なります",
+    ),
+];
+
+/// Formats a message of the catalog in the current language, e.g.
+/// `msg!("init.done", name)`.
+macro_rules! msg {
+    ($key:literal $(, $arg:expr)* $(,)?) => {
+        $crate::messages::format($key, &[$(&$arg as &dyn std::fmt::Display),*])
+    };
+}
+pub(crate) use msg;
+
+/// Sets the language from the configuration, or the locale when it has none. Unlike loading
+/// the configuration, this never fails: messages are needed to report that it failed.
+pub(crate) fn init(config_file_name: &str) {
+    let configured = config::load_effective_config(config_file_name)
+        .ok()
+        .and_then(|effective| {
+            let general = effective.table.get("general")?;
+            general.get("message_lang")?.as_str().map(str::to_string)
+        })
+        .or_else(|| std::env::var("AHC_GENERAL__MESSAGE_LANG").ok())
+        .and_then(|value| Lang::from_str(value.trim_matches('"'), true).ok());
+    let _ = LANG.set(configured.unwrap_or_else(locale_lang));
+}
+
+fn locale_lang() -> Lang {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    if locale.starts_with("ja") {
+        Lang::Ja
+    } else {
+        Lang::En
+    }
+}
+
+fn lang() -> Lang {
+    LANG.get().copied().unwrap_or(Lang::En)
+}
+
+pub(crate) fn format(key: &str, args: &[&dyn Display]) -> String {
+    render(template(key, lang()), args)
+}
+
+fn template(key: &str, lang: Lang) -> &'static str {
+    match CATALOG.iter().find(|(k, _, _)| *k == key) {
+        Some((_, en, ja)) => match lang {
+            Lang::En => en,
+            Lang::Ja => ja,
+        },
+        None => {
+            debug_assert!(false, "Unknown message: {}", key);
+            "{}"
+        }
+    }
+}
+
+fn render(template: &str, args: &[&dyn Display]) -> String {
+    let mut output = String::new();
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        let index = match rest[start + 1..end].parse::<usize>() {
+            Ok(index) => index,
+            Err(_) => {
+                next += 1;
+                next - 1
+            }
+        };
+        if let Some(arg) = args.get(index) {
+            output.push_str(&arg.to_string());
+        }
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(template: &str) -> usize {
+        template.matches('{').count()
+    }
+
+    #[test]
+    fn translations_take_the_same_arguments() {
+        for (i, (key, en, ja)) in CATALOG.iter().enumerate() {
+            assert_eq!(placeholders(en), placeholders(ja), "{}", key);
+            assert!(
+                CATALOG[i + 1..].iter().all(|(other, _, _)| other != key),
+                "duplicate key {}",
+                key
+            );
+        }
+    }
+
+    /// The arguments a template shows, in the order it shows them.
+    fn arguments(template: &str) -> Vec<usize> {
+        let markers: Vec<String> = (0..placeholders(template))
+            .map(|i| format!("<{}>", i))
+            .collect();
+        let args: Vec<&dyn Display> = markers.iter().map(|m| m as &dyn Display).collect();
+        let rendered = render(template, &args);
+        let mut shown: Vec<(usize, usize)> = (0..markers.len())
+            .flat_map(|i| {
+                rendered
+                    .match_indices(&markers[i])
+                    .map(move |(at, _)| (at, i))
+            })
+            .collect();
+        shown.sort();
+        shown.into_iter().map(|(_, i)| i).collect()
+    }
+
+    #[test]
+    fn translations_show_every_argument_once() {
+        for (key, en, ja) in CATALOG {
+            for template in [en, ja] {
+                let mut shown = arguments(template);
+                shown.sort();
+                assert_eq!(shown, (0..shown.len()).collect::<Vec<_>>(), "{}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn translations_keep_the_meaning_of_each_argument() {
+        // Messages whose arguments are alike, where a translation may swap them unnoticed
+        let cases: &[(&str, &[&dyn Display], &str, &str)] = &[
+            (
+                "download.incomplete",
+                &[&"u", &10, &100],
+                "received 10 bytes of 100",
+                "100 バイト中 10 バイト",
+            ),
+            (
+                "test.near_time_limit",
+                &[&1.9, &95, &2],
+                "1.9s is 95% of the time limit 2s",
+                "制限時間 2s の 95%",
+            ),
+            (
+                "test.resuming",
+                &[&"r", &3, &10],
+                "3 of 10 seeds",
+                "3 / 10 シード",
+            ),
+            (
+                "archive.created",
+                &[&3, &"a.tar", &100],
+                "3 files into a.tar (100 bytes)",
+                "a.tar に 3 個のファイル",
+            ),
+            (
+                "tune.race_step",
+                &[&4, &2, &6],
+                "After 4 seed(s): eliminated 2, 6 candidate(s)",
+                "シード 4 個の後: 2 個を除外、残り 6 個",
+            ),
+        ];
+        for (key, args, en, ja) in cases {
+            let rendered = render(template(key, Lang::En), args);
+            assert!(rendered.contains(en), "{}: {}", key, rendered);
+            let rendered = render(template(key, Lang::Ja), args);
+            assert!(rendered.contains(ja), "{}: {}", key, rendered);
+        }
+    }
+
+    #[test]
+    fn renders_arguments_in_order_or_by_index() {
+        assert_eq!(render("Trial #{}: {}", &[&3, &"ok"]), "Trial #3: ok");
+        assert_eq!(
+            render("{1} の {0} 件", &[&2, &"default"]),
+            "default の 2 件"
+        );
+        assert_eq!(
+            render(template("tune.discarding", Lang::Ja), &[&2, &"s"]),
+            "スタディ s の中断された試行 2 件を破棄します。続けるには --resume を付けてください"
+        );
+    }
+}
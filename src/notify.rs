@@ -0,0 +1,578 @@
+mod email;
+mod github;
+mod sheets;
+mod webhook;
+
+use crate::http;
+use crate::messages::msg;
+use anyhow::{anyhow, Context, Result};
+use email::EmailConfig;
+use github::GithubConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+pub(crate) use sheets::format_timestamp;
+use sheets::SheetsConfig;
+use std::time::Duration;
+use tracing::warn;
+use webhook::{Webhook, WebhookConfig};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct NotifyConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) slack: Option<SlackConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) discord: Option<DiscordConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) desktop: Option<DesktopConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) github: Option<GithubConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sheets: Option<SheetsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) email: Option<EmailConfig>,
+    #[serde(default, rename = "webhook", skip_serializing_if = "Vec::is_empty")]
+    pub(crate) webhooks: Vec<WebhookConfig>,
+    /// Routes of notifications to backends, replacing the backends' own `on_*` settings
+    #[serde(default, rename = "route", skip_serializing_if = "Vec::is_empty")]
+    pub(crate) routes: Vec<RouteConfig>,
+}
+
+/// Sends the notifications matching `events` and `severity` to the backends in `to`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct RouteConfig {
+    /// Events routed, every event if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) events: Vec<Event>,
+    /// Least severity routed
+    #[serde(default)]
+    pub(crate) severity: Severity,
+    /// Backends by the name of their section, e.g. ["slack", "desktop"]
+    pub(crate) to: Vec<String>,
+}
+
+impl RouteConfig {
+    fn matches(&self, notification: &Notification) -> bool {
+        (self.events.is_empty() || self.events.contains(&notification.event))
+            && notification.severity >= self.severity
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct SlackConfig {
+    /// Incoming webhook URL, best set through AHC_NOTIFY__SLACK__WEBHOOK_URL
+    pub(crate) webhook_url: String,
+    /// Post when a run finishes
+    #[serde(default = "default_true")]
+    pub(crate) on_finish: bool,
+    /// Post when a run achieves a new best score
+    #[serde(default = "default_true")]
+    pub(crate) on_best: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct DiscordConfig {
+    /// Webhook URL, best set through AHC_NOTIFY__DISCORD__WEBHOOK_URL
+    pub(crate) webhook_url: String,
+    /// Post when a run finishes
+    #[serde(default = "default_true")]
+    pub(crate) on_finish: bool,
+    /// Post when a run achieves a new best score
+    #[serde(default = "default_true")]
+    pub(crate) on_best: bool,
+    /// Attach the score chart of the run as an SVG file
+    #[serde(default)]
+    pub(crate) attach_chart: bool,
+}
+
+/// Desktop notifications when a command finishes, through `notify-send` or, on macOS, `osascript`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct DesktopConfig {
+    /// Notify when `ahc tune` finishes
+    #[serde(default = "default_true")]
+    pub(crate) tune: bool,
+    /// Notify when `ahc test` finishes
+    #[serde(default = "default_true")]
+    pub(crate) test: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The command sending notifications.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Source {
+    Tune,
+    Test,
+    Commit,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Event {
+    Finished,
+    NewBest,
+    /// `ahc commit` made a commit
+    Committed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    #[default]
+    Info,
+    /// Something went wrong without stopping the run, e.g. failed cases
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Notification {
+    pub(crate) event: Event,
+    pub(crate) severity: Severity,
+    pub(crate) title: String,
+    pub(crate) body: String,
+    /// SVG chart of the scores, for services that can attach it
+    pub(crate) chart: Option<String>,
+    /// Markdown table of the runs, for services that show long messages
+    pub(crate) details: Option<String>,
+    /// The run or commit notified about, for webhooks to read
+    pub(crate) summary: Option<serde_json::Value>,
+}
+
+/// A finished run, such as a tuning trial, for services keeping a log of every run.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(crate) struct RunRecord {
+    /// Name of the study or experiment the run belongs to
+    pub(crate) name: String,
+    pub(crate) id: usize,
+    pub(crate) params: String,
+    pub(crate) objective: f64,
+    pub(crate) mean: f64,
+    pub(crate) min: u64,
+    pub(crate) max: u64,
+    /// Number of failed cases
+    pub(crate) failed: usize,
+}
+
+/// Formats a score with its difference to `baseline`, e.g. `12.50 (+2.50 vs 10.00)`.
+pub(crate) fn format_score(score: f64, baseline: Option<f64>) -> String {
+    match baseline {
+        Some(baseline) => format!("{:.2} ({:+.2} vs {:.2})", score, score - baseline, baseline),
+        None => format!("{:.2}", score),
+    }
+}
+
+/// A service notifications can be delivered to.
+trait Backend {
+    /// Name of the backend's config section, which routes refer to it by
+    fn name(&self) -> &'static str;
+    /// Whether the backend takes `event` by its own settings, when no routes are configured
+    fn wants(&self, event: Event) -> bool;
+    /// Whether the backend takes notifications from `source` at all
+    fn enabled_for(&self, _source: Source) -> bool {
+        true
+    }
+    fn deliver(&self, notification: &Notification) -> Result<()>;
+}
+
+impl Backend for SlackConfig {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    fn wants(&self, event: Event) -> bool {
+        match event {
+            Event::Finished => self.on_finish,
+            Event::NewBest => self.on_best,
+            Event::Committed => false,
+        }
+    }
+
+    fn deliver(&self, notification: &Notification) -> Result<()> {
+        let payload = slack_payload(notification).to_string();
+        post(&self.webhook_url, "application/json", payload)
+    }
+}
+
+impl Backend for DiscordConfig {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
+
+    fn wants(&self, event: Event) -> bool {
+        match event {
+            Event::Finished => self.on_finish,
+            Event::NewBest => self.on_best,
+            Event::Committed => false,
+        }
+    }
+
+    fn deliver(&self, notification: &Notification) -> Result<()> {
+        let payload = discord_payload(notification);
+        match notification.chart.as_ref().filter(|_| self.attach_chart) {
+            Some(chart) => {
+                let (content_type, body) = discord_multipart(payload, chart);
+                post(&self.webhook_url, &content_type, body)
+            }
+            None => post(&self.webhook_url, "application/json", payload.to_string()),
+        }
+    }
+}
+
+impl Backend for DesktopConfig {
+    fn name(&self) -> &'static str {
+        "desktop"
+    }
+
+    // A desktop popup for every new best would only be noise
+    fn wants(&self, event: Event) -> bool {
+        event == Event::Finished
+    }
+
+    fn enabled_for(&self, source: Source) -> bool {
+        match source {
+            Source::Tune => self.tune,
+            Source::Test => self.test,
+            Source::Commit => false,
+        }
+    }
+
+    fn deliver(&self, notification: &Notification) -> Result<()> {
+        show_desktop(notification)
+    }
+}
+
+fn post(url: &str, content_type: &str, body: String) -> Result<()> {
+    http::block_on(async {
+        http::client()
+            .post(url)
+            .timeout(TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context(format!("Failed to post to {}", url))
+    })?;
+    Ok(())
+}
+
+/// Sends notifications to the configured backends, by the routes if there are any. Failing to
+/// deliver one only warns, so that a broken webhook never interrupts a long run.
+pub(crate) struct Notifier {
+    backends: Vec<Box<dyn Backend>>,
+    routes: Vec<RouteConfig>,
+    sheets: Option<SheetsConfig>,
+    source: Source,
+}
+
+impl Notifier {
+    pub(crate) fn new(config: Option<&NotifyConfig>, source: Source) -> Result<Self> {
+        let config = config.cloned().unwrap_or_default();
+        let mut backends: Vec<Box<dyn Backend>> = vec![];
+        if let Some(slack) = config.slack {
+            backends.push(Box::new(slack));
+        }
+        if let Some(discord) = config.discord {
+            backends.push(Box::new(discord));
+        }
+        if let Some(desktop) = config.desktop {
+            backends.push(Box::new(desktop));
+        }
+        if let Some(email) = config.email {
+            backends.push(Box::new(email));
+        }
+        if let Some(github) = config.github {
+            backends.push(Box::new(github));
+        }
+        for webhook in config.webhooks {
+            backends.push(Box::new(Webhook {
+                config: webhook,
+                source,
+            }));
+        }
+        for name in config.routes.iter().flat_map(|route| &route.to) {
+            if !backends.iter().any(|backend| backend.name() == name) {
+                return Err(anyhow!(
+                    "[[notify.route]] sends to {}, but [notify.{}] is not configured",
+                    name,
+                    name
+                ));
+            }
+        }
+
+        Ok(Notifier {
+            backends,
+            routes: config.routes,
+            sheets: config.sheets,
+            source,
+        })
+    }
+
+    pub(crate) fn send(&self, notification: &Notification) {
+        for backend in &self.backends {
+            if !backend.enabled_for(self.source) {
+                continue;
+            }
+            let wanted = if self.routes.is_empty() {
+                backend.wants(notification.event)
+            } else {
+                self.routes.iter().any(|route| {
+                    route.matches(notification) && route.to.iter().any(|to| to == backend.name())
+                })
+            };
+            if wanted {
+                warn_on_failure(backend.name(), backend.deliver(notification));
+            }
+        }
+    }
+
+    pub(crate) fn record_run(&self, run: &RunRecord) {
+        if let Some(sheets) = &self.sheets {
+            warn_on_failure("sheets", sheets::append(sheets, run));
+        }
+    }
+}
+
+fn warn_on_failure(backend: &str, result: Result<()>) {
+    if let Err(e) = result {
+        warn!("{}", msg!("notify.failed", backend, format!("{:#}", e)));
+    }
+}
+
+fn show_desktop(notification: &Notification) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = std::process::Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            applescript_string(&notification.body),
+            applescript_string(&notification.title)
+        ));
+        command
+    } else {
+        let mut command = std::process::Command::new("notify-send");
+        command
+            .args(["--app-name", "ahc-tools"])
+            .arg(&notification.title)
+            .arg(&notification.body);
+        command
+    };
+    let output = command
+        .output()
+        .context(format!("Failed to run {:?}", command.get_program()))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{:?} failed: {}",
+            command.get_program(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn slack_payload(notification: &Notification) -> serde_json::Value {
+    match notification.event {
+        Event::Finished | Event::Committed => json!({
+            "text": format!("*{}*\n{}", notification.title, notification.body),
+        }),
+        // An attachment colors the new best so it stands out in the channel
+        Event::NewBest => json!({
+            "text": format!(":tada: *{}*", notification.title),
+            "attachments": [{ "color": "good", "text": notification.body }],
+        }),
+    }
+}
+
+fn discord_payload(notification: &Notification) -> serde_json::Value {
+    match notification.event {
+        Event::Finished | Event::Committed => json!({
+            "content": format!("**{}**\n{}", notification.title, notification.body),
+        }),
+        // A green embed makes the new best stand out in the channel
+        Event::NewBest => json!({
+            "embeds": [{
+                "title": format!("\u{1f389} {}", notification.title),
+                "description": notification.body,
+                "color": 0x2eb67d,
+            }],
+        }),
+    }
+}
+
+/// The multipart body Discord takes for a message with an attached file.
+fn discord_multipart(mut payload: serde_json::Value, chart: &str) -> (String, String) {
+    const BOUNDARY: &str = "ahc-tools-boundary-7d1f3c";
+    const FILE_NAME: &str = "chart.svg";
+    payload["attachments"] = json!([{ "id": 0, "filename": FILE_NAME }]);
+    let body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"payload_json\"\r\n\
+         Content-Type: application/json\r\n\r\n{}\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"files[0]\"; filename=\"{}\"\r\n\
+         Content-Type: image/svg+xml\r\n\r\n{}\r\n--{b}--\r\n",
+        payload,
+        FILE_NAME,
+        chart,
+        b = BOUNDARY
+    );
+    (format!("multipart/form-data; boundary={}", BOUNDARY), body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_scores_against_baseline() {
+        assert_eq!(format_score(12.5, Some(10.0)), "12.50 (+2.50 vs 10.00)");
+        assert_eq!(format_score(7.0, Some(8.0)), "7.00 (-1.00 vs 8.00)");
+        assert_eq!(format_score(7.0, None), "7.00");
+    }
+
+    #[test]
+    fn quotes_applescript_strings() {
+        assert_eq!(applescript_string(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+    }
+
+    #[test]
+    fn posts_to_slack_webhook() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/hook")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "attachments": [{ "color": "good", "text": "trial #1, score 2.00 (+1.00 vs 1.00)" }],
+            })))
+            .with_status(200)
+            .create();
+        let config = NotifyConfig {
+            slack: Some(SlackConfig {
+                webhook_url: format!("{}/hook", server.url()),
+                on_finish: false,
+                on_best: true,
+            }),
+            ..NotifyConfig::default()
+        };
+        let notifier = Notifier::new(Some(&config), Source::Tune).unwrap();
+
+        notifier.send(&Notification {
+            event: Event::NewBest,
+            severity: Severity::Info,
+            title: "New best".to_string(),
+            body: format!("trial #1, score {}", format_score(2.0, Some(1.0))),
+            chart: None,
+            details: None,
+            summary: None,
+        });
+        notifier.send(&Notification {
+            event: Event::Finished,
+            severity: Severity::Info,
+            title: "Done".to_string(),
+            body: String::new(),
+            chart: None,
+            details: None,
+            summary: None,
+        });
+
+        mock.assert();
+    }
+
+    #[test]
+    fn posts_to_discord_webhook_with_chart() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/hook")
+            .match_header(
+                "content-type",
+                mockito::Matcher::Regex("^multipart/form-data; boundary=".to_string()),
+            )
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(r#""content":"\*\*Done\*\*\\n3 trial\(s\)""#.to_string()),
+                mockito::Matcher::Regex(
+                    r#"filename="chart.svg"\r\nContent-Type: image/svg\+xml\r\n\r\n<svg/>"#
+                        .to_string(),
+                ),
+            ]))
+            .with_status(200)
+            .create();
+        let config = NotifyConfig {
+            discord: Some(DiscordConfig {
+                webhook_url: format!("{}/hook", server.url()),
+                on_finish: true,
+                on_best: true,
+                attach_chart: true,
+            }),
+            ..NotifyConfig::default()
+        };
+
+        Notifier::new(Some(&config), Source::Tune)
+            .unwrap()
+            .send(&Notification {
+                event: Event::Finished,
+                severity: Severity::Info,
+                title: "Done".to_string(),
+                body: "3 trial(s)".to_string(),
+                chart: Some("<svg/>".to_string()),
+                details: None,
+                summary: None,
+            });
+
+        mock.assert();
+    }
+
+    #[test]
+    fn routes_by_event_and_severity() {
+        let mut server = mockito::Server::new();
+        let slack = server.mock("POST", "/slack").expect(1).create();
+        let discord = server.mock("POST", "/discord").expect(1).create();
+        let config: NotifyConfig = toml::from_str(&format!(
+            r#"
+            slack.webhook_url = "{url}/slack"
+            discord.webhook_url = "{url}/discord"
+
+            [[route]]
+            events = ["new_best"]
+            to = ["slack"]
+
+            [[route]]
+            severity = "warning"
+            to = ["discord"]
+            "#,
+            url = server.url()
+        ))
+        .unwrap();
+        let notifier = Notifier::new(Some(&config), Source::Tune).unwrap();
+        let notification = |event, severity| Notification {
+            event,
+            severity,
+            title: "Study".to_string(),
+            body: String::new(),
+            chart: None,
+            details: None,
+            summary: None,
+        };
+
+        notifier.send(&notification(Event::NewBest, Severity::Info));
+        notifier.send(&notification(Event::Finished, Severity::Info));
+        notifier.send(&notification(Event::Finished, Severity::Warning));
+
+        slack.assert();
+        discord.assert();
+    }
+
+    #[test]
+    fn rejects_routes_to_unconfigured_backends() {
+        let config: NotifyConfig = toml::from_str("[[route]]\nto = [\"desktop\"]").unwrap();
+        let error = Notifier::new(Some(&config), Source::Tune)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("[notify.desktop] is not configured"));
+    }
+}
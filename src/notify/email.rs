@@ -0,0 +1,371 @@
+use super::sheets::civil_time;
+use super::{Backend, Event, Notification, TIMEOUT};
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Mails notifications through an SMTP server. Keep it in the global config, away from the
+/// project's repository, since it holds the password.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct EmailConfig {
+    pub(crate) host: String,
+    /// Port of the server, 465 for `tls` and 587 otherwise by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) port: Option<u16>,
+    #[serde(default)]
+    pub(crate) security: Security,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) username: Option<String>,
+    /// Password, best set through AHC_NOTIFY__EMAIL__PASSWORD
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) password: Option<String>,
+    pub(crate) from: String,
+    pub(crate) to: Vec<String>,
+    /// Mail when a run finishes
+    #[serde(default = "super::default_true")]
+    pub(crate) on_finish: bool,
+    /// Mail when a run achieves a new best score
+    #[serde(default)]
+    pub(crate) on_best: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Security {
+    /// TLS from the start of the connection
+    Tls,
+    /// Plain connection upgraded with STARTTLS
+    #[default]
+    Starttls,
+    /// No encryption, for a relay on the local machine only
+    None,
+}
+
+trait Stream: Read + Write {}
+
+impl<S: Read + Write> Stream for S {}
+
+struct Session {
+    stream: BufReader<Box<dyn Stream>>,
+}
+
+impl Session {
+    fn reply(&mut self) -> Result<(u16, String)> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line)? == 0 {
+                return Err(anyhow!("SMTP server closed the connection"));
+            }
+            let code = line
+                .get(..3)
+                .and_then(|code| code.parse().ok())
+                .ok_or_else(|| anyhow!("Malformed SMTP reply: {}", line.trim_end()))?;
+            text.push_str(line.get(4..).unwrap_or_default().trim_end());
+            // `250-` continues a multiline reply, `250 ` ends it
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok((code, text));
+            }
+            text.push('\n');
+        }
+    }
+
+    fn expect(&mut self, expected: u16) -> Result<String> {
+        let (code, text) = self.reply()?;
+        if code != expected {
+            return Err(anyhow!("SMTP server replied {} {}", code, text));
+        }
+        Ok(text)
+    }
+
+    fn command(&mut self, line: &str, expected: u16) -> Result<String> {
+        let stream = self.stream.get_mut();
+        stream.write_all(line.as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+        self.expect(expected)
+    }
+}
+
+fn tls(host: &str, stream: TcpStream) -> Result<Box<dyn Stream>> {
+    let connector = native_tls::TlsConnector::new()?;
+    let stream = connector
+        .connect(host, stream)
+        .map_err(|e| anyhow!("TLS handshake with {} failed: {}", host, e))?;
+    Ok(Box::new(stream))
+}
+
+impl Backend for EmailConfig {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    fn wants(&self, event: Event) -> bool {
+        match event {
+            Event::Finished => self.on_finish,
+            Event::NewBest => self.on_best,
+            Event::Committed => false,
+        }
+    }
+
+    fn deliver(&self, notification: &Notification) -> Result<()> {
+        send(self, notification)
+    }
+}
+
+pub(super) fn send(config: &EmailConfig, notification: &Notification) -> Result<()> {
+    if config.to.is_empty() {
+        return Err(anyhow!("[notify.email] to has no recipients"));
+    }
+    let port = config.port.unwrap_or(match config.security {
+        Security::Tls => 465,
+        Security::Starttls | Security::None => 587,
+    });
+    let tcp = TcpStream::connect((config.host.as_str(), port))
+        .context(format!("Failed to connect to {}:{}", config.host, port))?;
+    tcp.set_read_timeout(Some(TIMEOUT))?;
+    tcp.set_write_timeout(Some(TIMEOUT))?;
+
+    let stream: Box<dyn Stream> = match config.security {
+        Security::Tls => tls(&config.host, tcp.try_clone()?)?,
+        Security::Starttls | Security::None => Box::new(tcp.try_clone()?),
+    };
+    let mut session = Session {
+        stream: BufReader::new(stream),
+    };
+    session.expect(220)?;
+    session.command("EHLO ahc-tools", 250)?;
+    if config.security == Security::Starttls {
+        session.command("STARTTLS", 220)?;
+        session = Session {
+            stream: BufReader::new(tls(&config.host, tcp)?),
+        };
+        session.command("EHLO ahc-tools", 250)?;
+    }
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        let credentials = STANDARD.encode(format!("\0{}\0{}", username, password));
+        session.command(&format!("AUTH PLAIN {}", credentials), 235)?;
+    }
+    session.command(&format!("MAIL FROM:<{}>", config.from), 250)?;
+    for to in &config.to {
+        session.command(&format!("RCPT TO:<{}>", to), 250)?;
+    }
+    session.command("DATA", 354)?;
+    let message = message(config, notification, SystemTime::now());
+    session.command(&format!("{}\r\n.", message), 250)?;
+    session.command("QUIT", 221)?;
+    Ok(())
+}
+
+/// The message with its headers, sent at `now`. The body is base64-encoded, so that it can
+/// neither break the DATA command with a lone `.` line nor need an 8-bit clean server.
+fn message(config: &EmailConfig, notification: &Notification, now: SystemTime) -> String {
+    // A line break in the title would end the header and start headers of its own
+    let title = notification
+        .title
+        .split(['\r', '\n'])
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let subject = if title.is_ascii() {
+        title
+    } else {
+        format!("=?UTF-8?B?{}?=", STANDARD.encode(&title))
+    };
+    let body = STANDARD.encode(format!("{}\n", notification.body));
+    let lines = body
+        .as_bytes()
+        .chunks(76)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>();
+    format!(
+        "From: <{}>\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMessage-ID: {}\r\n\
+         MIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\n\
+         Content-Transfer-Encoding: base64\r\n\r\n{}",
+        config.from,
+        config
+            .to
+            .iter()
+            .map(|to| format!("<{}>", to))
+            .collect::<Vec<_>>()
+            .join(", "),
+        subject,
+        date(now),
+        message_id(&config.from, now),
+        lines.join("\r\n")
+    )
+}
+
+/// `now` as the Date header wants it, e.g. `Thu, 29 Feb 2024 12:34:56 +0000`.
+fn date(now: SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (year, month, day, secs) = civil_time(now);
+    let days = now
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() / 86400);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} +0000",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// A Message-ID unique to the message, in the domain of the sender.
+fn message_id(from: &str, now: SystemTime) -> String {
+    let domain = from
+        .rsplit_once('@')
+        .map_or("ahc-tools", |(_, domain)| domain);
+    let nanos = now
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos());
+    format!("<{:x}.{:016x}@{}>", nanos, rand::random::<u64>(), domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::Severity;
+    use std::net::TcpListener;
+
+    #[test]
+    fn sends_mail_over_smtp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut received = vec![];
+            writer.write_all(b"220 localhost\r\n").unwrap();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end().to_string();
+                let reply: &[u8] = if in_data {
+                    if line != "." {
+                        received.push(line);
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250-localhost\r\n250 AUTH PLAIN\r\n"
+                } else if line.starts_with("AUTH") {
+                    received.push(line);
+                    b"235 ok\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line == "QUIT" {
+                    writer.write_all(b"221 bye\r\n").unwrap();
+                    break;
+                } else {
+                    received.push(line);
+                    b"250 ok\r\n"
+                };
+                writer.write_all(reply).unwrap();
+            }
+            received
+        });
+
+        let config = EmailConfig {
+            host: "127.0.0.1".to_string(),
+            port: Some(port),
+            security: Security::None,
+            username: Some("user".to_string()),
+            password: Some("pass".to_string()),
+            from: "ahc@example.com".to_string(),
+            to: vec!["me@example.com".to_string()],
+            on_finish: true,
+            on_best: false,
+        };
+        let notification = Notification {
+            event: Event::Finished,
+            severity: Severity::Info,
+            title: "Tuning study default finished".to_string(),
+            body: "3 trial(s)".to_string(),
+            chart: None,
+            details: None,
+            summary: None,
+        };
+        send(&config, &notification).unwrap();
+
+        let received = server.join().unwrap();
+        assert_eq!(
+            received[0],
+            format!("AUTH PLAIN {}", STANDARD.encode("\0user\0pass"))
+        );
+        assert_eq!(received[1], "MAIL FROM:<ahc@example.com>");
+        assert_eq!(received[2], "RCPT TO:<me@example.com>");
+        assert!(received.contains(&"Subject: Tuning study default finished".to_string()));
+        assert!(received.contains(&STANDARD.encode("3 trial(s)\n")));
+    }
+
+    #[test]
+    fn encodes_non_ascii_subjects() {
+        let config: EmailConfig =
+            toml::from_str("host = \"h\"\nfrom = \"a@b\"\nto = [\"c@d\"]").unwrap();
+        let notification = Notification {
+            event: Event::NewBest,
+            severity: Severity::Info,
+            title: "最高スコア".to_string(),
+            body: String::new(),
+            chart: None,
+            details: None,
+            summary: None,
+        };
+        let message = message(&config, &notification, SystemTime::now());
+        assert!(message.contains(&format!(
+            "Subject: =?UTF-8?B?{}?=",
+            STANDARD.encode("最高スコア")
+        )));
+        assert_eq!(config.security, Security::Starttls);
+    }
+
+    #[test]
+    fn dates_and_identifies_the_message() {
+        let config: EmailConfig =
+            toml::from_str("host = \"h\"\nfrom = \"ahc@example.com\"\nto = [\"c@d\"]").unwrap();
+        let notification = Notification {
+            event: Event::Finished,
+            severity: Severity::Info,
+            title: "Run\r\nBcc: victim@example.com".to_string(),
+            body: String::new(),
+            chart: None,
+            details: None,
+            summary: None,
+        };
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(1_709_210_096);
+        let message = message(&config, &notification, time);
+        let headers = message.split("\r\n\r\n").next().unwrap();
+        assert!(headers.contains("\r\nSubject: Run Bcc: victim@example.com\r\n"));
+        assert!(!headers.contains("\r\nBcc:"));
+        assert!(headers.contains("\r\nDate: Thu, 29 Feb 2024 12:34:56 +0000\r\n"));
+        let id = headers
+            .lines()
+            .find_map(|line| line.strip_prefix("Message-ID: "))
+            .unwrap();
+        assert!(
+            id.starts_with('<') && id.ends_with("@example.com>"),
+            "{}",
+            id
+        );
+        assert_ne!(id, message_id(&config.from, time));
+        assert_eq!(date(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 +0000");
+    }
+}
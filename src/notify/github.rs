@@ -0,0 +1,235 @@
+use super::{Backend, Event, Notification, TIMEOUT};
+use crate::http;
+use anyhow::{anyhow, Context, Result};
+use reqwest::{Client, Method, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// Keeps a summary of each finished run in a GitHub issue, or in a comment on an issue or pull
+/// request, updating it on later runs instead of posting again.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct GithubConfig {
+    /// Repository as `owner/name`
+    pub(crate) repo: String,
+    /// Token allowed to write issues, best set through AHC_NOTIFY__GITHUB__TOKEN
+    pub(crate) token: String,
+    /// Issue or pull request to comment on; without it, the summary is an issue of its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) issue: Option<u64>,
+    /// Base URL of the REST API, for GitHub Enterprise Server
+    #[serde(default = "default_api_url")]
+    pub(crate) api_url: String,
+}
+
+fn default_api_url() -> String {
+    DEFAULT_API_URL.to_string()
+}
+
+/// A comment or an issue in a listing.
+#[derive(Deserialize)]
+struct Item {
+    id: u64,
+    /// Number of an issue, which its URL uses instead of the id
+    #[serde(default)]
+    number: Option<u64>,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+impl Backend for GithubConfig {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn wants(&self, event: Event) -> bool {
+        event == Event::Finished
+    }
+
+    fn deliver(&self, notification: &Notification) -> Result<()> {
+        http::block_on(publish(http::client(), self, notification))
+    }
+}
+
+pub(super) async fn publish(
+    client: &Client,
+    config: &GithubConfig,
+    notification: &Notification,
+) -> Result<()> {
+    // Hidden marker recognizing the summary posted by an earlier run
+    let marker = format!("<!-- ahc-tools: {} -->", notification.title);
+    let mut body = format!(
+        "{}\n### {}\n\n{}\n",
+        marker, notification.title, notification.body
+    );
+    if let Some(details) = &notification.details {
+        body.push('\n');
+        body.push_str(details);
+    }
+
+    let repo = &config.repo;
+    let (list, create, update) = match config.issue {
+        Some(issue) => (
+            format!("/repos/{}/issues/{}/comments?per_page=100", repo, issue),
+            format!("/repos/{}/issues/{}/comments", repo, issue),
+            format!("/repos/{}/issues/comments", repo),
+        ),
+        None => (
+            format!("/repos/{}/issues?state=open&per_page=100", repo),
+            format!("/repos/{}/issues", repo),
+            format!("/repos/{}/issues", repo),
+        ),
+    };
+    let items: Vec<Item> =
+        serde_json::from_str(&send(request(client, config, Method::GET, &list)).await?)
+            .context("Failed to parse GitHub response")?;
+    let existing = items.iter().find(|item| {
+        item.body
+            .as_deref()
+            .is_some_and(|body| body.starts_with(&marker))
+    });
+
+    let (request, payload) = match existing {
+        Some(item) => (
+            request(
+                client,
+                config,
+                Method::PATCH,
+                &format!("{}/{}", update, item.number.unwrap_or(item.id)),
+            ),
+            json!({ "body": body }),
+        ),
+        None if config.issue.is_some() => (
+            request(client, config, Method::POST, &create),
+            json!({ "body": body }),
+        ),
+        None => (
+            request(client, config, Method::POST, &create),
+            json!({ "title": notification.title, "body": body }),
+        ),
+    };
+    send(
+        request
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(payload.to_string()),
+    )
+    .await?;
+    Ok(())
+}
+
+fn request(client: &Client, config: &GithubConfig, method: Method, path: &str) -> RequestBuilder {
+    client
+        .request(
+            method,
+            format!("{}{}", config.api_url.trim_end_matches('/'), path),
+        )
+        .bearer_auth(&config.token)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .header(reqwest::header::USER_AGENT, "ahc-tools")
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        .timeout(TIMEOUT)
+}
+
+async fn send(request: RequestBuilder) -> Result<String> {
+    let response = request
+        .send()
+        .await
+        .context("Failed to send request to GitHub")?;
+    let status = response.status();
+    let text = response
+        .text()
+        .await
+        .context("Failed to read GitHub response")?;
+    if !status.is_success() {
+        return Err(anyhow!("GitHub responded with {}: {}", status, text));
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::Severity;
+
+    fn notification() -> Notification {
+        Notification {
+            event: Event::Finished,
+            severity: Severity::Info,
+            title: "Tuning study default finished".to_string(),
+            body: "2 trial(s)".to_string(),
+            chart: None,
+            details: Some("| id |\n| --- |\n| 0 |\n".to_string()),
+            summary: None,
+        }
+    }
+
+    fn config(server: &mockito::Server, issue: Option<u64>) -> GithubConfig {
+        GithubConfig {
+            repo: "team/ahc".to_string(),
+            token: "secret".to_string(),
+            issue,
+            api_url: server.url(),
+        }
+    }
+
+    #[test]
+    fn updates_the_comment_of_an_earlier_run() {
+        let mut server = mockito::Server::new();
+        let list = server
+            .mock("GET", "/repos/team/ahc/issues/7/comments?per_page=100")
+            .match_header("authorization", "Bearer secret")
+            .with_body(
+                json!([
+                    { "id": 1, "body": "unrelated" },
+                    { "id": 2, "body": "<!-- ahc-tools: Tuning study default finished -->\nold" },
+                ])
+                .to_string(),
+            )
+            .create();
+        let update = server
+            .mock("PATCH", "/repos/team/ahc/issues/comments/2")
+            .match_body(mockito::Matcher::Regex(
+                r"2 trial\(s\)\\n\\n\| id \|".to_string(),
+            ))
+            .with_body("{}")
+            .create();
+
+        http::block_on(publish(
+            &Client::new(),
+            &config(&server, Some(7)),
+            &notification(),
+        ))
+        .unwrap();
+
+        list.assert();
+        update.assert();
+    }
+
+    #[test]
+    fn opens_an_issue_for_the_first_run() {
+        let mut server = mockito::Server::new();
+        let list = server
+            .mock("GET", "/repos/team/ahc/issues?state=open&per_page=100")
+            .with_body(json!([{ "id": 90, "number": 3, "body": null }]).to_string())
+            .create();
+        let create = server
+            .mock("POST", "/repos/team/ahc/issues")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "title": "Tuning study default finished",
+            })))
+            .with_status(201)
+            .with_body("{}")
+            .create();
+
+        http::block_on(publish(
+            &Client::new(),
+            &config(&server, None),
+            &notification(),
+        ))
+        .unwrap();
+
+        list.assert();
+        create.assert();
+    }
+}
@@ -0,0 +1,205 @@
+use super::{RunRecord, TIMEOUT};
+use crate::http;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_API_URL: &str = "https://sheets.googleapis.com";
+
+/// Appends a row for every finished run to a Google Sheet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct SheetsConfig {
+    /// Id of the spreadsheet, the long part of its URL after `/d/`
+    pub(crate) spreadsheet_id: String,
+    /// Sheet, or range of it, the rows are appended to
+    #[serde(default = "default_range")]
+    pub(crate) range: String,
+    /// OAuth access token, best set through AHC_NOTIFY__SHEETS__ACCESS_TOKEN
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) access_token: Option<String>,
+    /// Command printing an access token, e.g. ["gcloud", "auth", "print-access-token"], run for
+    /// every row so that long runs outlive a token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) token_command: Option<Vec<String>>,
+    /// Base URL of the API
+    #[serde(default = "default_api_url")]
+    pub(crate) api_url: String,
+}
+
+fn default_range() -> String {
+    "Sheet1".to_string()
+}
+
+fn default_api_url() -> String {
+    DEFAULT_API_URL.to_string()
+}
+
+impl SheetsConfig {
+    fn access_token(&self) -> Result<String> {
+        match (&self.access_token, &self.token_command) {
+            (Some(token), None) => Ok(token.clone()),
+            (None, Some(command)) => {
+                let (program, args) = command
+                    .split_first()
+                    .ok_or_else(|| anyhow!("[notify.sheets] token_command is empty"))?;
+                let output = std::process::Command::new(program)
+                    .args(args)
+                    .output()
+                    .context(format!("Failed to run token command: {}", program))?;
+                if !output.status.success() {
+                    return Err(anyhow!(
+                        "Token command failed: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+                Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            }
+            _ => Err(anyhow!(
+                "[notify.sheets] needs exactly one of access_token and token_command"
+            )),
+        }
+    }
+}
+
+pub(super) fn append(config: &SheetsConfig, run: &RunRecord) -> Result<()> {
+    let mut url = url::Url::parse(&config.api_url).context("Invalid [notify.sheets] api_url")?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("Invalid [notify.sheets] api_url"))?
+        .extend([
+            "v4",
+            "spreadsheets",
+            &config.spreadsheet_id,
+            "values",
+            &format!("{}:append", config.range),
+        ]);
+    url.query_pairs_mut()
+        .append_pair("valueInputOption", "USER_ENTERED")
+        .append_pair("insertDataOption", "INSERT_ROWS");
+
+    let payload = json!({ "values": [row(run, SystemTime::now())] });
+    let request = http::client()
+        .post(url)
+        .timeout(TIMEOUT)
+        .bearer_auth(config.access_token()?)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(payload.to_string());
+    http::block_on(async {
+        let response = request
+            .send()
+            .await
+            .context("Failed to send request to Google Sheets")?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Google Sheets responded with {}: {}", status, text));
+        }
+        Ok(())
+    })
+}
+
+fn row(run: &RunRecord, now: SystemTime) -> serde_json::Value {
+    json!([
+        format_timestamp(now),
+        head_commit().unwrap_or_default(),
+        run.name,
+        run.id,
+        run.params,
+        run.objective,
+        run.mean,
+        run.min,
+        run.max,
+        run.failed,
+    ])
+}
+
+/// Short id of the commit checked out in the working directory.
+fn head_commit() -> Option<String> {
+    let repo = git2::Repository::open_from_env().ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string()[..7].to_string())
+}
+
+/// Formats a time as `YYYY-MM-DD HH:MM:SS` in UTC, which Sheets reads as a date.
+pub(crate) fn format_timestamp(time: SystemTime) -> String {
+    let (year, month, day, secs) = civil_time(time);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// The UTC year, month and day of `time`, and the seconds into that day.
+pub(super) fn civil_time(time: SystemTime) -> (i64, i64, i64, u64) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+    // Civil date from days since the epoch, after Howard Hinnant's algorithm
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day, secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn formats_timestamps_in_utc() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01 00:00:00");
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(format_timestamp(time), "2024-02-29 12:34:56");
+    }
+
+    #[test]
+    fn appends_rows() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock(
+                "POST",
+                "/v4/spreadsheets/abc/values/Runs%20log:append?valueInputOption=USER_ENTERED&insertDataOption=INSERT_ROWS",
+            )
+            .match_header("authorization", "Bearer token")
+            .match_body(mockito::Matcher::Regex(
+                r#""default",2,"\(k=3\)",30\.0,25\.0,20,30,0\]\]"#.to_string(),
+            ))
+            .with_body("{}")
+            .create();
+        let config = SheetsConfig {
+            spreadsheet_id: "abc".to_string(),
+            range: "Runs log".to_string(),
+            access_token: None,
+            token_command: Some(vec!["echo".to_string(), "token".to_string()]),
+            api_url: server.url(),
+        };
+        let run = RunRecord {
+            name: "default".to_string(),
+            id: 2,
+            params: "(k=3)".to_string(),
+            objective: 30.0,
+            mean: 25.0,
+            min: 20,
+            max: 30,
+            failed: 0,
+        };
+
+        append(&config, &run).unwrap();
+
+        mock.assert();
+    }
+}
@@ -0,0 +1,146 @@
+//! Generic webhooks: every notification POSTed as JSON to a URL of the user's own, for
+//! automations none of the other services cover.
+
+use super::{post, Backend, Event, Notification, Source};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Posts every notification as JSON to a URL of the user's own, for automations no backend
+/// covers. Each `[[notify.webhook]]` is a webhook of its own.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct WebhookConfig {
+    pub(crate) url: String,
+    /// Events posted, every event if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) events: Vec<Event>,
+}
+
+pub(super) struct Webhook {
+    pub(super) config: WebhookConfig,
+    pub(super) source: Source,
+}
+
+impl Backend for Webhook {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn wants(&self, event: Event) -> bool {
+        self.config.events.is_empty() || self.config.events.contains(&event)
+    }
+
+    fn deliver(&self, notification: &Notification) -> Result<()> {
+        let payload = payload(notification, self.source, head_commit());
+        post(&self.config.url, "application/json", payload.to_string())
+    }
+}
+
+/// The commit checked out in the working directory.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+struct CommitInfo {
+    id: String,
+    message: String,
+    /// Branch checked out, unless the HEAD is detached
+    branch: Option<String>,
+}
+
+fn head_commit() -> Option<CommitInfo> {
+    let repo = git2::Repository::open_from_env().ok()?;
+    let head = repo.head().ok()?;
+    let commit = head.peel_to_commit().ok()?;
+    Some(CommitInfo {
+        id: commit.id().to_string(),
+        message: commit.summary().unwrap_or_default().to_string(),
+        branch: head
+            .is_branch()
+            .then(|| head.shorthand().map(String::from))
+            .flatten(),
+    })
+}
+
+fn payload(
+    notification: &Notification,
+    source: Source,
+    commit: Option<CommitInfo>,
+) -> serde_json::Value {
+    json!({
+        "event": notification.event,
+        "source": source,
+        "severity": notification.severity,
+        "title": notification.title,
+        "body": notification.body,
+        "summary": notification.summary,
+        "commit": commit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::{Notifier, NotifyConfig, Severity};
+
+    #[test]
+    fn posts_the_event_with_its_summary_and_commit() {
+        let mut server = mockito::Server::new();
+        let finished = server
+            .mock("POST", "/finished")
+            .match_header("content-type", "application/json")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "event": "finished",
+                "source": "tune",
+                "severity": "warning",
+                "title": "Tuning study default finished",
+                "summary": { "trials": 3 },
+            })))
+            .expect(1)
+            .create();
+        let every = server.mock("POST", "/every").expect(2).create();
+        let config: NotifyConfig = toml::from_str(&format!(
+            r#"
+            [[webhook]]
+            url = "{url}/finished"
+            events = ["finished"]
+
+            [[webhook]]
+            url = "{url}/every"
+            "#,
+            url = server.url()
+        ))
+        .unwrap();
+        let notifier = Notifier::new(Some(&config), Source::Tune).unwrap();
+        let notification = |event, severity| Notification {
+            event,
+            severity,
+            title: "Tuning study default finished".to_string(),
+            body: "3 trial(s)".to_string(),
+            chart: None,
+            details: None,
+            summary: Some(json!({ "trials": 3 })),
+        };
+
+        notifier.send(&notification(Event::NewBest, Severity::Info));
+        notifier.send(&notification(Event::Finished, Severity::Warning));
+
+        finished.assert();
+        every.assert();
+
+        let commit = CommitInfo {
+            id: "0123456789abcdef0123456789abcdef01234567".to_string(),
+            message: "[1234] Try beam search".to_string(),
+            branch: Some("main".to_string()),
+        };
+        assert_eq!(
+            payload(
+                &notification(Event::Committed, Severity::Info),
+                Source::Commit,
+                Some(commit)
+            )["commit"],
+            json!({
+                "id": "0123456789abcdef0123456789abcdef01234567",
+                "message": "[1234] Try beam search",
+                "branch": "main",
+            })
+        );
+    }
+}
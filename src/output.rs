@@ -0,0 +1,25 @@
+//! What commands print on stdout: text for people, or with `--output json` a single JSON
+//! document for scripts. Progress and warnings go to stderr either way.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Output {
+    #[default]
+    Text,
+    Json,
+}
+
+impl Output {
+    pub(crate) fn is_json(self) -> bool {
+        self == Output::Json
+    }
+}
+
+/// Prints the JSON document of a command.
+pub(crate) fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
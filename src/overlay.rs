@@ -0,0 +1,251 @@
+use crate::messages::msg;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Files for a streaming overlay, e.g. an OBS text source, rewritten every second while a
+/// command runs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct OverlayConfig {
+    /// JSON file with the current score, the last run and the time remaining
+    #[serde(default = "default_path")]
+    pub(crate) path: PathBuf,
+    /// Plain text file with the same, for text sources that cannot read JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) text_path: Option<PathBuf>,
+    /// End of the contest, e.g. "2024-03-03 19:00:00+09:00"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) end_time: Option<String>,
+}
+
+fn default_path() -> PathBuf {
+    PathBuf::from("overlay.json")
+}
+
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+struct Snapshot {
+    /// Best score so far
+    score: Option<f64>,
+    last_score: Option<f64>,
+    /// Change the last run made to the best score, if it beat it
+    last_delta: Option<f64>,
+    time_remaining_secs: Option<u64>,
+}
+
+impl Snapshot {
+    fn to_text(&self) -> String {
+        let mut text = match self.score {
+            Some(score) => format!("Score: {:.2}", score),
+            None => "Score: -".to_string(),
+        };
+        if let Some(delta) = self.last_delta {
+            text.push_str(&format!(" ({:+.2})", delta));
+        }
+        text.push('\n');
+        if let Some(secs) = self.time_remaining_secs {
+            text.push_str(&format!(
+                "Time left: {}:{:02}:{:02}\n",
+                secs / 3600,
+                secs / 60 % 60,
+                secs % 60
+            ));
+        }
+        text
+    }
+}
+
+struct State {
+    config: OverlayConfig,
+    end: Option<SystemTime>,
+    snapshot: Snapshot,
+    warned: bool,
+}
+
+impl State {
+    fn write(&mut self) {
+        self.snapshot.time_remaining_secs = self.end.map(|end| {
+            end.duration_since(SystemTime::now())
+                .unwrap_or_default()
+                .as_secs()
+        });
+        let json = serde_json::to_string(&self.snapshot).unwrap_or_default();
+        let mut result = write_replacing(&self.config.path, &json);
+        if let Some(text_path) = &self.config.text_path {
+            result = result.and(write_replacing(text_path, &self.snapshot.to_text()));
+        }
+        if let Err(e) = result {
+            if !self.warned {
+                warn!("{}", msg!("overlay.failed", format!("{:#}", e)));
+                self.warned = true;
+            }
+        }
+    }
+}
+
+/// Writes through a temporary file, so that a reader never sees a half-written file.
+fn write_replacing(path: &Path, content: &str) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    std::fs::write(&temp, content)
+        .and_then(|_| std::fs::rename(&temp, path))
+        .context(format!("Failed to write {}", path.display()))
+}
+
+/// Keeps the overlay files up to date until dropped.
+pub(crate) struct Overlay {
+    state: Arc<Mutex<State>>,
+    stop: Option<mpsc::Sender<()>>,
+    refresher: Option<JoinHandle<()>>,
+}
+
+impl Overlay {
+    pub(crate) fn start(config: &OverlayConfig) -> Result<Self> {
+        let end = config.end_time.as_deref().map(parse_time).transpose()?;
+        let state = Arc::new(Mutex::new(State {
+            config: config.clone(),
+            end,
+            snapshot: Snapshot::default(),
+            warned: false,
+        }));
+        state.lock().unwrap().write();
+
+        let (stop, stopped) = mpsc::channel::<()>();
+        let refresher = {
+            let state = state.clone();
+            std::thread::spawn(move || {
+                while let Err(mpsc::RecvTimeoutError::Timeout) =
+                    stopped.recv_timeout(REFRESH_INTERVAL)
+                {
+                    state.lock().unwrap().write();
+                }
+            })
+        };
+        Ok(Overlay {
+            state,
+            stop: Some(stop),
+            refresher: Some(refresher),
+        })
+    }
+
+    /// Shows a finished run with `score`, and `best` as the best score so far including it.
+    pub(crate) fn update(&self, score: f64, best: f64, delta: Option<f64>) {
+        let mut state = self.state.lock().unwrap();
+        state.snapshot.score = Some(best);
+        state.snapshot.last_score = Some(score);
+        state.snapshot.last_delta = delta;
+        state.write();
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(refresher) = self.refresher.take() {
+            let _ = refresher.join();
+        }
+    }
+}
+
+/// Parses `YYYY-MM-DD HH:MM[:SS]` followed by `Z` or a UTC offset like `+09:00`; a `T` may
+/// separate the date and the time.
+pub(crate) fn parse_time(text: &str) -> Result<SystemTime> {
+    let invalid = || {
+        anyhow!(
+            "Invalid time {:?}, expected e.g. 2024-03-03 19:00:00+09:00",
+            text
+        )
+    };
+    let text = text.trim();
+    let (date, rest) = text.split_at_checked(10).ok_or_else(invalid)?;
+    let rest = rest.strip_prefix(['T', ' ']).ok_or_else(invalid)?;
+    let offset_start = rest.find(['Z', '+', '-']).ok_or_else(invalid)?;
+    let (time, offset) = rest.split_at(offset_start);
+
+    let numbers = |text: &str, separator: char| {
+        text.split(separator)
+            .map(|part| part.parse::<i64>().ok())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)
+    };
+    let [year, month, day] = numbers(date, '-')?[..] else {
+        return Err(invalid());
+    };
+    let (hour, minute, second) = match numbers(time, ':')?[..] {
+        [hour, minute] => (hour, minute, 0),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return Err(invalid()),
+    };
+    let offset_secs = match offset {
+        "Z" => 0,
+        _ => {
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            match numbers(&offset[1..], ':')?[..] {
+                [hours, minutes] => sign * (hours * 3600 + minutes * 60),
+                _ => return Err(invalid()),
+            }
+        }
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return Err(invalid());
+    }
+
+    // Days since the epoch of a civil date, after Howard Hinnant's algorithm
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    let secs = days * 86400 + hour * 3600 + minute * 60 + second - offset_secs;
+    let secs = u64::try_from(secs).map_err(|_| invalid())?;
+    Ok(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_times_with_offsets() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        assert_eq!(parse_time("1970-01-01 00:00Z").unwrap(), at(0));
+        assert_eq!(
+            parse_time("2024-03-03 19:00:00+09:00").unwrap(),
+            at(1_709_460_000)
+        );
+        assert_eq!(
+            parse_time("2024-03-03T05:00:00-05:00").unwrap(),
+            at(1_709_460_000)
+        );
+        assert!(parse_time("2024-03-03 19:00").is_err());
+        assert!(parse_time("2024-13-03 19:00Z").is_err());
+    }
+
+    #[test]
+    fn writes_json_and_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = OverlayConfig {
+            path: dir.path().join("overlay.json"),
+            text_path: Some(dir.path().join("overlay.txt")),
+            end_time: Some("2999-01-01 00:00Z".to_string()),
+        };
+        let overlay = Overlay::start(&config).unwrap();
+        overlay.update(120.0, 120.0, Some(20.0));
+        drop(overlay);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&config.path).unwrap()).unwrap();
+        assert_eq!(json["score"], 120.0);
+        assert_eq!(json["last_delta"], 20.0);
+        assert!(json["time_remaining_secs"].as_u64().unwrap() > 0);
+        let text = std::fs::read_to_string(config.text_path.unwrap()).unwrap();
+        assert!(text.starts_with("Score: 120.00 (+20.00)\nTime left: "));
+    }
+}
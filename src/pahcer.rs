@@ -1,7 +1,84 @@
+//! Results of pahcer runs.
+
+use anyhow::{Context, Result};
 use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// A run of pahcer, as in its `result_*.json` files.
+#[derive(Deserialize, Debug)]
+pub struct ExecResult {
+    pub case_count: usize,
+    pub total_score: usize,
+    /// Time of the slowest case in seconds
+    #[serde(default)]
+    pub max_execution_time: f64,
+    /// Solver of `[solver.<name>]`, in results of `ahc test --solver`
+    #[serde(default)]
+    pub solver: Option<String>,
+    #[serde(default)]
+    pub cases: Vec<CaseResult>,
+}
 
 #[derive(Deserialize, Debug)]
-pub(crate) struct ExecResult {
-    pub(crate) case_count: usize,
-    pub(crate) total_score: usize,
+pub struct CaseResult {
+    pub seed: u64,
+    pub score: u64,
+    #[serde(default)]
+    pub execution_time: f64,
+}
+
+impl ExecResult {
+    pub fn average_score(&self) -> f64 {
+        self.total_score as f64 / self.case_count as f64
+    }
+
+    /// Mean time of the cases in seconds, 0 without any cases.
+    pub fn mean_execution_time(&self) -> f64 {
+        if self.cases.is_empty() {
+            return 0.0;
+        }
+        let total = self
+            .cases
+            .iter()
+            .map(|case| case.execution_time)
+            .sum::<f64>();
+        total / self.cases.len() as f64
+    }
+}
+
+/// Lists the `result_*.json` files in `results_dir`, oldest first going by the timestamps in
+/// their names.
+pub fn list_results(results_dir: &Path) -> Result<Vec<PathBuf>> {
+    if !results_dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut paths = vec![];
+    for entry in std::fs::read_dir(results_dir).context(format!(
+        "Failed to read directory: {}",
+        results_dir.display()
+    ))? {
+        let path = entry?.path();
+        let is_result = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("result_") && name.ends_with(".json"));
+        if is_result {
+            paths.push(path);
+        }
+    }
+    paths.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    Ok(paths)
+}
+
+/// Finds the newest `result_*.json` in `results_dir`.
+pub fn find_latest_result(results_dir: &Path) -> Result<Option<PathBuf>> {
+    Ok(list_results(results_dir)?.pop())
+}
+
+/// Reads a pahcer result file.
+pub fn read_result(path: &Path) -> Result<ExecResult> {
+    let file = std::fs::File::open(path)
+        .context(format!("Failed to open result file: {}", path.display()))?;
+    serde_json::from_reader(file)
+        .context(format!("Failed to parse result file: {}", path.display()))
 }
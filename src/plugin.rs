@@ -0,0 +1,112 @@
+//! External subcommands: `ahc foo args...` runs an `ahc-foo` executable found on PATH, like git
+//! does, passing it the project through `AHC_*` environment variables.
+
+use crate::config::load_config;
+use crate::messages::msg;
+use crate::output::Output;
+use anyhow::{anyhow, Context, Result};
+use serde_json::json;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+const PREFIX: &str = "ahc-";
+
+/// Runs the plugin named by the first of `args` with the rest of them.
+pub(crate) fn run(args: Vec<String>, config_file_name: &str, output: Output) -> Result<()> {
+    let (name, rest) = args
+        .split_first()
+        .ok_or_else(|| anyhow!("No command given"))?;
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let program = find(name, &path)
+        .ok_or_else(|| anyhow!(msg!("plugin.unknown", name, format!("{}{}", PREFIX, name))))?;
+    debug!("Running plugin {}", program.display());
+
+    let mut command = std::process::Command::new(&program);
+    command.args(rest);
+    command.env("AHC_PROJECT_DIR", std::env::current_dir()?);
+    command.env("AHC_OUTPUT", if output.is_json() { "json" } else { "text" });
+    let config_path = Path::new(config_file_name);
+    if config_path.exists() {
+        let config = load_config(config_file_name)?;
+        command.env("AHC_CONFIG_FILE", std::path::absolute(config_path)?);
+        command.env("AHC_CONTEST", &config.general.name);
+        command.env("AHC_PROBLEM_URL", &config.general.problem_url);
+        command.env(
+            "AHC_CONTEXT",
+            json!({ "general": config.general, "paths": config.paths }).to_string(),
+        );
+    }
+
+    let status = command
+        .status()
+        .context(format!("Failed to run {}", program.display()))?;
+    if !status.success() {
+        return Err(anyhow!("{}{} exited with {}", PREFIX, name, status));
+    }
+    Ok(())
+}
+
+/// Finds the executable of the plugin `name` in the directories of `path`, a PATH value.
+fn find(name: &str, path: &OsStr) -> Option<PathBuf> {
+    let file_name = format!("{}{}{}", PREFIX, name, std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(path)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Names of the plugins on PATH, for shell completion.
+pub(crate) fn names() -> Vec<String> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let mut names = std::env::split_paths(&path)
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| is_executable(&entry.path()))
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            let name = file_name.strip_prefix(PREFIX)?;
+            let name = name.strip_suffix(std::env::consts::EXE_SUFFIX)?;
+            Some(name.to_string())
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn finds_executables_in_path_order() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let write = |dir: &Path, mode| {
+            let path = dir.join("ahc-stats");
+            std::fs::write(&path, "#!/bin/sh\n").unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            path
+        };
+        write(first.path(), 0o644);
+        let executable = write(second.path(), 0o755);
+        let path = std::env::join_paths([first.path(), second.path()]).unwrap();
+
+        assert_eq!(find("stats", &path), Some(executable));
+        assert_eq!(find("missing", &path), None);
+    }
+}
@@ -0,0 +1,323 @@
+//! `ahc problem`, which keeps the problem statement in `.ahc` as Markdown, in each language the
+//! page has, so that it can be read again without the site, and records the limits and scoring
+//! in `[problem]` of the project config.
+
+mod markdown;
+
+use crate::config::{Config, Lang};
+use crate::download;
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::tune::Objective;
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+const STATE_DIR: &str = ".ahc";
+
+#[derive(Args)]
+pub(crate) struct ProblemArgs {
+    /// Problem page to fetch instead of [general] problem_url
+    #[arg(short, long)]
+    url: Option<String>,
+    /// Print the statement in the language of the project after saving it
+    #[arg(long)]
+    print: bool,
+}
+
+/// `[problem]`, what `ahc problem` read from the problem page. `[test] time_limit_ms` and
+/// `[tune] objective` follow it unless set.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct ProblemConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) time_limit_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) memory_limit_mb: Option<u64>,
+    /// Whether higher or lower scores are better, when the scoring section says it plainly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) objective: Option<Objective>,
+    /// The scoring section in Markdown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) scoring: Option<String>,
+}
+
+/// What `ahc problem --output json` prints.
+#[derive(Serialize, Debug)]
+struct ProblemSummary {
+    problem_url: String,
+    /// The statement in each language, the one of the project first
+    files: Vec<PathBuf>,
+    problem: ProblemConfig,
+}
+
+pub(crate) fn problem(
+    args: ProblemArgs,
+    config: Config,
+    config_file_name: &str,
+    output: Output,
+) -> Result<()> {
+    let lang = config.general.lang;
+    let url = args.url.unwrap_or(config.general.problem_url.clone());
+    let url = download::localize_url(&url, lang);
+    let retry = config.download.clone().unwrap_or_default().retry();
+    let html = download::fetch_html(&url, retry)?;
+
+    let statements = statements(&html)?;
+    let mut files = vec![];
+    for (statement_lang, statement) in &statements {
+        let path = statement_path(Path::new(STATE_DIR), lang, *statement_lang);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+        std::fs::write(&path, statement)
+            .context(format!("Failed to write file: {}", path.display()))?;
+        info!("{}", msg!("problem.saved", path.display()));
+        files.push(path);
+    }
+    files.sort_by_key(|path| path != &statement_path(Path::new(STATE_DIR), lang, lang));
+
+    let problem = problem_config(&html, lang);
+    let config_path = Path::new(config_file_name);
+    if config_path.exists() {
+        write_section(config_path, &problem)?;
+        info!("{}", msg!("problem.configured", config_path.display()));
+    }
+
+    if output.is_json() {
+        print_json(&ProblemSummary {
+            problem_url: url,
+            files,
+            problem,
+        })?;
+    } else if args.print {
+        let statement = statements
+            .iter()
+            .find(|(statement_lang, _)| *statement_lang == lang)
+            .or(statements.first())
+            .map(|(_, statement)| statement)
+            .expect("a statement was found");
+        print!("{}", statement);
+    }
+    Ok(())
+}
+
+/// `problem.md` for the language of the project, `problem.<lang>.md` for the other.
+fn statement_path(dir: &Path, project_lang: Lang, lang: Lang) -> PathBuf {
+    if lang == project_lang {
+        dir.join("problem.md")
+    } else {
+        dir.join(format!("problem.{}.md", lang.as_str()))
+    }
+}
+
+/// The statement of an AtCoder task page in Markdown, in each language it has. Pages without
+/// the language spans are taken for Japanese.
+fn statements(html: &str) -> Result<Vec<(Lang, String)>> {
+    let document = scraper::Html::parse_document(html);
+    let selector =
+        |selector: &str| scraper::Selector::parse(selector).expect("the selector is valid");
+    let statement = document
+        .select(&selector("#task-statement"))
+        .next()
+        .ok_or_else(|| anyhow!(msg!("problem.no_statement")))?;
+    let mut statements = vec![];
+    for lang in [Lang::Ja, Lang::En] {
+        let span = selector(&format!(".lang-{}", lang.as_str()));
+        if let Some(element) = statement.select(&span).next() {
+            statements.push((lang, markdown::to_markdown(element)));
+        }
+    }
+    if statements.is_empty() {
+        statements.push((Lang::Ja, markdown::to_markdown(statement)));
+    }
+    Ok(statements)
+}
+
+/// The limits in the header of the page and the scoring section of the statement in `lang`, or
+/// in the other language if the page has only that.
+fn problem_config(html: &str, lang: Lang) -> ProblemConfig {
+    let document = scraper::Html::parse_document(html);
+    let text = document.root_element().text().collect::<String>();
+    let time_limit = Regex::new(r"(?:Time Limit|実行時間制限)\s*:\s*([\d.]+)\s*sec")
+        .expect("the time limit regex is valid");
+    let memory_limit = Regex::new(r"(?:Memory Limit|メモリ制限)\s*:\s*(\d+)\s*Mi?B")
+        .expect("the memory limit regex is valid");
+    let scoring = [lang, other_lang(lang)]
+        .into_iter()
+        .find_map(|lang| scoring_section(&document, lang));
+    ProblemConfig {
+        time_limit_ms: time_limit
+            .captures(&text)
+            .and_then(|captures| captures[1].parse::<f64>().ok())
+            .map(|seconds| (seconds * 1000.0).round() as u64),
+        memory_limit_mb: memory_limit
+            .captures(&text)
+            .and_then(|captures| captures[1].parse().ok()),
+        objective: scoring.as_deref().and_then(objective),
+        scoring,
+    }
+}
+
+fn other_lang(lang: Lang) -> Lang {
+    match lang {
+        Lang::Ja => Lang::En,
+        Lang::En => Lang::Ja,
+    }
+}
+
+/// The section of the statement in `lang` headed 得点 or Scoring, in Markdown.
+fn scoring_section(document: &scraper::Html, lang: Lang) -> Option<String> {
+    let selector =
+        |selector: &str| scraper::Selector::parse(selector).expect("the selector is valid");
+    let statement = document.select(&selector("#task-statement")).next()?;
+    let span = statement
+        .select(&selector(&format!(".lang-{}", lang.as_str())))
+        .next()?;
+    let heading = selector("h3");
+    span.select(&selector("section"))
+        .find(|section| {
+            section.select(&heading).next().is_some_and(|heading| {
+                let text = heading.text().collect::<String>();
+                matches!(text.trim(), "得点" | "Scoring")
+            })
+        })
+        .map(markdown::to_markdown)
+}
+
+/// The objective a scoring section states, e.g. by "The lower the score, the better" or
+/// 最小化, unless it speaks of both.
+fn objective(scoring: &str) -> Option<Objective> {
+    let said = |pattern: &str| {
+        Regex::new(pattern)
+            .expect("the objective regex is valid")
+            .is_match(scoring)
+    };
+    let min = said(r"(?i)minimi[sz]e|(lower|smaller).{0,40}better|最小化|小さいほど|少ないほど");
+    let max = said(r"(?i)maximi[sz]e|(higher|larger).{0,40}better|最大化|大きいほど|高いほど");
+    match (min, max) {
+        (true, false) => Some(Objective::Min),
+        (false, true) => Some(Objective::Max),
+        _ => None,
+    }
+}
+
+/// Replaces the `[problem]` section of the config at `path`, keeping the rest of the file as it
+/// is, comments included.
+fn write_section(path: &Path, problem: &ProblemConfig) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .context(format!("Failed to read config file: {}", path.display()))?;
+    let mut kept = vec![];
+    let mut in_section = false;
+    for line in content.lines() {
+        let header = line.trim();
+        if header.starts_with('[') {
+            in_section = header == "[problem]";
+        }
+        if !in_section {
+            kept.push(line);
+        }
+    }
+    while kept.last().is_some_and(|line| line.trim().is_empty()) {
+        kept.pop();
+    }
+    let section = toml::to_string(problem).context("Failed to serialize [problem]")?;
+    let content = format!("{}\n\n[problem]\n{}", kept.join("\n"), section);
+    std::fs::write(path, content)
+        .context(format!("Failed to write config file: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_statement_in_each_language() {
+        let html = r#"<html><body>
+            <p>Time Limit: 2 sec / Memory Limit: 1024 MB</p>
+            <div id="task-statement"><span class="lang">
+                <span class="lang-ja"><section><h3>問題文</h3><p><var>N</var> 個の点</p></section></span>
+                <span class="lang-en"><section><h3>Problem Statement</h3><p><var>N</var> points</p></section></span>
+            </span></div>
+        </body></html>"#;
+        assert_eq!(
+            statements(html).unwrap(),
+            vec![
+                (Lang::Ja, "### 問題文\n\n$N$ 個の点\n".to_string()),
+                (
+                    Lang::En,
+                    "### Problem Statement\n\n$N$ points\n".to_string()
+                ),
+            ]
+        );
+        assert!(statements("<html></html>").is_err());
+
+        let dir = Path::new(".ahc");
+        assert_eq!(
+            statement_path(dir, Lang::En, Lang::Ja),
+            PathBuf::from(".ahc/problem.ja.md")
+        );
+        assert_eq!(
+            statement_path(dir, Lang::En, Lang::En),
+            PathBuf::from(".ahc/problem.md")
+        );
+    }
+
+    #[test]
+    fn reads_the_limits_and_the_scoring() {
+        let html = r#"<html><body>
+            <p>実行時間制限: 2.5 sec / メモリ制限: 1024 MiB</p>
+            <div id="task-statement"><span class="lang">
+                <span class="lang-ja"><section><h3>得点</h3>
+                    <p>絶対スコアは小さいほど良い。</p></section></span>
+            </span></div>
+        </body></html>"#;
+        assert_eq!(
+            problem_config(html, Lang::En),
+            ProblemConfig {
+                time_limit_ms: Some(2500),
+                memory_limit_mb: Some(1024),
+                objective: Some(Objective::Min),
+                scoring: Some("### 得点\n\n絶対スコアは小さいほど良い。\n".to_string()),
+            }
+        );
+
+        assert_eq!(
+            objective("The higher the score, the better. Maximize it."),
+            Some(Objective::Max)
+        );
+        assert_eq!(
+            objective("Minimize the cost; the smaller the better."),
+            Some(Objective::Min)
+        );
+        assert_eq!(objective("Higher is better, lower is better"), None);
+    }
+
+    #[test]
+    fn replaces_only_the_problem_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ahc_tools.toml");
+        std::fs::write(
+            &path,
+            "# contest\n[general]\nname = \"ahc001\"\n\n[problem]\ntime_limit_ms = 1000\n\n\
+             [test]\ncommand = [\"./a.out\"]\n",
+        )
+        .unwrap();
+        let problem = ProblemConfig {
+            time_limit_ms: Some(2000),
+            objective: Some(Objective::Max),
+            ..Default::default()
+        };
+
+        write_section(&path, &problem).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# contest\n[general]\nname = \"ahc001\"\n\n[test]\ncommand = [\"./a.out\"]\n\n\
+             [problem]\ntime_limit_ms = 2000\nobjective = \"max\"\n"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Just enough HTML to Markdown for AtCoder problem statements: headings, paragraphs, lists,
+//! tables, samples in `<pre>` and the TeX of `<var>`, which is kept as `$...$`.
+
+use scraper::node::Node;
+use scraper::{ElementRef, Selector};
+
+/// The Markdown of the content of `element`.
+pub(super) fn to_markdown(element: ElementRef) -> String {
+    tidy(&render_children(element))
+}
+
+fn render_children(element: ElementRef) -> String {
+    element
+        .children()
+        .map(|node| match node.value() {
+            Node::Text(text) => collapse_whitespace(text),
+            Node::Element(_) => ElementRef::wrap(node).map_or(String::new(), render_element),
+            _ => String::new(),
+        })
+        .collect()
+}
+
+fn render_element(element: ElementRef) -> String {
+    let name = element.value().name();
+    let attr = |name| element.value().attr(name).unwrap_or_default();
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse::<usize>().unwrap_or(1);
+            let text = render_children(element);
+            format!("\n\n{} {}\n\n", "#".repeat(level), text.trim())
+        }
+        "p" | "div" | "section" | "blockquote" => {
+            format!("\n\n{}\n\n", render_children(element).trim())
+        }
+        "br" => "\n".to_string(),
+        "hr" => "\n\n---\n\n".to_string(),
+        "pre" => {
+            let text = element.text().collect::<String>();
+            format!("\n\n```\n{}\n```\n\n", text.trim_end())
+        }
+        "code" => format!("`{}`", element.text().collect::<String>()),
+        "var" => format!("${}$", element.text().collect::<String>().trim()),
+        "strong" | "b" => format!("**{}**", render_children(element).trim()),
+        "em" | "i" => format!("*{}*", render_children(element).trim()),
+        "a" if !attr("href").is_empty() => {
+            format!("[{}]({})", render_children(element).trim(), attr("href"))
+        }
+        "img" => format!("![{}]({})", attr("alt"), attr("src")),
+        "ul" | "ol" => render_list(element, name == "ol"),
+        "table" => render_table(element),
+        "script" | "style" | "button" => String::new(),
+        _ => render_children(element),
+    }
+}
+
+fn render_list(element: ElementRef, ordered: bool) -> String {
+    let items = element
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|child| child.value().name() == "li")
+        .enumerate()
+        .map(|(i, item)| {
+            let marker = if ordered {
+                format!("{}. ", i + 1)
+            } else {
+                "- ".to_string()
+            };
+            let text = tidy(&render_children(item));
+            let indent = format!("\n{}", " ".repeat(marker.len()));
+            format!("{}{}", marker, text.trim_end().replace('\n', &indent))
+        })
+        .collect::<Vec<_>>();
+    format!("\n\n{}\n\n", items.join("\n"))
+}
+
+fn render_table(element: ElementRef) -> String {
+    let rows_selector = Selector::parse("tr").expect("the row selector is valid");
+    let cells_selector = Selector::parse("th, td").expect("the cell selector is valid");
+    let rows = element
+        .select(&rows_selector)
+        .map(|row| {
+            let cells = row
+                .select(&cells_selector)
+                .map(|cell| {
+                    let text = render_children(cell);
+                    text.split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                        .replace('|', "\\|")
+                })
+                .collect::<Vec<_>>();
+            format!("| {} |", cells.join(" | "))
+        })
+        .collect::<Vec<_>>();
+    let Some(header) = rows.first() else {
+        return String::new();
+    };
+    let columns = header.matches(" | ").count() + 1;
+    let separator = format!("|{}", " --- |".repeat(columns));
+    let mut lines = vec![header.clone(), separator];
+    lines.extend(rows[1..].iter().cloned());
+    format!("\n\n{}\n\n", lines.join("\n"))
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::new();
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            space = true;
+        } else {
+            if space {
+                collapsed.push(' ');
+                space = false;
+            }
+            collapsed.push(c);
+        }
+    }
+    if space {
+        collapsed.push(' ');
+    }
+    collapsed
+}
+
+/// Removes the trailing spaces, the space left at the start of lines by whitespace between
+/// blocks and runs of blank lines, except in code blocks.
+fn tidy(markdown: &str) -> String {
+    let mut lines: Vec<&str> = vec![];
+    let mut in_code = false;
+    for line in markdown.lines() {
+        if in_code {
+            in_code = !line.starts_with("```");
+            lines.push(line);
+            continue;
+        }
+        let line = line.trim_end();
+        let line = match line.strip_prefix(' ') {
+            Some(rest) if !rest.starts_with(' ') => rest,
+            _ => line,
+        };
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        in_code = line.starts_with("```");
+        lines.push(line);
+    }
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(html: &str) -> String {
+        let document = scraper::Html::parse_fragment(html);
+        to_markdown(document.root_element())
+    }
+
+    #[test]
+    fn converts_a_statement() {
+        let html = r#"
+            <section>
+                <h3>Problem Statement</h3>
+                <p>Place <var>N</var> points on a <strong>grid</strong>.
+                See <a href="https://example.net">the tools</a>.</p>
+                <ul>
+                    <li><var>1 \leq N \leq 100</var></li>
+                    <li>All values are integers.</li>
+                </ul>
+            </section>
+            <section>
+                <h3>Sample Input 1</h3>
+                <pre>3
+1 2
+</pre>
+            </section>
+            <table><tr><th>Rank</th><th>Score</th></tr><tr><td>1</td><td>1|2</td></tr></table>
+        "#;
+        assert_eq!(
+            convert(html),
+            "### Problem Statement\n\
+             \n\
+             Place $N$ points on a **grid**. See [the tools](https://example.net).\n\
+             \n\
+             - $1 \\leq N \\leq 100$\n\
+             - All values are integers.\n\
+             \n\
+             ### Sample Input 1\n\
+             \n\
+             ```\n\
+             3\n\
+             1 2\n\
+             ```\n\
+             \n\
+             | Rank | Score |\n\
+             | --- | --- |\n\
+             | 1 | 1\\|2 |\n"
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! `ahc remote`, which runs a command of `ahc` such as `ahc test` on another machine over SSH. The
+//! project is copied to the host with rsync before the command, and the results and outputs are
+//! copied back after it, so that `ahc commit` and `ahc status` read them as if run here.
+
+use crate::config::Config;
+use crate::error::ErrorKind;
+use crate::messages::msg;
+use crate::output::Output;
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info, warn};
+
+#[derive(Args)]
+pub(crate) struct RemoteArgs {
+    /// Command of ahc to run on the host and its arguments, e.g. `test --seeds 0..100`
+    #[arg(
+        required = true,
+        num_args = 1..,
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
+    args: Vec<String>,
+}
+
+/// `[remote]`, the machine `ahc remote` runs on.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct RemoteConfig {
+    /// SSH destination, e.g. a host of `~/.ssh/config` or `user@192.0.2.1`
+    pub(crate) host: String,
+    /// Directory of the project on the host, relative to the home directory unless absolute,
+    /// `ahc/<contest>` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) dir: Option<String>,
+    /// Command running ahc on the host
+    #[serde(default = "default_command")]
+    pub(crate) command: Vec<String>,
+    /// Command connecting to the host, e.g. `["ssh", "-p", "2222"]`, also used by rsync
+    #[serde(default = "default_ssh")]
+    pub(crate) ssh: Vec<String>,
+    /// Paths left out of the copy, as rsync's `--exclude`. Those on the host are kept, such as the
+    /// build of the previous run
+    #[serde(default = "default_exclude")]
+    pub(crate) exclude: Vec<String>,
+}
+
+fn default_command() -> Vec<String> {
+    vec!["ahc".to_string()]
+}
+
+fn default_ssh() -> Vec<String> {
+    vec!["ssh".to_string()]
+}
+
+fn default_exclude() -> Vec<String> {
+    vec!["target".to_string()]
+}
+
+pub(crate) fn remote(args: RemoteArgs, config: Config, output: Output) -> Result<()> {
+    let remote = config
+        .remote
+        .as_ref()
+        .ok_or_else(|| ErrorKind::Config.error(msg!("remote.no_config")))?;
+    if remote.ssh.is_empty() || remote.command.is_empty() {
+        return Err(ErrorKind::Config.error("[remote] ssh and command must not be empty"));
+    }
+    let dir = remote
+        .dir
+        .clone()
+        .unwrap_or_else(|| format!("ahc/{}", config.general.name));
+
+    info!("{}", msg!("remote.pushing", remote.host, dir));
+    run(ssh_command(
+        remote,
+        &format!("mkdir -p {}", shell_quote(&dir)),
+    ))?;
+    run(push_command(remote, &dir))?;
+
+    let mut command = remote.command.clone();
+    if output.is_json() {
+        command.extend(["--output".to_string(), "json".to_string()]);
+    }
+    command.extend(args.args);
+    let script = format!(
+        "cd {} && {}",
+        shell_quote(&dir),
+        command
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+    info!("{}", msg!("remote.running", command.join(" "), remote.host));
+    let mut ssh = ssh_command(remote, &script);
+    debug!("Running {:?}", ssh);
+    let status = ssh
+        .status()
+        .context(format!("Failed to run {}", remote.ssh[0]))?;
+
+    // Pulled even when the command failed, e.g. for the result of a run stopped early
+    info!("{}", msg!("remote.pulling", remote.host));
+    for path in [&config.paths.results_dir, &config.paths.outputs_dir] {
+        if let Err(e) = pull(remote, &dir, path) {
+            warn!("{:#}", e);
+        }
+    }
+
+    if !status.success() {
+        let error = msg!("remote.failed", remote.host, status);
+        return Err(match status.code().and_then(ErrorKind::from_exit_code) {
+            Some(kind) => kind.error(error),
+            None => anyhow!(error),
+        });
+    }
+    Ok(())
+}
+
+fn run(mut command: Command) -> Result<()> {
+    debug!("Running {:?}", command);
+    let program = command.get_program().to_string_lossy().to_string();
+    let status = command
+        .status()
+        .context(format!("Failed to run {}", program))?;
+    if !status.success() {
+        return Err(anyhow!("{} exited with {}", program, status));
+    }
+    Ok(())
+}
+
+/// `ssh <host> <script>`, the script run by the shell of the host.
+fn ssh_command(remote: &RemoteConfig, script: &str) -> Command {
+    let mut command = Command::new(&remote.ssh[0]);
+    command.args(&remote.ssh[1..]).arg(&remote.host).arg(script);
+    command
+}
+
+/// rsync over the ssh of `remote`.
+fn rsync_command(remote: &RemoteConfig) -> Command {
+    let mut command = Command::new("rsync");
+    command.args(["-az", "-e"]).arg(remote.ssh.join(" "));
+    command
+}
+
+/// Copies the project to `dir` of the host, removing files there which are no longer here.
+fn push_command(remote: &RemoteConfig, dir: &str) -> Command {
+    let mut command = rsync_command(remote);
+    command.arg("--delete");
+    for exclude in &remote.exclude {
+        command.arg(format!("--exclude={}", exclude));
+    }
+    command.arg("./").arg(format!("{}:{}/", remote.host, dir));
+    command
+}
+
+/// Copies `path` of the project on the host back here, keeping what is here.
+fn pull(remote: &RemoteConfig, dir: &str, path: &Path) -> Result<()> {
+    std::fs::create_dir_all(path)
+        .context(format!("Failed to create directory: {}", path.display()))?;
+    run(pull_command(remote, dir, path))
+}
+
+fn pull_command(remote: &RemoteConfig, dir: &str, path: &Path) -> Command {
+    let source = PathBuf::from(dir).join(path);
+    let mut command = rsync_command(remote);
+    command
+        .arg(format!("{}:{}/", remote.host, source.display()))
+        .arg(format!("{}/", path.display()));
+    command
+}
+
+/// `text` as a single word of a POSIX shell.
+fn shell_quote(text: &str) -> String {
+    let plain = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@".contains(c));
+    if plain {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &Command) -> Vec<String> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn copies_the_project_and_runs_over_ssh() {
+        let remote: RemoteConfig =
+            toml::from_str("host = \"desktop\"\nssh = [\"ssh\", \"-p\", \"2222\"]").unwrap();
+
+        assert_eq!(
+            args(&push_command(&remote, "ahc/ahc030")),
+            vec![
+                "rsync",
+                "-az",
+                "-e",
+                "ssh -p 2222",
+                "--delete",
+                "--exclude=target",
+                "./",
+                "desktop:ahc/ahc030/"
+            ]
+        );
+        assert_eq!(
+            args(&pull_command(
+                &remote,
+                "ahc/ahc030",
+                Path::new("pahcer/json")
+            ))[4..],
+            ["desktop:ahc/ahc030/pahcer/json/", "pahcer/json/"]
+        );
+        assert_eq!(
+            args(&ssh_command(&remote, "cd ahc && ahc test")),
+            vec!["ssh", "-p", "2222", "desktop", "cd ahc && ahc test"]
+        );
+    }
+
+    #[test]
+    fn quotes_words_for_the_shell() {
+        assert_eq!(shell_quote("--seeds=0..100"), "--seeds=0..100");
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+    }
+}
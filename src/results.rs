@@ -0,0 +1,282 @@
+//! `ahc results`, and result files of runs split across machines or workers merged back into one
+//! result of all the seeds, in the format of pahcer and `ahc test` so that `ahc commit` reads it
+//! as any other.
+
+use crate::config::Config;
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::pahcer;
+use crate::runner::{runs, Partition};
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, Subcommand};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::info;
+
+#[derive(Args)]
+pub(crate) struct ResultsArgs {
+    #[command(subcommand)]
+    command: ResultsCommands,
+}
+
+#[derive(Subcommand)]
+enum ResultsCommands {
+    /// Merge the results of disjoint sets of seeds, e.g. run on several machines, into one
+    Merge {
+        /// Result files to merge
+        #[arg(required = true, num_args = 2..)]
+        files: Vec<PathBuf>,
+        /// File to write, a new result in [paths] results_dir by default
+        #[arg(short = 'o', long = "output-file", value_name = "FILE")]
+        output_file: Option<PathBuf>,
+    },
+}
+
+/// What `ahc results merge --output json` prints.
+#[derive(Serialize, Debug)]
+struct MergeSummary {
+    result_file: PathBuf,
+    case_count: usize,
+    average_score: f64,
+}
+
+pub(crate) fn results(args: ResultsArgs, config: Config, output: Output) -> Result<()> {
+    match args.command {
+        ResultsCommands::Merge { files, output_file } => {
+            let results = files
+                .iter()
+                .map(|file| read_result_json(file))
+                .collect::<Result<Vec<_>>>()?;
+            let merged = merge(&results)?;
+            let results_dir = &config.paths.results_dir;
+            let result_file = match output_file {
+                Some(file) => file,
+                None => {
+                    let id =
+                        runs::new_run_id(SystemTime::now(), results_dir, &config.paths.outputs_dir);
+                    results_dir.join(runs::result_file_name(&id))
+                }
+            };
+            if files.contains(&result_file) {
+                return Err(anyhow!(
+                    "Not overwriting {}, which is merged",
+                    result_file.display()
+                ));
+            }
+            if let Some(parent) = result_file
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                std::fs::create_dir_all(parent)
+                    .context(format!("Failed to create directory: {}", parent.display()))?;
+            }
+            std::fs::write(&result_file, serde_json::to_string_pretty(&merged)?)
+                .context(format!("Failed to write file: {}", result_file.display()))?;
+
+            let result = pahcer::read_result(&result_file)?;
+            info!(
+                "{}",
+                msg!(
+                    "results.merged",
+                    files.len(),
+                    result_file.display(),
+                    format!("{:.2}", result.average_score()),
+                    result.case_count
+                )
+            );
+            if output.is_json() {
+                print_json(&MergeSummary {
+                    result_file,
+                    case_count: result.case_count,
+                    average_score: result.average_score(),
+                })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fields describing a run as a whole, which do not hold for the merged run
+const RUN_FIELDS: [&str; 4] = ["subset", "early_stopped", "output_dir", "partition"];
+
+/// Reads a result file as JSON, keeping the fields `ahc` does not know of.
+pub(crate) fn read_result_json(path: &Path) -> Result<Value> {
+    let text = std::fs::read_to_string(path)
+        .context(format!("Failed to read result file: {}", path.display()))?;
+    let result: Value = serde_json::from_str(&text)
+        .context(format!("Failed to parse result file: {}", path.display()))?;
+    if !result.get("cases").is_some_and(Value::is_array) {
+        return Err(anyhow!("No cases in result file: {}", path.display()));
+    }
+    Ok(result)
+}
+
+/// Merges results of disjoint sets of seeds: the cases of all of them in the order of the seeds,
+/// with the totals computed again. Shards of one partition merge into a run of all of them. The
+/// other fields are those of the first result.
+pub(crate) fn merge(results: &[Value]) -> Result<Value> {
+    let first = results
+        .first()
+        .and_then(Value::as_object)
+        .ok_or_else(|| anyhow!("No results to merge"))?;
+    let mut cases = BTreeMap::new();
+    for (index, result) in results.iter().enumerate() {
+        let result_cases = result
+            .get("cases")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow!("Result {} has no cases", index + 1))?;
+        for case in result_cases {
+            let seed = case
+                .get("seed")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| anyhow!("A case of result {} has no seed", index + 1))?;
+            if cases.insert(seed, case.clone()).is_some() {
+                return Err(anyhow!("Seed {} is in more than one result", seed));
+            }
+        }
+    }
+    let cases = cases.into_values().collect::<Vec<_>>();
+
+    let sum = |field: &str| -> Option<Value> {
+        let values = cases
+            .iter()
+            .map(|case| case.get(field))
+            .collect::<Option<Vec<_>>>()?;
+        if values.iter().all(|value| value.is_u64()) {
+            Some(
+                values
+                    .iter()
+                    .filter_map(|value| value.as_u64())
+                    .sum::<u64>()
+                    .into(),
+            )
+        } else {
+            Some(
+                values
+                    .iter()
+                    .filter_map(|value| value.as_f64())
+                    .sum::<f64>()
+                    .into(),
+            )
+        }
+    };
+    let times = cases
+        .iter()
+        .filter_map(|case| case.get("execution_time").and_then(Value::as_f64))
+        .collect::<Vec<_>>();
+
+    let mut merged = Map::new();
+    for (key, value) in first {
+        if !RUN_FIELDS.contains(&key.as_str()) {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    merged.insert("case_count".to_string(), cases.len().into());
+    merged.insert(
+        "total_score".to_string(),
+        sum("score").unwrap_or_else(|| 0.into()),
+    );
+    if let Some(total) = sum("relative_score") {
+        merged.insert("total_relative_score".to_string(), total);
+    }
+    if let Some(max) = times.iter().copied().reduce(f64::max) {
+        merged.insert("max_execution_time".to_string(), max.into());
+    }
+    if merged.contains_key("mean_execution_time") && !times.is_empty() {
+        let mean = times.iter().sum::<f64>() / times.len() as f64;
+        merged.insert("mean_execution_time".to_string(), mean.into());
+    }
+    if let Some(max) = cases
+        .iter()
+        .filter_map(|case| case.get("max_memory_kb").and_then(Value::as_u64))
+        .max()
+    {
+        merged.insert("max_memory_kb".to_string(), max.into());
+    }
+    if let Some(partition) = merged_partition(results) {
+        merged.insert("partition".to_string(), partition);
+    }
+    merged.insert("cases".to_string(), Value::Array(cases));
+    Ok(Value::Object(merged))
+}
+
+/// The partition of the results if they are shards of the same one, without the index of a shard.
+fn merged_partition(results: &[Value]) -> Option<Value> {
+    let partitions = results
+        .iter()
+        .map(|result| serde_json::from_value(result.get("partition")?.clone()).ok())
+        .collect::<Option<Vec<Partition>>>()?;
+    let first = partitions.first()?;
+    let same = partitions
+        .iter()
+        .all(|partition| (partition.strategy, partition.count) == (first.strategy, first.count));
+    same.then(|| {
+        let partition = Partition {
+            index: None,
+            ..first.clone()
+        };
+        serde_json::to_value(partition).expect("a partition serializes")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn merges_cases_and_computes_totals_again() {
+        let a = json!({
+            "start_time": "2024-01-01 00:00:00",
+            "case_count": 2,
+            "total_score": 30,
+            "max_execution_time": 0.5,
+            "subset": {"selection": "--seeds 0,2", "seeds": [0, 2]},
+            "cases": [
+                {"seed": 2, "score": 20, "execution_time": 0.5},
+                {"seed": 0, "score": 10, "execution_time": 0.25},
+            ],
+        });
+        let b = json!({
+            "start_time": "2024-01-01 00:01:00",
+            "case_count": 1,
+            "total_score": 5,
+            "max_execution_time": 1.5,
+            "cases": [{"seed": 1, "score": 5, "execution_time": 1.5}],
+        });
+
+        let merged = merge(&[a.clone(), b.clone()]).unwrap();
+        assert_eq!(merged["start_time"], "2024-01-01 00:00:00");
+        assert_eq!(merged["case_count"], 3);
+        assert_eq!(merged["total_score"], 35);
+        assert_eq!(merged["max_execution_time"], 1.5);
+        assert_eq!(merged.get("subset"), None);
+        let seeds = merged["cases"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|case| case["seed"].as_u64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(seeds, vec![0, 1, 2]);
+
+        let shard = |index: usize, seed: u64| {
+            json!({
+                "case_count": 1,
+                "total_score": 1,
+                "partition": {"strategy": "hash", "count": 2, "index": index},
+                "cases": [{"seed": seed, "score": 1}],
+            })
+        };
+        let merged = merge(&[shard(0, 0), shard(1, 1)]).unwrap();
+        assert_eq!(merged["partition"], json!({"strategy": "hash", "count": 2}));
+        let merged = merge(&[shard(0, 0), b.clone()]).unwrap();
+        assert_eq!(merged.get("partition"), None);
+
+        let error = merge(&[a.clone(), a]).unwrap_err();
+        assert_eq!(error.to_string(), "Seed 2 is in more than one result");
+        assert!(merge(&[]).is_err());
+    }
+}
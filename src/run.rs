@@ -0,0 +1,153 @@
+//! `ahc run`, which runs pahcer, prints the average score of the result it wrote and, with
+//! `--commit`, commits it right away as `ahc commit` would.
+
+use crate::commit::{self, CommitArgs, CommitSummary};
+use crate::config::Config;
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::pahcer;
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::{debug, info};
+
+#[derive(Args)]
+pub(crate) struct RunArgs {
+    /// Commit the result with this message, as `ahc commit` does
+    #[arg(short = 'm', long = "commit", value_name = "MESSAGE")]
+    message: Option<String>,
+    /// Also tag the commit
+    #[arg(long, requires = "message")]
+    tag: Option<String>,
+    /// Also stage the changes of tracked files before committing, like `git commit -a`
+    #[arg(short, long, requires = "message")]
+    all: bool,
+    /// More arguments for pahcer, after those of [run] args
+    #[arg(last = true)]
+    args: Vec<String>,
+}
+
+/// `[run]`, how `ahc run` invokes pahcer.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct RunConfig {
+    /// Command running the cases and writing a `result_*.json` to `[paths] results_dir`
+    #[serde(default = "default_command")]
+    pub(crate) command: Vec<String>,
+    /// Arguments added to the command, e.g. `["--shuffle"]`
+    #[serde(default)]
+    pub(crate) args: Vec<String>,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig {
+            command: default_command(),
+            args: vec![],
+        }
+    }
+}
+
+fn default_command() -> Vec<String> {
+    vec!["pahcer".to_string(), "run".to_string()]
+}
+
+/// What `ahc run --output json` prints.
+#[derive(Serialize, Debug)]
+struct RunSummary {
+    result_file: PathBuf,
+    case_count: usize,
+    average_score: f64,
+    commit: Option<CommitSummary>,
+}
+
+pub(crate) fn run(args: RunArgs, config: Config, output: Output) -> Result<()> {
+    let run_config = config.run.clone().unwrap_or_default();
+    let (program, command_args) = run_config
+        .command
+        .split_first()
+        .ok_or_else(|| anyhow!("[run] command is empty"))?;
+    let results_dir = &config.paths.results_dir;
+    let before = pahcer::list_results(results_dir)?;
+
+    let mut command = Command::new(program);
+    command
+        .args(command_args)
+        .args(&run_config.args)
+        .args(&args.args);
+    if output.is_json() {
+        // Keep stdout for the summary
+        command.stdout(Stdio::from(std::io::stderr()));
+    }
+    debug!("Running {:?}", command);
+    let status = command
+        .status()
+        .context(format!("Failed to run {}", program))?;
+    if !status.success() {
+        return Err(anyhow!(msg!("run.failed", program, status)));
+    }
+
+    let result_file = pahcer::list_results(results_dir)?
+        .into_iter()
+        .rfind(|path| !before.contains(path))
+        .ok_or_else(|| anyhow!(msg!("run.no_result", program, results_dir.display())))?;
+    let result = pahcer::read_result(&result_file)?;
+    info!(
+        "{}",
+        msg!(
+            "run.done",
+            format!("{:.2}", result.average_score()),
+            result.case_count,
+            result_file.display()
+        )
+    );
+
+    let commit = match args.message {
+        Some(message) => {
+            stage(&result_file, args.all)?;
+            let commit_args = CommitArgs {
+                message,
+                tag: args.tag,
+            };
+            commit::commit_staged_changes(&commit_args, &config)?
+        }
+        None => None,
+    };
+
+    if output.is_json() {
+        print_json(&RunSummary {
+            result_file,
+            case_count: result.case_count,
+            average_score: result.average_score(),
+            commit,
+        })?;
+    }
+    Ok(())
+}
+
+/// Stages the result file, and the changes of tracked files if `all`.
+fn stage(result_file: &Path, all: bool) -> Result<()> {
+    let repo = Repository::open_from_env().context("Failed to open git repository")?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("The git repository has no working directory"))?;
+    let path = std::fs::canonicalize(result_file)?;
+    let relative = path
+        .strip_prefix(std::fs::canonicalize(workdir)?)
+        .context(format!(
+            "{} is outside of the git repository",
+            result_file.display()
+        ))?;
+
+    let mut index = repo.index()?;
+    if all {
+        index.update_all(["*"], None)?;
+    }
+    index
+        .add_path(relative)
+        .context(format!("Failed to stage {}", result_file.display()))?;
+    index.write()?;
+    Ok(())
+}
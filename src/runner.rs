@@ -0,0 +1,1436 @@
+//! `ahc test`, a runner for the cases in `[paths] inputs_dir` which needs no pahcer. It runs the
+//! solver on the inputs in parallel, scores the outputs with the official `vis` or a scoring
+//! command of the contest, takes the score the solver prints itself, or runs the solver under the
+//! official `tester` for interactive problems, and writes a result in pahcer's format so that
+//! `ahc commit` and `ahc status` read it as usual.
+
+pub(crate) mod build;
+mod cache;
+mod container;
+mod early_stop;
+mod process;
+mod regressions;
+mod repeats;
+pub(crate) mod runs;
+mod seeds;
+mod table;
+mod worktree;
+
+use crate::config::Config;
+use crate::error::ErrorKind;
+use crate::messages::msg;
+use crate::notify::{
+    format_score, format_timestamp, Event, Notification, Notifier, Severity, Source,
+};
+use crate::output::{print_json, Output};
+use crate::overlay::Overlay;
+use crate::pahcer;
+use crate::tune::{
+    injection, parse_param, parse_score, Assignment, Objective, ParamSpace, ParamValue, ParamsVia,
+    DEFAULT_PARAMS_FILE, DEFAULT_SCORE_REGEX,
+};
+use anyhow::{anyhow, Context, Result};
+use build::BuildProfile;
+use cache::BinaryCache;
+use clap::Args;
+use container::{Container, ContainerConfig};
+use early_stop::{EarlyStop, EarlyStopConfig, Verdict};
+use regex::Regex;
+use regressions::Regression;
+use repeats::{Aggregate, RepeatStats};
+use runs::{Manifest, ManifestCase};
+use seeds::Subset;
+pub(crate) use seeds::{partition, Partition, PartitionStrategy, SeedArgs};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use table::LiveTable;
+use tracing::{debug, info, warn};
+use worktree::Worktree;
+
+/// Share of `[test] time_limit_ms` from which the slowest case is warned about, as a score gain
+/// close to the limit may not hold on the judge
+const NEAR_TIME_LIMIT: f64 = 0.9;
+
+#[derive(Args)]
+pub(crate) struct TestArgs {
+    #[command(flatten)]
+    options: RunOptions,
+    /// Stop early once the run is significantly worse than this pahcer result, with the
+    /// settings of [test.early_stop]
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+    /// Run the solver of this commit, built in a checkout of it, e.g. to run a baseline again on
+    /// a new set of seeds
+    #[arg(long, value_name = "COMMIT")]
+    at: Option<String>,
+    /// Hand a parameter to the solver as [test] params_via says, e.g. --param T0=2.5
+    #[arg(long = "param", value_name = "NAME=VALUE", value_parser = parse_param)]
+    params: Vec<(String, ParamValue)>,
+    /// Go on with an interrupted run, the newest one by default, running only the seeds it did
+    /// not finish. Give the options of the interrupted run again
+    #[arg(
+        long,
+        value_name = "RUN_ID",
+        num_args = 0..=1,
+        default_missing_value = runs::LATEST
+    )]
+    resume: Option<String>,
+}
+
+/// Options of `ahc test` shared by the commands running it, such as `ahc ab`.
+#[derive(Args)]
+pub(crate) struct RunOptions {
+    /// Number of cases to run at once, overriding [test] jobs
+    #[arg(short, long)]
+    jobs: Option<usize>,
+    /// Run the solver of [solver.<name>] instead of [test] command
+    #[arg(long)]
+    solver: Option<String>,
+    /// Build the solver with this profile of [test.profiles], or the built-in `release` or
+    /// `native`
+    #[arg(long)]
+    profile: Option<String>,
+    /// Build the solver even if a binary of the commit is cached
+    #[arg(long)]
+    no_cache: bool,
+    /// Build and run the solver in a container of this image, overriding [test.container] image
+    #[arg(long, value_name = "IMAGE")]
+    container: Option<String>,
+    /// Run each seed this many times and score it as [test] aggregate says, overriding [test]
+    /// repeats
+    #[arg(long, value_name = "COUNT")]
+    repeats: Option<usize>,
+    #[command(flatten)]
+    seeds: SeedArgs,
+}
+
+impl RunOptions {
+    /// Runs the seeds of `spec`, e.g. `0..10`, unless the options chose some.
+    pub(crate) fn default_seeds(&mut self, spec: &str) {
+        self.seeds.default_seeds(spec);
+    }
+}
+
+/// `[test]`, how `ahc test` runs the solver.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct TestConfig {
+    /// Command running the solver on stdin, e.g. `["target/release/ahc030"]`, unless a solver of
+    /// `[solver.<name>]` is run
+    #[serde(default)]
+    pub(crate) command: Vec<String>,
+    /// Solver of `[solver.<name>]` run when `--solver` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) solver: Option<String>,
+    /// Command run once before the cases, e.g. `["cargo", "build", "--release"]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) build: Option<Vec<String>>,
+    /// Build profile used when `--profile` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) profile: Option<String>,
+    /// `[test.profiles.<name>]`, build profiles besides the built-in `release` and `native`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) profiles: Option<BTreeMap<String, BuildProfile>>,
+    /// Keep the solver built at a clean commit in `.ahc/cache` and run it instead of building
+    /// that commit again
+    #[serde(default = "default_cache")]
+    pub(crate) cache: bool,
+    /// Number of cases to run at once, the number of CPUs by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) jobs: Option<usize>,
+    /// Run the solver under the `tester` of the tools, for interactive problems
+    #[serde(default)]
+    pub(crate) interactive: bool,
+    /// Regex finding the score in the output of `vis`, `tester` or `score_command`, or in the
+    /// stderr of the solver with `score_from_stderr`
+    #[serde(default = "default_score_regex")]
+    pub(crate) score_regex: String,
+    /// Take the score from what the solver prints on stderr instead of running `vis`
+    #[serde(default)]
+    pub(crate) score_from_stderr: bool,
+    /// Command scoring a case instead of `vis`, given the input and output files as its last
+    /// arguments, e.g. `["python3", "score.py"]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) score_command: Option<Vec<String>>,
+    /// Time limit of a case, e.g. 2000 for 2 seconds. Cases over it are TLE and score 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) time_limit_ms: Option<u64>,
+    /// How long past the time limit a case may run before it is killed
+    #[serde(default = "default_time_limit_grace_ms")]
+    pub(crate) time_limit_grace_ms: u64,
+    /// Keep the outputs of only this many of the latest runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) keep_runs: Option<usize>,
+    /// Stop runs significantly worse than a baseline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) early_stop: Option<EarlyStopConfig>,
+    /// Number of seeds with the largest drops from the baseline listed after a run, 0 for none
+    #[serde(default = "default_regressions")]
+    pub(crate) regressions: usize,
+    /// How parameters of `--param` or `ahc sweep` reach the solver: `env`, `args` or `file`
+    #[serde(default)]
+    pub(crate) params_via: ParamsVia,
+    /// File the parameters are written to with `params_via = "file"`, `params.txt` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) params_file: Option<PathBuf>,
+    /// Build and run the solver in a container, e.g. of the image of the judge
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) container: Option<ContainerConfig>,
+    /// Cores the cases run on, e.g. `[2, 3, 4, 5]`, each running case pinned to one of them.
+    /// The number of cases run at once is their number by default. Linux only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cpus: Option<Vec<usize>>,
+    /// Niceness of the solver, e.g. 10 to run in the background of other work
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) nice: Option<i32>,
+    /// Number of times each seed is run, for solvers whose score varies between runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) repeats: Option<usize>,
+    /// Score of a seed run several times: `mean` or `median` of its runs
+    #[serde(default)]
+    pub(crate) aggregate: Aggregate,
+}
+
+/// `[solver.<name>]`, a variant of the solver such as a greedy one or an annealing one.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct SolverConfig {
+    /// Command running this solver on stdin, e.g. `["target/release/sa"]`
+    pub(crate) command: Vec<String>,
+    /// Command building this solver, `[test] build` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) build: Option<Vec<String>>,
+    /// How parameters reach this solver, `[test] params_via` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) params_via: Option<ParamsVia>,
+    /// File the parameters are written to, `[test] params_file` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) params_file: Option<PathBuf>,
+}
+
+fn default_score_regex() -> String {
+    DEFAULT_SCORE_REGEX.to_string()
+}
+
+fn default_cache() -> bool {
+    true
+}
+
+fn default_regressions() -> usize {
+    5
+}
+
+fn default_time_limit_grace_ms() -> u64 {
+    500
+}
+
+/// A run of `ahc test`, in the format of pahcer's `result_*.json`.
+#[derive(Serialize, Debug)]
+struct TestResult {
+    start_time: String,
+    /// Name of the solver of `[solver.<name>]` which ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    solver: Option<String>,
+    /// Build profile the solver was built with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+    /// Commit the solver was built at with `--at`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    commit: Option<String>,
+    /// Image of the container the solver was built and run in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    container: Option<String>,
+    /// Parameters handed to the solver, by `ahc sweep` or `--param`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    params: Option<Assignment>,
+    /// Directory of the outputs, named by the id of the run
+    output_dir: PathBuf,
+    case_count: usize,
+    total_score: u64,
+    max_execution_time: f64,
+    mean_execution_time: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_memory_kb: Option<u64>,
+    /// Stopped before running every seed, as worse than the baseline
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    early_stopped: bool,
+    /// The seeds chosen with `--seeds`, `--seeds-file`, `--sample` or `--shard`, if not every
+    /// input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    subset: Option<Subset>,
+    /// The shard of the seeds run with `--shard`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    partition: Option<Partition>,
+    /// Number of times each seed ran, if more than once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    repeats: Option<usize>,
+    cases: Vec<CaseResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct CaseResult {
+    seed: u64,
+    score: u64,
+    execution_time: f64,
+    /// Empty unless the solver or the scorer failed, which scores 0
+    error_message: String,
+    /// Went over `[test] time_limit_ms`, which scores 0
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    tle: bool,
+    /// Peak resident memory of the solver, or of the tester and the solver for interactive
+    /// problems
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_memory_kb: Option<u64>,
+    /// The scores of the runs of the seed, when it ran several times
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    repeats: Option<RepeatStats>,
+}
+
+/// What `ahc test --output json` prints.
+#[derive(Serialize, Debug)]
+pub(crate) struct TestSummary {
+    run_id: String,
+    solver: Option<String>,
+    profile: Option<String>,
+    commit: Option<String>,
+    container: Option<String>,
+    pub(crate) result_file: PathBuf,
+    output_dir: PathBuf,
+    case_count: usize,
+    pub(crate) average_score: f64,
+    /// Time of the slowest case in seconds
+    max_execution_time: f64,
+    mean_execution_time: f64,
+    /// Seeds which failed other than by TLE
+    failed_seeds: Vec<u64>,
+    tle_seeds: Vec<u64>,
+    max_memory_kb: Option<u64>,
+    /// Seeds which dropped the most from the baseline
+    regressions: Vec<Regression>,
+    /// Number of times each seed ran, if more than once
+    repeats: Option<usize>,
+    /// Standard deviation of the scores of a seed relative to their mean, averaged over seeds
+    score_noise: Option<f64>,
+    /// Why the run stopped early
+    #[serde(skip)]
+    verdict: Option<Verdict>,
+}
+
+pub(crate) fn test(args: TestArgs, config: Config, output: Output) -> Result<()> {
+    let notifier = Notifier::new(config.notify.as_ref(), Source::Test)?;
+    let overlay = config.overlay.as_ref().map(Overlay::start).transpose()?;
+    let objective = config.objective();
+    // Found before the result of this run is written
+    let best_before = (config.notify.is_some() || overlay.is_some())
+        .then(|| best_average(&config.paths.results_dir, objective))
+        .flatten();
+    let space = ParamSpace::new();
+    let params = args.params.into_iter().collect::<Assignment>();
+    let run = TestRun {
+        at: args.at.as_deref(),
+        early_stop: true,
+        regressions: true,
+        baseline: args.baseline,
+        params: (!params.is_empty()).then_some((&space, &params)),
+        resume: args.resume.as_deref(),
+    };
+    let summary = run_test(&args.options, run, &config, output)?;
+    notify_test(
+        &notifier,
+        overlay.as_ref(),
+        &summary,
+        best_before,
+        objective,
+    );
+    if output.is_json() {
+        print_json(&summary)?;
+    }
+    if let Some(verdict) = &summary.verdict {
+        return Err(ErrorKind::Regression.error(msg!(
+            "test.early_stopped",
+            verdict.cases,
+            format!("{:+.2}", verdict.mean_change * 100.0),
+            format!("{:.4}", verdict.p_value)
+        )));
+    }
+    Ok(())
+}
+
+/// The best average score of the results recorded so far, if any.
+fn best_average(results_dir: &Path, objective: Objective) -> Option<f64> {
+    pahcer::list_results(results_dir)
+        .ok()?
+        .iter()
+        .filter_map(|path| pahcer::read_result(path).ok())
+        .filter(|result| result.case_count > 0)
+        .map(|result| result.average_score())
+        .reduce(|best, score| match objective.is_better(score, best) {
+            true => score,
+            false => best,
+        })
+}
+
+/// Notifies that a run finished, and of a new best if it beat `best_before` with every case run,
+/// and shows it on the overlay.
+fn notify_test(
+    notifier: &Notifier,
+    overlay: Option<&Overlay>,
+    summary: &TestSummary,
+    best_before: Option<f64>,
+    objective: Objective,
+) {
+    let score = summary.average_score;
+    let failed = summary.failed_seeds.len() + summary.tle_seeds.len();
+    let mut body = format!(
+        "{} case(s), average score {}",
+        summary.case_count,
+        format_score(score, best_before)
+    );
+    if failed > 0 {
+        body.push_str(&format!("; {} case(s) failed", failed));
+    }
+    let details = serde_json::to_value(summary).ok();
+    notifier.send(&Notification {
+        event: Event::Finished,
+        severity: match failed {
+            0 => Severity::Info,
+            _ => Severity::Warning,
+        },
+        title: format!("Test run {} finished", summary.run_id),
+        body,
+        chart: None,
+        details: None,
+        summary: details.clone(),
+    });
+    // A run stopped early has not run every case
+    let improved = best_before.filter(|best| {
+        summary.verdict.is_none() && failed == 0 && objective.is_better(score, *best)
+    });
+    if let Some(previous) = improved {
+        notifier.send(&Notification {
+            event: Event::NewBest,
+            severity: Severity::Info,
+            title: format!("New best in test run {}", summary.run_id),
+            body: format!("Average score {}", format_score(score, Some(previous))),
+            chart: None,
+            details: None,
+            summary: details,
+        });
+    }
+    if let Some(overlay) = overlay {
+        let best = match best_before {
+            Some(best) if improved.is_none() => best,
+            _ => score,
+        };
+        overlay.update(score, best, improved.map(|previous| score - previous));
+    }
+}
+
+/// How a run of [`run_test`] differs from a plain `ahc test`.
+#[derive(Default)]
+pub(crate) struct TestRun<'a> {
+    /// Commit to build the solver at
+    pub(crate) at: Option<&'a str>,
+    /// Stop early as `[test.early_stop]` says
+    pub(crate) early_stop: bool,
+    /// List the seeds which dropped the most from the baseline, as many as `[test] regressions`
+    pub(crate) regressions: bool,
+    /// Baseline of the early stop and the regressions, the latest result by default
+    pub(crate) baseline: Option<PathBuf>,
+    /// Parameters handed to the solver, as `params_via` and the `env` and `arg` of their spec say
+    pub(crate) params: Option<(&'a ParamSpace, &'a Assignment)>,
+    /// Interrupted run to go on with, or `latest`
+    pub(crate) resume: Option<&'a str>,
+}
+
+/// Builds and runs the solver as `ahc test` does, writing the result and the outputs of the run.
+pub(crate) fn run_test(
+    options: &RunOptions,
+    run: TestRun,
+    config: &Config,
+    output: Output,
+) -> Result<TestSummary> {
+    let test_config = config
+        .test
+        .clone()
+        .ok_or_else(|| ErrorKind::Config.error(msg!("test.no_config")))?;
+    let solver = options.solver.clone().or(test_config.solver.clone());
+    let (command, build, params_via, params_file) = match &solver {
+        Some(name) => {
+            let solver_config = find_solver(config, name)?;
+            (
+                solver_config.command.clone(),
+                solver_config.build.clone().or(test_config.build.clone()),
+                solver_config.params_via.unwrap_or(test_config.params_via),
+                solver_config
+                    .params_file
+                    .clone()
+                    .or(test_config.params_file.clone()),
+            )
+        }
+        None => (
+            test_config.command.clone(),
+            test_config.build.clone(),
+            test_config.params_via,
+            test_config.params_file.clone(),
+        ),
+    };
+    if command.is_empty() {
+        return Err(ErrorKind::Config.error(match &solver {
+            Some(name) => format!("[solver.{}] command is empty", name),
+            None => "[test] command is empty".to_string(),
+        }));
+    }
+    if let Some(cpus) = &test_config.cpus {
+        if cpus.is_empty() {
+            return Err(ErrorKind::Config.error("[test] cpus is empty"));
+        }
+        if !cfg!(target_os = "linux") {
+            warn!("{}", msg!("test.no_affinity"));
+        }
+    }
+    let repeat_count = match options.repeats.or(test_config.repeats) {
+        Some(0) => return Err(anyhow!(msg!("test.no_repeats"))),
+        repeats => repeats.unwrap_or(1),
+    };
+    let jobs = match options.jobs.or(test_config.jobs) {
+        Some(0) => return Err(anyhow!(msg!("test.no_jobs"))),
+        Some(jobs) => jobs,
+        None => match &test_config.cpus {
+            Some(cpus) => cpus.len(),
+            None => std::thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+        },
+    };
+    let paths = &config.paths;
+    let (seeds, subset) = choose_seeds(&options.seeds, &paths.inputs_dir)?;
+
+    let at = run.at.map(worktree::resolve_commit).transpose()?;
+    let profile = options.profile.clone().or(test_config.profile.clone());
+    let build_profile = profile
+        .as_deref()
+        .map(|name| build::find_profile(test_config.profiles.as_ref(), name))
+        .transpose()?;
+
+    if let Some(name) = &solver {
+        info!("{}", msg!("test.solver", name));
+    }
+    let container = match (&options.container, test_config.container.clone()) {
+        (Some(image), container_config) => {
+            Some(ContainerConfig::with_image(container_config, image))
+        }
+        (None, container_config) => container_config,
+    }
+    .map(|container_config| Container::start(&container_config))
+    .transpose()?;
+    let use_cache = test_config.cache && !options.no_cache;
+    let run_command = match &at {
+        Some(commit) => build_at(
+            commit,
+            &command,
+            build.as_deref(),
+            profile.as_deref(),
+            build_profile,
+            use_cache,
+            container.as_ref(),
+        )?,
+        None if build.is_none() && build_profile.is_none() => command.clone(),
+        None => {
+            let cache = use_cache
+                .then(cache::clean_commit)
+                .flatten()
+                .map(|commit| binary_cache(&commit, profile.as_deref(), container.as_ref()));
+            build_solver(
+                &command,
+                build.as_deref(),
+                profile.as_deref(),
+                build_profile,
+                cache,
+                container.as_ref(),
+            )?
+        }
+    };
+    let container_image = container
+        .as_ref()
+        .map(|container| container.image().to_string());
+    let scorer = scorer(&test_config, &paths.tools_dir)?;
+    let start = SystemTime::now();
+    let (run_id, mut finished) = match run.resume {
+        Some(id) => {
+            let id = runs::unfinished_run(&paths.outputs_dir, id)?;
+            let finished = runs::read_progress(&paths.outputs_dir, &id)?;
+            (id, finished)
+        }
+        None => (
+            runs::new_run_id(start, &paths.results_dir, &paths.outputs_dir),
+            vec![],
+        ),
+    };
+    finished.retain(|case| seeds.contains(&case.seed));
+    if run.resume.is_some() {
+        info!(
+            "{}",
+            msg!("test.resuming", run_id, finished.len(), seeds.len())
+        );
+    }
+    let run_dir = paths.outputs_dir.join(&run_id);
+    std::fs::create_dir_all(&run_dir)
+        .context(format!("Failed to create directory: {}", run_dir.display()))?;
+    let injection = run
+        .params
+        .map(|(space, assignment)| injection(params_via, space, assignment))
+        .unwrap_or_default();
+    if let Some(contents) = &injection.file {
+        let params_file = params_file.unwrap_or_else(|| PathBuf::from(DEFAULT_PARAMS_FILE));
+        std::fs::write(&params_file, contents).context(format!(
+            "Failed to write params file: {}",
+            params_file.display()
+        ))?;
+    }
+    let runner = Runner {
+        command: [run_command, injection.args].concat(),
+        envs: injection.envs,
+        scorer,
+        score_regex: Regex::new(&test_config.score_regex).context(format!(
+            "Failed to parse score regex: {}",
+            test_config.score_regex
+        ))?,
+        inputs_dir: paths.inputs_dir.clone(),
+        outputs_dir: run_dir.clone(),
+        time_limit: test_config.time_limit_ms.map(Duration::from_millis),
+        time_limit_grace: Duration::from_millis(test_config.time_limit_grace_ms),
+        container,
+        cpus: test_config.cpus.clone().unwrap_or_default(),
+        nice: test_config.nice,
+        repeats: repeat_count,
+        aggregate: test_config.aggregate,
+    };
+    // Found before the result of this run is written, which would be the latest
+    let regression_baseline = match &run.baseline {
+        _ if !run.regressions || test_config.regressions == 0 => None,
+        Some(baseline) => Some(baseline.clone()),
+        None => pahcer::find_latest_result(&paths.results_dir)?,
+    };
+    let early_stop = match (run.baseline, test_config.early_stop.clone()) {
+        _ if !run.early_stop => None,
+        (None, None) => None,
+        (baseline, early_stop_config) => {
+            let early_stop_config = early_stop_config.unwrap_or_default();
+            early_stop_config.validate()?;
+            let baseline = match baseline.or(early_stop_config.baseline.clone()) {
+                Some(baseline) => Some(baseline),
+                None => pahcer::find_latest_result(&paths.results_dir)?,
+            };
+            match baseline {
+                Some(baseline) => {
+                    info!("{}", msg!("test.baseline", baseline.display()));
+                    let early_stop =
+                        EarlyStop::new(early_stop_config, &baseline, config.objective())?;
+                    Some(Mutex::new(early_stop))
+                }
+                None => {
+                    warn!("{}", msg!("test.no_baseline", paths.results_dir.display()));
+                    None
+                }
+            }
+        }
+    };
+
+    let remaining = seeds
+        .iter()
+        .copied()
+        .filter(|seed| finished.iter().all(|case| case.seed != *seed))
+        .collect::<Vec<_>>();
+    info!("{}", msg!("test.start", remaining.len(), jobs));
+    let table = (!output.is_json()).then(|| Mutex::new(LiveTable::new(seeds.len())));
+    if let Some(table) = &table {
+        println!("{}", table.lock().unwrap().header());
+    }
+    let verdict = Mutex::new(None);
+    let on_done = |case: &CaseResult| {
+        if let Some(table) = &table {
+            println!("{}", table.lock().unwrap().row(case));
+        }
+        if let Some(early_stop) = &early_stop {
+            if let Some(stopped) = early_stop.lock().unwrap().add(case) {
+                verdict.lock().unwrap().get_or_insert(stopped);
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    };
+    // The cases finished before an interruption count as if run again
+    let stopped = finished.iter().any(|case| on_done(case).is_break());
+    let progress = Mutex::new(runs::Progress::open(&run_dir)?);
+    let run_cases = if stopped {
+        vec![]
+    } else {
+        runner.run_all(&remaining, jobs, &|case| {
+            if let Err(e) = progress.lock().unwrap().record(case) {
+                warn!("{:#}", e);
+            }
+            on_done(case)
+        })
+    };
+    let mut by_seed = finished
+        .into_iter()
+        .chain(run_cases)
+        .map(|case| (case.seed, case))
+        .collect::<BTreeMap<_, _>>();
+    let cases = seeds
+        .iter()
+        .filter_map(|seed| by_seed.remove(seed))
+        .collect::<Vec<_>>();
+    // Averaging no cases would report a NaN score
+    if cases.is_empty() {
+        return Err(anyhow!(msg!("test.no_cases")));
+    }
+    let verdict = verdict.into_inner().unwrap();
+    let (mut failed_seeds, mut tle_seeds) = (vec![], vec![]);
+    for case in cases.iter().filter(|case| !case.error_message.is_empty()) {
+        if case.tle {
+            tle_seeds.push(case.seed);
+        } else {
+            warn!(
+                "{}",
+                msg!("test.case_failed", case.seed, case.error_message)
+            );
+            failed_seeds.push(case.seed);
+        }
+    }
+    if !tle_seeds.is_empty() {
+        let seeds = tle_seeds.iter().map(u64::to_string).collect::<Vec<_>>();
+        warn!("{}", msg!("test.tle", tle_seeds.len(), seeds.join(", ")));
+    }
+    let result = TestResult {
+        start_time: format_timestamp(start),
+        solver: solver.clone(),
+        profile: profile.clone(),
+        commit: at.clone(),
+        container: container_image.clone(),
+        params: run.params.map(|(_, assignment)| assignment.clone()),
+        output_dir: run_dir.clone(),
+        case_count: cases.len(),
+        total_score: cases.iter().map(|case| case.score).sum(),
+        max_execution_time: cases
+            .iter()
+            .map(|case| case.execution_time)
+            .fold(0.0, f64::max),
+        mean_execution_time: cases.iter().map(|case| case.execution_time).sum::<f64>()
+            / cases.len().max(1) as f64,
+        max_memory_kb: cases.iter().filter_map(|case| case.max_memory_kb).max(),
+        early_stopped: verdict.is_some(),
+        subset,
+        partition: options.seeds.partition(),
+        repeats: (repeat_count > 1).then_some(repeat_count),
+        cases,
+    };
+    let result_file = write_result(&paths.results_dir, &run_id, &result)?;
+    let manifest = Manifest {
+        id: run_id.clone(),
+        start_time: result.start_time.clone(),
+        solver: solver.clone(),
+        commit: at.clone(),
+        command,
+        result_file: result_file.clone(),
+        cases: result
+            .cases
+            .iter()
+            .map(|case| ManifestCase {
+                seed: case.seed,
+                score: case.score,
+                output: PathBuf::from(format!("{:04}.txt", case.seed)),
+            })
+            .collect(),
+    };
+    runs::write_manifest(&run_dir, &manifest)?;
+    runs::finish_progress(&run_dir);
+    if let Some(keep) = test_config.keep_runs {
+        for id in runs::prune(&paths.outputs_dir, keep.max(1))? {
+            debug!("Removed the outputs of run {}", id);
+        }
+    }
+    if let Some(peak) = result
+        .cases
+        .iter()
+        .filter(|case| case.max_memory_kb.is_some())
+        .max_by_key(|case| case.max_memory_kb)
+    {
+        let mb = peak.max_memory_kb.unwrap_or_default() as f64 / 1024.0;
+        info!("{}", msg!("test.memory", format!("{:.1}", mb), peak.seed));
+    }
+
+    let average_score = result.total_score as f64 / result.case_count as f64;
+    info!(
+        "{}",
+        msg!(
+            "test.done",
+            format!("{:.2}", average_score),
+            result.case_count,
+            failed_seeds.len(),
+            result_file.display()
+        )
+    );
+    if let Some(slowest) = result
+        .cases
+        .iter()
+        .max_by(|a, b| a.execution_time.total_cmp(&b.execution_time))
+    {
+        info!(
+            "{}",
+            msg!(
+                "test.time",
+                format!("{:.3}", slowest.execution_time),
+                slowest.seed,
+                format!("{:.3}", result.mean_execution_time)
+            )
+        );
+    }
+    let score_noise = repeats::relative_noise(&result.cases);
+    if let Some(noise) = score_noise {
+        info!(
+            "{}",
+            msg!(
+                "test.score_noise",
+                repeat_count,
+                format!("{:.2}", noise * 100.0)
+            )
+        );
+    }
+    if let Some(limit) = test_config.time_limit_ms {
+        let ratio = result.max_execution_time * 1000.0 / limit as f64;
+        if ratio >= NEAR_TIME_LIMIT {
+            warn!(
+                "{}",
+                msg!(
+                    "test.near_time_limit",
+                    format!("{:.3}", result.max_execution_time),
+                    format!("{:.0}", ratio * 100.0),
+                    format!("{:.3}", limit as f64 / 1000.0)
+                )
+            );
+        }
+    }
+    let regressions = match regression_baseline {
+        Some(baseline) => match pahcer::read_result(&baseline) {
+            Ok(baseline_result) => {
+                let regressions = regressions::find_regressions(
+                    &result.cases,
+                    &baseline_result,
+                    config.objective(),
+                    &paths.inputs_dir,
+                    test_config.regressions,
+                );
+                if !regressions.is_empty() {
+                    info!("{}", msg!("test.regressions", baseline.display()));
+                    for row in regressions::format_regressions(&regressions) {
+                        info!("{}", row);
+                    }
+                }
+                regressions
+            }
+            Err(e) => {
+                warn!("{:#}", e);
+                vec![]
+            }
+        },
+        None => vec![],
+    };
+    info!("{}", msg!("test.outputs", run_id, run_dir.display()));
+    Ok(TestSummary {
+        run_id,
+        solver,
+        profile,
+        commit: at,
+        container: container_image,
+        result_file,
+        output_dir: run_dir,
+        case_count: result.case_count,
+        average_score,
+        max_execution_time: result.max_execution_time,
+        mean_execution_time: result.mean_execution_time,
+        failed_seeds,
+        tle_seeds,
+        max_memory_kb: result.max_memory_kb,
+        regressions,
+        repeats: result.repeats,
+        score_noise,
+        verdict,
+    })
+}
+
+/// The seeds of the inputs which `seed_args` choose, with the subset if they chose one.
+pub(crate) fn choose_seeds(
+    seed_args: &SeedArgs,
+    inputs_dir: &Path,
+) -> Result<(Vec<u64>, Option<Subset>)> {
+    let seeds = list_seeds(inputs_dir)?;
+    if seeds.is_empty() {
+        return Err(anyhow!(msg!("test.no_inputs", inputs_dir.display())));
+    }
+    let (seeds, subset) = seed_args.select(seeds)?;
+    if seeds.is_empty() {
+        return Err(anyhow!(msg!("test.no_seeds")));
+    }
+    Ok((seeds, subset))
+}
+
+/// Seeds of the inputs, named like `0042.txt`.
+fn list_seeds(inputs_dir: &Path) -> Result<Vec<u64>> {
+    let entries = std::fs::read_dir(inputs_dir).context(format!(
+        "Failed to read directory: {}",
+        inputs_dir.display()
+    ))?;
+    let mut seeds = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "txt") {
+            if let Some(seed) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse().ok())
+            {
+                seeds.push(seed);
+            }
+        }
+    }
+    seeds.sort();
+    Ok(seeds)
+}
+
+/// Builds the solver, or finds the binary of `command` in `cache`, returning the command running
+/// the binary built.
+fn build_solver(
+    command: &[String],
+    build: Option<&[String]>,
+    profile: Option<&str>,
+    build_profile: Option<BuildProfile>,
+    cache: Option<BinaryCache>,
+    container: Option<&Container>,
+) -> Result<Vec<String>> {
+    let binary = cache::built_binary(command);
+    let mut command = command.to_vec();
+    if let Some((cache, binary)) = cache.as_ref().zip(binary) {
+        if let Some(cached) = cache.get(binary) {
+            info!("{}", msg!("test.cached", cached.display()));
+            command[0] = cached.to_string_lossy().into_owned();
+            return Ok(command);
+        }
+    }
+
+    if let Some(name) = profile {
+        info!("{}", msg!("test.profile", name));
+    }
+    build::build(build, build_profile.as_ref(), None, container)?;
+    if let Some((cache, binary)) = cache.as_ref().zip(binary) {
+        if binary.is_file() {
+            match cache.put(binary) {
+                Ok(cached) => debug!("Cached {} as {}", binary.display(), cached.display()),
+                Err(e) => warn!("{:#}", e),
+            }
+        }
+    }
+    Ok(command)
+}
+
+/// Builds the solver of `commit` in a checkout of it, or finds it in the cache, returning the
+/// command running the binary built.
+fn build_at(
+    commit: &str,
+    command: &[String],
+    build: Option<&[String]>,
+    profile: Option<&str>,
+    build_profile: Option<BuildProfile>,
+    use_cache: bool,
+    container: Option<&Container>,
+) -> Result<Vec<String>> {
+    let binary = cache::built_binary(command)
+        .ok_or_else(|| ErrorKind::Config.error(msg!("test.at_no_binary", command[0])))?;
+    if build.is_none() && build_profile.is_none() {
+        return Err(ErrorKind::Config.error(msg!("test.at_no_build")));
+    }
+    let cache = binary_cache(commit, profile, container);
+    let mut command = command.to_vec();
+    if let Some(cached) = cache.get(binary).filter(|_| use_cache) {
+        info!("{}", msg!("test.cached", cached.display()));
+        command[0] = cached.to_string_lossy().into_owned();
+        return Ok(command);
+    }
+
+    info!("{}", msg!("test.checkout", &commit[..7]));
+    let worktree = Worktree::checkout(commit)?;
+    if let Some(name) = profile {
+        info!("{}", msg!("test.profile", name));
+    }
+    build::build(
+        build,
+        build_profile.as_ref(),
+        Some(worktree.project_dir()),
+        container,
+    )?;
+    let built = worktree.project_dir().join(binary);
+    if !built.is_file() {
+        return Err(anyhow!(msg!(
+            "test.not_produced",
+            &commit[..7],
+            binary.display()
+        )));
+    }
+    // The binary outlives the checkout in the cache
+    command[0] = cache.put(&built)?.to_string_lossy().into_owned();
+    Ok(command)
+}
+
+/// The cache of the binaries of `commit` built with `profile`, here or in `container`.
+fn binary_cache(commit: &str, profile: Option<&str>, container: Option<&Container>) -> BinaryCache {
+    let cache = BinaryCache::new(commit, profile);
+    match container {
+        Some(container) => cache.in_container(container.image()),
+        None => cache,
+    }
+}
+
+fn find_solver<'a>(config: &'a Config, name: &str) -> Result<&'a SolverConfig> {
+    let solvers = config.solver.as_ref();
+    solvers
+        .and_then(|solvers| solvers.get(name))
+        .ok_or_else(|| {
+            let names = solvers
+                .map(|solvers| solvers.keys().cloned().collect::<Vec<_>>())
+                .unwrap_or_default();
+            ErrorKind::Config.error(msg!("test.unknown_solver", name, names.join(", ")))
+        })
+}
+
+/// The release binary `name` of the tools, built first if it is not yet.
+pub(crate) fn tool(tools_dir: &Path, name: &str) -> Result<PathBuf> {
+    let path = tool_path(tools_dir, name);
+    if path.exists() {
+        return Ok(path);
+    }
+    info!("{}", msg!("test.building_tool", name));
+    build_tools(tools_dir, &[name.to_string()])?;
+    Ok(path)
+}
+
+fn tool_path(tools_dir: &Path, name: &str) -> PathBuf {
+    tools_dir
+        .join("target/release")
+        .join(format!("{}{}", name, std::env::consts::EXE_SUFFIX))
+}
+
+/// Builds the release binaries `names` of the tools, every one if empty, and returns the paths
+/// of those named. The errors of cargo are left on stderr.
+pub(crate) fn build_tools(tools_dir: &Path, names: &[String]) -> Result<Vec<PathBuf>> {
+    let manifest = tools_dir.join("Cargo.toml");
+    if !manifest.exists() {
+        return Err(anyhow!(msg!("test.no_tools", tools_dir.display())));
+    }
+    let mut command = Command::new("cargo");
+    command.args(["build", "--release", "--quiet"]);
+    if names.is_empty() {
+        command.arg("--bins");
+    }
+    for name in names {
+        command.args(["--bin", name]);
+    }
+    command.arg("--manifest-path").arg(&manifest);
+    debug!("Running {:?}", command);
+    let status = command.status().context("Failed to run cargo")?;
+    if !status.success() {
+        return Err(anyhow!(msg!(
+            "test.tools_build_failed",
+            tools_dir.display(),
+            status
+        )));
+    }
+    let paths = names
+        .iter()
+        .map(|name| tool_path(tools_dir, name))
+        .collect::<Vec<_>>();
+    if let Some(missing) = paths.iter().find(|path| !path.exists()) {
+        return Err(anyhow!(msg!("test.tool_not_built", missing.display())));
+    }
+    Ok(paths)
+}
+
+/// The scorer `[test]` asks for, building `vis` or `tester` if needed.
+fn scorer(test_config: &TestConfig, tools_dir: &Path) -> Result<Scorer> {
+    match &test_config.score_command {
+        Some(_) if test_config.interactive || test_config.score_from_stderr => {
+            Err(ErrorKind::Config
+                .error("[test] score_command cannot be used with interactive or score_from_stderr"))
+        }
+        Some(command) if command.is_empty() => {
+            Err(ErrorKind::Config.error("[test] score_command is empty"))
+        }
+        Some(command) => Ok(Scorer::Command(command.clone())),
+        // The tester prints the stderr of the solver along with its own
+        None if test_config.interactive => Ok(Scorer::Tester(tool(tools_dir, "tester")?)),
+        None if test_config.score_from_stderr => Ok(Scorer::Stderr),
+        None => Ok(Scorer::Vis(tool(tools_dir, "vis")?)),
+    }
+}
+
+/// Writes `result_<run id>.json` into `results_dir`.
+fn write_result(results_dir: &Path, run_id: &str, result: &TestResult) -> Result<PathBuf> {
+    std::fs::create_dir_all(results_dir).context(format!(
+        "Failed to create directory: {}",
+        results_dir.display()
+    ))?;
+    let path = results_dir.join(runs::result_file_name(run_id));
+    let json = serde_json::to_string_pretty(result)?;
+    std::fs::write(&path, json).context(format!("Failed to write file: {}", path.display()))?;
+    Ok(path)
+}
+
+/// How the cases are scored.
+enum Scorer {
+    /// `vis <input> <output>` prints the score of the output
+    Vis(PathBuf),
+    /// `tester <solver...>` talks to the solver and prints the score
+    Tester(PathBuf),
+    /// The solver prints its own score on stderr
+    Stderr,
+    /// `<command...> <input> <output>` prints the score of the output
+    Command(Vec<String>),
+}
+
+struct Runner {
+    command: Vec<String>,
+    /// Environment variables of the solver, such as its parameters
+    envs: Vec<(String, String)>,
+    scorer: Scorer,
+    score_regex: Regex,
+    inputs_dir: PathBuf,
+    outputs_dir: PathBuf,
+    time_limit: Option<Duration>,
+    /// How long past the time limit a case may run before it is killed
+    time_limit_grace: Duration,
+    /// Container the solver runs in, removed once the run is done
+    container: Option<Container>,
+    /// Cores the workers are pinned to, one each in turn
+    cpus: Vec<usize>,
+    /// Niceness of the processes of the cases
+    nice: Option<i32>,
+    /// Number of times each seed runs, one after another as the runs share the output file
+    repeats: usize,
+    aggregate: Aggregate,
+}
+
+impl Runner {
+    /// Runs the seeds with up to `jobs` threads, returning the results in the order of `seeds`.
+    /// `on_done` sees each case as it finishes, and may stop the run: the cases running then
+    /// finish, and the seeds not started yet are left out.
+    fn run_all(
+        &self,
+        seeds: &[u64],
+        jobs: usize,
+        on_done: &(dyn Fn(&CaseResult) -> ControlFlow<()> + Sync),
+    ) -> Vec<CaseResult> {
+        let next = AtomicUsize::new(0);
+        let stopped = AtomicBool::new(false);
+        let results = Mutex::new(vec![None; seeds.len()]);
+        let (next, stopped, results_ref) = (&next, &stopped, &results);
+        std::thread::scope(|scope| {
+            for worker in 0..jobs.min(seeds.len()) {
+                // Each worker keeps to a core of its own, as long as there are enough
+                let cpu = (!self.cpus.is_empty()).then(|| self.cpus[worker % self.cpus.len()]);
+                scope.spawn(move || loop {
+                    if stopped.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(seed) = seeds.get(i) else {
+                        break;
+                    };
+                    let case = self.run_case(*seed, cpu);
+                    if on_done(&case).is_break() {
+                        stopped.store(true, Ordering::Relaxed);
+                    }
+                    results_ref.lock().unwrap()[i] = Some(case);
+                });
+            }
+        });
+        results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    fn run_case(&self, seed: u64, cpu: Option<usize>) -> CaseResult {
+        if self.repeats > 1 {
+            let runs = (0..self.repeats)
+                .map(|_| self.run_once(seed, cpu))
+                .collect();
+            return repeats::combine(runs, self.aggregate);
+        }
+        self.run_once(seed, cpu)
+    }
+
+    fn run_once(&self, seed: u64, cpu: Option<usize>) -> CaseResult {
+        let mut case = CaseResult {
+            seed,
+            score: 0,
+            execution_time: 0.0,
+            error_message: String::new(),
+            tle: false,
+            max_memory_kb: None,
+            repeats: None,
+        };
+        if let Err(e) = self.try_run_case(&mut case, cpu) {
+            case.score = 0;
+            case.error_message = format!("{:#}", e);
+        }
+        debug!(
+            "Seed {}: score {} in {:.3}s",
+            seed, case.score, case.execution_time
+        );
+        case
+    }
+
+    /// Runs the seed of `case` on `cpu` if given, filling in its score and execution time.
+    fn try_run_case(&self, case: &mut CaseResult, cpu: Option<usize>) -> Result<()> {
+        let input_path = self.inputs_dir.join(format!("{:04}.txt", case.seed));
+        let output_path = self.outputs_dir.join(format!("{:04}.txt", case.seed));
+        let input = std::fs::File::open(&input_path)
+            .context(format!("Failed to open file: {}", input_path.display()))?;
+        let output = std::fs::File::create(&output_path)
+            .context(format!("Failed to create file: {}", output_path.display()))?;
+        let kill_after = self.time_limit.map(|limit| limit + self.time_limit_grace);
+
+        let solver = self.solver_command();
+        if let Scorer::Tester(tester) = &self.scorer {
+            let mut command = Command::new(tester);
+            command
+                .args(&solver)
+                .envs(self.envs.iter().cloned())
+                .stdin(input)
+                .stdout(output);
+            process::place(&mut command, cpu, self.nice);
+            let tested = process::run(command, kill_after)
+                .context(format!("Failed to run {}", tester.display()))?;
+            let status = self.record_usage(&tested, case)?;
+            if !status.success() {
+                return Err(anyhow!(msg!("test.tester_failed", status)));
+            }
+            case.score = parse_score(&self.score_regex, &tested.stderr)?;
+            return Ok(());
+        }
+
+        let mut command = Command::new(&solver[0]);
+        command
+            .args(&solver[1..])
+            .envs(self.envs.iter().cloned())
+            .stdin(input)
+            .stdout(output);
+        process::place(&mut command, cpu, self.nice);
+        let solved = process::run(command, kill_after)
+            .context(format!("Failed to run {}", self.command[0]))?;
+        let status = self.record_usage(&solved, case)?;
+        if !status.success() {
+            return Err(anyhow!(msg!("test.solver_failed", status)));
+        }
+        case.score = match &self.scorer {
+            Scorer::Vis(vis) => {
+                let mut command = Command::new(vis);
+                command.arg(&input_path).arg(&output_path);
+                self.score_with(command, &vis.display().to_string())?
+            }
+            Scorer::Command(score_command) => {
+                let mut command = Command::new(&score_command[0]);
+                command
+                    .args(&score_command[1..])
+                    .arg(&input_path)
+                    .arg(&output_path);
+                self.score_with(command, &score_command[0])?
+            }
+            Scorer::Stderr => parse_score(&self.score_regex, &solved.stderr)
+                .context("No score in the stderr of the solver")?,
+            Scorer::Tester(_) => unreachable!("the tester scores while solving"),
+        };
+        Ok(())
+    }
+
+    /// The words running the solver, in the container if any. The tools such as `vis` and
+    /// `tester` run here, as they do not decide the score or the time.
+    fn solver_command(&self) -> Vec<String> {
+        match &self.container {
+            Some(container) => container.exec(&self.command, &self.envs, None),
+            None => self.command.clone(),
+        }
+    }
+
+    /// Runs a scoring command and finds the score in its stdout and stderr.
+    fn score_with(&self, mut command: Command, name: &str) -> Result<u64> {
+        let scored = command
+            .output()
+            .context(format!("Failed to run {}", name))?;
+        let text = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&scored.stdout),
+            String::from_utf8_lossy(&scored.stderr)
+        );
+        parse_score(&self.score_regex, &text)
+    }
+
+    /// Records the execution time and memory, failing the case as TLE if it went over the time
+    /// limit.
+    fn record_usage(
+        &self,
+        finished: &process::Finished,
+        case: &mut CaseResult,
+    ) -> Result<ExitStatus> {
+        case.execution_time = finished.elapsed.as_secs_f64();
+        case.max_memory_kb = finished.max_rss_kb;
+        let over = self
+            .time_limit
+            .is_some_and(|limit| finished.elapsed > limit);
+        match finished.status {
+            Some(status) if !over => Ok(status),
+            _ => {
+                case.tle = true;
+                Err(anyhow!(msg!(
+                    "test.time_limit_exceeded",
+                    format!("{:.3}", case.execution_time)
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// A runner of `solver` over seeds 0 to 2 whose input is the seed, with a visualizer
+    /// scoring the output as is.
+    fn runner(root: &Path, solver: &str) -> Runner {
+        fs::create_dir_all(root.join("in")).unwrap();
+        fs::create_dir_all(root.join("out")).unwrap();
+        for seed in 0..3 {
+            fs::write(
+                root.join(format!("in/{:04}.txt", seed)),
+                format!("{}\n", seed),
+            )
+            .unwrap();
+        }
+        fs::write(root.join("in/notes.md"), "").unwrap();
+        let vis = root.join("vis");
+        fs::write(&vis, "#!/bin/sh\necho \"Score = $(cat \"$2\")\"\n").unwrap();
+        fs::set_permissions(&vis, fs::Permissions::from_mode(0o755)).unwrap();
+        Runner {
+            command: vec!["sh".to_string(), "-c".to_string(), solver.to_string()],
+            envs: vec![],
+            scorer: Scorer::Vis(vis),
+            score_regex: Regex::new(DEFAULT_SCORE_REGEX).unwrap(),
+            inputs_dir: root.join("in"),
+            outputs_dir: root.join("out"),
+            time_limit: None,
+            time_limit_grace: Duration::ZERO,
+            container: None,
+            cpus: vec![],
+            nice: None,
+            repeats: 1,
+            aggregate: Aggregate::Mean,
+        }
+    }
+
+    #[test]
+    fn builds_the_binaries_of_the_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(build_tools(dir.path(), &[]).is_err());
+        fs::create_dir_all(dir.path().join("src/bin")).unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"tools\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("src/bin/gen.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join("src/bin/vis.rs"), "fn main() {}\n").unwrap();
+
+        let paths = build_tools(dir.path(), &["gen".to_string()]).unwrap();
+        assert_eq!(paths, vec![tool_path(dir.path(), "gen")]);
+        assert!(paths[0].exists());
+        assert!(!tool_path(dir.path(), "vis").exists());
+        assert!(build_tools(dir.path(), &["tester".to_string()]).is_err());
+    }
+
+    #[test]
+    fn scores_outputs_with_vis() {
+        let dir = tempfile::tempdir().unwrap();
+        // The solver doubles the input and fails on 2
+        let runner = runner(dir.path(), "read n; [ $n -ne 2 ] && echo $((n * 2))");
+
+        let seeds = list_seeds(&dir.path().join("in")).unwrap();
+        assert_eq!(seeds, vec![0, 1, 2]);
+        let cases = runner.run_all(&seeds, 2, &|_| ControlFlow::Continue(()));
+        let scores = cases.iter().map(|case| case.score).collect::<Vec<_>>();
+        assert_eq!(scores, vec![0, 2, 0]);
+        assert_eq!(cases[1].error_message, "");
+        assert!(cases[1].max_memory_kb.is_some_and(|kb| kb > 0));
+        assert!(cases[2].error_message.starts_with("Solver exited with"));
+    }
+
+    #[test]
+    fn scores_with_the_stderr_of_the_solver_or_a_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = Runner {
+            scorer: Scorer::Stderr,
+            ..runner(
+                dir.path(),
+                "read n; echo $n; echo \"Score = $((n + 10))\" >&2",
+            )
+        };
+        let cases = runner.run_all(&[0, 1, 2], 3, &|_| ControlFlow::Continue(()));
+        let scores = cases.iter().map(|case| case.score).collect::<Vec<_>>();
+        assert_eq!(scores, vec![10, 11, 12]);
+
+        // The command gets the input and the output, and scores their sum here
+        let runner = Runner {
+            scorer: Scorer::Command(vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                "echo \"score: $(($(cat \"$0\") + $(cat \"$1\")))\"".to_string(),
+            ]),
+            score_regex: Regex::new(r"score: (?P<score>\d+)").unwrap(),
+            ..runner
+        };
+        let cases = runner.run_all(&[1, 2], 2, &|_| ControlFlow::Continue(()));
+        let scores = cases.iter().map(|case| case.score).collect::<Vec<_>>();
+        assert_eq!(scores, vec![2, 4]);
+    }
+
+    #[test]
+    fn marks_cases_over_the_time_limit_as_tle() {
+        let dir = tempfile::tempdir().unwrap();
+        // Seed 1 finishes within the grace margin and seed 2 hangs
+        let runner = Runner {
+            time_limit: Some(Duration::from_millis(300)),
+            time_limit_grace: Duration::from_millis(700),
+            ..runner(
+                dir.path(),
+                "read n; [ $n -eq 1 ] && sleep 0.5; [ $n -eq 2 ] && sleep 10; echo 5",
+            )
+        };
+
+        let cases = runner.run_all(&[0, 1, 2], 3, &|_| ControlFlow::Continue(()));
+        assert_eq!(
+            cases
+                .iter()
+                .map(|case| (case.score, case.tle))
+                .collect::<Vec<_>>(),
+            vec![(5, false), (0, true), (0, true)]
+        );
+        assert!(cases[1].execution_time < 1.0);
+        assert!(cases[2].execution_time < 5.0);
+        assert!(cases[2].error_message.starts_with("Time limit exceeded"));
+    }
+}
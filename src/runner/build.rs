@@ -0,0 +1,184 @@
+//! Building the solver before a run, with a build profile such as `release` or `native` so that
+//! the scores and times are those of the binary submitted.
+
+use super::container::Container;
+use crate::error::ErrorKind;
+use crate::messages::msg;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Profiles there are without any `[test.profiles]`
+const BUILTIN_PROFILES: [&str; 2] = ["release", "native"];
+
+/// `[test.profiles.<name>]`, a way of building the solver with cargo.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct BuildProfile {
+    /// Cargo profile of `cargo build --profile`
+    #[serde(default = "default_cargo_profile")]
+    pub(crate) cargo_profile: String,
+    /// Flags added to RUSTFLAGS, e.g. `["-C", "target-cpu=native"]`
+    #[serde(default)]
+    pub(crate) rustflags: Vec<String>,
+    /// More arguments of `cargo build`, e.g. `["--features", "local"]`
+    #[serde(default)]
+    pub(crate) args: Vec<String>,
+}
+
+impl Default for BuildProfile {
+    fn default() -> Self {
+        BuildProfile {
+            cargo_profile: default_cargo_profile(),
+            rustflags: vec![],
+            args: vec![],
+        }
+    }
+}
+
+fn default_cargo_profile() -> String {
+    "release".to_string()
+}
+
+/// The profile `name` of `profiles`, or a built-in one: `release`, and `native` which builds for
+/// the CPU it runs on.
+pub(super) fn find_profile(
+    profiles: Option<&BTreeMap<String, BuildProfile>>,
+    name: &str,
+) -> Result<BuildProfile> {
+    if let Some(profile) = profiles.and_then(|profiles| profiles.get(name)) {
+        return Ok(profile.clone());
+    }
+    match name {
+        "release" => Ok(BuildProfile::default()),
+        "native" => Ok(BuildProfile {
+            rustflags: vec!["-C".to_string(), "target-cpu=native".to_string()],
+            ..BuildProfile::default()
+        }),
+        _ => Err(ErrorKind::Config.error(msg!(
+            "test.unknown_profile",
+            name,
+            profile_names(profiles).join(", ")
+        ))),
+    }
+}
+
+/// Names of the built-in profiles and those of `profiles`.
+pub(crate) fn profile_names(profiles: Option<&BTreeMap<String, BuildProfile>>) -> Vec<String> {
+    let mut names = BUILTIN_PROFILES.map(String::from).to_vec();
+    for name in profiles.into_iter().flat_map(|profiles| profiles.keys()) {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    names
+}
+
+/// Runs the build `command` in `dir`, the current directory by default, with the RUSTFLAGS of
+/// `profile` if any, in `container` if given. Without a command, the profile builds with
+/// `cargo build --profile`.
+pub(super) fn build(
+    command: Option<&[String]>,
+    profile: Option<&BuildProfile>,
+    dir: Option<&Path>,
+    container: Option<&Container>,
+) -> Result<()> {
+    let default_command;
+    let command = match (command, profile) {
+        (Some(command), _) => command,
+        (None, Some(profile)) => {
+            default_command = [
+                vec![
+                    "cargo".to_string(),
+                    "build".to_string(),
+                    "--profile".to_string(),
+                    profile.cargo_profile.clone(),
+                ],
+                profile.args.clone(),
+            ]
+            .concat();
+            &default_command
+        }
+        (None, None) => return Ok(()),
+    };
+    if command.is_empty() {
+        return Err(ErrorKind::Config.error("The build command is empty"));
+    }
+    let envs = profile
+        .and_then(|profile| rustflags(&profile.rustflags))
+        .map(|rustflags| ("RUSTFLAGS".to_string(), rustflags))
+        .into_iter()
+        .collect::<Vec<_>>();
+    let program = &command[0];
+    let mut build = match container {
+        Some(container) => {
+            let words = container.exec(command, &envs, dir);
+            let mut build = Command::new(&words[0]);
+            build.args(&words[1..]);
+            build
+        }
+        None => {
+            let mut build = Command::new(program);
+            build.args(&command[1..]).envs(envs);
+            if let Some(dir) = dir {
+                build.current_dir(dir);
+            }
+            build
+        }
+    };
+    build.stdout(Stdio::null());
+    let status = build
+        .status()
+        .context(format!("Failed to run {}", program))?;
+    if !status.success() {
+        return Err(anyhow!(msg!("test.build_failed", program, status)));
+    }
+    Ok(())
+}
+
+/// RUSTFLAGS of the environment with `flags` added, or `None` if there are none to add.
+fn rustflags(flags: &[String]) -> Option<String> {
+    if flags.is_empty() {
+        return None;
+    }
+    let current = std::env::var("RUSTFLAGS").unwrap_or_default();
+    let all = current
+        .split_whitespace()
+        .map(str::to_string)
+        .chain(flags.iter().cloned())
+        .collect::<Vec<_>>();
+    Some(all.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_configured_and_builtin_profiles() {
+        let profiles = BTreeMap::from([(
+            "debug".to_string(),
+            BuildProfile {
+                cargo_profile: "dev".to_string(),
+                ..BuildProfile::default()
+            },
+        )]);
+
+        assert_eq!(
+            find_profile(Some(&profiles), "debug")
+                .unwrap()
+                .cargo_profile,
+            "dev"
+        );
+        assert_eq!(
+            find_profile(Some(&profiles), "native").unwrap().rustflags,
+            vec!["-C", "target-cpu=native"]
+        );
+        assert!(find_profile(None, "debug").is_err());
+        assert_eq!(
+            profile_names(Some(&profiles)),
+            vec!["release", "native", "debug"]
+        );
+    }
+}
@@ -0,0 +1,112 @@
+//! Solver binaries built at a clean commit, kept in `.ahc/cache/<commit>/<profile>/` so that
+//! running the commit again, such as a baseline, does not build it again.
+
+use anyhow::{Context, Result};
+use git2::{Repository, StatusOptions};
+use std::path::{Path, PathBuf};
+
+const CACHE_DIR: &str = ".ahc/cache";
+/// Directory of the binaries built without a build profile
+const NO_PROFILE: &str = "default";
+
+/// The commit checked out if no tracked file is changed, so that it alone decides the build.
+pub(super) fn clean_commit() -> Option<String> {
+    let repo = Repository::open_from_env().ok()?;
+    let head = repo.head().ok()?.peel_to_commit().ok()?;
+    let mut options = StatusOptions::new();
+    // Results and outputs of runs are untracked until committed, and do not change the build
+    options.include_untracked(false).include_ignored(false);
+    let statuses = repo.statuses(Some(&mut options)).ok()?;
+    statuses.is_empty().then(|| head.id().to_string())
+}
+
+/// The binaries of a commit built with a profile.
+pub(super) struct BinaryCache {
+    dir: PathBuf,
+}
+
+impl BinaryCache {
+    pub(super) fn new(commit: &str, profile: Option<&str>) -> Self {
+        Self::at(Path::new(CACHE_DIR), commit, profile)
+    }
+
+    fn at(root: &Path, commit: &str, profile: Option<&str>) -> Self {
+        BinaryCache {
+            dir: root.join(commit).join(profile.unwrap_or(NO_PROFILE)),
+        }
+    }
+
+    /// The binaries built in a container of `image`, kept apart from those built here.
+    pub(super) fn in_container(self, image: &str) -> Self {
+        let name = image.replace(|c: char| !c.is_ascii_alphanumeric() && c != '.', "_");
+        BinaryCache {
+            dir: self.dir.join("container").join(name),
+        }
+    }
+
+    /// The cached copy of `binary`, if any.
+    pub(super) fn get(&self, binary: &Path) -> Option<PathBuf> {
+        let cached = self.dir.join(binary.file_name()?);
+        cached.is_file().then_some(cached)
+    }
+
+    /// Copies `binary` into the cache.
+    pub(super) fn put(&self, binary: &Path) -> Result<PathBuf> {
+        let name = binary
+            .file_name()
+            .context(format!("Not a file: {}", binary.display()))?;
+        std::fs::create_dir_all(&self.dir).context(format!(
+            "Failed to create directory: {}",
+            self.dir.display()
+        ))?;
+        let cached = self.dir.join(name);
+        std::fs::copy(binary, &cached)
+            .context(format!("Failed to copy {} to the cache", binary.display()))?;
+        Ok(cached)
+    }
+}
+
+/// The binary run by `command` which a build may produce, e.g. `target/release/ahc030`, rather
+/// than a program found in PATH.
+pub(super) fn built_binary(command: &[String]) -> Option<&Path> {
+    let program = Path::new(command.first()?);
+    program
+        .parent()
+        .is_some_and(|parent| !parent.as_os_str().is_empty())
+        .then_some(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_binaries_by_commit_and_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("target/release/ahc001");
+        std::fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        std::fs::write(&binary, "binary").unwrap();
+        let cache = BinaryCache::at(&dir.path().join("cache"), "abc", Some("native"));
+        assert_eq!(cache.get(&binary), None);
+
+        let cached = cache.put(&binary).unwrap();
+        assert_eq!(cached, dir.path().join("cache/abc/native/ahc001"));
+        assert_eq!(cache.get(&binary), Some(cached));
+        let other = BinaryCache::at(&dir.path().join("cache"), "abc", None);
+        assert_eq!(other.get(&binary), None);
+        let other = BinaryCache::at(&dir.path().join("cache"), "abc", Some("native"))
+            .in_container("rust:1.70");
+        assert_eq!(
+            other.dir,
+            dir.path().join("cache/abc/native/container/rust_1.70")
+        );
+        assert_eq!(other.get(&binary), None);
+
+        let command = |program: &str| vec![program.to_string(), "--verbose".to_string()];
+        assert_eq!(
+            built_binary(&command("target/release/ahc001")),
+            Some(Path::new("target/release/ahc001"))
+        );
+        assert_eq!(built_binary(&command("python3")), None);
+    }
+}
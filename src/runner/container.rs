@@ -0,0 +1,195 @@
+//! A container of a pinned image, e.g. one with the rustc and glibc of the judge, in which the
+//! solver is built and run so that scores and times measured on different machines compare.
+
+use crate::messages::msg;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::{debug, info, warn};
+
+/// `[test.container]`, the container the solver is built and run in.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct ContainerConfig {
+    /// Image of the container, e.g. `rust:1.70.0-bookworm`. Pin it by a tag or a digest
+    pub(crate) image: String,
+    /// Command managing containers, e.g. `podman`
+    #[serde(default = "default_engine")]
+    pub(crate) engine: String,
+    /// More arguments of `docker run`, e.g. `["--cpus", "1", "--user", "1000:1000"]`
+    #[serde(default)]
+    pub(crate) args: Vec<String>,
+}
+
+fn default_engine() -> String {
+    "docker".to_string()
+}
+
+impl ContainerConfig {
+    /// The container of `--container <image>`, with the other settings of `config` if any.
+    pub(super) fn with_image(config: Option<ContainerConfig>, image: &str) -> Self {
+        ContainerConfig {
+            image: image.to_string(),
+            ..config.unwrap_or_else(|| ContainerConfig {
+                image: String::new(),
+                engine: default_engine(),
+                args: vec![],
+            })
+        }
+    }
+}
+
+/// A container started for a run, removed again when dropped. The project directory is mounted
+/// at the same path as here, so that paths such as those of the binary cache hold in both.
+pub(super) struct Container {
+    engine: String,
+    id: String,
+    image: String,
+    /// Directory commands run in unless told otherwise
+    dir: PathBuf,
+}
+
+impl Container {
+    pub(super) fn start(config: &ContainerConfig) -> Result<Self> {
+        let dir = std::env::current_dir()?;
+        info!("{}", msg!("test.container", config.image));
+        let mut command = Command::new(&config.engine);
+        command.args(run_args(config, &dir));
+        debug!("Running {:?}", command);
+        let started = command
+            .stderr(Stdio::inherit())
+            .output()
+            .context(format!("Failed to run {}", config.engine))?;
+        if !started.status.success() {
+            return Err(anyhow!(msg!(
+                "test.container_failed",
+                config.image,
+                config.engine,
+                started.status
+            )));
+        }
+        Ok(Container {
+            engine: config.engine.clone(),
+            id: String::from_utf8_lossy(&started.stdout).trim().to_string(),
+            image: config.image.clone(),
+            dir,
+        })
+    }
+
+    pub(super) fn image(&self) -> &str {
+        &self.image
+    }
+
+    /// The words running `command` in the container with `envs`, in `dir` or the project
+    /// directory. The time of a case run so includes that of starting the process in the
+    /// container, some tens of milliseconds.
+    pub(super) fn exec(
+        &self,
+        command: &[String],
+        envs: &[(String, String)],
+        dir: Option<&Path>,
+    ) -> Vec<String> {
+        let dir = dir.map_or(self.dir.clone(), |dir| self.dir.join(dir));
+        let mut words = vec![
+            self.engine.clone(),
+            "exec".to_string(),
+            "-i".to_string(),
+            "-w".to_string(),
+            dir.display().to_string(),
+        ];
+        for (name, value) in envs {
+            words.extend(["-e".to_string(), format!("{}={}", name, value)]);
+        }
+        words.push(self.id.clone());
+        words.extend(command.iter().cloned());
+        words
+    }
+}
+
+impl Drop for Container {
+    fn drop(&mut self) {
+        // Also kills the processes of cases left running past the time limit
+        let removed = Command::new(&self.engine)
+            .args(["rm", "-f", &self.id])
+            .stdout(Stdio::null())
+            .status();
+        if !removed.is_ok_and(|status| status.success()) {
+            warn!("Failed to remove container {}", self.id);
+        }
+    }
+}
+
+/// `run` of the engine starting a container idle until removed.
+fn run_args(config: &ContainerConfig, dir: &Path) -> Vec<String> {
+    let volume = format!("{}:{}", dir.display(), dir.display());
+    [
+        vec!["run".to_string(), "-d".to_string(), "--rm".to_string()],
+        vec!["-v".to_string(), volume],
+        vec!["-w".to_string(), dir.display().to_string()],
+        config.args.clone(),
+        vec![
+            config.image.clone(),
+            "sleep".to_string(),
+            "infinity".to_string(),
+        ],
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_commands_in_the_project_directory_of_the_container() {
+        let config: ContainerConfig =
+            toml::from_str("image = \"rust:1.70\"\nargs = [\"--cpus\", \"1\"]").unwrap();
+        assert_eq!(
+            run_args(&config, Path::new("/home/me/ahc030")),
+            vec![
+                "run",
+                "-d",
+                "--rm",
+                "-v",
+                "/home/me/ahc030:/home/me/ahc030",
+                "-w",
+                "/home/me/ahc030",
+                "--cpus",
+                "1",
+                "rust:1.70",
+                "sleep",
+                "infinity"
+            ]
+        );
+        let config = ContainerConfig::with_image(Some(config), "rust:1.79");
+        assert_eq!((config.image.as_str(), config.args.len()), ("rust:1.79", 2));
+
+        let container = Container {
+            engine: "podman".to_string(),
+            id: "c0ffee".to_string(),
+            image: "rust:1.70".to_string(),
+            dir: PathBuf::from("/home/me/ahc030"),
+        };
+        let words = container.exec(
+            &["target/release/ahc030".to_string()],
+            &[("K".to_string(), "3".to_string())],
+            Some(Path::new(".ahc/worktree/abc")),
+        );
+        assert_eq!(
+            words,
+            vec![
+                "podman",
+                "exec",
+                "-i",
+                "-w",
+                "/home/me/ahc030/.ahc/worktree/abc",
+                "-e",
+                "K=3",
+                "c0ffee",
+                "target/release/ahc030"
+            ]
+        );
+        // The container is not removed for real
+        std::mem::forget(container);
+    }
+}
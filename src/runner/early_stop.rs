@@ -0,0 +1,181 @@
+//! Stopping `ahc test` early once the finished seeds are significantly worse than a baseline run,
+//! by a paired t-test on the scores relative to the baseline's on the same seeds.
+
+use super::CaseResult;
+use crate::error::ErrorKind;
+use crate::pahcer;
+use crate::tune::stats::{mean_and_sd, paired_t_test_less};
+use crate::tune::Objective;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// `[test.early_stop]`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct EarlyStopConfig {
+    /// pahcer result to compare with, the latest in `[paths] results_dir` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) baseline: Option<PathBuf>,
+    /// How sure the run must be worse to stop it
+    #[serde(default = "default_confidence")]
+    pub(crate) confidence: f64,
+    /// Relative loss tolerated before counting as worse, e.g. 0.01 for 1%
+    #[serde(default)]
+    pub(crate) threshold: f64,
+    /// Seeds to finish before testing at all
+    #[serde(default = "default_min_seeds")]
+    pub(crate) min_seeds: usize,
+}
+
+impl Default for EarlyStopConfig {
+    fn default() -> Self {
+        EarlyStopConfig {
+            baseline: None,
+            confidence: default_confidence(),
+            threshold: 0.0,
+            min_seeds: default_min_seeds(),
+        }
+    }
+}
+
+fn default_confidence() -> f64 {
+    0.95
+}
+
+fn default_min_seeds() -> usize {
+    10
+}
+
+impl EarlyStopConfig {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if !(0.0 < self.confidence && self.confidence < 1.0) {
+            return Err(ErrorKind::Config
+                .error("[test.early_stop] confidence must be between 0 and 1, e.g. 0.95"));
+        }
+        if self.threshold < 0.0 {
+            return Err(ErrorKind::Config.error("[test.early_stop] threshold must not be negative"));
+        }
+        Ok(())
+    }
+}
+
+/// Why a run was stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Verdict {
+    pub(super) cases: usize,
+    /// Mean relative change from the baseline, negative when worse
+    pub(super) mean_change: f64,
+    pub(super) p_value: f64,
+}
+
+pub(super) struct EarlyStop {
+    config: EarlyStopConfig,
+    objective: Objective,
+    baseline: BTreeMap<u64, u64>,
+    /// Relative changes from the baseline, positive when better
+    changes: Vec<f64>,
+}
+
+impl EarlyStop {
+    pub(super) fn new(
+        config: EarlyStopConfig,
+        baseline: &Path,
+        objective: Objective,
+    ) -> Result<Self> {
+        let baseline = pahcer::read_result(baseline)?
+            .cases
+            .into_iter()
+            .map(|case| (case.seed, case.score))
+            .collect();
+        Ok(Self::with_baseline(config, baseline, objective))
+    }
+
+    fn with_baseline(
+        config: EarlyStopConfig,
+        baseline: BTreeMap<u64, u64>,
+        objective: Objective,
+    ) -> Self {
+        EarlyStop {
+            config,
+            objective,
+            baseline,
+            changes: vec![],
+        }
+    }
+
+    /// Adds a finished case, returning the verdict once the run is significantly worse.
+    pub(super) fn add(&mut self, case: &CaseResult) -> Option<Verdict> {
+        let baseline = *self.baseline.get(&case.seed)?;
+        let ratio = case.score as f64 / baseline.max(1) as f64;
+        self.changes.push(match self.objective {
+            Objective::Max => ratio - 1.0,
+            Objective::Min => 1.0 - ratio,
+        });
+        if self.changes.len() < self.config.min_seeds {
+            return None;
+        }
+        // Worse than tolerated when the changes plus the threshold are below zero
+        let shifted = self
+            .changes
+            .iter()
+            .map(|change| change + self.config.threshold)
+            .collect::<Vec<_>>();
+        let p_value = paired_t_test_less(&shifted);
+        (p_value < 1.0 - self.config.confidence).then(|| Verdict {
+            cases: self.changes.len(),
+            mean_change: mean_and_sd(&self.changes).0,
+            p_value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(seed: u64, score: u64) -> CaseResult {
+        CaseResult {
+            seed,
+            score,
+            execution_time: 0.0,
+            error_message: String::new(),
+            tle: false,
+            max_memory_kb: None,
+            repeats: None,
+        }
+    }
+
+    #[test]
+    fn stops_once_significantly_worse() {
+        let baseline = (0..20).map(|seed| (seed, 1000 + seed * 10)).collect();
+        let config = EarlyStopConfig {
+            min_seeds: 5,
+            ..EarlyStopConfig::default()
+        };
+        let mut early_stop = EarlyStop::with_baseline(config.clone(), baseline, Objective::Max);
+        // About 10% worse with some noise
+        let mut verdict = None;
+        for seed in 0..20 {
+            let score = (1000 + seed * 10) * (88 + seed % 4) / 100;
+            verdict = early_stop.add(&case(seed, score));
+            if verdict.is_some() {
+                break;
+            }
+        }
+        let verdict = verdict.expect("the run is stopped");
+        assert_eq!(verdict.cases, 5);
+        assert!((verdict.mean_change + 0.105).abs() < 0.01);
+
+        // Not with a threshold beyond the loss, nor when minimizing
+        let baseline = (0..20).map(|seed| (seed, 1000)).collect::<BTreeMap<_, _>>();
+        let tolerant = EarlyStopConfig {
+            threshold: 0.2,
+            ..config.clone()
+        };
+        let mut early_stop = EarlyStop::with_baseline(tolerant, baseline.clone(), Objective::Max);
+        assert!((0..20).all(|seed| early_stop.add(&case(seed, 900 - seed)).is_none()));
+        let mut early_stop = EarlyStop::with_baseline(config, baseline, Objective::Min);
+        assert!((0..20).all(|seed| early_stop.add(&case(seed, 900 - seed)).is_none()));
+    }
+}
@@ -0,0 +1,189 @@
+//! Running a process of a case under a time limit.
+
+#[cfg(unix)]
+mod groups;
+
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+/// How often a running process is checked on.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+pub(super) struct Finished {
+    /// `None` if the process was killed for running too long
+    pub(super) status: Option<ExitStatus>,
+    pub(super) stderr: String,
+    pub(super) elapsed: Duration,
+    /// Peak resident memory of the process and the processes it waited for, where the platform
+    /// reports it
+    pub(super) max_rss_kb: Option<u64>,
+}
+
+/// Has `command` run on `cpu` only and with the niceness `nice`, where given. The processes it
+/// starts, such as the solver of a tester, inherit both. Cores are only chosen on Linux.
+pub(super) fn place(command: &mut Command, cpu: Option<usize>, nice: Option<i32>) {
+    if cpu.is_none() && nice.is_none() {
+        return;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+
+        // SAFETY: the hook only makes system calls, which are safe between fork and exec
+        unsafe {
+            command.pre_exec(move || {
+                #[cfg(target_os = "linux")]
+                if let Some(cpu) = cpu {
+                    let mut set: libc::cpu_set_t = std::mem::zeroed();
+                    libc::CPU_SET(cpu, &mut set);
+                    if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0
+                    {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                if let Some(nice) = nice {
+                    if libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+}
+
+/// Runs `command` with its stderr captured, killing it and every process it started once it
+/// runs longer than `kill_after`.
+pub(super) fn run(mut command: Command, kill_after: Option<Duration>) -> std::io::Result<Finished> {
+    command.stderr(Stdio::piped());
+    // A process group of its own, so that a tester is killed together with the solver it runs
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let start = Instant::now();
+    let mut child = command.spawn()?;
+    // Out of the terminal's group, it misses a Ctrl-C, so it is killed when ahc is interrupted
+    #[cfg(unix)]
+    let _group = groups::Group::register(child.id());
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let reader = std::thread::spawn(move || {
+        let mut text = vec![];
+        let _ = stderr.read_to_end(&mut text);
+        text
+    });
+
+    let (status, max_rss_kb) = loop {
+        if let Some((status, max_rss_kb)) = try_wait(&mut child, false)? {
+            break (Some(status), max_rss_kb);
+        }
+        if kill_after.is_some_and(|limit| start.elapsed() > limit) {
+            kill(&mut child);
+            let (_, max_rss_kb) = try_wait(&mut child, true)?.expect("waited for the child");
+            break (None, max_rss_kb);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+    let elapsed = start.elapsed();
+    let stderr = reader.join().unwrap_or_default();
+    Ok(Finished {
+        status,
+        stderr: String::from_utf8_lossy(&stderr).into_owned(),
+        elapsed,
+        max_rss_kb,
+    })
+}
+
+/// Reaps the child if it exited, or waits for it if `block`, with its peak memory.
+#[cfg(unix)]
+fn try_wait(child: &mut Child, block: bool) -> std::io::Result<Option<(ExitStatus, Option<u64>)>> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let mut status = 0;
+    // SAFETY: rusage is plain data which wait4 fills in
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    let options = if block { 0 } else { libc::WNOHANG };
+    // wait4 rather than Child::try_wait, which does not report the resource usage
+    let pid = unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, options, &mut usage) };
+    match pid {
+        -1 => Err(std::io::Error::last_os_error()),
+        0 => Ok(None),
+        _ => {
+            // macOS reports bytes, other systems kilobytes
+            let max_rss = usage.ru_maxrss as u64;
+            let max_rss_kb = if cfg!(target_os = "macos") {
+                max_rss / 1024
+            } else {
+                max_rss
+            };
+            Ok(Some((ExitStatus::from_raw(status), Some(max_rss_kb))))
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn try_wait(child: &mut Child, block: bool) -> std::io::Result<Option<(ExitStatus, Option<u64>)>> {
+    let status = if block {
+        Some(child.wait()?)
+    } else {
+        child.try_wait()?
+    };
+    Ok(status.map(|status| (status, None)))
+}
+
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        // SAFETY: kill only sends a signal, to the process group led by the child
+        unsafe {
+            libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = child.kill();
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kills_processes_running_too_long() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo started >&2; sleep 10"]);
+        let finished = run(command, Some(Duration::from_millis(200))).unwrap();
+        assert!(finished.status.is_none());
+        assert_eq!(finished.stderr, "started\n");
+        assert!(finished.elapsed < Duration::from_secs(5));
+
+        let mut command = Command::new("sh");
+        command.args(["-c", "exit 3"]);
+        let finished = run(command, Some(Duration::from_secs(10))).unwrap();
+        assert_eq!(finished.status.and_then(|status| status.code()), Some(3));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn pins_processes_to_a_core_with_a_niceness() {
+        let mut command = Command::new("sh");
+        command.args([
+            "-c",
+            "grep Cpus_allowed_list /proc/$$/status >&2; cut -d ' ' -f 19 /proc/$$/stat >&2",
+        ]);
+        place(&mut command, Some(0), Some(5));
+        let finished = run(command, None).unwrap();
+        assert_eq!(finished.stderr, "Cpus_allowed_list:\t0\n5\n");
+    }
+
+    #[test]
+    fn measures_peak_memory() {
+        // The shell holds a 64 MB string of zeros for a moment
+        let mut command = Command::new("sh");
+        command.args([
+            "-c",
+            "x=$(head -c 67108864 /dev/zero | tr '\\0' a); echo ${#x} >&2",
+        ]);
+        let finished = run(command, None).unwrap();
+        assert_eq!(finished.stderr, "67108864\n");
+        assert!(finished.max_rss_kb.unwrap() > 64 * 1024);
+    }
+}
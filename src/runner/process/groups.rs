@@ -0,0 +1,81 @@
+//! The process groups of the cases still running, killed when ahc is interrupted. Each case runs
+//! in a group of its own, which a Ctrl-C in the terminal does not reach.
+
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Once;
+
+/// Most groups tracked at once, far more than cases run in parallel.
+const SLOTS: usize = 1024;
+
+/// Ids of the live groups, 0 for a free slot. Atomics, since the signal handler may not lock.
+static GROUPS: [AtomicI32; SLOTS] = [const { AtomicI32::new(0) }; SLOTS];
+
+/// A live process group, forgotten again when dropped.
+pub(super) struct Group {
+    slot: Option<usize>,
+}
+
+impl Group {
+    /// Tracks the group led by the process `pid`, installing the signal handlers first.
+    pub(super) fn register(pid: u32) -> Self {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+                // SAFETY: the handler only makes async-signal-safe calls
+                unsafe {
+                    libc::signal(signal, on_signal as *const () as libc::sighandler_t);
+                }
+            }
+        });
+        let slot = GROUPS.iter().position(|slot| {
+            slot.compare_exchange(0, pid as i32, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        });
+        Group { slot }
+    }
+}
+
+impl Drop for Group {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot {
+            GROUPS[slot].store(0, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Kills every live group.
+fn kill_all() {
+    for slot in &GROUPS {
+        let pgid = slot.load(Ordering::SeqCst);
+        if pgid > 0 {
+            // SAFETY: kill only sends a signal
+            unsafe {
+                libc::kill(-pgid, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+/// Kills the groups, then dies of `signal` as ahc would have without the handler.
+extern "C" fn on_signal(signal: libc::c_int) {
+    kill_all();
+    // SAFETY: signal and raise are async-signal-safe
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_groups_while_they_live() {
+        let live = |pid| GROUPS.iter().any(|slot| slot.load(Ordering::SeqCst) == pid);
+        let group = Group::register(999_999);
+        assert!(live(999_999));
+        drop(group);
+        assert!(!live(999_999));
+    }
+}
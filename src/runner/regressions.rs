@@ -0,0 +1,144 @@
+//! The seeds of a run which dropped the most from a baseline run, listed after `ahc test` with
+//! their inputs so that the worst cases are at hand.
+
+use super::CaseResult;
+use crate::pahcer;
+use crate::tune::Objective;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A seed scoring worse than in the baseline.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(crate) struct Regression {
+    seed: u64,
+    score: u64,
+    baseline_score: u64,
+    /// Change relative to the baseline score, negative whatever the objective, or `None` if the
+    /// baseline scored 0
+    change: Option<f64>,
+    input: PathBuf,
+}
+
+/// Up to `count` seeds of `cases` which scored worse than in `baseline`, the largest drops first.
+pub(super) fn find_regressions(
+    cases: &[CaseResult],
+    baseline: &pahcer::ExecResult,
+    objective: Objective,
+    inputs_dir: &Path,
+    count: usize,
+) -> Vec<Regression> {
+    let baseline_scores = baseline
+        .cases
+        .iter()
+        .map(|case| (case.seed, case.score))
+        .collect::<BTreeMap<_, _>>();
+    let mut regressions = cases
+        .iter()
+        .filter_map(|case| {
+            let &baseline_score = baseline_scores.get(&case.seed)?;
+            if !objective.is_better(baseline_score as f64, case.score as f64) {
+                return None;
+            }
+            let change = (baseline_score > 0).then(|| {
+                let change = (case.score as f64 - baseline_score as f64) / baseline_score as f64;
+                match objective {
+                    Objective::Max => change,
+                    Objective::Min => -change,
+                }
+            });
+            Some(Regression {
+                seed: case.seed,
+                score: case.score,
+                baseline_score,
+                change,
+                input: inputs_dir.join(format!("{:04}.txt", case.seed)),
+            })
+        })
+        .collect::<Vec<_>>();
+    // Drops from a score of 0 have no relative change, and come first as the worst
+    regressions.sort_by(|a, b| match (a.change, b.change) {
+        (None, None) => a.seed.cmp(&b.seed),
+        (None, Some(_)) => Ordering::Less,
+        (Some(_), None) => Ordering::Greater,
+        (Some(a), Some(b)) => a.total_cmp(&b),
+    });
+    regressions.truncate(count);
+    regressions
+}
+
+/// A row per regression: the seed, the scores, the change and the input.
+pub(super) fn format_regressions(regressions: &[Regression]) -> Vec<String> {
+    regressions
+        .iter()
+        .map(|regression| {
+            let change = regression
+                .change
+                .map_or("-".to_string(), |change| format!("{:+.2}%", change * 100.0));
+            format!(
+                "{:>6} {:>12} -> {:>12} {:>9}  {}",
+                format!("{:04}", regression.seed),
+                regression.baseline_score,
+                regression.score,
+                change,
+                regression.input.display()
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(seed: u64, score: u64) -> CaseResult {
+        CaseResult {
+            seed,
+            score,
+            execution_time: 0.0,
+            error_message: String::new(),
+            tle: false,
+            max_memory_kb: None,
+            repeats: None,
+        }
+    }
+
+    #[test]
+    fn lists_the_largest_drops_first() {
+        let baseline: pahcer::ExecResult = serde_json::from_value(serde_json::json!({
+            "case_count": 5,
+            "total_score": 450,
+            "cases": [
+                {"seed": 0, "score": 100},
+                {"seed": 1, "score": 100},
+                {"seed": 2, "score": 100},
+                {"seed": 3, "score": 0},
+                {"seed": 4, "score": 150},
+            ],
+        }))
+        .unwrap();
+        let cases = [
+            case(0, 90),
+            case(1, 50),
+            case(2, 120),
+            case(3, 10),
+            case(5, 0),
+        ];
+        let inputs_dir = Path::new("tools/in");
+
+        let regressions = find_regressions(&cases, &baseline, Objective::Max, inputs_dir, 5);
+        let seeds = regressions.iter().map(|r| r.seed).collect::<Vec<_>>();
+        assert_eq!(seeds, vec![1, 0]);
+        assert_eq!(regressions[0].change, Some(-0.5));
+        assert_eq!(regressions[0].input, Path::new("tools/in/0001.txt"));
+        assert_eq!(
+            format_regressions(&regressions[..1]),
+            vec!["  0001          100 ->           50   -50.00%  tools/in/0001.txt"]
+        );
+
+        let regressions = find_regressions(&cases, &baseline, Objective::Min, inputs_dir, 1);
+        assert_eq!(regressions.len(), 1);
+        assert_eq!((regressions[0].seed, regressions[0].change), (3, None));
+    }
+}
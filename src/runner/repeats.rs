@@ -0,0 +1,136 @@
+//! Running each seed several times for solvers whose score varies between runs, such as those
+//! annealing until a wall-clock deadline, and taking one score of the runs so that a single lucky
+//! or unlucky run decides less.
+
+use super::CaseResult;
+use serde::{Deserialize, Serialize};
+
+/// How the score of a seed is taken from those of its runs.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Aggregate {
+    #[default]
+    Mean,
+    Median,
+}
+
+/// The scores of the runs of a seed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct RepeatStats {
+    pub(super) scores: Vec<u64>,
+    pub(super) mean: f64,
+    pub(super) median: f64,
+    /// Sample standard deviation, 0 for a single run
+    pub(super) std: f64,
+}
+
+impl RepeatStats {
+    fn new(scores: Vec<u64>) -> Self {
+        let n = scores.len() as f64;
+        let mean = scores.iter().sum::<u64>() as f64 / n;
+        let mut sorted = scores.clone();
+        sorted.sort();
+        let middle = sorted.len() / 2;
+        let median = if sorted.len().is_multiple_of(2) {
+            (sorted[middle - 1] + sorted[middle]) as f64 / 2.0
+        } else {
+            sorted[middle] as f64
+        };
+        let std = if scores.len() > 1 {
+            let squares = scores
+                .iter()
+                .map(|&score| (score as f64 - mean).powi(2))
+                .sum::<f64>();
+            (squares / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        RepeatStats {
+            scores,
+            mean,
+            median,
+            std,
+        }
+    }
+}
+
+/// One case of the runs of a seed: the score as `aggregate` says, rounded, the time of the
+/// slowest run and the peak memory of all. A run which failed fails the case, which scores 0.
+pub(super) fn combine(runs: Vec<CaseResult>, aggregate: Aggregate) -> CaseResult {
+    let stats = RepeatStats::new(runs.iter().map(|run| run.score).collect());
+    let score = match aggregate {
+        Aggregate::Mean => stats.mean,
+        Aggregate::Median => stats.median,
+    };
+    let failed = runs.iter().find(|run| !run.error_message.is_empty());
+    CaseResult {
+        seed: runs[0].seed,
+        score: if failed.is_some() {
+            0
+        } else {
+            score.round() as u64
+        },
+        execution_time: runs
+            .iter()
+            .map(|run| run.execution_time)
+            .fold(0.0, f64::max),
+        error_message: failed.map_or(String::new(), |run| run.error_message.clone()),
+        tle: runs.iter().any(|run| run.tle),
+        max_memory_kb: runs.iter().filter_map(|run| run.max_memory_kb).max(),
+        repeats: Some(stats),
+    }
+}
+
+/// The standard deviation of the scores of a seed relative to their mean, averaged over the
+/// seeds which scored, or `None` without repeats.
+pub(super) fn relative_noise(cases: &[CaseResult]) -> Option<f64> {
+    let noises = cases
+        .iter()
+        .filter_map(|case| case.repeats.as_ref())
+        .filter(|stats| stats.mean > 0.0)
+        .map(|stats| stats.std / stats.mean)
+        .collect::<Vec<_>>();
+    (!noises.is_empty()).then(|| noises.iter().sum::<f64>() / noises.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(score: u64, execution_time: f64) -> CaseResult {
+        CaseResult {
+            seed: 7,
+            score,
+            execution_time,
+            error_message: String::new(),
+            tle: false,
+            max_memory_kb: None,
+            repeats: None,
+        }
+    }
+
+    #[test]
+    fn combines_the_runs_of_a_seed() {
+        let runs = vec![run(100, 1.0), run(130, 1.5), run(160, 1.2), run(90, 1.1)];
+        let case = combine(runs.clone(), Aggregate::Mean);
+        assert_eq!((case.seed, case.score, case.execution_time), (7, 120, 1.5));
+        let stats = case.repeats.clone().unwrap();
+        assert_eq!(stats.scores, vec![100, 130, 160, 90]);
+        assert_eq!(stats.median, 115.0);
+        assert!((stats.std - 31.62).abs() < 0.01);
+        assert_eq!(combine(runs, Aggregate::Median).score, 115);
+
+        let noise = relative_noise(&[case]).unwrap();
+        assert!((noise - 31.62 / 120.0).abs() < 1e-3);
+        assert_eq!(relative_noise(&[run(5, 1.0)]), None);
+
+        let mut failed = run(0, 2.5);
+        failed.error_message = "Time limit exceeded (2.500s)".to_string();
+        failed.tle = true;
+        let case = combine(vec![run(100, 1.0), failed], Aggregate::Mean);
+        assert_eq!(case.score, 0);
+        assert_eq!(case.repeats.unwrap().scores, vec![100, 0]);
+        assert!(case.tle);
+        assert_eq!(case.error_message, "Time limit exceeded (2.500s)");
+    }
+}
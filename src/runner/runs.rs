@@ -0,0 +1,268 @@
+//! The runs of `ahc test`, each keeping its outputs in `{outputs_dir}/<run id>/` with a
+//! manifest, so that later runs do not overwrite them. The id is the start time of the run as in
+//! its result, `result_<run id>.json`. Until the manifest is written, the cases finished so far
+//! are kept in `progress.jsonl`, from which `ahc test --resume` goes on with an interrupted run.
+
+use super::CaseResult;
+use crate::messages::msg;
+use crate::notify::format_timestamp;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const MANIFEST: &str = "manifest.json";
+const PROGRESS: &str = "progress.jsonl";
+/// Refers to the newest run wherever a run id is expected
+pub(crate) const LATEST: &str = "latest";
+
+/// `manifest.json` of a run.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) struct Manifest {
+    pub(super) id: String,
+    pub(super) start_time: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) solver: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) commit: Option<String>,
+    pub(super) command: Vec<String>,
+    pub(super) result_file: PathBuf,
+    pub(super) cases: Vec<ManifestCase>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) struct ManifestCase {
+    pub(super) seed: u64,
+    pub(super) score: u64,
+    /// Output of the seed, relative to the directory of the run
+    pub(super) output: PathBuf,
+}
+
+/// The id of a run starting at `start`: its time like `20240101_120000`, or the following second
+/// if a run already took that one.
+pub(crate) fn new_run_id(start: SystemTime, results_dir: &Path, outputs_dir: &Path) -> String {
+    (0..)
+        .map(|secs| {
+            format_timestamp(start + Duration::from_secs(secs))
+                .replace(['-', ':'], "")
+                .replace(' ', "_")
+        })
+        .find(|id| {
+            !results_dir.join(result_file_name(id)).exists() && !outputs_dir.join(id).exists()
+        })
+        .expect("a free id")
+}
+
+pub(crate) fn result_file_name(id: &str) -> String {
+    format!("result_{}.json", id)
+}
+
+pub(super) fn write_manifest(run_dir: &Path, manifest: &Manifest) -> Result<()> {
+    let path = run_dir.join(MANIFEST);
+    let json = serde_json::to_string_pretty(manifest)?;
+    std::fs::write(&path, json).context(format!("Failed to write file: {}", path.display()))
+}
+
+/// The cases of a run recorded as they finish.
+pub(super) struct Progress {
+    file: File,
+}
+
+impl Progress {
+    pub(super) fn open(run_dir: &Path) -> Result<Self> {
+        let path = run_dir.join(PROGRESS);
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context(format!("Failed to open file: {}", path.display()))?;
+        Ok(Progress { file })
+    }
+
+    pub(super) fn record(&mut self, case: &CaseResult) -> Result<()> {
+        let line = serde_json::to_string(case)?;
+        writeln!(self.file, "{}", line).context("Failed to record the progress of the run")
+    }
+}
+
+/// The cases of run `id` recorded so far, leaving out a line cut short when the run was killed.
+pub(super) fn read_progress(outputs_dir: &Path, id: &str) -> Result<Vec<CaseResult>> {
+    let path = outputs_dir.join(id).join(PROGRESS);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let text = std::fs::read_to_string(&path)
+        .context(format!("Failed to read file: {}", path.display()))?;
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Removes the progress of a run once its manifest is written.
+pub(super) fn finish_progress(run_dir: &Path) {
+    let _ = std::fs::remove_file(run_dir.join(PROGRESS));
+}
+
+/// The id of the unfinished run `id` in `outputs_dir`, or of the newest one for `latest`.
+pub(super) fn unfinished_run(outputs_dir: &Path, id: &str) -> Result<String> {
+    if id != LATEST {
+        let dir = outputs_dir.join(id);
+        if dir.join(MANIFEST).is_file() {
+            return Err(anyhow!(msg!("test.run_finished", id)));
+        }
+        if !dir.join(PROGRESS).is_file() {
+            return Err(anyhow!(msg!(
+                "test.run_not_unfinished",
+                id,
+                outputs_dir.display()
+            )));
+        }
+        return Ok(id.to_string());
+    }
+    let mut ids = vec![];
+    if outputs_dir.is_dir() {
+        for entry in std::fs::read_dir(outputs_dir).context(format!(
+            "Failed to read directory: {}",
+            outputs_dir.display()
+        ))? {
+            let path = entry?.path();
+            if path.join(PROGRESS).is_file() && !path.join(MANIFEST).is_file() {
+                if let Some(id) = path.file_name().and_then(|name| name.to_str()) {
+                    ids.push(id.to_string());
+                }
+            }
+        }
+    }
+    ids.into_iter()
+        .max()
+        .ok_or_else(|| anyhow!(msg!("test.no_unfinished_run", outputs_dir.display())))
+}
+
+/// Ids of the runs with outputs in `outputs_dir`, oldest first.
+pub(crate) fn list_runs(outputs_dir: &Path) -> Result<Vec<String>> {
+    if !outputs_dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut ids = vec![];
+    for entry in std::fs::read_dir(outputs_dir).context(format!(
+        "Failed to read directory: {}",
+        outputs_dir.display()
+    ))? {
+        let path = entry?.path();
+        if path.join(MANIFEST).is_file() {
+            if let Some(id) = path.file_name().and_then(|name| name.to_str()) {
+                ids.push(id.to_string());
+            }
+        }
+    }
+    ids.sort();
+    Ok(ids)
+}
+
+/// The output directory of run `id`, or of the newest run for `latest`.
+pub(crate) fn run_dir(outputs_dir: &Path, id: &str) -> Result<PathBuf> {
+    let runs = list_runs(outputs_dir)?;
+    let found = if id == LATEST {
+        runs.last()
+    } else {
+        runs.iter().find(|run| run.as_str() == id)
+    };
+    found
+        .map(|id| outputs_dir.join(id))
+        .ok_or_else(|| anyhow!(msg!("test.no_run", id, outputs_dir.display())))
+}
+
+/// Deletes the outputs of the oldest runs but the newest `keep`, returning their ids.
+pub(super) fn prune(outputs_dir: &Path, keep: usize) -> Result<Vec<String>> {
+    let runs = list_runs(outputs_dir)?;
+    let pruned = runs[..runs.len().saturating_sub(keep)].to_vec();
+    for id in &pruned {
+        let dir = outputs_dir.join(id);
+        std::fs::remove_dir_all(&dir)
+            .context(format!("Failed to remove directory: {}", dir.display()))?;
+    }
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn finds_and_prunes_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (results_dir, outputs_dir) = (dir.path().join("json"), dir.path().join("out"));
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for _ in 0..3 {
+            let id = new_run_id(start, &results_dir, &outputs_dir);
+            let run_dir = outputs_dir.join(&id);
+            std::fs::create_dir_all(&run_dir).unwrap();
+            let manifest = Manifest {
+                id: id.clone(),
+                start_time: String::new(),
+                solver: None,
+                commit: None,
+                command: vec![],
+                result_file: results_dir.join(result_file_name(&id)),
+                cases: vec![],
+            };
+            write_manifest(&run_dir, &manifest).unwrap();
+        }
+        // Not a run without a manifest
+        std::fs::write(outputs_dir.join("0000.txt"), "").unwrap();
+
+        let runs = list_runs(&outputs_dir).unwrap();
+        assert_eq!(
+            runs,
+            vec!["20231114_221320", "20231114_221321", "20231114_221322"]
+        );
+        assert_eq!(
+            run_dir(&outputs_dir, LATEST).unwrap(),
+            outputs_dir.join("20231114_221322")
+        );
+        assert!(run_dir(&outputs_dir, "20231114_000000").is_err());
+
+        assert_eq!(prune(&outputs_dir, 1).unwrap(), runs[..2].to_vec());
+        assert_eq!(list_runs(&outputs_dir).unwrap(), runs[2..].to_vec());
+    }
+
+    #[test]
+    fn keeps_the_progress_of_unfinished_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let outputs_dir = dir.path().join("out");
+        assert!(unfinished_run(&outputs_dir, LATEST).is_err());
+        let run_dir = outputs_dir.join("20240101_000000");
+        std::fs::create_dir_all(&run_dir).unwrap();
+
+        let mut progress = Progress::open(&run_dir).unwrap();
+        let case = CaseResult {
+            seed: 3,
+            score: 100,
+            execution_time: 0.5,
+            error_message: String::new(),
+            tle: false,
+            max_memory_kb: Some(1024),
+            repeats: None,
+        };
+        progress.record(&case).unwrap();
+        // A line cut short by a kill
+        write!(progress.file, "{{\"seed\": 4, \"sco").unwrap();
+
+        assert_eq!(
+            unfinished_run(&outputs_dir, LATEST).unwrap(),
+            "20240101_000000"
+        );
+        assert_eq!(
+            read_progress(&outputs_dir, "20240101_000000").unwrap(),
+            vec![case]
+        );
+        assert!(unfinished_run(&outputs_dir, "20240102_000000").is_err());
+
+        finish_progress(&run_dir);
+        assert!(unfinished_run(&outputs_dir, LATEST).is_err());
+    }
+}
@@ -0,0 +1,313 @@
+//! Choosing the seeds `ahc test` runs: ranges and lists with `--seeds`, a file of seeds with
+//! `--seeds-file`, a reproducible random sample of those with `--sample`, and a shard of them with
+//! `--shard` for runs split across machines.
+
+use crate::messages::msg;
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, ValueEnum};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+#[derive(Args, Debug, Default)]
+pub(crate) struct SeedArgs {
+    /// Only run these seeds, e.g. `0..100`, `0..=9,42` or `3,5,8`
+    #[arg(long, value_name = "SEEDS")]
+    seeds: Option<String>,
+    /// Only run the seeds listed in this file, separated by whitespace, `#` starting a comment
+    #[arg(long, value_name = "FILE")]
+    seeds_file: Option<PathBuf>,
+    /// Run a random sample of this many of the seeds
+    #[arg(long, value_name = "COUNT")]
+    sample: Option<usize>,
+    /// Seed of the random sample, so that the same sample can be run again
+    #[arg(long, default_value_t = 0, requires = "sample")]
+    sample_seed: u64,
+    /// Only run a shard of the seeds chosen, e.g. `0/4` for the first of four, numbered from 0
+    #[arg(long, value_name = "INDEX/COUNT", value_parser = parse_shard)]
+    shard: Option<(usize, usize)>,
+    /// How the seeds are split into shards
+    #[arg(long, value_enum, default_value_t, requires = "shard")]
+    shard_by: PartitionStrategy,
+}
+
+/// How seeds are split into shards, the same way on every machine.
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum PartitionStrategy {
+    /// The i-th seed in ascending order goes to shard i mod the count, evening out the shards
+    #[default]
+    RoundRobin,
+    /// A seed goes to shard SplitMix64(seed) mod the count, which depends on the seed alone and
+    /// not on the other seeds chosen
+    Hash,
+}
+
+/// The split of the seeds of a run, recorded in its result so that the run can be repeated and
+/// its shards merged.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Partition {
+    pub(crate) strategy: PartitionStrategy,
+    pub(crate) count: usize,
+    /// The shard run, or `None` for a run of every shard
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) index: Option<usize>,
+}
+
+/// How the seeds of a run were chosen, recorded in its result.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(crate) struct Subset {
+    /// The options choosing the seeds, e.g. `--seeds 0..100 --sample 50 --sample-seed 42`
+    pub(super) selection: String,
+    pub(super) seeds: Vec<u64>,
+}
+
+impl SeedArgs {
+    /// Runs the seeds of `spec`, e.g. `0..10`, unless the options chose some.
+    pub(super) fn default_seeds(&mut self, spec: &str) {
+        if self.seeds.is_none() && self.seeds_file.is_none() && self.sample.is_none() {
+            self.seeds = Some(spec.to_string());
+        }
+    }
+
+    /// The shard of the seeds `--shard` asks for, if any.
+    pub(crate) fn partition(&self) -> Option<Partition> {
+        self.shard.map(|(index, count)| Partition {
+            strategy: self.shard_by,
+            count,
+            index: Some(index),
+        })
+    }
+
+    /// Chooses from the seeds which have inputs, returning them with the subset if the options
+    /// chose one.
+    pub(super) fn select(&self, available: Vec<u64>) -> Result<(Vec<u64>, Option<Subset>)> {
+        let mut selection = vec![];
+        let mut seeds = available.clone();
+        if let Some(spec) = &self.seeds {
+            let chosen = parse_seeds(spec)?;
+            seeds = only_available(&available, chosen)?;
+            selection.push(format!("--seeds {}", spec));
+        }
+        if let Some(path) = &self.seeds_file {
+            let content = std::fs::read_to_string(path)
+                .context(format!("Failed to read seeds file: {}", path.display()))?;
+            let chosen = parse_seeds_file(&content)
+                .context(format!("Failed to parse seeds file: {}", path.display()))?;
+            let chosen = only_available(&available, chosen)?;
+            seeds.retain(|seed| chosen.contains(seed));
+            selection.push(format!("--seeds-file {}", path.display()));
+        }
+        if let Some(count) = self.sample {
+            seeds = sample(&seeds, count, self.sample_seed);
+            selection.push(format!(
+                "--sample {} --sample-seed {}",
+                count, self.sample_seed
+            ));
+        }
+        if let Some((index, count)) = self.shard {
+            seeds = partition(&seeds, self.shard_by, count).swap_remove(index);
+            selection.push(format!(
+                "--shard {}/{} --shard-by {}",
+                index,
+                count,
+                self.shard_by
+                    .to_possible_value()
+                    .expect("no skipped values")
+                    .get_name()
+            ));
+        }
+        if selection.is_empty() {
+            return Ok((seeds, None));
+        }
+        let subset = Subset {
+            selection: selection.join(" "),
+            seeds: seeds.clone(),
+        };
+        Ok((seeds, Some(subset)))
+    }
+}
+
+/// Splits `seeds` into `count` shards as `strategy` says, each in ascending order. Shards may be
+/// empty.
+pub(crate) fn partition(seeds: &[u64], strategy: PartitionStrategy, count: usize) -> Vec<Vec<u64>> {
+    let mut sorted = seeds.to_vec();
+    sorted.sort();
+    let mut shards = vec![vec![]; count];
+    for (i, seed) in sorted.into_iter().enumerate() {
+        let shard = match strategy {
+            PartitionStrategy::RoundRobin => i % count,
+            PartitionStrategy::Hash => (split_mix64(seed) % count as u64) as usize,
+        };
+        shards[shard].push(seed);
+    }
+    shards
+}
+
+/// The output function of SplitMix64, spreading consecutive seeds evenly.
+fn split_mix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Parses `INDEX/COUNT` of `--shard`.
+fn parse_shard(text: &str) -> Result<(usize, usize)> {
+    let invalid = || anyhow!(msg!("test.invalid_shard", format!("{:?}", text)));
+    let (index, count) = text.split_once('/').ok_or_else(invalid)?;
+    let index = index.trim().parse::<usize>().map_err(|_| invalid())?;
+    let count = count.trim().parse::<usize>().map_err(|_| invalid())?;
+    if index >= count {
+        return Err(anyhow!(msg!("test.shard_out_of_range", index, count)));
+    }
+    Ok((index, count))
+}
+
+fn only_available(available: &[u64], chosen: BTreeSet<u64>) -> Result<Vec<u64>> {
+    if let Some(missing) = chosen.iter().find(|seed| !available.contains(seed)) {
+        return Err(anyhow!(msg!("test.no_input_for_seed", missing)));
+    }
+    Ok(chosen.into_iter().collect())
+}
+
+/// Parses comma-separated seeds and ranges, `a..b` excluding `b` and `a..=b` including it.
+fn parse_seeds(spec: &str) -> Result<BTreeSet<u64>> {
+    let invalid = || anyhow!(msg!("test.invalid_seeds", format!("{:?}", spec)));
+    let number = |text: &str| text.trim().parse::<u64>().map_err(|_| invalid());
+    let mut seeds = BTreeSet::new();
+    for part in spec.split(',').filter(|part| !part.trim().is_empty()) {
+        if let Some((start, end)) = part.split_once("..=") {
+            seeds.extend(number(start)?..=number(end)?);
+        } else if let Some((start, end)) = part.split_once("..") {
+            seeds.extend(number(start)?..number(end)?);
+        } else {
+            seeds.insert(number(part)?);
+        }
+    }
+    if seeds.is_empty() {
+        return Err(invalid());
+    }
+    Ok(seeds)
+}
+
+fn parse_seeds_file(content: &str) -> Result<BTreeSet<u64>> {
+    let mut seeds = BTreeSet::new();
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        for word in line.split_whitespace() {
+            seeds.insert(
+                word.parse()
+                    .map_err(|_| anyhow!(msg!("test.invalid_seed", format!("{:?}", word))))?,
+            );
+        }
+    }
+    Ok(seeds)
+}
+
+/// A random sample of `count` of the seeds, in their order.
+fn sample(seeds: &[u64], count: usize, sample_seed: u64) -> Vec<u64> {
+    let mut rng = StdRng::seed_from_u64(sample_seed);
+    let mut indices =
+        rand::seq::index::sample(&mut rng, seeds.len(), count.min(seeds.len())).into_vec();
+    indices.sort();
+    indices.into_iter().map(|i| seeds[i]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ranges_and_lists() {
+        let seeds = |spec| parse_seeds(spec).unwrap().into_iter().collect::<Vec<_>>();
+        assert_eq!(seeds("0..3"), vec![0, 1, 2]);
+        assert_eq!(seeds("0..=2, 7,5"), vec![0, 1, 2, 5, 7]);
+        assert!(parse_seeds("a..3").is_err());
+        assert!(parse_seeds("").is_err());
+        assert_eq!(
+            parse_seeds_file("# hard cases\n3 17\n42 # slow\n")
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![3, 17, 42]
+        );
+    }
+
+    #[test]
+    fn selects_and_samples_reproducibly() {
+        let available = (0..100).collect::<Vec<_>>();
+        let args = SeedArgs {
+            seeds: Some("10..60".to_string()),
+            sample: Some(5),
+            sample_seed: 42,
+            ..SeedArgs::default()
+        };
+        let (seeds, subset) = args.select(available.clone()).unwrap();
+        assert_eq!(seeds.len(), 5);
+        assert!(seeds.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(seeds.iter().all(|seed| (10..60).contains(seed)));
+        let subset = subset.unwrap();
+        assert_eq!(
+            subset.selection,
+            "--seeds 10..60 --sample 5 --sample-seed 42"
+        );
+        assert_eq!(args.select(available.clone()).unwrap().0, seeds);
+
+        assert_eq!(
+            SeedArgs::default().select(available.clone()).unwrap(),
+            (available, None)
+        );
+        let missing = SeedArgs {
+            seeds: Some("99..=100".to_string()),
+            ..SeedArgs::default()
+        };
+        assert!(missing.select((0..100).collect()).is_err());
+    }
+
+    #[test]
+    fn splits_seeds_into_shards_deterministically() {
+        let seeds = [4, 0, 3, 1, 2];
+        assert_eq!(
+            partition(&seeds, PartitionStrategy::RoundRobin, 2),
+            vec![vec![0, 2, 4], vec![1, 3]]
+        );
+        assert_eq!(
+            partition(&seeds, PartitionStrategy::RoundRobin, 7)[6],
+            Vec::<u64>::new()
+        );
+
+        // The shard of a seed does not depend on the others
+        let all = (0..1000).collect::<Vec<_>>();
+        let shards = partition(&all, PartitionStrategy::Hash, 4);
+        assert_eq!(shards.iter().map(Vec::len).sum::<usize>(), 1000);
+        assert!(shards.iter().all(|shard| shard.len() > 200));
+        let some = partition(&all[..100], PartitionStrategy::Hash, 4);
+        for (shard, part) in shards.iter().zip(&some) {
+            assert!(part.iter().all(|seed| shard.contains(seed)));
+        }
+
+        assert_eq!(parse_shard("1/4").unwrap(), (1, 4));
+        assert!(parse_shard("4/4").is_err());
+        assert!(parse_shard("1").is_err());
+
+        let args = SeedArgs {
+            shard: Some((1, 2)),
+            shard_by: PartitionStrategy::Hash,
+            ..SeedArgs::default()
+        };
+        let (seeds, subset) = args.select(all.clone()).unwrap();
+        assert_eq!(seeds, partition(&all, PartitionStrategy::Hash, 2)[1]);
+        assert_eq!(subset.unwrap().selection, "--shard 1/2 --shard-by hash");
+        assert_eq!(
+            args.partition(),
+            Some(Partition {
+                strategy: PartitionStrategy::Hash,
+                count: 2,
+                index: Some(1)
+            })
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! The table `ahc test` prints while it runs, a row per finished seed with the running average.
+
+use super::CaseResult;
+
+pub(super) struct LiveTable {
+    total: usize,
+    done: usize,
+    total_score: u64,
+}
+
+impl LiveTable {
+    pub(super) fn new(total: usize) -> Self {
+        LiveTable {
+            total,
+            done: 0,
+            total_score: 0,
+        }
+    }
+
+    pub(super) fn header(&self) -> String {
+        format!(
+            "{:>6} {:>12} {:>8} {:>14} {:>11}",
+            "seed", "score", "time", "average", "progress"
+        )
+    }
+
+    /// The row of a case which just finished.
+    pub(super) fn row(&mut self, case: &CaseResult) -> String {
+        self.done += 1;
+        self.total_score += case.score;
+        let status = if case.tle {
+            " TLE".to_string()
+        } else if !case.error_message.is_empty() {
+            " ERROR".to_string()
+        } else if let Some(stats) = &case.repeats {
+            format!(" ±{:.1}", stats.std)
+        } else {
+            String::new()
+        };
+        let width = self.total.to_string().len();
+        let progress = format!("{:>w$}/{}", self.done, self.total, w = width);
+        format!(
+            "{:>6} {:>12} {:>7.3}s {:>14.2} {:>11}{}",
+            case.seed,
+            case.score,
+            case.execution_time,
+            self.total_score as f64 / self.done as f64,
+            progress,
+            status
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_carry_the_running_average() {
+        let case = |seed, score, error_message: &str| CaseResult {
+            seed,
+            score,
+            execution_time: 1.5,
+            error_message: error_message.to_string(),
+            tle: false,
+            max_memory_kb: None,
+            repeats: None,
+        };
+        let mut table = LiveTable::new(12);
+        assert_eq!(
+            table.row(&case(3, 100, "")),
+            "     3          100   1.500s         100.00        1/12"
+        );
+        assert_eq!(
+            table.row(&case(0, 0, "Solver exited with exit status: 1")),
+            "     0            0   1.500s          50.00        2/12 ERROR"
+        );
+        assert_eq!(table.header().len(), table.row(&case(1, 1, "")).len());
+    }
+}
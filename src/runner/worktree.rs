@@ -0,0 +1,75 @@
+//! Checkouts of past commits for `ahc test --at`, where the solver of the commit is built without
+//! touching the working tree of the repository.
+
+use crate::messages::msg;
+use anyhow::{anyhow, Context, Result};
+use git2::build::CheckoutBuilder;
+use git2::Repository;
+use std::path::{Path, PathBuf};
+
+const WORKTREE_DIR: &str = ".ahc/worktree";
+
+/// The id of the commit `rev` names, e.g. `HEAD~2`, a tag or a short hash.
+pub(super) fn resolve_commit(rev: &str) -> Result<String> {
+    let repo = Repository::open_from_env().context("Failed to open git repository")?;
+    let commit = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .context(format!("No commit {} in the repository", rev))?;
+    Ok(commit.id().to_string())
+}
+
+/// A commit checked out into `.ahc/worktree/<commit>`, removed again when dropped.
+pub(super) struct Worktree {
+    root: PathBuf,
+    /// The directory of the worktree matching the current one of the repository
+    project_dir: PathBuf,
+}
+
+impl Worktree {
+    pub(super) fn checkout(commit: &str) -> Result<Self> {
+        let repo = Repository::open_from_env().context("Failed to open git repository")?;
+        let workdir = repo
+            .workdir()
+            .ok_or_else(|| anyhow!(msg!("test.no_workdir")))?;
+        let current_dir = std::env::current_dir()?;
+        let relative = std::fs::canonicalize(&current_dir)?
+            .strip_prefix(std::fs::canonicalize(workdir)?)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let root = current_dir.join(WORKTREE_DIR).join(commit);
+        // Left over by a run which did not finish
+        if root.exists() {
+            std::fs::remove_dir_all(&root)
+                .context(format!("Failed to remove directory: {}", root.display()))?;
+        }
+        std::fs::create_dir_all(&root)
+            .context(format!("Failed to create directory: {}", root.display()))?;
+        let worktree = Worktree {
+            project_dir: root.join(relative),
+            root,
+        };
+
+        let object = repo.revparse_single(commit)?;
+        let mut checkout = CheckoutBuilder::new();
+        // Leave the index of the repository as it is
+        checkout
+            .target_dir(&worktree.root)
+            .update_index(false)
+            .force();
+        repo.checkout_tree(&object, Some(&mut checkout))
+            .context(format!("Failed to check out {}", commit))?;
+        Ok(worktree)
+    }
+
+    pub(super) fn project_dir(&self) -> &Path {
+        &self.project_dir
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
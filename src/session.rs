@@ -0,0 +1,211 @@
+//! The AtCoder session kept in the system keyring, or in a file next to the global config, sent
+//! with the requests to AtCoder so that the pages and tools of contests needing a login can be
+//! fetched. Requests go out anonymously when there is none.
+
+mod keyring;
+
+use crate::config::global_config_path;
+use crate::messages::msg;
+use anyhow::{anyhow, Context, Result};
+use keyring::Keyring;
+use reqwest::header::COOKIE;
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::{debug, warn};
+use url::Url;
+
+const SESSION_FILE: &str = "session.json";
+const COOKIE_NAME: &str = "REVEL_SESSION";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Session {
+    /// Value of the `REVEL_SESSION` cookie of AtCoder
+    pub(crate) revel_session: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) username: Option<String>,
+}
+
+/// Where a session is kept.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Store {
+    /// The system keyring
+    Keyring,
+    /// `session.json` in the directory of the global config, in plain text
+    File,
+}
+
+fn session_path() -> Result<PathBuf> {
+    global_config_path()
+        .and_then(|path| Some(path.parent()?.join(SESSION_FILE)))
+        .ok_or_else(|| anyhow!("Failed to locate global config directory"))
+}
+
+/// The session kept for this user and where, looked for in the keyring first.
+pub(crate) fn load() -> Result<Option<(Session, Store)>> {
+    if let Some(secret) = Keyring::detect().map(Keyring::load).transpose()?.flatten() {
+        let session =
+            serde_json::from_str(&secret).context("Failed to parse the session in the keyring")?;
+        return Ok(Some((session, Store::Keyring)));
+    }
+    Ok(read(&session_path()?)?.map(|session| (session, Store::File)))
+}
+
+/// Keeps `session` in `store`, removing any copy kept in the other.
+pub(crate) fn save(session: &Session, store: Store) -> Result<()> {
+    let path = session_path()?;
+    match store {
+        Store::Keyring => {
+            let keyring = Keyring::detect().ok_or_else(|| anyhow!(msg!("login.no_keyring")))?;
+            keyring
+                .store(&serde_json::to_string(session)?)
+                .context(msg!("login.no_keyring"))?;
+            remove_file(&path)
+        }
+        Store::File => {
+            write(&path, session)?;
+            if let Some(keyring) = Keyring::detect() {
+                keyring.delete()?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Forgets the session wherever it is kept.
+pub(crate) fn forget() -> Result<()> {
+    if let Some(keyring) = Keyring::detect() {
+        keyring.delete()?;
+    }
+    remove_file(&session_path()?)
+}
+
+fn remove_file(path: &Path) -> Result<()> {
+    if path.exists() {
+        std::fs::remove_file(path).context(format!("Failed to remove file: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// The session kept at `path`, if any.
+fn read(path: &Path) -> Result<Option<Session>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(path)
+        .context(format!("Failed to read session file: {}", path.display()))?;
+    let session = serde_json::from_str(&text)
+        .context(format!("Failed to parse session file: {}", path.display()))?;
+    Ok(Some(session))
+}
+
+/// Keeps `session` at `path`, readable only by the user on Unix.
+fn write(path: &Path, session: &Session) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .context(format!("Failed to create directory: {}", parent.display()))?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .context(format!("Failed to write session file: {}", path.display()))?;
+    // The mode above only applies to a new file, so a file left readable by others is fixed too
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .context(format!(
+                "Failed to restrict session file: {}",
+                path.display()
+            ))?;
+    }
+    file.write_all(serde_json::to_string_pretty(session)?.as_bytes())
+        .context(format!("Failed to write session file: {}", path.display()))
+}
+
+/// The session kept for this user, read once. An unreadable one is warned about and ignored.
+fn stored() -> Option<&'static Session> {
+    static STORED: OnceLock<Option<Session>> = OnceLock::new();
+    STORED
+        .get_or_init(|| match load() {
+            Ok(session) => session.map(|(session, _)| session),
+            Err(e) => {
+                warn!("{:#}", e);
+                None
+            }
+        })
+        .as_ref()
+}
+
+/// Adds the stored session to `request` if it goes to AtCoder. Other hosts never see it.
+pub(crate) fn authenticate(request: RequestBuilder, url: &str) -> RequestBuilder {
+    match (is_atcoder(url), stored()) {
+        (true, Some(session)) => {
+            debug!("Sending the AtCoder session with the request to {}", url);
+            request.header(COOKIE, cookie(session))
+        }
+        _ => request,
+    }
+}
+
+fn cookie(session: &Session) -> String {
+    format!("{}={}", COOKIE_NAME, session.revel_session)
+}
+
+/// Whether `url` is on `atcoder.jp` or one of its subdomains, e.g. `img.atcoder.jp`.
+fn is_atcoder(url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    url.scheme() == "https"
+        && url
+            .host_str()
+            .is_some_and(|host| host == "atcoder.jp" || host.ends_with(".atcoder.jp"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sends_the_session_only_to_atcoder() {
+        assert!(is_atcoder(
+            "https://atcoder.jp/contests/ahc001/tasks/ahc001_a"
+        ));
+        assert!(is_atcoder("https://img.atcoder.jp/ahc001/tools.zip"));
+        assert!(!is_atcoder("http://atcoder.jp/contests/ahc001"));
+        assert!(!is_atcoder("https://notatcoder.jp/tools.zip"));
+        assert!(!is_atcoder("https://atcoder.jp.example.net/tools.zip"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ahc-tools").join(SESSION_FILE);
+        assert_eq!(read(&path).unwrap(), None);
+        let session = Session {
+            revel_session: "abc%00def".to_string(),
+            username: Some("alice".to_string()),
+        };
+        write(&path, &session).unwrap();
+        assert_eq!(read(&path).unwrap(), Some(session.clone()));
+        assert_eq!(cookie(&session), "REVEL_SESSION=abc%00def");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+            write(&path, &session).unwrap();
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! The system keyring, reached through the platform's command as the clipboard is: `security`
+//! for the macOS Keychain, `secret-tool` for the Secret Service of GNOME Keyring or KWallet.
+
+use anyhow::{anyhow, Context, Result};
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Output, Stdio};
+use tracing::debug;
+
+const SERVICE: &str = "ahc-tools";
+const ACCOUNT: &str = "atcoder";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Keyring {
+    Keychain,
+    SecretService,
+}
+
+impl Keyring {
+    /// The keyring of this platform, if its command is installed.
+    pub(super) fn detect() -> Option<Keyring> {
+        let (keyring, program) = if cfg!(target_os = "macos") {
+            (Keyring::Keychain, "security")
+        } else if cfg!(unix) {
+            (Keyring::SecretService, "secret-tool")
+        } else {
+            return None;
+        };
+        match Command::new(program)
+            .arg("--help")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+        {
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            _ => Some(keyring),
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Keyring::Keychain => "security",
+            Keyring::SecretService => "secret-tool",
+        }
+    }
+
+    /// Keeps `secret`, replacing the one kept before. It is passed on stdin, never as an
+    /// argument others could see.
+    pub(super) fn store(self, secret: &str) -> Result<()> {
+        let (args, stdin) = match self {
+            Keyring::Keychain => (vec!["-i"], keychain_script(secret)),
+            Keyring::SecretService => (
+                vec![
+                    "store",
+                    "--label=ahc-tools AtCoder session",
+                    "service",
+                    SERVICE,
+                    "account",
+                    ACCOUNT,
+                ],
+                secret.to_string(),
+            ),
+        };
+        let output = self.run(&args, Some(&stdin))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{} failed to store the session: {}",
+                self.program(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    /// The secret kept, if any.
+    pub(super) fn load(self) -> Result<Option<String>> {
+        let args = match self {
+            Keyring::Keychain => vec!["find-generic-password", "-s", SERVICE, "-a", ACCOUNT, "-w"],
+            Keyring::SecretService => vec!["lookup", "service", SERVICE, "account", ACCOUNT],
+        };
+        let output = self.run(&args, None)?;
+        // Both exit with an error when nothing is kept
+        let secret = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok((output.status.success() && !secret.is_empty()).then_some(secret))
+    }
+
+    pub(super) fn delete(self) -> Result<()> {
+        let args = match self {
+            Keyring::Keychain => vec!["delete-generic-password", "-s", SERVICE, "-a", ACCOUNT],
+            Keyring::SecretService => vec!["clear", "service", SERVICE, "account", ACCOUNT],
+        };
+        self.run(&args, None)?;
+        Ok(())
+    }
+
+    fn run(self, args: &[&str], stdin: Option<&str>) -> Result<Output> {
+        let program = self.program();
+        debug!("Running {} {}", program, args.first().unwrap_or(&""));
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context(format!("Failed to run {}", program))?;
+        let mut pipe = child.stdin.take().expect("stdin is piped");
+        if let Some(stdin) = stdin {
+            pipe.write_all(stdin.as_bytes())
+                .context(format!("Failed to write to {}", program))?;
+        }
+        drop(pipe);
+        child
+            .wait_with_output()
+            .context(format!("Failed to run {}", program))
+    }
+}
+
+/// Commands of `security -i` keeping `secret` in the Keychain. The secret is given in hex, which
+/// needs no quoting.
+fn keychain_script(secret: &str) -> String {
+    let hex = secret
+        .bytes()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!(
+        "add-generic-password -U -s {} -a {} -X {}\n",
+        SERVICE, ACCOUNT, hex
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_secret_off_the_command_line() {
+        assert_eq!(
+            keychain_script("{\"a\": 1}"),
+            "add-generic-password -U -s ahc-tools -a atcoder -X 7b2261223a20317d\n"
+        );
+    }
+}
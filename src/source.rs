@@ -0,0 +1,91 @@
+//! `ahc source`, which prints the single-file source to submit, or with `--copy` puts it on the
+//! clipboard for submitting by hand.
+
+use crate::clipboard;
+use crate::config::Config;
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::PathBuf;
+use tracing::info;
+
+#[derive(Args)]
+pub(crate) struct SourceArgs {
+    /// Put the source on the clipboard instead of printing it
+    #[arg(long)]
+    copy: bool,
+}
+
+/// `[source]`, where the source to submit comes from.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct SourceConfig {
+    /// The solution, e.g. `src/bin/a.rs` in a cargo-compete package
+    #[serde(default = "default_path")]
+    pub(crate) path: PathBuf,
+    /// Command printing the solution bundled with its libraries into one file, e.g.
+    /// `["cargo", "equip", "--bin", "a"]`. Used instead of `path` when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) bundle_command: Option<Vec<String>>,
+}
+
+impl Default for SourceConfig {
+    fn default() -> Self {
+        SourceConfig {
+            path: default_path(),
+            bundle_command: None,
+        }
+    }
+}
+
+fn default_path() -> PathBuf {
+    PathBuf::from("src/main.rs")
+}
+
+pub(crate) fn source(args: SourceArgs, config: Config, output: Output) -> Result<()> {
+    let source_config = config.source.clone().unwrap_or_default();
+    let (source, origin) = match &source_config.bundle_command {
+        Some(command) => (bundle(command)?, command.join(" ")),
+        None => {
+            let path = &source_config.path;
+            let source = std::fs::read_to_string(path)
+                .context(format!("Failed to read source: {}", path.display()))?;
+            (source, path.display().to_string())
+        }
+    };
+
+    if args.copy {
+        clipboard::copy(&source, config.clipboard.as_ref())?;
+        info!("{}", msg!("clipboard.copied", origin));
+    } else if !output.is_json() {
+        print!("{}", source);
+    }
+    if output.is_json() {
+        print_json(&json!({
+            "from": origin,
+            "bytes": source.len(),
+            "copied": args.copy,
+            "source": (!args.copy).then_some(&source),
+        }))?;
+    }
+    Ok(())
+}
+
+fn bundle(command: &[String]) -> Result<String> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow!("[source] bundle_command is empty"))?;
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .context(format!("Failed to run bundle command: {}", program))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Bundle command failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout).context("Bundled source is not UTF-8")
+}
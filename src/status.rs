@@ -0,0 +1,249 @@
+//! `ahc status`, an overview of the project: the latest and best pahcer runs, what `ahc` did last
+//! according to the journal, the time left, and hints on what to do next.
+
+use crate::config::Config;
+use crate::journal::{self, Entry, Journal, Operation};
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::overlay::parse_time;
+use crate::pahcer;
+use crate::tune::Objective;
+use crate::workspace::format_duration;
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Args)]
+pub(crate) struct StatusArgs {}
+
+/// What `ahc status --output json` prints.
+#[derive(Serialize, Debug, Default)]
+struct Status {
+    contest: String,
+    problem_url: String,
+    /// Seconds until `[overlay] end_time`, 0 once the contest ended
+    time_remaining_secs: Option<u64>,
+    last_run: Option<Run>,
+    best_run: Option<Run>,
+    last_commit: Option<Entry>,
+    last_download: Option<Entry>,
+    last_tune: Option<Entry>,
+    hints: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+struct Run {
+    file: PathBuf,
+    case_count: usize,
+    average_score: f64,
+}
+
+pub(crate) fn status(_args: StatusArgs, config: Config, output: Output) -> Result<()> {
+    let mut runs = vec![];
+    for path in pahcer::list_results(&config.paths.results_dir)? {
+        let result = pahcer::read_result(&path)?;
+        runs.push(Run {
+            file: path,
+            case_count: result.case_count,
+            average_score: result.average_score(),
+        });
+    }
+    let objective = config.objective();
+    let entries = Journal::open().load()?;
+    let end = config
+        .overlay
+        .as_ref()
+        .and_then(|overlay| overlay.end_time.as_deref())
+        .map(parse_time)
+        .transpose()?;
+
+    let mut status = Status {
+        contest: config.general.name.clone(),
+        problem_url: config.general.problem_url.clone(),
+        time_remaining_secs: end.map(|end| {
+            end.duration_since(SystemTime::now())
+                .map_or(0, |left| left.as_secs())
+        }),
+        last_run: runs.last().cloned(),
+        best_run: best_run(&runs, objective).cloned(),
+        last_commit: journal::last_undoable(&entries).cloned(),
+        last_download: last_entry(&entries, |op| matches!(op, Operation::Download { .. })),
+        last_tune: last_entry(&entries, |op| matches!(op, Operation::Tune { .. })),
+        hints: vec![],
+    };
+    status.hints = hints(&status, &entries, &config.paths.results_dir);
+
+    if output.is_json() {
+        print_json(&status)?;
+    } else {
+        print!("{}", format_status(&status));
+    }
+    Ok(())
+}
+
+fn best_run(runs: &[Run], objective: Objective) -> Option<&Run> {
+    runs.iter().fold(None, |best, run| match best {
+        Some(best) if !objective.is_better(run.average_score, best.average_score) => Some(best),
+        _ => Some(run),
+    })
+}
+
+fn last_entry(entries: &[Entry], matches: impl Fn(&Operation) -> bool) -> Option<Entry> {
+    entries
+        .iter()
+        .rev()
+        .find(|entry| matches(&entry.operation))
+        .cloned()
+}
+
+fn hints(status: &Status, entries: &[Entry], results_dir: &Path) -> Vec<String> {
+    let mut hints = vec![];
+    if status.last_download.is_none() && !results_dir.exists() {
+        hints.push(msg!("status.hint_download"));
+    }
+    match &status.best_run {
+        None => hints.push(msg!("status.hint_no_runs", results_dir.display())),
+        Some(best) => {
+            // Commits record the result file relative to the repository, so compare names only
+            let committed = entries.iter().any(|entry| {
+                matches!(
+                    &entry.operation,
+                    Operation::Commit { result_file: Some(file), .. }
+                        if file.file_name() == best.file.file_name()
+                )
+            });
+            if !committed {
+                hints.push(msg!(
+                    "status.hint_commit_best",
+                    best.file.display(),
+                    format!("{:.2}", best.average_score)
+                ));
+            }
+        }
+    }
+    match status.time_remaining_secs {
+        Some(0) => hints.push(msg!("status.hint_ended")),
+        Some(secs) if secs < 3600 => hints.push(msg!("status.hint_ending", format_duration(secs))),
+        _ => {}
+    }
+    hints
+}
+
+fn format_status(status: &Status) -> String {
+    let run = |run: &Option<Run>| match run {
+        Some(run) => format!(
+            "{:.2} ({} cases, {})",
+            run.average_score,
+            run.case_count,
+            run.file.display()
+        ),
+        None => "-".to_string(),
+    };
+    let entry = |entry: &Option<Entry>| match entry {
+        Some(Entry {
+            time,
+            operation: Operation::Commit {
+                commit, message, ..
+            },
+        }) => format!("{} {} ({})", &commit[..7.min(commit.len())], message, time),
+        Some(Entry {
+            time,
+            operation: Operation::Download { files, .. },
+        }) => format!("{} files ({})", files, time),
+        Some(Entry {
+            time,
+            operation:
+                Operation::Tune {
+                    study,
+                    trials,
+                    best,
+                },
+        }) => {
+            let best = best.map_or("-".to_string(), |best| format!("{:.2}", best));
+            format!(
+                "study {}, {} trials, best {} ({})",
+                study, trials, best, time
+            )
+        }
+        _ => "-".to_string(),
+    };
+
+    let mut output = String::new();
+    output.push_str(&format!("Contest:        {}\n", status.contest));
+    if let Some(secs) = status.time_remaining_secs {
+        output.push_str(&format!("Time left:      {}\n", format_duration(secs)));
+    }
+    output.push_str(&format!("Last run:       {}\n", run(&status.last_run)));
+    output.push_str(&format!("Best run:       {}\n", run(&status.best_run)));
+    output.push_str(&format!("Last commit:    {}\n", entry(&status.last_commit)));
+    output.push_str(&format!(
+        "Last download:  {}\n",
+        entry(&status.last_download)
+    ));
+    output.push_str(&format!("Last tune:      {}\n", entry(&status.last_tune)));
+    for hint in &status.hints {
+        output.push_str(&format!("Hint: {}\n", hint));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(file: &str, average_score: f64) -> Run {
+        Run {
+            file: PathBuf::from(file),
+            case_count: 10,
+            average_score,
+        }
+    }
+
+    #[test]
+    fn hints_at_an_uncommitted_best_run() {
+        let runs = vec![run("result_1.json", 20.0), run("result_2.json", 10.0)];
+        let status = Status {
+            best_run: best_run(&runs, Objective::Max).cloned(),
+            time_remaining_secs: Some(600),
+            last_download: Some(Entry {
+                time: String::new(),
+                operation: Operation::Download {
+                    zip_url: "https://example.net/tools.zip".to_string(),
+                    files: 3,
+                },
+            }),
+            ..Status::default()
+        };
+        assert_eq!(status.best_run, Some(runs[0].clone()));
+        let commit = |file: &str| Entry {
+            time: String::new(),
+            operation: Operation::Commit {
+                commit: "abcdef0".to_string(),
+                parent: "1234567".to_string(),
+                message: "message".to_string(),
+                tag: None,
+                result_file: Some(PathBuf::from(format!("pahcer/json/{}", file))),
+                average_score: None,
+            },
+        };
+
+        let results_dir = Path::new("pahcer/json");
+        assert_eq!(
+            hints(&status, &[commit("result_2.json")], results_dir),
+            vec![
+                "Best run result_1.json (20.00) is not committed yet",
+                "The contest ends in 10m",
+            ]
+        );
+        assert_eq!(
+            hints(&status, &[commit("result_1.json")], results_dir),
+            vec!["The contest ends in 10m"]
+        );
+        assert_eq!(
+            best_run(&runs, Objective::Min).map(|run| run.average_score),
+            Some(10.0)
+        );
+    }
+}
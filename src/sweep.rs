@@ -0,0 +1,167 @@
+//! `ahc sweep`, which runs the solver as `ahc test` does for every combination of the parameter
+//! values of `[sweep.params]`, and tabulates the average score of each.
+
+use crate::config::Config;
+use crate::error::ErrorKind;
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::runner::{self, RunOptions, TestRun};
+use crate::tune::{
+    format_assignment, grid_point, grid_size, validate_space, Assignment, Objective, ParamSpace,
+};
+use anyhow::Result;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::info;
+
+#[derive(Args)]
+pub(crate) struct SweepArgs {
+    #[command(flatten)]
+    options: RunOptions,
+}
+
+/// `[sweep]`, the grid of parameters `ahc sweep` runs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct SweepConfig {
+    /// `[sweep.params]`, the values of each parameter, e.g. `T0 = [1.0, 2.0, 4.0]`, handed to the
+    /// solver as the `params_via` of `[test]` or of the solver says
+    pub(crate) params: ParamSpace,
+}
+
+/// What `ahc sweep --output json` prints.
+#[derive(Serialize, Debug)]
+struct SweepSummary {
+    combinations: Vec<Combination>,
+    best: Option<Assignment>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+struct Combination {
+    params: Assignment,
+    average_score: f64,
+    result_file: PathBuf,
+}
+
+pub(crate) fn sweep(args: SweepArgs, config: Config, output: Output) -> Result<()> {
+    let space = config
+        .sweep
+        .as_ref()
+        .map(|sweep| sweep.params.clone())
+        .filter(|params| !params.is_empty())
+        .ok_or_else(|| ErrorKind::Config.error(msg!("sweep.no_config")))?;
+    validate_space(&space)
+        .map_err(|e| ErrorKind::Config.error(format!("[sweep.params]: {}", e)))?;
+    let count = grid_size(&space);
+    if count == 0 {
+        return Err(ErrorKind::Config.error(msg!("sweep.no_values")));
+    }
+
+    let mut combinations = vec![];
+    for index in 0..count {
+        let params = grid_point(&space, index);
+        info!(
+            "{}",
+            msg!(
+                "sweep.combination",
+                index + 1,
+                count,
+                format_assignment(&params)
+            )
+        );
+        let run = TestRun {
+            params: Some((&space, &params)),
+            ..TestRun::default()
+        };
+        let summary = runner::run_test(&args.options, run, &config, output)?;
+        combinations.push(Combination {
+            params,
+            average_score: summary.average_score,
+            result_file: summary.result_file,
+        });
+    }
+
+    let objective = config.objective();
+    let best = best(&combinations, objective).map(|best| best.params.clone());
+    if let Some(best) = &best {
+        info!("{}", msg!("sweep.best", format_assignment(best)));
+    }
+    if output.is_json() {
+        print_json(&SweepSummary { combinations, best })?;
+    } else {
+        print!("{}", format_table(&space, &combinations, objective));
+    }
+    Ok(())
+}
+
+fn best(combinations: &[Combination], objective: Objective) -> Option<&Combination> {
+    combinations
+        .iter()
+        .fold(None, |best, combination| match best {
+            Some(best) if !objective.is_better(combination.average_score, best.average_score) => {
+                Some(best)
+            }
+            _ => Some(combination),
+        })
+}
+
+/// A column per parameter and the average score, the best combination marked with `*`.
+fn format_table(space: &ParamSpace, combinations: &[Combination], objective: Objective) -> String {
+    let best = best(combinations, objective);
+    let widths = space
+        .keys()
+        .map(|name| {
+            combinations
+                .iter()
+                .map(|combination| combination.params[name].to_string().len())
+                .chain([name.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let mut output = String::new();
+    for (name, width) in space.keys().zip(&widths) {
+        output.push_str(&format!("{:<width$}  ", name, width = width));
+    }
+    output.push_str(&format!("{:>12}\n", "average"));
+    for combination in combinations {
+        for (value, width) in combination.params.values().zip(&widths) {
+            output.push_str(&format!("{:<width$}  ", value.to_string(), width = width));
+        }
+        let mark = if Some(combination) == best { " *" } else { "" };
+        output.push_str(&format!("{:>12.2}{}\n", combination.average_score, mark));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tabulates_every_combination_marking_the_best() {
+        let space: ParamSpace =
+            toml::from_str("T0 = [1.0, 2.5]\nmode = [\"fast\", \"slow\"]").unwrap();
+        let combinations = (0..grid_size(&space))
+            .map(|index| Combination {
+                params: grid_point(&space, index),
+                average_score: [10.0, 30.0, 20.0, 5.0][index],
+                result_file: PathBuf::new(),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            format_table(&space, &combinations, Objective::Max),
+            "T0   mode       average\n\
+             1    fast         10.00\n\
+             1    slow         30.00 *\n\
+             2.5  fast         20.00\n\
+             2.5  slow          5.00\n"
+        );
+        assert_eq!(
+            best(&combinations, Objective::Min).map(|best| best.average_score),
+            Some(5.0)
+        );
+    }
+}
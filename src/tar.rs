@@ -0,0 +1,361 @@
+//! Just enough of the ustar format for `ahc archive` and `ahc restore`: writing gzipped tarballs
+//! of regular files, and reading the files and directories of one.
+
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const BLOCK: usize = 512;
+
+/// Writes a `.tar.gz` of files added by their path in the archive.
+pub(crate) struct Builder<W: Write> {
+    encoder: GzEncoder<W>,
+}
+
+impl<W: Write> Builder<W> {
+    pub(crate) fn new(writer: W) -> Self {
+        Builder {
+            encoder: GzEncoder::new(writer, Compression::default()),
+        }
+    }
+
+    pub(crate) fn append_file(&mut self, name: &str, content: &[u8]) -> Result<()> {
+        self.append_file_with_mode(name, content, 0o644)
+    }
+
+    /// Adds a file with the permission bits `mode`, e.g. `0o755` for an executable.
+    pub(crate) fn append_file_with_mode(
+        &mut self,
+        name: &str,
+        content: &[u8],
+        mode: u32,
+    ) -> Result<()> {
+        let header = header(name, content.len() as u64, mode)?;
+        self.encoder.write_all(&header)?;
+        self.encoder.write_all(content)?;
+        let padding = (BLOCK - content.len() % BLOCK) % BLOCK;
+        self.encoder.write_all(&vec![0; padding])?;
+        Ok(())
+    }
+
+    /// Writes the end of the archive, two empty blocks.
+    pub(crate) fn finish(mut self) -> Result<W> {
+        self.encoder.write_all(&[0; BLOCK * 2])?;
+        Ok(self.encoder.finish()?)
+    }
+}
+
+/// The header of a regular file.
+fn header(name: &str, size: u64, mode: u32) -> Result<[u8; BLOCK]> {
+    let mut header = [0u8; BLOCK];
+    // Names longer than the name field are split at a slash into the prefix field
+    let (prefix, name) = if name.len() <= 100 {
+        ("", name)
+    } else {
+        // Searching the bytes, as byte 156 may fall inside a multibyte character
+        let split = name.as_bytes()[..name.len().min(156)]
+            .iter()
+            .rposition(|b| *b == b'/')
+            .filter(|i| name.len() - i - 1 <= 100 && *i <= 155)
+            .ok_or_else(|| anyhow!("Path too long for the archive: {}", name))?;
+        (&name[..split], &name[split + 1..])
+    };
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut header[100..108], mode as u64);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], size);
+    let mtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    write_octal(&mut header[136..148], mtime);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+    // The checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum = header.iter().map(|b| *b as u64).sum::<u64>();
+    write_octal(&mut header[148..155], checksum);
+    Ok(header)
+}
+
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+}
+
+fn read_octal(field: &[u8]) -> Result<u64> {
+    let text = std::str::from_utf8(field)?.trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).context(format!("Invalid number in tar header: {:?}", text))
+}
+
+fn read_str(field: &[u8]) -> Result<&str> {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    std::str::from_utf8(&field[..end]).context("Invalid path in tar header")
+}
+
+/// An entry of an archive being read.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Entry {
+    File {
+        path: PathBuf,
+        content: Vec<u8>,
+        /// Permission bits, unless the header leaves them out
+        mode: Option<u32>,
+    },
+    Dir {
+        path: PathBuf,
+    },
+}
+
+/// Reads the files and directories of a `.tar.gz`, skipping other kinds of entries such as
+/// links. GNU long names and pax paths name the entry after them. Paths leaving the archive root
+/// are rejected.
+pub(crate) fn read_entries<R: Read>(reader: R) -> Result<Vec<Entry>> {
+    read_entries_within(reader, |_, _| Ok(()))
+}
+
+/// Reads the entries like [`read_entries`], giving `check` the path and size of each entry
+/// before its content is read, so that it can stop at entries too large to hold.
+pub(crate) fn read_entries_within<R, F>(reader: R, mut check: F) -> Result<Vec<Entry>>
+where
+    R: Read,
+    F: FnMut(&Path, u64) -> Result<()>,
+{
+    let mut decoder = GzDecoder::new(reader);
+    let mut entries = vec![];
+    let mut header = [0u8; BLOCK];
+    // Name of the next entry given by a GNU long name or pax header before it
+    let mut long_name = None;
+    loop {
+        if let Err(e) = decoder.read_exact(&mut header) {
+            if e.kind() == std::io::ErrorKind::UnexpectedEof && entries.is_empty() {
+                return Err(anyhow!("The archive is empty or truncated"));
+            }
+            break;
+        }
+        if header.iter().all(|b| *b == 0) {
+            break;
+        }
+        let kind = header[156];
+        let name = match long_name.take() {
+            Some(name) if !matches!(kind, b'L' | b'x' | b'g') => name,
+            next => {
+                long_name = next;
+                header_name(&header)?
+            }
+        };
+        let mode = read_octal(&header[100..108])? as u32 & 0o7777;
+        let size = read_octal(&header[124..136])? as usize;
+        check(Path::new(&name), size as u64)?;
+        let mut content = vec![0; size];
+        decoder
+            .read_exact(&mut content)
+            .context(format!("The archive is truncated in {}", name))?;
+        let padding = (BLOCK - size % BLOCK) % BLOCK;
+        decoder.read_exact(&mut vec![0; padding])?;
+
+        match kind {
+            b'L' => long_name = Some(read_str(&content)?.to_string()),
+            b'x' => {
+                if let Some(path) = pax_path(&content)? {
+                    long_name = Some(path);
+                }
+            }
+            // Global headers, such as the commit id `git archive` records, name no entry
+            b'g' if pax_path(&content)?.is_some() => {
+                return Err(anyhow!(
+                    "Unsupported tar entry: a global pax path in {}",
+                    name
+                ))
+            }
+            b'g' => {}
+            _ => {
+                let path = safe_path(&name)?;
+                match kind {
+                    b'0' | 0 => entries.push(Entry::File {
+                        path,
+                        content,
+                        mode: (mode != 0).then_some(mode),
+                    }),
+                    b'5' => entries.push(Entry::Dir { path }),
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// The path in the name and prefix fields of a header.
+fn header_name(header: &[u8; BLOCK]) -> Result<String> {
+    let name = read_str(&header[..100])?;
+    let prefix = read_str(&header[345..500])?;
+    Ok(if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", prefix, name)
+    })
+}
+
+/// The `path` of pax extended header records, each of the form `<length> <key>=<value>\n`.
+fn pax_path(mut records: &[u8]) -> Result<Option<String>> {
+    let mut path = None;
+    while !records.is_empty() {
+        let invalid = || anyhow!("Invalid pax header in the archive");
+        let space = records
+            .iter()
+            .position(|b| *b == b' ')
+            .ok_or_else(invalid)?;
+        let length = std::str::from_utf8(&records[..space])
+            .ok()
+            .and_then(|length| length.parse::<usize>().ok())
+            .filter(|length| space < *length && *length <= records.len())
+            .ok_or_else(invalid)?;
+        let record = std::str::from_utf8(&records[space + 1..length]).map_err(|_| invalid())?;
+        if let Some(value) = record.strip_prefix("path=") {
+            path = Some(value.trim_end_matches('\n').to_string());
+        }
+        records = &records[length..];
+    }
+    Ok(path)
+}
+
+/// The path of an entry, which must stay inside the directory it is extracted to.
+fn safe_path(name: &str) -> Result<PathBuf> {
+    let path = Path::new(name);
+    if path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(anyhow!("Unsafe path in the archive: {}", name));
+    }
+    Ok(path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_round_trip() {
+        let long_name = format!("{}/{}.txt", "d".repeat(120), "f".repeat(90));
+        let mut builder = Builder::new(vec![]);
+        builder.append_file("pahcer/result.json", b"{}").unwrap();
+        builder
+            .append_file_with_mode(&long_name, &[7; 1000], 0o755)
+            .unwrap();
+        let bytes = builder.finish().unwrap();
+
+        let entries = read_entries(bytes.as_slice()).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                Entry::File {
+                    path: PathBuf::from("pahcer/result.json"),
+                    content: b"{}".to_vec(),
+                    mode: Some(0o644),
+                },
+                Entry::File {
+                    path: PathBuf::from(long_name),
+                    content: vec![7; 1000],
+                    mode: Some(0o755),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_long_non_ascii_paths() {
+        // The byte 156 falls inside a character of the file name
+        let long_name = format!("{}/{}.txt", "あ".repeat(40), "い".repeat(20));
+        let mut builder = Builder::new(vec![]);
+        builder.append_file(&long_name, b"x").unwrap();
+        let bytes = builder.finish().unwrap();
+
+        let entries = read_entries(bytes.as_slice()).unwrap();
+        assert_eq!(
+            entries,
+            vec![Entry::File {
+                path: PathBuf::from(long_name),
+                content: b"x".to_vec(),
+                mode: Some(0o644),
+            }]
+        );
+    }
+
+    /// A raw entry of the type `kind`, which [`Builder`] only writes regular files of.
+    fn raw_entry(kind: u8, name: &str, content: &[u8]) -> Vec<u8> {
+        let mut header = header(name, content.len() as u64, 0o644).unwrap();
+        header[156] = kind;
+        let mut entry = header.to_vec();
+        entry.extend_from_slice(content);
+        entry.resize(entry.len().div_ceil(BLOCK) * BLOCK, 0);
+        entry
+    }
+
+    fn gzip(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        for entry in entries {
+            encoder.write_all(entry).unwrap();
+        }
+        encoder.write_all(&[0; BLOCK * 2]).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn applies_long_names_to_the_next_entry() {
+        // Too long for the name and prefix fields, e.g. from GNU tar
+        let gnu_name = format!("{}/{}.txt", "d".repeat(200), "f".repeat(120));
+        let pax_name = format!("{}/{}.rs", "p".repeat(200), "q".repeat(120));
+        let pax_record = format!("path={}\n", pax_name);
+        // The length counts its own three digits and the space too
+        let pax_record = format!("{} {}", pax_record.len() + 4, pax_record);
+        let bytes = gzip(&[
+            raw_entry(
+                b'g',
+                "pax_global_header",
+                b"52 comment=0123456789abcdef0123456789abcdef01234567\n",
+            ),
+            raw_entry(b'L', "././@LongLink", format!("{}\0", gnu_name).as_bytes()),
+            raw_entry(b'0', &gnu_name[..100], b"gnu"),
+            raw_entry(b'x', "PaxHeaders/q.rs", pax_record.as_bytes()),
+            raw_entry(b'0', "q.rs", b"pax"),
+            raw_entry(b'0', "short.txt", b"short"),
+        ]);
+
+        let entries = read_entries(bytes.as_slice()).unwrap();
+        let paths = entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::File { path, content, .. } => (path.clone(), content.clone()),
+                Entry::Dir { path } => (path.clone(), vec![]),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                (PathBuf::from(gnu_name), b"gnu".to_vec()),
+                (PathBuf::from(pax_name), b"pax".to_vec()),
+                (PathBuf::from("short.txt"), b"short".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_paths_leaving_the_root() {
+        let mut builder = Builder::new(vec![]);
+        builder.append_file("../evil.txt", b"x").unwrap();
+        let bytes = builder.finish().unwrap();
+        assert!(read_entries(bytes.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,1095 @@
+mod annealing;
+mod chart;
+mod classes;
+mod evaluate;
+mod export;
+mod grid;
+mod objective;
+mod params;
+mod plot;
+mod pruning;
+mod race;
+mod random;
+mod report;
+pub(crate) mod stats;
+mod study;
+mod tpe;
+mod validation;
+
+use crate::config::Config;
+use crate::error::{ErrorKind, ResultExt};
+use crate::journal::{Journal, Operation};
+use crate::messages::msg;
+use crate::notify::{format_score, Event, Notification, Notifier, RunRecord, Severity, Source};
+use crate::output::{print_json, Output};
+use crate::overlay::Overlay;
+use annealing::{AnnealingConfig, Monitor};
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, Subcommand, ValueEnum};
+use classes::ClassConfig;
+use evaluate::{CaseResult, CommandEvaluator};
+use grid::GridSampler;
+use objective::{Aggregate, Scoring, TimePenalty};
+use pruning::PruningConfig;
+use race::{Race, RaceConfig};
+use random::RandomSampler;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use study::{Checkpoint, StudyState, StudyStore};
+use tpe::TpeSampler;
+use tracing::{info, warn};
+use validation::ValidationConfig;
+
+pub(crate) use evaluate::parse_score;
+pub(crate) use grid::{grid_point, grid_size};
+pub(crate) use params::{
+    format_assignment, injection, parse_param, validate_space, Assignment, ParamSpace, ParamValue,
+    ParamsVia, DEFAULT_PARAMS_FILE,
+};
+
+pub(crate) const DEFAULT_SCORE_REGEX: &str = r"(?m)^\s*Score\s*=\s*(?P<score>\d+)\s*$";
+const DEFAULT_RANDOM_TRIALS: usize = 20;
+/// Latest trials in the table of the summary posted when a study finishes
+const SUMMARY_TRIALS: usize = 50;
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub(crate) struct TuneArgs {
+    #[command(subcommand)]
+    command: Option<TuneCommands>,
+    /// Number of trials to run, overriding [tune] trials
+    #[arg(short, long)]
+    trials: Option<usize>,
+    /// Name of the study to record trials to, overriding [tune] study
+    #[arg(short, long)]
+    study: Option<String>,
+    /// Search strategy, overriding [tune] strategy
+    #[arg(long, value_enum)]
+    strategy: Option<Strategy>,
+    /// Stop starting new trials after this long, e.g. 90s, 30m or 2h
+    #[arg(long)]
+    time_budget: Option<String>,
+    /// Number of trials to run at once, overriding [tune] jobs
+    #[arg(short, long)]
+    jobs: Option<usize>,
+    /// Finish the interrupted or unfinished run of the study with its original settings
+    #[arg(long, conflicts_with_all = ["trials", "strategy", "time_budget"])]
+    resume: bool,
+}
+
+#[derive(Subcommand)]
+enum TuneCommands {
+    /// Write the best parameters of a study, or its decision table, as source code
+    Export(export::ExportArgs),
+    /// Plot the objective history, parameter importance and parallel coordinates of a study
+    Plot(plot::PlotArgs),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct TuneConfig {
+    pub(crate) command: Vec<String>,
+    #[serde(default)]
+    pub(crate) stdin: Option<String>,
+    #[serde(default = "default_score_regex")]
+    pub(crate) score_regex: String,
+    #[serde(default)]
+    pub(crate) start_seed: u64,
+    #[serde(default = "default_end_seed")]
+    pub(crate) end_seed: u64,
+    #[serde(default)]
+    pub(crate) trials: Option<usize>,
+    #[serde(default)]
+    pub(crate) strategy: Strategy,
+    #[serde(default)]
+    pub(crate) time_budget: Option<String>,
+    #[serde(default = "default_study")]
+    pub(crate) study: String,
+    #[serde(default)]
+    pub(crate) objective: Objective,
+    #[serde(default)]
+    pub(crate) aggregate: Aggregate,
+    /// pahcer result whose per-seed scores `aggregate = "relative"` compares with
+    #[serde(default)]
+    pub(crate) baseline: Option<String>,
+    #[serde(default)]
+    pub(crate) time_penalty: Option<TimePenalty>,
+    #[serde(default)]
+    pub(crate) sampler_seed: u64,
+    /// Number of trials to run at once
+    #[serde(default)]
+    pub(crate) jobs: Option<usize>,
+    #[serde(default)]
+    pub(crate) params: ParamSpace,
+    /// How the parameters reach the command: `env`, `args` or `file`
+    #[serde(default)]
+    pub(crate) params_via: ParamsVia,
+    /// File the parameters are written to with `params_via = "file"`, `params.txt` by default
+    #[serde(default)]
+    pub(crate) params_file: Option<PathBuf>,
+    #[serde(default)]
+    pub(crate) classes: Option<ClassConfig>,
+    #[serde(default)]
+    pub(crate) pruning: Option<PruningConfig>,
+    #[serde(default)]
+    pub(crate) race: Option<RaceConfig>,
+    #[serde(default)]
+    pub(crate) annealing: Option<AnnealingConfig>,
+    #[serde(default)]
+    pub(crate) validation: Option<ValidationConfig>,
+}
+
+fn default_score_regex() -> String {
+    DEFAULT_SCORE_REGEX.to_string()
+}
+
+fn default_end_seed() -> u64 {
+    100
+}
+
+fn default_study() -> String {
+    "default".to_string()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Strategy {
+    #[default]
+    Random,
+    Grid,
+    Tpe,
+    /// Race a pool of candidates, eliminating the significantly worse ones seed by seed
+    Race,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Objective {
+    #[default]
+    #[serde(alias = "Max")]
+    Max,
+    #[serde(alias = "Min")]
+    Min,
+}
+
+impl Objective {
+    pub(crate) fn is_better(&self, a: f64, b: f64) -> bool {
+        match self {
+            Objective::Max => a > b,
+            Objective::Min => a < b,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct Trial {
+    pub(crate) id: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) class: Option<usize>,
+    pub(crate) params: Assignment,
+    #[serde(default)]
+    pub(crate) space: ParamSpace,
+    pub(crate) cases: Vec<CaseResult>,
+    pub(crate) objective: f64,
+    /// Stopped early at a pruning rung, so `objective` only covers part of the seeds
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) pruned: bool,
+}
+
+pub(crate) trait Sampler {
+    /// Proposes the next point given the finished trials and the points of trials still running.
+    /// Returns `None` once the sampler has no more points to propose.
+    fn propose(
+        &mut self,
+        space: &ParamSpace,
+        history: &[Trial],
+        running: &[Assignment],
+    ) -> Option<Assignment>;
+}
+
+pub(crate) fn tune(args: TuneArgs, config: Config, output: Output) -> Result<()> {
+    let mut tune_config = config
+        .tune
+        .ok_or_else(|| ErrorKind::Config.error(msg!("tune.no_section")))?;
+    match args.command {
+        Some(TuneCommands::Export(export_args)) => {
+            return export::export(export_args, &tune_config, output)
+        }
+        Some(TuneCommands::Plot(plot_args)) => return plot::plot(plot_args, &tune_config, output),
+        None => {}
+    }
+    if let Some(annealing) = &tune_config.annealing {
+        annealing.validate().kind(ErrorKind::Config)?;
+        annealing
+            .add_params(&mut tune_config.params)
+            .kind(ErrorKind::Config)?;
+    }
+    validate_space(&tune_config.params).kind(ErrorKind::Config)?;
+    if let Some(pruning) = &tune_config.pruning {
+        pruning.validate().kind(ErrorKind::Config)?;
+    }
+    if let Some(validation) = &tune_config.validation {
+        validation.validate().kind(ErrorKind::Config)?;
+    }
+    let study = args.study.as_deref().unwrap_or(&tune_config.study);
+    let store = StudyStore::new(study);
+    let mut history = store.load()?;
+    let mut checkpoints = store.load_checkpoints()?;
+    let state = if args.resume {
+        let state = store
+            .load_state()?
+            .ok_or_else(|| anyhow!(msg!("tune.no_run_to_resume", study)))?;
+        if state.space != tune_config.params {
+            return Err(anyhow!(msg!("tune.params_changed", study)));
+        }
+        state
+    } else {
+        if !checkpoints.is_empty() {
+            warn!("{}", msg!("tune.discarding", checkpoints.len(), study));
+            checkpoints.clear();
+            store.save_checkpoints(&checkpoints)?;
+        }
+        new_state(&args, &tune_config, history.len())?
+    };
+    if state.start_seed >= state.end_seed {
+        return Err(anyhow!(msg!(
+            "tune.no_seeds",
+            state.start_seed,
+            state.end_seed
+        )));
+    }
+    store.save_state(&state)?;
+    let jobs = args.jobs.or(tune_config.jobs).unwrap_or(1);
+    if jobs == 0 {
+        return Err(anyhow!(msg!("tune.no_jobs")));
+    }
+
+    let objective = state.objective;
+    let seeds = state.start_seed..state.end_seed;
+    let scoring = Scoring::new(
+        objective,
+        tune_config.aggregate,
+        tune_config.baseline.as_deref(),
+        tune_config.time_penalty.clone(),
+    )?;
+    scoring.check_seeds(seeds.clone())?;
+    scoring.rescore(&mut history);
+    let groups = match &tune_config.classes {
+        Some(classes) => {
+            classes.validate().kind(ErrorKind::Config)?;
+            classes
+                .group_seeds(seeds.clone(), &config.paths.inputs_dir)?
+                .into_iter()
+                .map(|(class, seeds)| (Some(class), seeds))
+                .collect()
+        }
+        None => vec![(None, seeds.clone().collect::<Vec<_>>())],
+    };
+    // Seeds held out for validation are split off per class, so every class is validated
+    let groups = groups
+        .into_iter()
+        .map(|(class, seeds)| match &tune_config.validation {
+            Some(validation) => {
+                let (tuning, held_out) = validation.split(&seeds, state.sampler_seed);
+                (class, tuning, held_out)
+            }
+            None => (class, seeds, vec![]),
+        })
+        .collect::<Vec<_>>();
+
+    let mut evaluator = CommandEvaluator::new(
+        &tune_config.command,
+        tune_config.stdin.as_deref(),
+        &tune_config.score_regex,
+        &tune_config.params,
+    )?
+    .with_params_via(tune_config.params_via, tune_config.params_file.as_deref());
+    if tune_config.params_via == ParamsVia::File && jobs > 1 {
+        return Err(ErrorKind::Config.error(msg!("tune.params_file_jobs")));
+    }
+    if let Some(annealing) = &tune_config.annealing {
+        evaluator = evaluator.with_monitor(Monitor::new(annealing, objective)?);
+    }
+    let time_budget = state
+        .time_budget
+        .as_deref()
+        .map(parse_duration)
+        .transpose()?;
+    let race = tune_config.race.clone().unwrap_or_default();
+    if state.strategy == Strategy::Race {
+        race.validate().kind(ErrorKind::Config)?;
+    }
+    let notifier = Notifier::new(config.notify.as_ref(), Source::Tune)?;
+    let overlay = config.overlay.as_ref().map(Overlay::start).transpose()?;
+    let tuner = Tuner {
+        evaluator: &evaluator,
+        notifier: &notifier,
+        overlay: overlay.as_ref(),
+        scoring: &scoring,
+        pruning: tune_config.pruning.as_ref(),
+        race: &race,
+        jobs,
+    };
+
+    if args.resume {
+        info!("{}", msg!("tune.resuming", study));
+    }
+    info!("{}", msg!("tune.start", study, seeds.start, seeds.end));
+    let held_out = groups
+        .iter()
+        .map(|(_, _, held_out)| held_out.len())
+        .sum::<usize>();
+    if held_out > 0 {
+        info!("{}", msg!("tune.holding_out", held_out));
+    }
+    let best_before = best_trial(&history, objective).map(|best| best.objective);
+    let mut study = Study {
+        name: study.to_string(),
+        elapsed_before: Duration::from_secs_f64(state.elapsed_secs),
+        start: Instant::now(),
+        store,
+        state,
+        history,
+        checkpoints,
+    };
+    for (i, (class, seeds, _)) in groups.iter().enumerate() {
+        if let (Some(class), Some(classes)) = (class, &tune_config.classes) {
+            info!("{}", msg!("tune.class", classes.label(*class), seeds.len()));
+        }
+        let done = study
+            .history
+            .iter()
+            .filter(|trial| trial.class == *class && trial.id >= study.state.first_trial)
+            .count();
+        let trials = study
+            .state
+            .trials
+            .map_or(usize::MAX, |trials| trials.saturating_sub(done));
+        // Each class gets an equal share of the time budget
+        let deadline = time_budget.map(|budget| budget * (i + 1) as u32 / groups.len() as u32);
+        let class_run = ClassRun {
+            class: *class,
+            seeds,
+            trials,
+            deadline,
+        };
+        match study.state.strategy {
+            Strategy::Race => run_race(&mut study, &tuner, &class_run)?,
+            _ => run_trials(&mut study, &tuner, &class_run)?,
+        }
+    }
+
+    let Study {
+        name,
+        store,
+        state,
+        history,
+        ..
+    } = study;
+    if !history.is_empty() {
+        if !output.is_json() {
+            print!("{}", report::format_table(&history, objective));
+        }
+        report::write_csv(&store.csv_path(), &history)?;
+    }
+    let mut validation_rows = vec![];
+    if let Some(validation) = &tune_config.validation {
+        let rows = &mut validation_rows;
+        for (class, _, held_out) in &groups {
+            let class_history = history
+                .iter()
+                .filter(|trial| trial.class == *class)
+                .cloned()
+                .collect::<Vec<_>>();
+            if held_out.is_empty() || class_history.is_empty() {
+                continue;
+            }
+            match (class, &tune_config.classes) {
+                (Some(class), Some(classes)) => info!(
+                    "{}",
+                    msg!(
+                        "tune.validating_class",
+                        classes.label(*class),
+                        held_out.len()
+                    )
+                ),
+                _ => info!("{}", msg!("tune.validating", held_out.len())),
+            }
+            let class_rows = validation::validate(
+                validation,
+                &evaluator,
+                &scoring,
+                jobs,
+                &class_history,
+                held_out,
+            );
+            if !output.is_json() {
+                print!("{}", validation::format_validation(&class_rows));
+            }
+            for warning in
+                validation::overfitting_warnings(&class_rows, objective, validation.max_gap)
+            {
+                warn!("{}", warning);
+            }
+            rows.extend(class_rows);
+        }
+        let path = store.validation_path();
+        std::fs::write(&path, serde_json::to_string_pretty(rows)?).context(format!(
+            "Failed to write validation results: {}",
+            path.display()
+        ))?;
+    }
+    let finished = history.len().saturating_sub(state.first_trial);
+    // Objectives of different classes are not comparable
+    let best = best_trial(&history, objective).filter(|_| tune_config.classes.is_none());
+    let mut body = match best {
+        Some(best) => format!(
+            "{} trial(s), best trial #{} ({}), score {}",
+            finished,
+            best.id,
+            format_assignment(&best.params),
+            format_score(best.objective, best_before)
+        ),
+        None => format!("{} trial(s)", finished),
+    };
+    let failing = history
+        .get(state.first_trial..)
+        .unwrap_or_default()
+        .iter()
+        .filter(|trial| {
+            trial
+                .cases
+                .iter()
+                .any(|case| !case.error_message.is_empty())
+        })
+        .count();
+    if failing > 0 {
+        body.push_str(&format!("; {} trial(s) had failed cases", failing));
+    }
+    let chart = (tune_config.classes.is_none() && !history.is_empty())
+        .then(|| plot::history_chart(&history.iter().collect::<Vec<_>>(), objective));
+    notifier.send(&Notification {
+        event: Event::Finished,
+        severity: if failing > 0 {
+            Severity::Warning
+        } else {
+            Severity::Info
+        },
+        title: format!("Tuning study {} finished", name),
+        body,
+        chart,
+        details: Some(report::format_markdown(&history, objective, SUMMARY_TRIALS)),
+        summary: Some(json!({
+            "study": name,
+            "trials": finished,
+            "failing_trials": failing,
+            "best": best.map(|best| run_record(&name, best)),
+        })),
+    });
+    let decision_table = match &tune_config.classes {
+        Some(classes) => {
+            let table = classes::decision_table(classes, &history, objective);
+            if !output.is_json() {
+                print!("{}", classes::format_decision_table(&table));
+            }
+            let path = store.decision_table_path();
+            std::fs::write(&path, serde_json::to_string_pretty(&table)?).context(format!(
+                "Failed to write decision table: {}",
+                path.display()
+            ))?;
+            Some(table)
+        }
+        None => {
+            match best_trial(&history, objective) {
+                Some(best) => info!(
+                    "{}",
+                    msg!(
+                        "tune.best",
+                        best.id,
+                        format!("{:.2}", best.objective),
+                        format_assignment(&best.params)
+                    )
+                ),
+                None => info!("{}", msg!("tune.no_trials")),
+            }
+            None
+        }
+    };
+    Journal::open().append(Operation::Tune {
+        study: name.clone(),
+        trials: history.len(),
+        best: best_trial(&history, objective).map(|best| best.objective),
+    })?;
+    if output.is_json() {
+        print_json(&TuneSummary {
+            study: &name,
+            objective,
+            best: best_trial(&history, objective).filter(|_| tune_config.classes.is_none()),
+            trials: &history,
+            validation: &validation_rows,
+            decision_table: decision_table.as_ref(),
+        })?;
+    }
+    Ok(())
+}
+
+/// What `ahc tune --output json` prints.
+#[derive(Serialize)]
+struct TuneSummary<'a> {
+    study: &'a str,
+    objective: Objective,
+    /// Best trial, unless the trials of different classes are not comparable
+    best: Option<&'a Trial>,
+    trials: &'a [Trial],
+    validation: &'a [validation::ValidationRow],
+    decision_table: Option<&'a classes::DecisionTable>,
+}
+
+/// What `ahc tune` evaluates trials with.
+struct Tuner<'a> {
+    evaluator: &'a CommandEvaluator,
+    notifier: &'a Notifier,
+    overlay: Option<&'a Overlay>,
+    scoring: &'a Scoring,
+    pruning: Option<&'a PruningConfig>,
+    race: &'a RaceConfig,
+    jobs: usize,
+}
+
+/// The trials one class of seeds still has to run.
+struct ClassRun<'a> {
+    class: Option<usize>,
+    seeds: &'a [u64],
+    trials: usize,
+    /// Time since the study started after which no more trials start
+    deadline: Option<Duration>,
+}
+
+/// The study being tuned, with everything recorded so far.
+struct Study {
+    name: String,
+    store: StudyStore,
+    state: StudyState,
+    history: Vec<Trial>,
+    /// Unfinished trials of the classes which have not been tuned yet
+    checkpoints: Vec<Checkpoint>,
+    elapsed_before: Duration,
+    start: Instant,
+}
+
+impl Study {
+    fn out_of_time(&self, deadline: Option<Duration>) -> bool {
+        let elapsed = self.elapsed_before + self.start.elapsed();
+        deadline.is_some_and(|deadline| elapsed >= deadline)
+    }
+
+    fn class_history(&self, class: Option<usize>) -> Vec<Trial> {
+        self.history
+            .iter()
+            .filter(|trial| trial.class == class)
+            .cloned()
+            .collect()
+    }
+
+    fn record(&mut self, tuner: &Tuner, checkpoint: Checkpoint, pruned: bool) -> Result<Trial> {
+        let trial = Trial {
+            id: self.history.len(),
+            class: checkpoint.class,
+            objective: tuner.scoring.objective(&checkpoint.cases),
+            params: checkpoint.params,
+            space: self.state.space.clone(),
+            cases: checkpoint.cases,
+            pruned,
+        };
+        self.store.append(&trial)?;
+        self.state.elapsed_secs = (self.elapsed_before + self.start.elapsed()).as_secs_f64();
+        self.store.save_state(&self.state)?;
+        report_trial(&trial);
+        tuner.notifier.record_run(&run_record(&self.name, &trial));
+        let objective = tuner.scoring.objective;
+        let previous = self
+            .history
+            .iter()
+            .filter(|other| other.class == trial.class && !other.pruned)
+            .map(|other| other.objective)
+            .reduce(|best, other| {
+                if objective.is_better(other, best) {
+                    other
+                } else {
+                    best
+                }
+            });
+        if let Some(previous) = previous
+            .filter(|previous| !trial.pruned && objective.is_better(trial.objective, *previous))
+        {
+            let class = trial
+                .class
+                .map(|class| format!(" of class {}", class))
+                .unwrap_or_default();
+            tuner.notifier.send(&Notification {
+                event: Event::NewBest,
+                severity: Severity::Info,
+                title: format!("New best in study {}", self.name),
+                body: format!(
+                    "Trial #{}{} ({}), score {}",
+                    trial.id,
+                    class,
+                    format_assignment(&trial.params),
+                    format_score(trial.objective, Some(previous))
+                ),
+                chart: None,
+                details: None,
+                summary: serde_json::to_value(run_record(&self.name, &trial)).ok(),
+            });
+        }
+        if let (Some(overlay), false) = (tuner.overlay, trial.pruned) {
+            let improved =
+                previous.filter(|previous| objective.is_better(trial.objective, *previous));
+            let best = match previous {
+                Some(previous) if improved.is_none() => previous,
+                _ => trial.objective,
+            };
+            overlay.update(
+                trial.objective,
+                best,
+                improved.map(|previous| trial.objective - previous),
+            );
+        }
+        self.history.push(trial.clone());
+        Ok(trial)
+    }
+
+    fn save_checkpoints<'a>(&self, running: impl Iterator<Item = &'a Checkpoint>) -> Result<()> {
+        let mut unfinished = running.cloned().collect::<Vec<_>>();
+        unfinished.extend(self.checkpoints.iter().cloned());
+        self.store.save_checkpoints(&unfinished)
+    }
+}
+
+/// Runs trials proposed by the sampler of the study, up to `jobs` at a time.
+fn run_trials(study: &mut Study, tuner: &Tuner, run: &ClassRun) -> Result<()> {
+    let mut sampler = build_sampler(&study.state, run.class)?;
+    let mut class_history = study.class_history(run.class);
+    let mut resumable =
+        take_resumable(&mut study.checkpoints, run.class, run.seeds, &class_history);
+    let seeds = run.seeds;
+
+    std::thread::scope(|scope| -> Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        let mut running = BTreeMap::<usize, Checkpoint>::new();
+        let mut started = 0;
+        let mut exhausted = false;
+        loop {
+            while running.len() < tuner.jobs && started < run.trials && !exhausted {
+                if study.out_of_time(run.deadline) {
+                    info!("{}", msg!("tune.out_of_time"));
+                    exhausted = true;
+                    break;
+                }
+                let checkpoint = match resumable.pop() {
+                    Some(checkpoint) => {
+                        info!(
+                            "{}",
+                            msg!(
+                                "tune.resuming_trial",
+                                checkpoint.cases.len(),
+                                format_assignment(&checkpoint.params)
+                            )
+                        );
+                        checkpoint
+                    }
+                    None => {
+                        let in_flight = running
+                            .values()
+                            .map(|checkpoint| checkpoint.params.clone())
+                            .collect::<Vec<_>>();
+                        match sampler.propose(&study.state.space, &class_history, &in_flight) {
+                            Some(params) => Checkpoint {
+                                class: run.class,
+                                params,
+                                cases: vec![],
+                            },
+                            None => {
+                                exhausted = true;
+                                break;
+                            }
+                        }
+                    }
+                };
+
+                let slot = started;
+                let sender = sender.clone();
+                let params = checkpoint.params.clone();
+                let mut cases = checkpoint.cases.clone();
+                let trials_so_far = class_history.clone();
+                let (evaluator, pruning, scoring) = (tuner.evaluator, tuner.pruning, tuner.scoring);
+                scope.spawn(move || {
+                    let best = best_trial(&trials_so_far, scoring.objective);
+                    for seed in &seeds[cases.len()..] {
+                        let reference =
+                            best.and_then(|best| best.cases.iter().find(|case| case.seed == *seed));
+                        let case = evaluator.evaluate_against(&params, *seed, reference);
+                        cases.push(case.clone());
+                        if sender.send(Progress::Case(slot, case)).is_err() {
+                            return;
+                        }
+                        let pruned = cases.len() < seeds.len()
+                            && pruning.is_some_and(|pruning| {
+                                pruning.should_prune(&cases, &trials_so_far, scoring)
+                            });
+                        if pruned {
+                            let _ = sender.send(Progress::Finished(slot, true));
+                            return;
+                        }
+                    }
+                    let _ = sender.send(Progress::Finished(slot, false));
+                });
+                running.insert(slot, checkpoint);
+                started += 1;
+            }
+            if running.is_empty() {
+                return Ok(());
+            }
+
+            match receiver.recv()? {
+                Progress::Case(slot, case) => {
+                    if let Some(checkpoint) = running.get_mut(&slot) {
+                        checkpoint.cases.push(case);
+                    }
+                }
+                Progress::Finished(slot, pruned) => {
+                    if let Some(checkpoint) = running.remove(&slot) {
+                        class_history.push(study.record(tuner, checkpoint, pruned)?);
+                    }
+                }
+            }
+            study.save_checkpoints(running.values().chain(&resumable))?;
+        }
+    })
+}
+
+/// Races a pool of candidates over the seeds, or continues the race of an interrupted run.
+fn run_race(study: &mut Study, tuner: &Tuner, run: &ClassRun) -> Result<()> {
+    let class_history = study.class_history(run.class);
+    let mut pool = take_resumable(&mut study.checkpoints, run.class, run.seeds, &class_history);
+    pool.reverse();
+    if !pool.is_empty() {
+        info!("{}", msg!("tune.resuming_race", pool.len()));
+    } else if run.trials > 0 {
+        // A race needs a pool of a known size even when only a time budget is set
+        let size = match study.state.trials {
+            Some(_) => run.trials,
+            None => DEFAULT_RANDOM_TRIALS,
+        };
+        let seed = class_seed(&study.state, run.class);
+        pool = race::sample_pool(&study.state.space, seed, &class_history, size)
+            .into_iter()
+            .map(|params| Checkpoint {
+                class: run.class,
+                params,
+                cases: vec![],
+            })
+            .collect();
+        info!("{}", msg!("tune.racing", pool.len()));
+    }
+    if pool.is_empty() {
+        return Ok(());
+    }
+
+    let race = Race {
+        config: tuner.race,
+        evaluator: tuner.evaluator,
+        scoring: tuner.scoring,
+        jobs: tuner.jobs,
+    };
+    let (elapsed_before, start, deadline) = (study.elapsed_before, study.start, run.deadline);
+    let out_of_time =
+        move || deadline.is_some_and(|deadline| elapsed_before + start.elapsed() >= deadline);
+    race.run(pool, run.seeds, out_of_time, |racing, finished| {
+        for (checkpoint, eliminated) in finished {
+            study.record(tuner, checkpoint, eliminated)?;
+        }
+        study.save_checkpoints(racing.iter())
+    })
+}
+
+/// Reported by the threads evaluating trials.
+enum Progress {
+    Case(usize, CaseResult),
+    /// The trial has finished, or was pruned if `true`
+    Finished(usize, bool),
+}
+
+/// Removes the checkpoints of a class from `checkpoints`, keeping those which can be continued:
+/// cases from the start of `seeds` of a trial which has not been recorded since.
+fn take_resumable(
+    checkpoints: &mut Vec<Checkpoint>,
+    class: Option<usize>,
+    seeds: &[u64],
+    history: &[Trial],
+) -> Vec<Checkpoint> {
+    let (mut resumable, rest) = std::mem::take(checkpoints)
+        .into_iter()
+        .partition::<Vec<_>, _>(|checkpoint| checkpoint.class == class);
+    *checkpoints = rest;
+    resumable.retain(|checkpoint| {
+        let continues_seeds = checkpoint.cases.len() <= seeds.len()
+            && checkpoint
+                .cases
+                .iter()
+                .zip(seeds)
+                .all(|(case, seed)| case.seed == *seed);
+        let recorded = history.iter().any(|trial| {
+            trial.params == checkpoint.params && trial.cases.starts_with(&checkpoint.cases)
+        });
+        continues_seeds && !recorded
+    });
+    // Popped from the back, so the first checkpoint resumes first
+    resumable.reverse();
+    resumable
+}
+
+fn new_state(args: &TuneArgs, tune_config: &TuneConfig, first_trial: usize) -> Result<StudyState> {
+    let strategy = args.strategy.unwrap_or(tune_config.strategy);
+    let time_budget = args.time_budget.clone().or(tune_config.time_budget.clone());
+    // A time budget alone lets the search run until the budget is spent
+    let trials = match (args.trials.or(tune_config.trials), &time_budget) {
+        (Some(trials), _) => Some(trials),
+        (None, Some(_)) if matches!(strategy, Strategy::Random | Strategy::Tpe) => None,
+        (None, _) if strategy == Strategy::Grid => Some(grid::grid_size(&tune_config.params)),
+        (None, _) => Some(DEFAULT_RANDOM_TRIALS),
+    };
+    Ok(StudyState {
+        strategy,
+        objective: tune_config.objective,
+        sampler_seed: tune_config.sampler_seed,
+        start_seed: tune_config.start_seed,
+        end_seed: tune_config.end_seed,
+        space: tune_config.params.clone(),
+        trials,
+        time_budget,
+        first_trial,
+        elapsed_secs: 0.0,
+    })
+}
+
+// Classes are searched independently, so each gets its own stream of proposals
+fn class_seed(state: &StudyState, class: Option<usize>) -> u64 {
+    state
+        .sampler_seed
+        .wrapping_add(class.map_or(0, |class| class as u64 + 1))
+}
+
+fn build_sampler(state: &StudyState, class: Option<usize>) -> Result<Box<dyn Sampler>> {
+    let seed = class_seed(state, class);
+    Ok(match state.strategy {
+        Strategy::Random => Box::new(RandomSampler::new(seed)),
+        Strategy::Tpe => Box::new(TpeSampler::new(seed, state.objective)),
+        Strategy::Grid => Box::new(GridSampler::new(&state.space)?),
+        Strategy::Race => unreachable!("races draw their own pool of candidates"),
+    })
+}
+
+fn parse_duration(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(i) => text.split_at(i),
+        None => (text, "s"),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow!(msg!("tune.invalid_duration", text)))?;
+    let seconds = match unit {
+        "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(anyhow!(msg!("tune.duration_unit", text))),
+    };
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// Names of the studies recorded in the project, for shell completion.
+pub(crate) fn study_names() -> Vec<String> {
+    StudyStore::list()
+}
+
+/// Loads the trials of a study, scored with the objective it was run with and the current
+/// [tune] scoring settings.
+fn load_scored_history(
+    store: &StudyStore,
+    tune_config: &TuneConfig,
+) -> Result<(Vec<Trial>, Scoring)> {
+    let mut history = store.load()?;
+    let objective = match store.load_state()? {
+        Some(state) => state.objective,
+        None => tune_config.objective,
+    };
+    let scoring = Scoring::new(
+        objective,
+        tune_config.aggregate,
+        tune_config.baseline.as_deref(),
+        tune_config.time_penalty.clone(),
+    )?;
+    scoring.rescore(&mut history);
+    Ok((history, scoring))
+}
+
+fn report_trial(trial: &Trial) {
+    if trial.pruned {
+        info!(
+            "{}",
+            msg!(
+                "tune.trial_pruned",
+                trial.id,
+                trial.cases.len(),
+                format!("{:.2}", trial.objective),
+                format_assignment(&trial.params)
+            )
+        );
+    } else {
+        info!(
+            "{}",
+            msg!(
+                "tune.trial",
+                trial.id,
+                format!("{:.2}", trial.objective),
+                format_assignment(&trial.params)
+            )
+        );
+    }
+    let errors = trial
+        .cases
+        .iter()
+        .filter(|case| !case.error_message.is_empty())
+        .collect::<Vec<_>>();
+    if let Some(first) = errors.first() {
+        warn!(
+            "{}",
+            msg!(
+                "tune.failed_cases",
+                errors.len(),
+                first.seed,
+                first.error_message
+            )
+        );
+    }
+    let stopped = trial
+        .cases
+        .iter()
+        .filter(|case| {
+            case.curve
+                .as_ref()
+                .is_some_and(|curve| curve.stopped_at.is_some())
+        })
+        .count();
+    if stopped > 0 {
+        info!("{}", msg!("tune.stopped_cases", stopped));
+    }
+}
+
+fn run_record(name: &str, trial: &Trial) -> RunRecord {
+    let scores = trial.cases.iter().map(|case| case.score);
+    RunRecord {
+        name: name.to_string(),
+        id: trial.id,
+        params: format_assignment(&trial.params),
+        objective: trial.objective,
+        mean: scores.clone().sum::<u64>() as f64 / trial.cases.len().max(1) as f64,
+        min: scores.clone().min().unwrap_or(0),
+        max: scores.max().unwrap_or(0),
+        failed: trial
+            .cases
+            .iter()
+            .filter(|case| !case.error_message.is_empty())
+            .count(),
+    }
+}
+
+fn best_trial(trials: &[Trial], objective: Objective) -> Option<&Trial> {
+    let completed = trials.iter().filter(|trial| !trial.pruned);
+    completed.fold(None, |best, trial| match best {
+        Some(best) if !objective.is_better(trial.objective, best.objective) => Some(best),
+        _ => Some(trial),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use params::ParamValue;
+
+    fn trial(id: usize, objective: f64) -> Trial {
+        let mut params = Assignment::new();
+        params.insert("temp".to_string(), ParamValue::Float(id as f64));
+        Trial {
+            id,
+            pruned: false,
+            class: None,
+            space: ParamSpace::new(),
+            params,
+            cases: vec![],
+            objective,
+        }
+    }
+
+    #[test]
+    fn best_trial_respects_objective() {
+        let trials = vec![trial(0, 10.0), trial(1, 30.0), trial(2, 20.0)];
+        assert_eq!(best_trial(&trials, Objective::Max).unwrap().id, 1);
+        assert_eq!(best_trial(&trials, Objective::Min).unwrap().id, 0);
+        assert!(best_trial(&[], Objective::Max).is_none());
+
+        let mut trials = trials;
+        trials[1].pruned = true;
+        assert_eq!(best_trial(&trials, Objective::Max).unwrap().id, 2);
+    }
+
+    #[test]
+    fn take_resumable_keeps_unrecorded_checkpoints_of_the_class() {
+        let case = |seed| CaseResult {
+            seed,
+            score: 1,
+            execution_time: 0.0,
+            error_message: String::new(),
+            curve: None,
+        };
+        let checkpoint = |class, temp: f64, seeds: &[u64]| Checkpoint {
+            class,
+            params: [("temp".to_string(), ParamValue::Float(temp))].into(),
+            cases: seeds.iter().map(|seed| case(*seed)).collect(),
+        };
+        let mut recorded = trial(0, 1.0);
+        recorded.cases = vec![case(0), case(1)];
+        let mut checkpoints = vec![
+            checkpoint(None, 0.0, &[0]),
+            checkpoint(None, 5.0, &[0]),
+            checkpoint(None, 6.0, &[1]),
+            checkpoint(Some(1), 7.0, &[0]),
+            checkpoint(None, 8.0, &[]),
+        ];
+
+        let resumable = take_resumable(&mut checkpoints, None, &[0, 1], &[recorded]);
+
+        let temps = resumable
+            .iter()
+            .rev()
+            .map(|checkpoint| checkpoint.params["temp"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(temps, vec![ParamValue::Float(5.0), ParamValue::Float(8.0)]);
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(checkpoints[0].class, Some(1));
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("1.5m").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert!(parse_duration("2d").is_err());
+        assert!(parse_duration("m").is_err());
+    }
+}
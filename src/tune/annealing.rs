@@ -0,0 +1,348 @@
+use super::evaluate::CaseResult;
+use super::params::{ParamSpace, ParamSpec, ParamType, ParamValue, RangeSpec, Scale};
+use super::Objective;
+use crate::messages::msg;
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+
+const DEFAULT_PROGRESS_REGEX: &str =
+    r"^\s*t\s*=\s*(?P<time>[0-9.]+)\s*,?\s*score\s*=\s*(?P<score>\d+)\s*$";
+
+/// Settings for tuning a simulated-annealing schedule. The solver reports its best score so far
+/// as it runs, and cases falling behind the best trial's curve are stopped at the cutoffs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct AnnealingConfig {
+    /// Time limit of the solver in seconds, which the reported times are relative to
+    pub(crate) time_limit: f64,
+    /// Regex with named groups `time` (seconds) and `score` matching the solver's progress lines
+    #[serde(default = "default_progress_regex")]
+    pub(crate) progress_regex: String,
+    /// Fractions of the time limit at which a case is compared with the best trial's curve
+    #[serde(default = "default_cutoffs")]
+    pub(crate) cutoffs: Vec<f64>,
+    /// Relative gap to the best trial's curve which a case may fall behind before it is stopped
+    #[serde(default = "default_margin")]
+    pub(crate) margin: f64,
+    /// Range of the `start_temp` parameter, tuned on a log scale
+    #[serde(default)]
+    pub(crate) start_temp: Option<[f64; 2]>,
+    /// Range of the `end_temp` parameter, tuned on a log scale
+    #[serde(default)]
+    pub(crate) end_temp: Option<[f64; 2]>,
+    /// Choices of the `cooling` parameter, e.g. ["exponential", "linear"]
+    #[serde(default)]
+    pub(crate) cooling: Option<Vec<String>>,
+}
+
+fn default_progress_regex() -> String {
+    DEFAULT_PROGRESS_REGEX.to_string()
+}
+
+fn default_cutoffs() -> Vec<f64> {
+    vec![0.3, 0.6]
+}
+
+fn default_margin() -> f64 {
+    0.05
+}
+
+impl AnnealingConfig {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.time_limit <= 0.0 {
+            return Err(anyhow!(msg!("tune.annealing_time_limit")));
+        }
+        if self
+            .cutoffs
+            .iter()
+            .any(|cutoff| !(0.0..1.0).contains(cutoff) || *cutoff == 0.0)
+            || self.cutoffs.windows(2).any(|pair| pair[0] >= pair[1])
+        {
+            return Err(anyhow!(msg!("tune.annealing_cutoffs")));
+        }
+        if self.margin < 0.0 {
+            return Err(anyhow!(msg!("tune.annealing_margin")));
+        }
+        for (name, range) in [("start_temp", self.start_temp), ("end_temp", self.end_temp)] {
+            if let Some([low, high]) = range {
+                if low <= 0.0 || low > high {
+                    return Err(anyhow!(msg!("tune.annealing_temp", name)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the schedule parameters to the tuned parameters.
+    pub(crate) fn add_params(&self, space: &mut ParamSpace) -> Result<()> {
+        let temps = [("start_temp", self.start_temp), ("end_temp", self.end_temp)];
+        let mut params = temps
+            .into_iter()
+            .filter_map(|(name, range)| {
+                let [low, high] = range?;
+                let spec = ParamSpec::range(RangeSpec {
+                    low,
+                    high,
+                    kind: ParamType::Float,
+                    scale: Scale::Log,
+                });
+                Some((name, spec))
+            })
+            .collect::<Vec<_>>();
+        if let Some(cooling) = &self.cooling {
+            let values = cooling.iter().cloned().map(ParamValue::Str).collect();
+            params.push(("cooling", ParamSpec::values(values)));
+        }
+        for (name, spec) in params {
+            if space.contains_key(name) {
+                return Err(anyhow!(msg!("tune.param_twice", name)));
+            }
+            space.insert(name.to_string(), spec);
+        }
+        Ok(())
+    }
+}
+
+/// The score-vs-time curve of a case at the cutoffs, as far as the case ran.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Curve {
+    /// Best score reported by each cutoff the case reached, as (fraction of time limit, score)
+    pub(crate) marks: Vec<(f64, u64)>,
+    /// Cutoff at which the case was stopped, its score then being projected from the reference
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) stopped_at: Option<f64>,
+}
+
+pub(crate) struct Monitored {
+    /// Everything the solver printed
+    pub(crate) output: String,
+    pub(crate) curve: Curve,
+    /// Projected final score of a stopped case
+    pub(crate) projected: Option<u64>,
+    /// Whether the solver exited successfully; a stopped case counts as successful
+    pub(crate) success: bool,
+}
+
+/// Watches the progress lines of solver runs.
+pub(crate) struct Monitor {
+    progress_regex: Regex,
+    time_limit: f64,
+    cutoffs: Vec<f64>,
+    margin: f64,
+    objective: Objective,
+}
+
+impl Monitor {
+    pub(crate) fn new(config: &AnnealingConfig, objective: Objective) -> Result<Self> {
+        let progress_regex = Regex::new(&config.progress_regex).context(format!(
+            "Failed to parse progress regex: {}",
+            config.progress_regex
+        ))?;
+        let names = progress_regex.capture_names().flatten().collect::<Vec<_>>();
+        if !names.contains(&"time") || !names.contains(&"score") {
+            return Err(anyhow!(msg!("tune.no_progress_groups")));
+        }
+        Ok(Monitor {
+            progress_regex,
+            time_limit: config.time_limit,
+            cutoffs: config.cutoffs.clone(),
+            margin: config.margin,
+            objective,
+        })
+    }
+
+    fn parse(&self, line: &str) -> Option<(f64, u64)> {
+        let captures = self.progress_regex.captures(line)?;
+        let time = captures["time"].parse().ok()?;
+        let score = captures["score"].parse().ok()?;
+        Some((time, score))
+    }
+
+    /// The projected final score if a case with `score` at `cutoff` is behind the reference.
+    fn projection(&self, cutoff: f64, score: u64, reference: &CaseResult) -> Option<u64> {
+        let curve = reference.curve.as_ref()?;
+        if curve.stopped_at.is_some() {
+            return None;
+        }
+        let (_, mark) = curve
+            .marks
+            .iter()
+            .find(|(at, _)| (at - cutoff).abs() < 1e-9)?;
+        let (score, mark) = (score as f64, *mark as f64);
+        let behind = match self.objective {
+            Objective::Max => score < mark * (1.0 - self.margin),
+            Objective::Min => score > mark * (1.0 + self.margin),
+        };
+        if !behind {
+            return None;
+        }
+        let ratio = if mark > 0.0 { score / mark } else { 1.0 };
+        Some((reference.score as f64 * ratio).round() as u64)
+    }
+
+    /// Runs the solver, stopping it at a cutoff where it is behind `reference`, the case of the
+    /// best trial so far on the same seed.
+    pub(crate) fn run(
+        &self,
+        mut command: Command,
+        reference: Option<&CaseResult>,
+    ) -> Result<Monitored> {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        // Own process group, so that stopping a solver started through a shell stops the solver
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = command.spawn().context("Failed to run command")?;
+        let (sender, receiver) = mpsc::channel();
+        for pipe in [
+            child
+                .stdout
+                .take()
+                .map(|pipe| Box::new(pipe) as Box<dyn Read + Send>),
+            child
+                .stderr
+                .take()
+                .map(|pipe| Box::new(pipe) as Box<dyn Read + Send>),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(pipe).lines() {
+                    let Ok(line) = line else { break };
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let mut output = String::new();
+        let mut marks = vec![];
+        let mut best = None;
+        for line in receiver {
+            output.push_str(&line);
+            output.push('\n');
+            let Some((time, score)) = self.parse(&line) else {
+                continue;
+            };
+            best = Some(match best {
+                Some(best) if !self.objective.is_better(score as f64, best as f64) => best,
+                _ => score,
+            });
+            while let Some(cutoff) = self.cutoffs.get(marks.len()) {
+                if time < cutoff * self.time_limit {
+                    break;
+                }
+                let score = best.unwrap_or(score);
+                marks.push((*cutoff, score));
+                if let Some(projected) = reference.and_then(|r| self.projection(*cutoff, score, r))
+                {
+                    stop(&mut child);
+                    let curve = Curve {
+                        marks,
+                        stopped_at: Some(*cutoff),
+                    };
+                    return Ok(Monitored {
+                        output,
+                        curve,
+                        projected: Some(projected),
+                        success: true,
+                    });
+                }
+            }
+        }
+        let status = child.wait().context("Failed to wait for command")?;
+        Ok(Monitored {
+            output,
+            curve: Curve {
+                marks,
+                stopped_at: None,
+            },
+            projected: None,
+            success: status.success(),
+        })
+    }
+}
+
+fn stop(child: &mut Child) {
+    #[cfg(unix)]
+    let _ = Command::new("kill")
+        .args(["-KILL", "--", &format!("-{}", child.id())])
+        .status();
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AnnealingConfig {
+        toml::from_str("time_limit = 2.0\ncutoffs = [0.5]\nmargin = 0.1").unwrap()
+    }
+
+    fn reference(marks: Vec<(f64, u64)>, score: u64) -> CaseResult {
+        CaseResult {
+            seed: 0,
+            score,
+            execution_time: 2.0,
+            error_message: String::new(),
+            curve: Some(Curve {
+                marks,
+                stopped_at: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn projects_cases_behind_the_reference() {
+        let monitor = Monitor::new(&config(), Objective::Max).unwrap();
+        let reference = reference(vec![(0.5, 100)], 200);
+        assert_eq!(monitor.projection(0.5, 80, &reference), Some(160));
+        assert_eq!(monitor.projection(0.5, 95, &reference), None);
+        assert_eq!(monitor.projection(0.3, 10, &reference), None);
+
+        let monitor = Monitor::new(&config(), Objective::Min).unwrap();
+        assert_eq!(monitor.projection(0.5, 120, &reference), Some(240));
+    }
+
+    #[test]
+    fn stops_runs_behind_the_reference() {
+        let monitor = Monitor::new(&config(), Objective::Max).unwrap();
+        let mut command = Command::new("sh");
+        command.args([
+            "-c",
+            "echo 't=0.5 score=10' >&2; echo 't=1.0 score=20' >&2; echo 'Score = 30'",
+        ]);
+
+        let monitored = monitor
+            .run(command, Some(&reference(vec![(0.5, 0)], 0)))
+            .unwrap();
+        assert_eq!(monitored.curve.marks, vec![(0.5, 20)]);
+        assert_eq!(monitored.curve.stopped_at, None);
+
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo 't=1.0 score=10'; sleep 5; echo 'Score = 30'"]);
+        let monitored = monitor
+            .run(command, Some(&reference(vec![(0.5, 100)], 200)))
+            .unwrap();
+        assert_eq!(monitored.curve.stopped_at, Some(0.5));
+        assert_eq!(monitored.projected, Some(20));
+    }
+
+    #[test]
+    fn adds_schedule_params() {
+        let mut config = config();
+        config.start_temp = Some([1.0, 100.0]);
+        config.cooling = Some(vec!["linear".to_string()]);
+        let mut space = ParamSpace::new();
+        config.add_params(&mut space).unwrap();
+        assert_eq!(space.keys().collect::<Vec<_>>(), ["cooling", "start_temp"]);
+        assert!(config.add_params(&mut space).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use std::fmt::Write;
+
+const MARGIN_LEFT: f64 = 70.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 20.0;
+const MARGIN_BOTTOM: f64 = 50.0;
+
+/// Minimal SVG document builder for the charts of `ahc tune plot`.
+pub(crate) struct Svg {
+    width: f64,
+    height: f64,
+    body: String,
+}
+
+impl Svg {
+    pub(crate) fn new(width: f64, height: f64) -> Self {
+        Svg {
+            width,
+            height,
+            body: String::new(),
+        }
+    }
+
+    pub(crate) fn line(&mut self, from: (f64, f64), to: (f64, f64), stroke: &str) {
+        let _ = writeln!(
+            self.body,
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}"/>"#,
+            from.0, from.1, to.0, to.1, stroke
+        );
+    }
+
+    pub(crate) fn polyline(&mut self, points: &[(f64, f64)], stroke: &str, width: f64) {
+        let points = points
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            self.body,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+            points, stroke, width
+        );
+    }
+
+    pub(crate) fn circle(&mut self, center: (f64, f64), radius: f64, fill: &str, title: &str) {
+        let _ = writeln!(
+            self.body,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="{}" fill="{}"><title>{}</title></circle>"#,
+            center.0,
+            center.1,
+            radius,
+            fill,
+            escape(title)
+        );
+    }
+
+    pub(crate) fn rect(&mut self, origin: (f64, f64), size: (f64, f64), fill: &str) {
+        let _ = writeln!(
+            self.body,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"/>"#,
+            origin.0, origin.1, size.0, size.1, fill
+        );
+    }
+
+    /// Draws text anchored at `start`, `middle` or `end`.
+    pub(crate) fn text(&mut self, position: (f64, f64), anchor: &str, content: &str) {
+        let _ = writeln!(
+            self.body,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="{}">{}</text>"#,
+            position.0,
+            position.1,
+            anchor,
+            escape(content)
+        );
+    }
+
+    pub(crate) fn finish(self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" font-size=\"12\">\n{}</svg>\n",
+            self.body,
+            w = self.width,
+            h = self.height
+        )
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Linear mapping from data values to pixels.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Scale {
+    low: f64,
+    high: f64,
+    from: f64,
+    to: f64,
+}
+
+impl Scale {
+    /// Maps `[low, high]` onto `[from, to]`, widening a domain that is a single point.
+    pub(crate) fn new(low: f64, high: f64, from: f64, to: f64) -> Self {
+        let (low, high) = if high > low {
+            (low, high)
+        } else {
+            (low - 1.0, high + 1.0)
+        };
+        Scale {
+            low,
+            high,
+            from,
+            to,
+        }
+    }
+
+    /// The scale over the extent of `values`.
+    pub(crate) fn fit(values: impl IntoIterator<Item = f64>, from: f64, to: f64) -> Self {
+        let (low, high) = values
+            .into_iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), value| {
+                (low.min(value), high.max(value))
+            });
+        if low > high {
+            Scale::new(0.0, 1.0, from, to)
+        } else {
+            Scale::new(low, high, from, to)
+        }
+    }
+
+    pub(crate) fn map(&self, value: f64) -> f64 {
+        self.from + (value - self.low) / (self.high - self.low) * (self.to - self.from)
+    }
+
+    /// Round values within the domain to label the axis with.
+    pub(crate) fn ticks(&self, count: usize) -> Vec<f64> {
+        let raw = (self.high - self.low) / count.max(1) as f64;
+        let magnitude = 10f64.powf(raw.log10().floor());
+        let step = [1.0, 2.0, 5.0, 10.0]
+            .into_iter()
+            .map(|factor| factor * magnitude)
+            .find(|step| *step >= raw)
+            .unwrap_or(10.0 * magnitude);
+        let first = (self.low / step).ceil() as i64;
+        let last = (self.high / step).floor() as i64;
+        (first..=last).map(|i| i as f64 * step).collect()
+    }
+}
+
+pub(crate) fn format_number(value: f64) -> String {
+    if value != 0.0 && (value.abs() >= 1e6 || value.abs() < 1e-3) {
+        format!("{:.2e}", value)
+    } else {
+        let formatted = format!("{:.3}", value);
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+/// The plotting area of a chart with axes, in an SVG of the given size.
+pub(crate) struct Frame {
+    pub(crate) x: Scale,
+    pub(crate) y: Scale,
+    left: f64,
+    right: f64,
+    top: f64,
+    bottom: f64,
+}
+
+impl Frame {
+    pub(crate) fn new(width: f64, height: f64, x: (f64, f64), y: (f64, f64)) -> Self {
+        let (left, right) = (MARGIN_LEFT, width - MARGIN_RIGHT);
+        let (top, bottom) = (MARGIN_TOP, height - MARGIN_BOTTOM);
+        Frame {
+            x: Scale::new(x.0, x.1, left, right),
+            y: Scale::new(y.0, y.1, bottom, top),
+            left,
+            right,
+            top,
+            bottom,
+        }
+    }
+
+    pub(crate) fn draw_axes(&self, svg: &mut Svg, x_label: &str, y_label: &str) {
+        svg.line((self.left, self.bottom), (self.right, self.bottom), "#333");
+        svg.line((self.left, self.top), (self.left, self.bottom), "#333");
+        for tick in self.x.ticks(8) {
+            let x = self.x.map(tick);
+            svg.line((x, self.bottom), (x, self.bottom + 4.0), "#333");
+            svg.text((x, self.bottom + 18.0), "middle", &format_number(tick));
+        }
+        for tick in self.y.ticks(6) {
+            let y = self.y.map(tick);
+            svg.line((self.left, y), (self.right, y), "#eee");
+            svg.line((self.left - 4.0, y), (self.left, y), "#333");
+            svg.text((self.left - 8.0, y + 4.0), "end", &format_number(tick));
+        }
+        let center = (self.left + self.right) / 2.0;
+        svg.text((center, self.bottom + 38.0), "middle", x_label);
+        svg.text((self.left, self.top - 6.0), "middle", y_label);
+    }
+}
+
+/// Color from light blue for 0 to dark red for 1.
+pub(crate) fn gradient(t: f64) -> String {
+    let t = t.clamp(0.0, 1.0);
+    let channel = |from: f64, to: f64| (from + (to - from) * t).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(170.0, 200.0),
+        channel(200.0, 30.0),
+        channel(230.0, 30.0)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_maps_and_ticks() {
+        let scale = Scale::new(0.0, 10.0, 100.0, 0.0);
+        assert_eq!(scale.map(2.5), 75.0);
+        assert_eq!(scale.ticks(5), vec![0.0, 2.0, 4.0, 6.0, 8.0, 10.0]);
+        assert_eq!(Scale::new(3.0, 3.0, 0.0, 10.0).map(3.0), 5.0);
+        assert_eq!(format_number(2.50), "2.5");
+        assert_eq!(format_number(1234567.0), "1.23e6");
+    }
+
+    #[test]
+    fn escapes_text() {
+        let mut svg = Svg::new(10.0, 10.0);
+        svg.text((0.0, 0.0), "start", "a<b & \"c\"");
+        assert!(svg.finish().contains(">a&lt;b &amp; &quot;c&quot;</text>"));
+    }
+}
@@ -0,0 +1,236 @@
+use super::evaluate::expand;
+use super::params::{format_assignment, Assignment};
+use super::{best_trial, Objective, Trial};
+use crate::messages::msg;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Buckets seeds by a numeric feature read from their input file, e.g. the `N` on the first line.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct ClassConfig {
+    pub(crate) feature: String,
+    /// Index of the whitespace-separated token in the input file holding the feature
+    #[serde(default)]
+    pub(crate) token: usize,
+    /// Class boundaries; `[50, 100]` gives the classes `< 50`, `50..100` and `>= 100`
+    pub(crate) bounds: Vec<f64>,
+    /// Input file template, defaults to `{inputs_dir}/{SEED04}.txt`
+    #[serde(default)]
+    pub(crate) input: Option<String>,
+}
+
+impl ClassConfig {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.bounds.is_empty() {
+            return Err(anyhow!(msg!("tune.classes_empty")));
+        }
+        if self.bounds.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(anyhow!(msg!("tune.classes_order")));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn class_of(&self, value: f64) -> usize {
+        self.bounds.iter().filter(|bound| **bound <= value).count()
+    }
+
+    fn range(&self, class: usize) -> (Option<f64>, Option<f64>) {
+        let low = class.checked_sub(1).map(|i| self.bounds[i]);
+        (low, self.bounds.get(class).copied())
+    }
+
+    pub(crate) fn label(&self, class: usize) -> String {
+        match self.range(class) {
+            (None, Some(high)) => format!("{} < {}", self.feature, high),
+            (Some(low), Some(high)) => format!("{} <= {} < {}", low, self.feature, high),
+            (Some(low), None) => format!("{} >= {}", self.feature, low),
+            (None, None) => self.feature.clone(),
+        }
+    }
+
+    fn read_feature(&self, path: &Path) -> Result<f64> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read input file: {}", path.display()))?;
+        let token = content
+            .split_whitespace()
+            .nth(self.token)
+            .ok_or_else(|| anyhow!(msg!("tune.no_class_token", path.display(), self.token)))?;
+        token.parse().context(format!(
+            "Feature {} is not a number in {}: {}",
+            self.feature,
+            path.display(),
+            token
+        ))
+    }
+
+    /// Returns the seeds of each non-empty class, in class order.
+    pub(crate) fn group_seeds(
+        &self,
+        seeds: impl Iterator<Item = u64>,
+        inputs_dir: &Path,
+    ) -> Result<Vec<(usize, Vec<u64>)>> {
+        let mut groups = vec![vec![]; self.bounds.len() + 1];
+        for seed in seeds {
+            let path = match &self.input {
+                Some(template) => expand(template, seed, &Assignment::new()).into(),
+                None => inputs_dir.join(format!("{:04}.txt", seed)),
+            };
+            let class = self.class_of(self.read_feature(&path)?);
+            groups[class].push(seed);
+        }
+        Ok(groups
+            .into_iter()
+            .enumerate()
+            .filter(|(_, seeds)| !seeds.is_empty())
+            .collect())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct DecisionTable {
+    pub(crate) feature: String,
+    pub(crate) rows: Vec<DecisionRow>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct DecisionRow {
+    pub(crate) class: usize,
+    pub(crate) label: String,
+    /// Inclusive lower bound of the feature, `None` for the first class
+    pub(crate) low: Option<f64>,
+    /// Exclusive upper bound of the feature, `None` for the last class
+    pub(crate) high: Option<f64>,
+    pub(crate) trial: usize,
+    pub(crate) objective: f64,
+    pub(crate) params: Assignment,
+}
+
+pub(crate) fn decision_table(
+    config: &ClassConfig,
+    history: &[Trial],
+    objective: Objective,
+) -> DecisionTable {
+    let rows = (0..=config.bounds.len())
+        .filter_map(|class| {
+            let trials = history
+                .iter()
+                .filter(|trial| trial.class == Some(class))
+                .cloned()
+                .collect::<Vec<_>>();
+            let best = best_trial(&trials, objective)?;
+            let (low, high) = config.range(class);
+            Some(DecisionRow {
+                class,
+                label: config.label(class),
+                low,
+                high,
+                trial: best.id,
+                objective: best.objective,
+                params: best.params.clone(),
+            })
+        })
+        .collect();
+    DecisionTable {
+        feature: config.feature.clone(),
+        rows,
+    }
+}
+
+pub(crate) fn format_decision_table(table: &DecisionTable) -> String {
+    let width = table
+        .rows
+        .iter()
+        .map(|row| row.label.len())
+        .max()
+        .unwrap_or(0);
+    table
+        .rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{:<width$}  {:.2}  ({})\n",
+                row.label,
+                row.objective,
+                format_assignment(&row.params),
+                width = width
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tune::params::{ParamSpace, ParamValue};
+
+    fn config() -> ClassConfig {
+        ClassConfig {
+            feature: "N".to_string(),
+            token: 1,
+            bounds: vec![50.0, 100.0],
+            input: None,
+        }
+    }
+
+    #[test]
+    fn classify_by_bounds() {
+        let config = config();
+        assert_eq!(config.class_of(10.0), 0);
+        assert_eq!(config.class_of(50.0), 1);
+        assert_eq!(config.class_of(150.0), 2);
+        assert_eq!(config.label(0), "N < 50");
+        assert_eq!(config.label(1), "50 <= N < 100");
+        assert_eq!(config.label(2), "N >= 100");
+    }
+
+    #[test]
+    fn group_seeds_reads_input_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for (seed, n) in [(0, 10), (1, 200), (2, 20)] {
+            std::fs::write(
+                dir.path().join(format!("{:04}.txt", seed)),
+                format!("3 {}\n", n),
+            )
+            .unwrap();
+        }
+
+        let groups = config().group_seeds(0..3, dir.path()).unwrap();
+
+        assert_eq!(groups, vec![(0, vec![0, 2]), (2, vec![1])]);
+    }
+
+    #[test]
+    fn decision_table_picks_best_per_class() {
+        let trial = |id, class, objective, k| {
+            let mut params = Assignment::new();
+            params.insert("k".to_string(), ParamValue::Int(k));
+            Trial {
+                id,
+                pruned: false,
+                class: Some(class),
+                space: ParamSpace::new(),
+                params,
+                cases: vec![],
+                objective,
+            }
+        };
+        let history = vec![
+            trial(0, 0, 10.0, 1),
+            trial(1, 0, 20.0, 2),
+            trial(2, 2, 5.0, 3),
+        ];
+
+        let table = decision_table(&config(), &history, Objective::Max);
+
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[0].trial, 1);
+        assert_eq!(table.rows[0].high, Some(50.0));
+        assert_eq!(table.rows[1].class, 2);
+        assert_eq!(table.rows[1].low, Some(100.0));
+        assert_eq!(
+            format_decision_table(&table),
+            "N < 50    20.00  (k=2)\nN >= 100  5.00  (k=3)\n"
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use super::annealing::{Curve, Monitor};
+use super::params::{injection, Assignment, ParamSpace, ParamsVia, DEFAULT_PARAMS_FILE};
+use crate::messages::msg;
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct CaseResult {
+    pub(crate) seed: u64,
+    pub(crate) score: u64,
+    pub(crate) execution_time: f64,
+    pub(crate) error_message: String,
+    /// Progress of the solver, recorded when tuning an annealing schedule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) curve: Option<Curve>,
+}
+
+const SEED_PLACEHOLDERS: &[&str] = &["seed", "SEED04"];
+
+pub(crate) struct CommandEvaluator {
+    command: Vec<String>,
+    stdin: Option<String>,
+    score_regex: Regex,
+    space: ParamSpace,
+    params_via: ParamsVia,
+    params_file: PathBuf,
+    monitor: Option<Monitor>,
+}
+
+impl CommandEvaluator {
+    pub(crate) fn new(
+        command: &[String],
+        stdin: Option<&str>,
+        score_regex: &str,
+        space: &ParamSpace,
+    ) -> Result<Self> {
+        if command.is_empty() {
+            return Err(anyhow!(msg!("tune.no_command")));
+        }
+        let placeholder = Regex::new(r"\{([A-Za-z0-9_]+)\}").unwrap();
+        for template in command.iter().map(String::as_str).chain(stdin) {
+            for captures in placeholder.captures_iter(template) {
+                let name = &captures[1];
+                if !SEED_PLACEHOLDERS.contains(&name) && !space.contains_key(name) {
+                    return Err(anyhow!(msg!(
+                        "tune.unknown_placeholder",
+                        format!("{{{}}}", name),
+                        template
+                    )));
+                }
+            }
+        }
+        let score_regex = Regex::new(score_regex)
+            .context(format!("Failed to parse score regex: {}", score_regex))?;
+        if !score_regex
+            .capture_names()
+            .any(|name| name == Some("score"))
+        {
+            return Err(anyhow!(msg!("tune.no_score_group")));
+        }
+        Ok(CommandEvaluator {
+            command: command.to_vec(),
+            stdin: stdin.map(|s| s.to_string()),
+            score_regex,
+            space: space.clone(),
+            params_via: ParamsVia::default(),
+            params_file: PathBuf::from(DEFAULT_PARAMS_FILE),
+            monitor: None,
+        })
+    }
+
+    /// Hands the parameters to the command as `via` says, writing them to `file` if it is a file.
+    pub(crate) fn with_params_via(mut self, via: ParamsVia, file: Option<&Path>) -> Self {
+        self.params_via = via;
+        if let Some(file) = file {
+            self.params_file = file.to_path_buf();
+        }
+        self
+    }
+
+    /// Watches the progress of the solver, so that cases can be stopped early.
+    pub(crate) fn with_monitor(mut self, monitor: Monitor) -> Self {
+        self.monitor = Some(monitor);
+        self
+    }
+
+    pub(crate) fn evaluate(&self, assignment: &Assignment, seed: u64) -> CaseResult {
+        self.evaluate_against(assignment, seed, None)
+    }
+
+    /// Evaluates a case, which with a monitor may stop early when it falls behind `reference`.
+    pub(crate) fn evaluate_against(
+        &self,
+        assignment: &Assignment,
+        seed: u64,
+        reference: Option<&CaseResult>,
+    ) -> CaseResult {
+        let start = Instant::now();
+        let (score, curve, error_message) = match self.run(assignment, seed, reference) {
+            Ok((score, curve)) => (score, curve, String::new()),
+            Err(e) => (0, None, e.to_string()),
+        };
+        CaseResult {
+            seed,
+            score,
+            execution_time: start.elapsed().as_secs_f64(),
+            error_message,
+            curve,
+        }
+    }
+
+    /// Evaluates each (assignment, seed) pair with up to `jobs` threads.
+    pub(crate) fn evaluate_all(&self, work: &[(&Assignment, u64)], jobs: usize) -> Vec<CaseResult> {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(vec![None; work.len()]);
+        std::thread::scope(|scope| {
+            for _ in 0..jobs.min(work.len()) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some((params, seed)) = work.get(i) else {
+                        break;
+                    };
+                    let case = self.evaluate(params, *seed);
+                    results.lock().unwrap()[i] = Some(case);
+                });
+            }
+        });
+        results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|case| case.expect("every pair is evaluated"))
+            .collect()
+    }
+
+    fn run(
+        &self,
+        assignment: &Assignment,
+        seed: u64,
+        reference: Option<&CaseResult>,
+    ) -> Result<(u64, Option<Curve>)> {
+        let mut args = self
+            .command
+            .iter()
+            .map(|arg| expand(arg, seed, assignment))
+            .collect::<Vec<_>>();
+        let injection = injection(self.params_via, &self.space, assignment);
+        if let Some(contents) = &injection.file {
+            std::fs::write(&self.params_file, contents).context(format!(
+                "Failed to write params file: {}",
+                self.params_file.display()
+            ))?;
+        }
+        args.extend(injection.args);
+        let mut command = Command::new(&args[0]);
+        command.args(&args[1..]).envs(injection.envs);
+        match &self.stdin {
+            Some(stdin) => {
+                let path = expand(stdin, seed, assignment);
+                let file = std::fs::File::open(&path)
+                    .context(format!("Failed to open input file: {}", path))?;
+                command.stdin(file);
+            }
+            None => {
+                command.stdin(Stdio::null());
+            }
+        }
+
+        if let Some(monitor) = &self.monitor {
+            let monitored = monitor
+                .run(command, reference)
+                .context(format!("Failed to run command: {}", args.join(" ")))?;
+            if !monitored.success {
+                return Err(anyhow!(msg!("tune.command_failed")));
+            }
+            let score = match monitored.projected {
+                Some(projected) => projected,
+                None => parse_score(&self.score_regex, &monitored.output)?,
+            };
+            return Ok((score, Some(monitored.curve)));
+        }
+
+        let output = command
+            .output()
+            .context(format!("Failed to run command: {}", args.join(" ")))?;
+        if !output.status.success() {
+            return Err(anyhow!(msg!("tune.command_exited", output.status)));
+        }
+
+        let text = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        Ok((parse_score(&self.score_regex, &text)?, None))
+    }
+}
+
+pub(crate) fn parse_score(score_regex: &Regex, text: &str) -> Result<u64> {
+    let captures = score_regex
+        .captures_iter(text)
+        .last()
+        .ok_or_else(|| anyhow!(msg!("tune.no_score")))?;
+    captures["score"]
+        .parse()
+        .context(format!("Failed to parse score: {}", &captures["score"]))
+}
+
+pub(crate) fn expand(template: &str, seed: u64, assignment: &Assignment) -> String {
+    let mut expanded = template
+        .replace("{SEED04}", &format!("{:04}", seed))
+        .replace("{seed}", &seed.to_string());
+    for (name, value) in assignment {
+        expanded = expanded.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tune::params::{ParamSpec, ParamValue};
+
+    #[test]
+    fn expand_placeholders() {
+        let mut assignment = Assignment::new();
+        assignment.insert("temp".to_string(), ParamValue::Float(1.5));
+        assert_eq!(
+            expand(
+                "tools/in/{SEED04}.txt --seed {seed} --temp {temp}",
+                7,
+                &assignment
+            ),
+            "tools/in/0007.txt --seed 7 --temp 1.5"
+        );
+    }
+
+    #[test]
+    fn parse_last_score() {
+        let re = Regex::new(r"(?m)^\s*Score\s*=\s*(?P<score>\d+)\s*$").unwrap();
+        assert_eq!(parse_score(&re, "Score = 10\nScore = 20\n").unwrap(), 20);
+        assert!(parse_score(&re, "no score").is_err());
+    }
+
+    #[test]
+    fn evaluate_injects_params_as_env() {
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo \"Score = $((TEMP + {seed}))\"".to_string(),
+        ];
+        let mut space = ParamSpace::new();
+        space.insert(
+            "temp".to_string(),
+            ParamSpec::values(vec![ParamValue::Int(40)]),
+        );
+        let evaluator =
+            CommandEvaluator::new(&command, None, r"Score = (?P<score>\d+)", &space).unwrap();
+        let mut assignment = Assignment::new();
+        assignment.insert("temp".to_string(), ParamValue::Int(40));
+
+        let result = evaluator.evaluate(&assignment, 2);
+
+        assert_eq!(result.score, 42);
+        assert_eq!(result.error_message, "");
+    }
+
+    #[test]
+    fn evaluate_injects_params_as_args() {
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo \"Score = $1\"".to_string(),
+            "sh".to_string(),
+        ];
+        let space: ParamSpace =
+            toml::from_str(r#"k = { choices = [7], arg = "{value}" }"#).unwrap();
+        let evaluator =
+            CommandEvaluator::new(&command, None, r"Score = (?P<score>\d+)", &space).unwrap();
+        let mut assignment = Assignment::new();
+        assignment.insert("k".to_string(), ParamValue::Int(7));
+
+        assert_eq!(evaluator.evaluate(&assignment, 0).score, 7);
+    }
+
+    #[test]
+    fn evaluate_injects_params_as_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("params.txt");
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo \"Score = $(cut -d ' ' -f 2 \"$0\")\"".to_string(),
+            file.to_str().unwrap().to_string(),
+        ];
+        let space: ParamSpace = toml::from_str("k = [7]").unwrap();
+        let evaluator = CommandEvaluator::new(&command, None, r"Score = (?P<score>\d+)", &space)
+            .unwrap()
+            .with_params_via(ParamsVia::File, Some(&file));
+        let mut assignment = Assignment::new();
+        assignment.insert("k".to_string(), ParamValue::Int(7));
+
+        assert_eq!(evaluator.evaluate(&assignment, 0).score, 7);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "k 7\n");
+    }
+
+    #[test]
+    fn new_rejects_unknown_placeholders() {
+        let command = vec!["./a.out".to_string(), "{tmep}".to_string()];
+        let space: ParamSpace = toml::from_str("temp = [1]").unwrap();
+        let result = CommandEvaluator::new(&command, None, r"(?P<score>\d+)", &space);
+        assert!(result.is_err());
+        assert!(result.err().unwrap().to_string().contains("{tmep}"));
+    }
+}
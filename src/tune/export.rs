@@ -0,0 +1,309 @@
+use super::classes::{decision_table, DecisionRow};
+use super::params::{Assignment, ParamValue};
+use super::study::StudyStore;
+use super::{best_trial, load_scored_history, TuneConfig};
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use anyhow::{anyhow, Context, Result};
+use clap::{ArgGroup, Args};
+use serde_json::json;
+use std::fmt::Write;
+use std::path::PathBuf;
+use tracing::info;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod",
+    "move", "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true",
+    "try", "type", "unsafe", "use", "where", "while", "yield",
+];
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("format").required(true).args(["rust"])))]
+pub(crate) struct ExportArgs {
+    /// Write a Rust module with the tuned parameters as constants
+    #[arg(long)]
+    rust: bool,
+    /// File to write to instead of stdout
+    #[arg(short, long)]
+    output_file: Option<PathBuf>,
+    /// Name of the study to export, overriding [tune] study
+    #[arg(short, long)]
+    study: Option<String>,
+}
+
+pub(crate) fn export(args: ExportArgs, tune_config: &TuneConfig, output: Output) -> Result<()> {
+    let study = args.study.as_deref().unwrap_or(&tune_config.study);
+    let store = StudyStore::new(study);
+    let (history, scoring) = load_scored_history(&store, tune_config)?;
+    let objective = scoring.objective;
+
+    let source = match &tune_config.classes {
+        Some(classes) => {
+            let table = decision_table(classes, &history, objective);
+            if table.rows.is_empty() {
+                return Err(anyhow!(msg!("tune.no_finished_trials", study)));
+            }
+            rust_decision_table(study, &table.feature, &table.rows)?
+        }
+        None => {
+            let best = best_trial(&history, objective)
+                .ok_or_else(|| anyhow!(msg!("tune.no_finished_trials", study)))?;
+            let header = format!(
+                "trial #{} of study {} (objective {:.2})",
+                best.id, study, best.objective
+            );
+            rust_constants(&header, &best.params)?
+        }
+    };
+
+    match &args.output_file {
+        Some(path) => {
+            std::fs::write(path, source)
+                .context(format!("Failed to write file: {}", path.display()))?;
+            info!("{}", msg!("tune.params_written", path.display()));
+            if output.is_json() {
+                print_json(&json!({ "study": study, "path": path }))?;
+            }
+        }
+        None if output.is_json() => print_json(&json!({ "study": study, "source": source }))?,
+        None => print!("{}", source),
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RustType {
+    Int,
+    Float,
+    Str,
+}
+
+impl RustType {
+    fn of(name: &str, values: &[&ParamValue]) -> Result<Self> {
+        let is_str = |value: &&ParamValue| matches!(value, ParamValue::Str(_));
+        if values.iter().all(is_str) {
+            Ok(RustType::Str)
+        } else if values.iter().any(is_str) {
+            Err(anyhow!(msg!("tune.mixed_param", name)))
+        } else if values
+            .iter()
+            .any(|value| matches!(value, ParamValue::Float(_)))
+        {
+            Ok(RustType::Float)
+        } else {
+            Ok(RustType::Int)
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            RustType::Int => "i64",
+            RustType::Float => "f64",
+            RustType::Str => "&str",
+        }
+    }
+
+    fn literal(&self, value: &ParamValue) -> String {
+        match (self, value) {
+            (RustType::Float, ParamValue::Int(value)) => float_literal(*value as f64),
+            (_, ParamValue::Float(value)) => float_literal(*value),
+            (_, ParamValue::Int(value)) => value.to_string(),
+            // Debug escapes match Rust string literals
+            (_, ParamValue::Str(value)) => format!("{:?}", value),
+        }
+    }
+}
+
+fn float_literal(value: f64) -> String {
+    if value.is_nan() {
+        "f64::NAN".to_string()
+    } else if value.is_infinite() {
+        let sign = if value < 0.0 { "-" } else { "" };
+        format!("{}f64::INFINITY", sign)
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+/// Turns a parameter name into a Rust identifier, e.g. `start-temp` into `START_TEMP`.
+fn identifier(name: &str, upper: bool) -> String {
+    let mut ident = name
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() && upper => c.to_ascii_uppercase(),
+            c if c.is_ascii_alphanumeric() => c.to_ascii_lowercase(),
+            _ => '_',
+        })
+        .collect::<String>();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+fn rust_constants(header: &str, params: &Assignment) -> Result<String> {
+    let mut source = format!("// Generated by `ahc tune export` from {}\n\n", header);
+    for (name, value) in params {
+        let kind = RustType::of(name, &[value])?;
+        writeln!(
+            source,
+            "pub const {}: {} = {};",
+            identifier(name, true),
+            kind.name(),
+            kind.literal(value)
+        )?;
+    }
+    Ok(source)
+}
+
+fn rust_decision_table(study: &str, feature: &str, rows: &[DecisionRow]) -> Result<String> {
+    let names = rows[0].params.keys().collect::<Vec<_>>();
+    let mut kinds = vec![];
+    for name in &names {
+        let mut values = vec![];
+        for row in rows {
+            let value = row
+                .params
+                .get(*name)
+                .ok_or_else(|| anyhow!(msg!("tune.param_not_in_class", name, row.label)))?;
+            values.push(value);
+        }
+        kinds.push(RustType::of(name, &values)?);
+    }
+
+    let mut source = format!(
+        "// Generated by `ahc tune export` from study {}, tuned per {}\n\n",
+        study, feature
+    );
+    source.push_str("#[derive(Clone, Copy, Debug)]\npub struct Params {\n");
+    for (name, kind) in names.iter().zip(&kinds) {
+        let kind = match kind {
+            RustType::Str => "&'static str",
+            kind => kind.name(),
+        };
+        writeln!(source, "    pub {}: {},", identifier(name, false), kind)?;
+    }
+    source.push_str("}\n");
+
+    for row in rows {
+        writeln!(
+            source,
+            "\n/// {}: trial #{} (objective {:.2})",
+            row.label, row.trial, row.objective
+        )?;
+        writeln!(source, "pub const CLASS_{}: Params = Params {{", row.class)?;
+        for (name, kind) in names.iter().zip(&kinds) {
+            let value = kind.literal(&row.params[*name]);
+            writeln!(source, "    {}: {},", identifier(name, false), value)?;
+        }
+        source.push_str("};\n");
+    }
+
+    // Classes without trials fall through to the next tuned class
+    let argument = identifier(feature, false);
+    writeln!(
+        source,
+        "\n/// Returns the tuned parameters for an input with the given {}.",
+        feature
+    )?;
+    writeln!(source, "pub fn params({}: f64) -> Params {{", argument)?;
+    let (last, conditional) = rows.split_last().expect("rows must not be empty");
+    for (i, row) in conditional.iter().enumerate() {
+        let keyword = if i == 0 { "if" } else { "} else if" };
+        let high = row.high.expect("only the last class is unbounded");
+        writeln!(
+            source,
+            "    {} {} < {} {{",
+            keyword,
+            argument,
+            float_literal(high)
+        )?;
+        writeln!(source, "        CLASS_{}", row.class)?;
+    }
+    if conditional.is_empty() {
+        writeln!(source, "    let _ = {};", argument)?;
+        writeln!(source, "    CLASS_{}", last.class)?;
+    } else {
+        writeln!(
+            source,
+            "    }} else {{\n        CLASS_{}\n    }}",
+            last.class
+        )?;
+    }
+    source.push_str("}\n");
+    Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(k: i64, temp: f64, mode: &str) -> Assignment {
+        [
+            ("k".to_string(), ParamValue::Int(k)),
+            ("start-temp".to_string(), ParamValue::Float(temp)),
+            ("type".to_string(), ParamValue::Str(mode.to_string())),
+        ]
+        .into()
+    }
+
+    #[test]
+    fn constants_for_best_trial() {
+        let source = rust_constants("trial #3", &params(4, 2.0, "fast")).unwrap();
+        assert_eq!(
+            source,
+            "// Generated by `ahc tune export` from trial #3\n\n\
+             pub const K: i64 = 4;\n\
+             pub const START_TEMP: f64 = 2.0;\n\
+             pub const TYPE: &str = \"fast\";\n"
+        );
+    }
+
+    #[test]
+    fn decision_table_as_function() {
+        let row = |class, label: &str, high, k, temp| DecisionRow {
+            class,
+            label: label.to_string(),
+            low: None,
+            high,
+            trial: class,
+            objective: 1.0,
+            params: params(k, temp, "a"),
+        };
+        let mut rows = vec![
+            row(0, "N < 50", Some(50.0), 1, 0.5),
+            row(2, "N >= 100", None, 3, 1.0),
+        ];
+        // An integer in one class and a float in another makes the field a float
+        rows[1]
+            .params
+            .insert("start-temp".to_string(), ParamValue::Int(1));
+
+        let source = rust_decision_table("default", "N", &rows).unwrap();
+
+        assert!(source.contains("pub struct Params {\n    pub k: i64,\n    pub start_temp: f64,\n    pub type_: &'static str,\n}"));
+        assert!(source.contains(
+            "/// N >= 100: trial #2 (objective 1.00)\npub const CLASS_2: Params = Params {\n    k: 3,\n    start_temp: 1.0,\n    type_: \"a\",\n};"
+        ));
+        assert!(source.ends_with(
+            "pub fn params(n: f64) -> Params {\n    if n < 50.0 {\n        CLASS_0\n    } else {\n        CLASS_2\n    }\n}\n"
+        ));
+    }
+
+    #[test]
+    fn rejects_mixed_types() {
+        let values = [&ParamValue::Int(1), &ParamValue::Str("a".to_string())];
+        assert!(RustType::of("k", &values).is_err());
+    }
+
+    #[test]
+    fn identifiers_are_valid() {
+        assert_eq!(identifier("start-temp", true), "START_TEMP");
+        assert_eq!(identifier("2opt", false), "_2opt");
+        assert_eq!(identifier("fn", false), "fn_");
+    }
+}
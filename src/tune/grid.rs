@@ -0,0 +1,138 @@
+use super::params::{Assignment, Domain, ParamSpace};
+use super::{Sampler, Trial};
+use crate::messages::msg;
+use anyhow::{anyhow, Result};
+
+/// Walks the cartesian product of every parameter's values, skipping points already in the study
+/// or still running.
+pub(crate) struct GridSampler {
+    next_index: usize,
+}
+
+impl GridSampler {
+    pub(crate) fn new(space: &ParamSpace) -> Result<Self> {
+        for (name, spec) in space {
+            if let Domain::Range(_) = spec.domain {
+                return Err(anyhow!(msg!("tune.grid_no_values", name)));
+            }
+        }
+        Ok(GridSampler { next_index: 0 })
+    }
+}
+
+pub(crate) fn grid_size(space: &ParamSpace) -> usize {
+    space
+        .values()
+        .map(|spec| match &spec.domain {
+            Domain::Values(values) => values.len(),
+            Domain::Range(_) => 0,
+        })
+        .product()
+}
+
+pub(crate) fn grid_point(space: &ParamSpace, mut index: usize) -> Assignment {
+    let mut assignment = Assignment::new();
+    for (name, spec) in space.iter().rev() {
+        if let Domain::Values(values) = &spec.domain {
+            assignment.insert(name.clone(), values[index % values.len()].clone());
+            index /= values.len();
+        }
+    }
+    assignment
+}
+
+impl Sampler for GridSampler {
+    fn propose(
+        &mut self,
+        space: &ParamSpace,
+        history: &[Trial],
+        running: &[Assignment],
+    ) -> Option<Assignment> {
+        while self.next_index < grid_size(space) {
+            let assignment = grid_point(space, self.next_index);
+            self.next_index += 1;
+            if !history.iter().any(|trial| trial.params == assignment)
+                && !running.contains(&assignment)
+            {
+                return Some(assignment);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tune::params::{ParamSpec, ParamType, ParamValue, RangeSpec, Scale};
+
+    fn space() -> ParamSpace {
+        let mut space = ParamSpace::new();
+        space.insert(
+            "a".to_string(),
+            ParamSpec::values(vec![ParamValue::Int(1), ParamValue::Int(2)]),
+        );
+        space.insert(
+            "b".to_string(),
+            ParamSpec::values(vec![
+                ParamValue::Str("x".to_string()),
+                ParamValue::Str("y".to_string()),
+                ParamValue::Str("z".to_string()),
+            ]),
+        );
+        space
+    }
+
+    #[test]
+    fn enumerates_cartesian_product() {
+        let space = space();
+        let mut sampler = GridSampler::new(&space).unwrap();
+
+        let mut points = vec![];
+        while let Some(point) = sampler.propose(&space, &[], &[]) {
+            points.push(point);
+        }
+
+        assert_eq!(points.len(), 6);
+        assert_eq!(points[0]["a"], ParamValue::Int(1));
+        assert_eq!(points[0]["b"], ParamValue::Str("x".to_string()));
+        assert_eq!(points[1]["b"], ParamValue::Str("y".to_string()));
+        assert_eq!(points[3]["a"], ParamValue::Int(2));
+    }
+
+    #[test]
+    fn skips_points_already_tried_or_running() {
+        let space = space();
+        let mut sampler = GridSampler::new(&space).unwrap();
+        let history = vec![Trial {
+            id: 0,
+            pruned: false,
+            class: None,
+            space: ParamSpace::new(),
+            params: grid_point(&space, 0),
+            cases: vec![],
+            objective: 0.0,
+        }];
+
+        let point = sampler
+            .propose(&space, &history, &[grid_point(&space, 1)])
+            .unwrap();
+
+        assert_eq!(point, grid_point(&space, 2));
+    }
+
+    #[test]
+    fn rejects_ranges() {
+        let mut space = space();
+        space.insert(
+            "c".to_string(),
+            ParamSpec::range(RangeSpec {
+                low: 0.0,
+                high: 1.0,
+                kind: ParamType::Float,
+                scale: Scale::Linear,
+            }),
+        );
+        assert!(GridSampler::new(&space).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use super::evaluate::CaseResult;
+use super::{Objective, Trial};
+use crate::messages::msg;
+use crate::pahcer::ExecResult;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How the scores of a trial's cases are combined into its objective.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Aggregate {
+    #[default]
+    Mean,
+    /// Geometric mean, so that seeds with large scores do not dominate
+    LogMean,
+    /// Mean percentage of the baseline's score on the same seed
+    Relative,
+}
+
+/// Penalizes cases which ran close to the time limit, as they risk a TLE on the judge.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct TimePenalty {
+    /// Time limit in seconds
+    pub(crate) time_limit: f64,
+    /// Fraction of the time limit above which a case counts as risky
+    #[serde(default = "default_threshold")]
+    pub(crate) threshold: f64,
+    /// Deducted from the score of every risky case, or added when minimizing; for `log_mean` it
+    /// applies to the raw score
+    pub(crate) penalty: f64,
+}
+
+fn default_threshold() -> f64 {
+    0.95
+}
+
+/// Turns case results into objectives according to the [tune] scoring settings.
+pub(crate) struct Scoring {
+    pub(crate) objective: Objective,
+    aggregate: Aggregate,
+    baseline: BTreeMap<u64, u64>,
+    time_penalty: Option<TimePenalty>,
+}
+
+impl Scoring {
+    pub(crate) fn new(
+        objective: Objective,
+        aggregate: Aggregate,
+        baseline: Option<&str>,
+        time_penalty: Option<TimePenalty>,
+    ) -> Result<Self> {
+        let baseline = match (aggregate, baseline) {
+            (Aggregate::Relative, Some(path)) => read_baseline(path)?,
+            (Aggregate::Relative, None) => return Err(anyhow!(msg!("tune.no_baseline"))),
+            _ => BTreeMap::new(),
+        };
+        if let Some(time_penalty) = &time_penalty {
+            if time_penalty.time_limit <= 0.0 || time_penalty.threshold <= 0.0 {
+                return Err(anyhow!(msg!("tune.time_penalty")));
+            }
+        }
+        Ok(Scoring {
+            objective,
+            aggregate,
+            baseline,
+            time_penalty,
+        })
+    }
+
+    /// Scoring by the plain mean, as for a study without scoring settings.
+    #[cfg(test)]
+    pub(crate) fn mean(objective: Objective) -> Self {
+        Scoring {
+            objective,
+            aggregate: Aggregate::Mean,
+            baseline: BTreeMap::new(),
+            time_penalty: None,
+        }
+    }
+
+    pub(crate) fn check_seeds(&self, seeds: impl IntoIterator<Item = u64>) -> Result<()> {
+        if self.aggregate != Aggregate::Relative {
+            return Ok(());
+        }
+        let missing = seeds
+            .into_iter()
+            .filter(|seed| !self.baseline.contains_key(seed))
+            .collect::<Vec<_>>();
+        match missing.first() {
+            Some(first) => Err(anyhow!(msg!("tune.baseline_missing", missing.len(), first))),
+            None => Ok(()),
+        }
+    }
+
+    /// The contribution of one case to the objective, comparable across trials on the same seed.
+    /// A failed case counts as the worst score there is, so that a trial crashing on every seed
+    /// never becomes the best.
+    pub(crate) fn case_value(&self, case: &CaseResult) -> f64 {
+        let penalty = match &self.time_penalty {
+            Some(time_penalty)
+                if case.execution_time > time_penalty.threshold * time_penalty.time_limit =>
+            {
+                match self.objective {
+                    Objective::Max => -time_penalty.penalty,
+                    Objective::Min => time_penalty.penalty,
+                }
+            }
+            _ => 0.0,
+        };
+        let score = match (case.error_message.is_empty(), self.objective) {
+            (true, _) => case.score as f64,
+            (false, Objective::Max) => 0.0,
+            (false, Objective::Min) => u64::MAX as f64,
+        };
+        match self.aggregate {
+            Aggregate::Mean => score + penalty,
+            Aggregate::LogMean => (score + penalty).max(1.0).ln(),
+            Aggregate::Relative => {
+                let baseline = self.baseline.get(&case.seed).copied().unwrap_or(0).max(1);
+                100.0 * score / baseline as f64 + penalty
+            }
+        }
+    }
+
+    pub(crate) fn objective(&self, cases: &[CaseResult]) -> f64 {
+        if cases.is_empty() {
+            return 0.0;
+        }
+        let mean = cases.iter().map(|case| self.case_value(case)).sum::<f64>() / cases.len() as f64;
+        match self.aggregate {
+            Aggregate::LogMean => mean.exp(),
+            Aggregate::Mean | Aggregate::Relative => mean,
+        }
+    }
+
+    /// Recomputes the objectives of recorded trials, which may have been scored differently.
+    pub(crate) fn rescore(&self, trials: &mut [Trial]) {
+        for trial in trials {
+            trial.objective = self.objective(&trial.cases);
+        }
+    }
+}
+
+fn read_baseline(path: &str) -> Result<BTreeMap<u64, u64>> {
+    let content = std::fs::read_to_string(path)
+        .context(format!("Failed to read baseline result: {}", path))?;
+    let result: ExecResult = serde_json::from_str(&content)
+        .context(format!("Failed to parse baseline result: {}", path))?;
+    Ok(result
+        .cases
+        .into_iter()
+        .map(|case| (case.seed, case.score))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(seed: u64, score: u64, execution_time: f64) -> CaseResult {
+        CaseResult {
+            seed,
+            score,
+            execution_time,
+            error_message: String::new(),
+            curve: None,
+        }
+    }
+
+    #[test]
+    fn aggregates_cases() {
+        let cases = vec![case(0, 10, 0.0), case(1, 1000, 0.0)];
+        assert_eq!(Scoring::mean(Objective::Max).objective(&cases), 505.0);
+        let log_mean = Scoring::new(Objective::Max, Aggregate::LogMean, None, None).unwrap();
+        assert!((log_mean.objective(&cases) - 100.0).abs() < 1e-9);
+        assert_eq!(Scoring::mean(Objective::Max).objective(&[]), 0.0);
+    }
+
+    #[test]
+    fn failed_cases_score_the_worst() {
+        let failed = |seed| CaseResult {
+            error_message: "exit status: 1".to_string(),
+            ..case(seed, 0, 0.0)
+        };
+        let scoring = Scoring::mean(Objective::Min);
+        let passing = scoring.objective(&[case(0, 100, 0.0), case(1, 200, 0.0)]);
+        let crashing = scoring.objective(&[failed(0), failed(1)]);
+        let flaky = scoring.objective(&[case(0, 100, 0.0), failed(1)]);
+        assert!(Objective::Min.is_better(passing, crashing));
+        assert!(Objective::Min.is_better(passing, flaky));
+        assert!(crashing.is_finite());
+        assert_eq!(Scoring::mean(Objective::Max).objective(&[failed(0)]), 0.0);
+    }
+
+    #[test]
+    fn relative_to_baseline() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("result.json");
+        std::fs::write(
+            &path,
+            r#"{"case_count": 2, "total_score": 300, "cases": [
+                {"seed": 0, "score": 100}, {"seed": 1, "score": 200}]}"#,
+        )
+        .unwrap();
+        let scoring = Scoring::new(
+            Objective::Max,
+            Aggregate::Relative,
+            Some(path.to_str().unwrap()),
+            None,
+        )
+        .unwrap();
+
+        let cases = vec![case(0, 110, 0.0), case(1, 180, 0.0)];
+        assert!((scoring.objective(&cases) - 100.0).abs() < 1e-9);
+        assert!(scoring.check_seeds(0..2).is_ok());
+        assert!(scoring.check_seeds(0..3).is_err());
+        assert!(Scoring::new(Objective::Max, Aggregate::Relative, None, None).is_err());
+    }
+
+    #[test]
+    fn penalizes_cases_close_to_the_time_limit() {
+        let time_penalty = TimePenalty {
+            time_limit: 2.0,
+            threshold: 0.95,
+            penalty: 50.0,
+        };
+        let cases = vec![case(0, 100, 1.0), case(1, 100, 1.95)];
+
+        let max = Scoring::new(
+            Objective::Max,
+            Aggregate::Mean,
+            None,
+            Some(time_penalty.clone()),
+        )
+        .unwrap();
+        assert_eq!(max.objective(&cases), 75.0);
+        let min = Scoring::new(Objective::Min, Aggregate::Mean, None, Some(time_penalty)).unwrap();
+        assert_eq!(min.objective(&cases), 125.0);
+    }
+}
@@ -0,0 +1,524 @@
+use crate::messages::msg;
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A tunable parameter: the values it can take and how they are handed to the solver.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "RawParamSpec", into = "RawParamSpec")]
+pub(crate) struct ParamSpec {
+    pub(crate) domain: Domain,
+    /// Environment variable set to the value, defaults to the upper-cased parameter name
+    pub(crate) env: Option<String>,
+    /// Extra command argument, with `{value}` replaced by the value
+    pub(crate) arg: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Domain {
+    Values(Vec<ParamValue>),
+    Range(RangeSpec),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawParamSpec {
+    List(Vec<ParamValue>),
+    Table(TableSpec),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TableSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    choices: Option<Vec<ParamValue>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    low: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    high: Option<f64>,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    kind: Option<ParamType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scale: Option<Scale>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    env: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    arg: Option<String>,
+}
+
+impl TryFrom<RawParamSpec> for ParamSpec {
+    type Error = String;
+
+    fn try_from(raw: RawParamSpec) -> Result<Self, Self::Error> {
+        let table = match raw {
+            RawParamSpec::List(values) => return Ok(ParamSpec::values(values)),
+            RawParamSpec::Table(table) => table,
+        };
+        let spec = match (table.choices, table.low, table.high) {
+            (Some(choices), None, None) => ParamSpec::values(choices),
+            (None, Some(low), Some(high)) => ParamSpec::range(RangeSpec {
+                low,
+                high,
+                kind: table.kind.unwrap_or_default(),
+                scale: table.scale.unwrap_or_default(),
+            }),
+            _ => return Err("a parameter needs either `choices` or both `low` and `high`".into()),
+        };
+        Ok(ParamSpec {
+            env: table.env,
+            arg: table.arg,
+            ..spec
+        })
+    }
+}
+
+impl From<ParamSpec> for RawParamSpec {
+    fn from(spec: ParamSpec) -> Self {
+        let mut table = TableSpec {
+            choices: None,
+            low: None,
+            high: None,
+            kind: None,
+            scale: None,
+            env: spec.env,
+            arg: spec.arg,
+        };
+        match spec.domain {
+            Domain::Values(values) => table.choices = Some(values),
+            Domain::Range(range) => {
+                table.low = Some(range.low);
+                table.high = Some(range.high);
+                table.kind = Some(range.kind);
+                table.scale = Some(range.scale);
+            }
+        }
+        RawParamSpec::Table(table)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct RangeSpec {
+    pub(crate) low: f64,
+    pub(crate) high: f64,
+    #[serde(default, rename = "type")]
+    pub(crate) kind: ParamType,
+    #[serde(default)]
+    pub(crate) scale: Scale,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ParamType {
+    #[default]
+    Float,
+    Int,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Scale {
+    #[default]
+    Linear,
+    Log,
+}
+
+impl RangeSpec {
+    pub(crate) fn sample<R: Rng>(&self, rng: &mut R) -> ParamValue {
+        let value = match self.scale {
+            Scale::Linear => rng.random_range(self.low..=self.high),
+            Scale::Log => rng
+                .random_range(self.low.ln()..=self.high.ln())
+                .exp()
+                .clamp(self.low, self.high),
+        };
+        self.to_value(value)
+    }
+
+    pub(crate) fn to_value(&self, value: f64) -> ParamValue {
+        match self.kind {
+            ParamType::Float => ParamValue::Float(value),
+            ParamType::Int => ParamValue::Int(value.round().clamp(self.low, self.high) as i64),
+        }
+    }
+}
+
+impl ParamSpec {
+    pub(crate) fn values(values: Vec<ParamValue>) -> Self {
+        ParamSpec {
+            domain: Domain::Values(values),
+            env: None,
+            arg: None,
+        }
+    }
+
+    pub(crate) fn range(range: RangeSpec) -> Self {
+        ParamSpec {
+            domain: Domain::Range(range),
+            env: None,
+            arg: None,
+        }
+    }
+
+    pub(crate) fn env_var_name(&self, name: &str) -> String {
+        self.env.clone().unwrap_or_else(|| name.to_uppercase())
+    }
+
+    pub(crate) fn validate(&self, name: &str) -> Result<()> {
+        let env = self.env_var_name(name);
+        let valid_env = env.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && env.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_env {
+            return Err(anyhow!(msg!("tune.param_env", name, env)));
+        }
+        if let Some(arg) = &self.arg {
+            if !arg.contains("{value}") {
+                return Err(anyhow!(msg!("tune.param_arg", name, "{value}", arg)));
+            }
+        }
+
+        match &self.domain {
+            Domain::Values(values) => {
+                if values.is_empty() {
+                    return Err(anyhow!(msg!("tune.param_no_values", name)));
+                }
+            }
+            Domain::Range(range) => {
+                if !range.low.is_finite() || !range.high.is_finite() || range.low > range.high {
+                    return Err(anyhow!(msg!(
+                        "tune.param_range",
+                        name,
+                        range.low,
+                        range.high
+                    )));
+                }
+                if range.scale == Scale::Log && range.low <= 0.0 {
+                    return Err(anyhow!(msg!("tune.param_log", name)));
+                }
+                if range.kind == ParamType::Int && range.low.ceil() > range.high.floor() {
+                    return Err(anyhow!(msg!(
+                        "tune.param_no_int",
+                        name,
+                        range.low,
+                        range.high
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum ParamValue {
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamValue::Int(value) => write!(f, "{}", value),
+            ParamValue::Float(value) => write!(f, "{}", value),
+            ParamValue::Str(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+pub(crate) type ParamSpace = BTreeMap<String, ParamSpec>;
+
+pub(crate) type Assignment = BTreeMap<String, ParamValue>;
+
+pub(crate) fn validate_space(space: &ParamSpace) -> Result<()> {
+    if space.is_empty() {
+        return Err(anyhow!(msg!("tune.no_params")));
+    }
+    let mut env_names = std::collections::BTreeSet::new();
+    for (name, spec) in space {
+        spec.validate(name)?;
+        if !env_names.insert(spec.env_var_name(name)) {
+            return Err(anyhow!(msg!(
+                "tune.param_env_taken",
+                name,
+                spec.env_var_name(name)
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// How parameters reach the solver, the `params_via` of `[test]`, `[solver.<name>]` or `[tune]`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ParamsVia {
+    /// An environment variable per parameter, named by its `env` or the upper-cased name
+    #[default]
+    Env,
+    /// Arguments `--<name> <value>` added to the command, or the `arg` of the parameter
+    Args,
+    /// Lines `<name> <value>` written to `params_file`, `params.txt` by default
+    File,
+}
+
+pub(crate) const DEFAULT_PARAMS_FILE: &str = "params.txt";
+
+/// What hands an assignment to the solver.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Injection {
+    pub(crate) envs: Vec<(String, String)>,
+    pub(crate) args: Vec<String>,
+    /// Contents of the params file, with `params_via = "file"`
+    pub(crate) file: Option<String>,
+}
+
+/// Hands `assignment` to the solver as `via` says. The `arg` of a parameter in `space` is added
+/// to the command whatever the way, and parameters not in `space` are handed by their name.
+pub(crate) fn injection(via: ParamsVia, space: &ParamSpace, assignment: &Assignment) -> Injection {
+    let mut injection = Injection::default();
+    let mut file = String::new();
+    for (name, value) in assignment {
+        let spec = space.get(name);
+        let value = value.to_string();
+        let arg = spec.and_then(|spec| spec.arg.as_ref());
+        if let Some(arg) = arg {
+            injection.args.push(arg.replace("{value}", &value));
+        }
+        match via {
+            ParamsVia::Env => {
+                let env = spec.map_or_else(|| name.to_uppercase(), |spec| spec.env_var_name(name));
+                injection.envs.push((env, value));
+            }
+            ParamsVia::Args if arg.is_none() => {
+                injection.args.extend([format!("--{}", name), value]);
+            }
+            ParamsVia::Args => {}
+            ParamsVia::File => file.push_str(&format!("{} {}\n", name, value)),
+        }
+    }
+    if via == ParamsVia::File {
+        injection.file = Some(file);
+    }
+    injection
+}
+
+/// Parses `NAME=VALUE` of `--param`, the value an integer, a float or else a string.
+pub(crate) fn parse_param(param: &str) -> Result<(String, ParamValue), String> {
+    let (name, value) = param
+        .split_once('=')
+        .filter(|(name, _)| !name.is_empty())
+        .ok_or_else(|| format!("expected NAME=VALUE: {}", param))?;
+    let value = if let Ok(value) = value.parse() {
+        ParamValue::Int(value)
+    } else if let Ok(value) = value.parse() {
+        ParamValue::Float(value)
+    } else {
+        ParamValue::Str(value.to_string())
+    };
+    Ok((name.to_string(), value))
+}
+
+pub(crate) fn format_assignment(assignment: &Assignment) -> String {
+    assignment
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_rejects_inverted_range() {
+        let mut space = ParamSpace::new();
+        space.insert(
+            "temp".to_string(),
+            ParamSpec::range(RangeSpec {
+                low: 10.0,
+                high: 1.0,
+                kind: ParamType::Float,
+                scale: Scale::Linear,
+            }),
+        );
+        assert!(validate_space(&space).is_err());
+
+        let mut space = ParamSpace::new();
+        space.insert(
+            "temp".to_string(),
+            ParamSpec::range(RangeSpec {
+                low: 0.0,
+                high: 1.0,
+                kind: ParamType::Float,
+                scale: Scale::Log,
+            }),
+        );
+        assert!(validate_space(&space).is_err());
+        assert!(validate_space(&ParamSpace::new()).is_err());
+    }
+
+    #[test]
+    fn parse_values_and_ranges() {
+        let space: ParamSpace = toml::from_str(
+            r#"
+            temp_start = [1e3, 3e3]
+            neighbors = [2, 3, 4]
+            cooling = { low = 0.5, high = 1.0 }
+            iterations = { low = 100, high = 100000, type = "int", scale = "log" }
+            "#,
+        )
+        .unwrap();
+
+        let Domain::Values(temps) = &space["temp_start"].domain else {
+            panic!("temp_start should be a value list");
+        };
+        assert_eq!(temps[1], ParamValue::Float(3000.0));
+        let Domain::Values(neighbors) = &space["neighbors"].domain else {
+            panic!("neighbors should be a value list");
+        };
+        assert_eq!(neighbors[0], ParamValue::Int(2));
+        assert!(matches!(space["cooling"].domain, Domain::Range(_)));
+        let Domain::Range(iterations) = &space["iterations"].domain else {
+            panic!("iterations should be a range");
+        };
+        assert_eq!(iterations.kind, ParamType::Int);
+        assert_eq!(iterations.scale, Scale::Log);
+        assert!(validate_space(&space).is_ok());
+    }
+
+    #[test]
+    fn parse_injection_mapping() {
+        let space: ParamSpace = toml::from_str(
+            r#"
+            temp = { low = 1.0, high = 2.0, env = "T0", arg = "--temp={value}" }
+            mode = { choices = ["fast", "slow"] }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(space["temp"].env_var_name("temp"), "T0");
+        assert_eq!(space["temp"].arg.as_deref(), Some("--temp={value}"));
+        assert_eq!(space["mode"].env_var_name("mode"), "MODE");
+        assert!(validate_space(&space).is_ok());
+
+        assert!(toml::from_str::<ParamSpace>("temp = { low = 1.0 }").is_err());
+        assert!(
+            toml::from_str::<ParamSpace>("temp = { low = 1.0, high = 2.0, evn = \"T\" }").is_err()
+        );
+    }
+
+    #[test]
+    fn hands_parameters_by_env_args_or_file() {
+        let space: ParamSpace =
+            toml::from_str(r#"temp = { low = 1.0, high = 2.0, env = "T0", arg = "-t={value}" }"#)
+                .unwrap();
+        let assignment = Assignment::from([
+            ("k".to_string(), ParamValue::Int(3)),
+            ("temp".to_string(), ParamValue::Float(1.5)),
+        ]);
+
+        let injection_via = |via| injection(via, &space, &assignment);
+        assert_eq!(
+            injection_via(ParamsVia::Env),
+            Injection {
+                envs: vec![
+                    ("K".to_string(), "3".to_string()),
+                    ("T0".to_string(), "1.5".to_string())
+                ],
+                args: vec!["-t=1.5".to_string()],
+                file: None,
+            }
+        );
+        assert_eq!(injection_via(ParamsVia::Args).args, ["--k", "3", "-t=1.5"]);
+        assert_eq!(
+            injection_via(ParamsVia::File).file.as_deref(),
+            Some("k 3\ntemp 1.5\n")
+        );
+        assert!(injection_via(ParamsVia::File).envs.is_empty());
+
+        assert_eq!(
+            parse_param("k=3"),
+            Ok(("k".to_string(), ParamValue::Int(3)))
+        );
+        assert_eq!(
+            parse_param("mode=fast=1").map(|(_, value)| value),
+            Ok(ParamValue::Str("fast=1".to_string()))
+        );
+        assert_eq!(
+            parse_param("t=0.5").map(|(_, value)| value),
+            Ok(ParamValue::Float(0.5))
+        );
+        assert!(parse_param("=3").is_err());
+        assert!(parse_param("k").is_err());
+    }
+
+    #[test]
+    fn validate_injection_mapping() {
+        let space: ParamSpace = toml::from_str(
+            r#"
+            a = { low = 1.0, high = 2.0, env = "SAME" }
+            b = { low = 1.0, high = 2.0, env = "SAME" }
+            "#,
+        )
+        .unwrap();
+        assert!(validate_space(&space).is_err());
+
+        let space: ParamSpace = toml::from_str(r#"a = { choices = [1], arg = "--a" }"#).unwrap();
+        assert!(validate_space(&space).is_err());
+
+        let space: ParamSpace = toml::from_str(r#"a = { choices = [1], env = "1A" }"#).unwrap();
+        assert!(validate_space(&space).is_err());
+    }
+
+    #[test]
+    fn spec_round_trips_through_json() {
+        let space: ParamSpace =
+            toml::from_str(r#"temp = { low = 1.0, high = 2.0, scale = "log", env = "T0" }"#)
+                .unwrap();
+
+        let json = serde_json::to_string(&space).unwrap();
+        let parsed: ParamSpace = serde_json::from_str(&json).unwrap();
+
+        let Domain::Range(range) = &parsed["temp"].domain else {
+            panic!("temp should be a range");
+        };
+        assert_eq!(range.scale, Scale::Log);
+        assert_eq!(parsed["temp"].env.as_deref(), Some("T0"));
+    }
+
+    #[test]
+    fn sample_respects_type_and_scale() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let range = RangeSpec {
+            low: 1.0,
+            high: 1000.0,
+            kind: ParamType::Int,
+            scale: Scale::Log,
+        };
+
+        let mut below_ten = 0;
+        for _ in 0..1000 {
+            let ParamValue::Int(value) = range.sample(&mut rng) else {
+                panic!("int parameter sampled a non-integer");
+            };
+            assert!((1..=1000).contains(&value));
+            if value < 10 {
+                below_ten += 1;
+            }
+        }
+        // A third of the log range lies below 10, far more than the 1% of a linear range
+        assert!(below_ten > 200);
+    }
+
+    #[test]
+    fn format_assignment_is_sorted_by_name() {
+        let mut assignment = Assignment::new();
+        assignment.insert("t1".to_string(), ParamValue::Float(0.5));
+        assignment.insert("k".to_string(), ParamValue::Int(3));
+        assert_eq!(format_assignment(&assignment), "k=3 t1=0.5");
+    }
+}
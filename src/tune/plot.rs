@@ -0,0 +1,431 @@
+use super::chart::{escape, format_number, gradient, Frame, Scale, Svg};
+use super::params::{format_assignment, Domain, ParamValue};
+use super::study::StudyStore;
+use super::{load_scored_history, Objective, Trial, TuneConfig};
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tracing::info;
+
+/// Number of bins a numeric parameter with many distinct values is split into for its importance
+const IMPORTANCE_BINS: usize = 5;
+
+#[derive(Args)]
+pub(crate) struct PlotArgs {
+    /// File to write the HTML report to instead of plots.html in the study directory
+    #[arg(short, long)]
+    output_file: Option<PathBuf>,
+    /// Name of the study to plot, overriding [tune] study
+    #[arg(short, long)]
+    study: Option<String>,
+    /// Only plot the trials of this input class
+    #[arg(long)]
+    class: Option<usize>,
+}
+
+pub(crate) fn plot(args: PlotArgs, tune_config: &TuneConfig, output: Output) -> Result<()> {
+    let study = args.study.as_deref().unwrap_or(&tune_config.study);
+    let store = StudyStore::new(study);
+    let (history, scoring) = load_scored_history(&store, tune_config)?;
+    let trials = history
+        .iter()
+        .filter(|trial| args.class.is_none() || trial.class == args.class)
+        .collect::<Vec<_>>();
+    if trials.is_empty() {
+        return Err(anyhow!(msg!("tune.nothing_to_plot", study)));
+    }
+    let objective = scoring.objective;
+    let finished = trials
+        .iter()
+        .copied()
+        .filter(|trial| !trial.pruned)
+        .collect::<Vec<_>>();
+    let names = param_names(&trials);
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Study {0}</title>\n\
+         <style>body {{ font-family: sans-serif; margin: 2em; }}</style>\n</head>\n<body>\n\
+         <h1>Study {0}</h1>\n",
+        escape(study)
+    );
+    html.push_str("<h2>Objective by trial</h2>\n");
+    html.push_str(&history_chart(&trials, objective));
+    if !names.is_empty() && !finished.is_empty() {
+        html.push_str("<h2>Parameter importance</h2>\n");
+        html.push_str(&importance_chart(&importance(&finished, &names)));
+        html.push_str("<h2>Parallel coordinates</h2>\n");
+        html.push_str(&parallel_coordinates(&finished, &names, objective));
+    }
+    html.push_str("</body>\n</html>\n");
+
+    let path = args.output_file.unwrap_or_else(|| store.plots_path());
+    std::fs::write(&path, html).context(format!("Failed to write plots: {}", path.display()))?;
+    info!(
+        "{}",
+        msg!("tune.plots_written", trials.len(), path.display())
+    );
+    if output.is_json() {
+        print_json(&json!({ "study": study, "path": path, "trials": trials.len() }))?;
+    }
+    Ok(())
+}
+
+fn param_names(trials: &[&Trial]) -> Vec<String> {
+    let mut names = trials
+        .iter()
+        .flat_map(|trial| trial.params.keys().cloned())
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn numeric(value: &ParamValue) -> Option<f64> {
+    match value {
+        ParamValue::Int(value) => Some(*value as f64),
+        ParamValue::Float(value) => Some(*value),
+        ParamValue::Str(_) => None,
+    }
+}
+
+fn tooltip(trial: &Trial) -> String {
+    format!(
+        "#{}: {} ({})",
+        trial.id,
+        format_number(trial.objective),
+        format_assignment(&trial.params)
+    )
+}
+
+/// Objective of every trial in the order they ran, with the best objective so far.
+pub(crate) fn history_chart(trials: &[&Trial], objective: Objective) -> String {
+    let (width, height) = (800.0, 360.0);
+    let last = trials.iter().map(|trial| trial.id).max().unwrap_or(0);
+    let objectives = trials.iter().map(|trial| trial.objective);
+    let low = objectives.clone().fold(f64::INFINITY, f64::min);
+    let high = objectives.fold(f64::NEG_INFINITY, f64::max);
+    let frame = Frame::new(width, height, (0.0, last as f64), (low, high));
+    let mut svg = Svg::new(width, height);
+    frame.draw_axes(&mut svg, "trial", "objective");
+
+    let mut best: Option<f64> = None;
+    let mut steps = vec![];
+    for trial in trials.iter().filter(|trial| !trial.pruned) {
+        if best.is_none_or(|best| objective.is_better(trial.objective, best)) {
+            let x = frame.x.map(trial.id as f64);
+            if let Some(best) = best {
+                steps.push((x, frame.y.map(best)));
+            }
+            steps.push((x, frame.y.map(trial.objective)));
+            best = Some(trial.objective);
+        }
+    }
+    if let (Some(best), Some(_)) = (best, steps.last()) {
+        steps.push((frame.x.map(last as f64), frame.y.map(best)));
+    }
+    svg.polyline(&steps, "#d62728", 2.0);
+    for trial in trials {
+        let fill = if trial.pruned { "#bbbbbb" } else { "#3366cc" };
+        let center = (frame.x.map(trial.id as f64), frame.y.map(trial.objective));
+        svg.circle(center, 3.5, fill, &tooltip(trial));
+    }
+    svg.finish()
+}
+
+/// Share of the variance of the objective explained by each parameter, highest first.
+fn importance(trials: &[&Trial], names: &[String]) -> Vec<(String, f64)> {
+    let mut importances = names
+        .iter()
+        .map(|name| {
+            let values = trials
+                .iter()
+                .filter_map(|trial| trial.params.get(name).map(|value| (value, trial.objective)))
+                .collect::<Vec<_>>();
+            (name.clone(), explained_variance(&values))
+        })
+        .collect::<Vec<_>>();
+    importances.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    importances
+}
+
+/// Correlation ratio of the objective grouped by the parameter's value, or by quantile bins of
+/// it when it has many distinct values.
+fn explained_variance(values: &[(&ParamValue, f64)]) -> f64 {
+    let mut distinct = values
+        .iter()
+        .map(|(value, _)| value.to_string())
+        .collect::<Vec<_>>();
+    distinct.sort();
+    distinct.dedup();
+    let all_numeric = values.iter().all(|(value, _)| numeric(value).is_some());
+
+    let mut groups: BTreeMap<usize, Vec<f64>> = BTreeMap::new();
+    if all_numeric && distinct.len() > IMPORTANCE_BINS {
+        let mut sorted = values
+            .iter()
+            .map(|(value, objective)| (numeric(value).unwrap_or(0.0), *objective))
+            .collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (rank, (_, objective)) in sorted.iter().enumerate() {
+            let bin = rank * IMPORTANCE_BINS / sorted.len();
+            groups.entry(bin).or_default().push(*objective);
+        }
+    } else {
+        for (value, objective) in values {
+            let key = distinct
+                .binary_search(&value.to_string())
+                .unwrap_or_default();
+            groups.entry(key).or_default().push(*objective);
+        }
+    }
+
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mean = values.iter().map(|(_, objective)| objective).sum::<f64>() / values.len() as f64;
+    let total = values
+        .iter()
+        .map(|(_, objective)| (objective - mean).powi(2))
+        .sum::<f64>();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    let between = groups
+        .values()
+        .map(|group| {
+            let group_mean = group.iter().sum::<f64>() / group.len() as f64;
+            group.len() as f64 * (group_mean - mean).powi(2)
+        })
+        .sum::<f64>();
+    (between / total).clamp(0.0, 1.0)
+}
+
+fn importance_chart(importances: &[(String, f64)]) -> String {
+    let bar = 24.0;
+    let label_width = 160.0;
+    let (width, height) = (800.0, 40.0 + bar * importances.len() as f64);
+    let scale = Scale::new(0.0, 1.0, label_width, width - 80.0);
+    let mut svg = Svg::new(width, height);
+    for (i, (name, importance)) in importances.iter().enumerate() {
+        let top = 10.0 + bar * i as f64;
+        svg.text((label_width - 8.0, top + 16.0), "end", name);
+        let length = scale.map(*importance) - label_width;
+        svg.rect((label_width, top + 4.0), (length, bar - 8.0), "#3366cc");
+        svg.text(
+            (label_width + length + 6.0, top + 16.0),
+            "start",
+            &format!("{:.0}%", importance * 100.0),
+        );
+    }
+    svg.finish()
+}
+
+/// One vertical axis per parameter and one for the objective, with a line per trial colored from
+/// worst to best.
+fn parallel_coordinates(trials: &[&Trial], names: &[String], objective: Objective) -> String {
+    let (top, bottom) = (30.0, 330.0);
+    let spacing = 140.0;
+    let width = 120.0 + spacing * names.len() as f64;
+    let mut svg = Svg::new(width, bottom + 30.0);
+
+    let axes = names
+        .iter()
+        .map(|name| Axis::new(name, trials, top, bottom))
+        .chain(std::iter::once(Axis::objective(trials, top, bottom)))
+        .collect::<Vec<_>>();
+    let xs = (0..axes.len())
+        .map(|i| 60.0 + spacing * i as f64)
+        .collect::<Vec<_>>();
+
+    let mut ordered = trials.to_vec();
+    ordered.sort_by(|a, b| {
+        let ordering = a.objective.total_cmp(&b.objective);
+        match objective {
+            Objective::Max => ordering,
+            Objective::Min => ordering.reverse(),
+        }
+    });
+    for (rank, trial) in ordered.iter().enumerate() {
+        let t = if ordered.len() > 1 {
+            rank as f64 / (ordered.len() - 1) as f64
+        } else {
+            1.0
+        };
+        let points = axes
+            .iter()
+            .zip(&xs)
+            .filter_map(|(axis, x)| axis.position(trial).map(|y| (*x, y)))
+            .collect::<Vec<_>>();
+        svg.polyline(&points, &gradient(t), 1.5);
+    }
+
+    for (axis, x) in axes.iter().zip(&xs) {
+        svg.line((*x, top), (*x, bottom), "#333");
+        svg.text((*x, top - 12.0), "middle", &axis.name);
+        for (y, label) in &axis.labels {
+            svg.text((x + 5.0, y + 4.0), "start", label);
+        }
+    }
+    svg.finish()
+}
+
+struct Axis {
+    name: String,
+    kind: AxisKind,
+    labels: Vec<(f64, String)>,
+}
+
+enum AxisKind {
+    Numeric { scale: Scale, log: bool },
+    Categories(Vec<String>, Scale),
+    Objective(Scale),
+}
+
+impl Axis {
+    fn new(name: &str, trials: &[&Trial], top: f64, bottom: f64) -> Self {
+        let values = trials
+            .iter()
+            .filter_map(|trial| trial.params.get(name))
+            .collect::<Vec<_>>();
+        let log = trials.iter().any(|trial| {
+            trial.space.get(name).is_some_and(|spec| {
+                matches!(&spec.domain, Domain::Range(range) if range.scale == super::params::Scale::Log)
+            })
+        });
+
+        if values.iter().all(|value| numeric(value).is_some()) {
+            let transform = |value: f64| if log { value.ln() } else { value };
+            let numbers = values
+                .iter()
+                .filter_map(|value| numeric(value))
+                .collect::<Vec<_>>();
+            let scale = Scale::fit(numbers.iter().map(|v| transform(*v)), bottom, top);
+            let (low, high) = numbers
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), v| {
+                    (low.min(*v), high.max(*v))
+                });
+            let labels = if numbers.is_empty() {
+                vec![]
+            } else {
+                vec![
+                    (scale.map(transform(low)), format_number(low)),
+                    (scale.map(transform(high)), format_number(high)),
+                ]
+            };
+            Axis {
+                name: name.to_string(),
+                kind: AxisKind::Numeric { scale, log },
+                labels,
+            }
+        } else {
+            let mut categories = values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>();
+            categories.sort();
+            categories.dedup();
+            let scale = Scale::new(0.0, categories.len().saturating_sub(1) as f64, bottom, top);
+            let labels = categories
+                .iter()
+                .enumerate()
+                .map(|(i, category)| (scale.map(i as f64), category.clone()))
+                .collect();
+            Axis {
+                name: name.to_string(),
+                kind: AxisKind::Categories(categories, scale),
+                labels,
+            }
+        }
+    }
+
+    fn objective(trials: &[&Trial], top: f64, bottom: f64) -> Self {
+        let scale = Scale::fit(trials.iter().map(|trial| trial.objective), bottom, top);
+        let labels = scale
+            .ticks(5)
+            .into_iter()
+            .map(|tick| (scale.map(tick), format_number(tick)))
+            .collect();
+        Axis {
+            name: "objective".to_string(),
+            kind: AxisKind::Objective(scale),
+            labels,
+        }
+    }
+
+    fn position(&self, trial: &Trial) -> Option<f64> {
+        match &self.kind {
+            AxisKind::Objective(scale) => Some(scale.map(trial.objective)),
+            AxisKind::Numeric { scale, log } => {
+                let value = numeric(trial.params.get(&self.name)?)?;
+                Some(scale.map(if *log { value.ln() } else { value }))
+            }
+            AxisKind::Categories(categories, scale) => {
+                let value = trial.params.get(&self.name)?.to_string();
+                let i = categories.binary_search(&value).ok()?;
+                Some(scale.map(i as f64))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tune::params::ParamSpace;
+
+    fn trial(id: usize, k: i64, mode: &str, objective: f64) -> Trial {
+        Trial {
+            id,
+            class: None,
+            params: [
+                ("k".to_string(), ParamValue::Int(k)),
+                ("mode".to_string(), ParamValue::Str(mode.to_string())),
+            ]
+            .into(),
+            space: ParamSpace::new(),
+            cases: vec![],
+            objective,
+            pruned: false,
+        }
+    }
+
+    #[test]
+    fn importance_ranks_the_parameter_driving_the_objective() {
+        let trials = (0..8)
+            .map(|i| {
+                trial(
+                    i,
+                    i as i64,
+                    if i % 2 == 0 { "a" } else { "b" },
+                    i as f64 * 10.0,
+                )
+            })
+            .collect::<Vec<_>>();
+        let trials = trials.iter().collect::<Vec<_>>();
+
+        let importances = importance(&trials, &param_names(&trials));
+
+        assert_eq!(importances[0].0, "k");
+        assert!(importances[0].1 > 0.9);
+        assert!(importances[1].1 < 0.1);
+    }
+
+    #[test]
+    fn charts_are_svg() {
+        let trials = [trial(0, 1, "a", 5.0), trial(1, 2, "b", 7.0)];
+        let trials = trials.iter().collect::<Vec<_>>();
+        let names = param_names(&trials);
+
+        let history = history_chart(&trials, Objective::Max);
+        assert!(history.starts_with("<svg"));
+        assert_eq!(history.matches("<circle").count(), 2);
+        let parallel = parallel_coordinates(&trials, &names, Objective::Max);
+        assert_eq!(parallel.matches("<polyline").count(), 2);
+        assert!(parallel.contains(">objective</text>"));
+    }
+}
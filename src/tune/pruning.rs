@@ -0,0 +1,134 @@
+use super::evaluate::CaseResult;
+use super::objective::Scoring;
+use super::{Objective, Trial};
+use crate::messages::msg;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct PruningConfig {
+    /// Numbers of evaluated seeds at which a trial is compared with earlier ones
+    pub(crate) rungs: Vec<usize>,
+    /// Fraction of trials allowed past each rung; 0.5 is median pruning
+    #[serde(default = "default_keep")]
+    pub(crate) keep: f64,
+    /// Trials which must have reached a rung before anything is pruned there
+    #[serde(default = "default_warmup_trials")]
+    pub(crate) warmup_trials: usize,
+}
+
+fn default_keep() -> f64 {
+    0.5
+}
+
+fn default_warmup_trials() -> usize {
+    5
+}
+
+impl PruningConfig {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.rungs.is_empty() || self.rungs.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(anyhow!(msg!("tune.pruning_rungs")));
+        }
+        if !(self.keep > 0.0 && self.keep <= 1.0) {
+            return Err(anyhow!(msg!("tune.pruning_keep")));
+        }
+        Ok(())
+    }
+
+    /// Checks a running trial once it has evaluated exactly a rung's number of seeds.
+    pub(crate) fn should_prune(
+        &self,
+        partial: &[CaseResult],
+        history: &[Trial],
+        scoring: &Scoring,
+    ) -> bool {
+        let rung = partial.len();
+        if !self.rungs.contains(&rung) {
+            return false;
+        }
+
+        let mut scores = history
+            .iter()
+            .filter(|trial| trial.cases.len() >= rung)
+            .map(|trial| scoring.objective(&trial.cases[..rung]))
+            .collect::<Vec<_>>();
+        if scores.len() < self.warmup_trials.max(1) {
+            return false;
+        }
+        let objective = scoring.objective;
+        scores.sort_by(|a, b| match objective {
+            Objective::Max => b.total_cmp(a),
+            Objective::Min => a.total_cmp(b),
+        });
+        let kept = ((scores.len() as f64 * self.keep).ceil() as usize).clamp(1, scores.len());
+        let threshold = scores[kept - 1];
+        objective.is_better(threshold, scoring.objective(partial))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tune::params::{Assignment, ParamSpace};
+
+    fn cases(scores: &[u64]) -> Vec<CaseResult> {
+        scores
+            .iter()
+            .enumerate()
+            .map(|(seed, score)| CaseResult {
+                seed: seed as u64,
+                score: *score,
+                execution_time: 0.0,
+                error_message: String::new(),
+                curve: None,
+            })
+            .collect()
+    }
+
+    fn history() -> Vec<Trial> {
+        [10, 20, 30, 40]
+            .into_iter()
+            .enumerate()
+            .map(|(id, score)| Trial {
+                id,
+                class: None,
+                params: Assignment::new(),
+                space: ParamSpace::new(),
+                cases: cases(&[score, score, 0, 0]),
+                objective: score as f64 / 2.0,
+                pruned: false,
+            })
+            .collect()
+    }
+
+    fn config() -> PruningConfig {
+        PruningConfig {
+            rungs: vec![2],
+            keep: 0.5,
+            warmup_trials: 3,
+        }
+    }
+
+    #[test]
+    fn prunes_trials_below_median() {
+        let config = config();
+        let history = history();
+        assert!(config.should_prune(&cases(&[15, 15]), &history, &Scoring::mean(Objective::Max)));
+        assert!(!config.should_prune(&cases(&[35, 35]), &history, &Scoring::mean(Objective::Max)));
+        assert!(!config.should_prune(&cases(&[15, 15]), &history, &Scoring::mean(Objective::Min)));
+    }
+
+    #[test]
+    fn only_checks_at_rungs_after_warmup() {
+        let config = config();
+        let history = history();
+        assert!(!config.should_prune(&cases(&[1]), &history, &Scoring::mean(Objective::Max)));
+        assert!(!config.should_prune(&cases(&[1, 1, 1]), &history, &Scoring::mean(Objective::Max)));
+        assert!(!config.should_prune(
+            &cases(&[1, 1]),
+            &history[..2],
+            &Scoring::mean(Objective::Max)
+        ));
+    }
+}
@@ -0,0 +1,256 @@
+use super::evaluate::CommandEvaluator;
+use super::grid;
+use super::objective::Scoring;
+use super::params::{Assignment, Domain, ParamSpace};
+use super::random::RandomSampler;
+use super::stats::paired_t_test_less;
+use super::study::Checkpoint;
+use super::{Objective, Sampler, Trial};
+use crate::messages::msg;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// Settings of the racing strategy, which evaluates a pool of candidates seed by seed and drops
+/// those that are significantly worse than the leader.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct RaceConfig {
+    /// Seeds every candidate runs before the first elimination
+    #[serde(default = "default_first_test")]
+    pub(crate) first_test: usize,
+    /// Confidence required to eliminate a candidate
+    #[serde(default = "default_confidence")]
+    pub(crate) confidence: f64,
+}
+
+fn default_first_test() -> usize {
+    5
+}
+
+fn default_confidence() -> f64 {
+    0.95
+}
+
+impl Default for RaceConfig {
+    fn default() -> Self {
+        RaceConfig {
+            first_test: default_first_test(),
+            confidence: default_confidence(),
+        }
+    }
+}
+
+impl RaceConfig {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.first_test < 2 {
+            return Err(anyhow!(msg!("tune.race_first_test")));
+        }
+        if !(0.5..1.0).contains(&self.confidence) {
+            return Err(anyhow!(msg!("tune.race_confidence")));
+        }
+        Ok(())
+    }
+}
+
+/// Draws the candidates of a race: the whole grid when it fits in the pool, otherwise random points.
+pub(crate) fn sample_pool(
+    space: &ParamSpace,
+    seed: u64,
+    history: &[Trial],
+    size: usize,
+) -> Vec<Assignment> {
+    let is_grid = space
+        .values()
+        .all(|spec| matches!(spec.domain, Domain::Values(_)));
+    if is_grid && grid::grid_size(space) <= size {
+        if let Ok(mut sampler) = grid::GridSampler::new(space) {
+            let mut pool = vec![];
+            while let Some(point) = sampler.propose(space, history, &pool) {
+                pool.push(point);
+            }
+            return pool;
+        }
+    }
+    let mut sampler = RandomSampler::new(seed);
+    let mut pool = vec![];
+    while pool.len() < size {
+        match sampler.propose(space, history, &pool) {
+            Some(point) => pool.push(point),
+            None => break,
+        }
+    }
+    pool
+}
+
+pub(crate) struct Race<'a> {
+    pub(crate) config: &'a RaceConfig,
+    pub(crate) evaluator: &'a CommandEvaluator,
+    pub(crate) scoring: &'a Scoring,
+    pub(crate) jobs: usize,
+}
+
+impl Race<'_> {
+    /// Races `pool` over `seeds`. After every seed, `on_step` gets the candidates still racing and
+    /// those eliminated by that seed; the survivors are handed over as not eliminated at the end.
+    pub(crate) fn run(
+        &self,
+        mut pool: Vec<Checkpoint>,
+        seeds: &[u64],
+        out_of_time: impl Fn() -> bool,
+        mut on_step: impl FnMut(&[Checkpoint], Vec<(Checkpoint, bool)>) -> Result<()>,
+    ) -> Result<()> {
+        loop {
+            let step = pool.iter().map(|c| c.cases.len()).min().unwrap_or(0);
+            if pool.len() <= 1 || step >= seeds.len() {
+                break;
+            }
+            if out_of_time() {
+                info!("{}", msg!("tune.out_of_time"));
+                break;
+            }
+            let behind = (0..pool.len())
+                .filter(|i| pool[*i].cases.len() == step)
+                .collect::<Vec<_>>();
+            let work = behind
+                .iter()
+                .map(|i| (&pool[*i].params, seeds[step]))
+                .collect::<Vec<_>>();
+            let results = self.evaluator.evaluate_all(&work, self.jobs);
+            for (i, case) in behind.into_iter().zip(results) {
+                pool[i].cases.push(case);
+            }
+
+            let eliminated = eliminate(self.config, self.scoring, &mut pool);
+            if !eliminated.is_empty() {
+                info!(
+                    "{}",
+                    msg!("tune.race_step", step + 1, eliminated.len(), pool.len())
+                );
+            }
+            on_step(&pool, eliminated.into_iter().map(|c| (c, true)).collect())?;
+        }
+        on_step(&[], pool.into_iter().map(|c| (c, false)).collect())
+    }
+}
+
+/// Removes the candidates whose per-seed scores are significantly worse than the leader's.
+fn eliminate(
+    config: &RaceConfig,
+    scoring: &Scoring,
+    pool: &mut Vec<Checkpoint>,
+) -> Vec<Checkpoint> {
+    if pool[0].cases.len() < config.first_test {
+        return vec![];
+    }
+    let means = pool
+        .iter()
+        .map(|candidate| scoring.objective(&candidate.cases))
+        .collect::<Vec<_>>();
+    let objective = scoring.objective;
+    let leader = (1..pool.len()).fold(0, |best, i| {
+        if objective.is_better(means[i], means[best]) {
+            i
+        } else {
+            best
+        }
+    });
+    let values = |candidate: &Checkpoint| {
+        candidate
+            .cases
+            .iter()
+            .map(|case| scoring.case_value(case))
+            .collect::<Vec<_>>()
+    };
+    let leader_values = values(&pool[leader]);
+    let significance = 1.0 - config.confidence;
+    let is_dominated = |candidate: &Checkpoint| {
+        // Oriented so that a negative difference means worse than the leader
+        let differences = values(candidate)
+            .iter()
+            .zip(&leader_values)
+            .map(|(value, leader)| match objective {
+                Objective::Max => value - leader,
+                Objective::Min => leader - value,
+            })
+            .collect::<Vec<_>>();
+        paired_t_test_less(&differences) < significance
+    };
+
+    let mut eliminated = vec![];
+    let mut kept = vec![];
+    for (i, candidate) in std::mem::take(pool).into_iter().enumerate() {
+        if i != leader && is_dominated(&candidate) {
+            eliminated.push(candidate);
+        } else {
+            kept.push(candidate);
+        }
+    }
+    *pool = kept;
+    eliminated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tune::evaluate::CaseResult;
+    use crate::tune::params::ParamValue;
+
+    fn candidate(k: i64, scores: &[u64]) -> Checkpoint {
+        Checkpoint {
+            class: None,
+            params: [("k".to_string(), ParamValue::Int(k))].into(),
+            cases: scores
+                .iter()
+                .enumerate()
+                .map(|(seed, score)| CaseResult {
+                    seed: seed as u64,
+                    score: *score,
+                    execution_time: 0.0,
+                    error_message: String::new(),
+                    curve: None,
+                })
+                .collect(),
+        }
+    }
+
+    const CONFIG: RaceConfig = RaceConfig {
+        first_test: 3,
+        confidence: 0.95,
+    };
+
+    #[test]
+    fn eliminates_consistently_worse_candidates() {
+        let mut pool = vec![
+            candidate(0, &[100, 210, 300, 400]),
+            candidate(1, &[90, 200, 290, 380]),
+            candidate(2, &[120, 180, 320, 390]),
+        ];
+
+        let eliminated = eliminate(&CONFIG, &Scoring::mean(Objective::Max), &mut pool);
+
+        assert_eq!(eliminated.len(), 1);
+        assert_eq!(eliminated[0].params["k"], ParamValue::Int(1));
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn waits_for_first_test() {
+        let mut pool = vec![candidate(0, &[100, 200]), candidate(1, &[1, 2])];
+        assert!(eliminate(&CONFIG, &Scoring::mean(Objective::Max), &mut pool).is_empty());
+
+        let mut pool = vec![candidate(0, &[100, 200, 300]), candidate(1, &[1, 2, 3])];
+        let eliminated = eliminate(&CONFIG, &Scoring::mean(Objective::Min), &mut pool);
+        assert_eq!(eliminated[0].params["k"], ParamValue::Int(0));
+    }
+
+    #[test]
+    fn pool_uses_grid_when_it_fits() {
+        let mut space = ParamSpace::new();
+        space.insert(
+            "k".to_string(),
+            crate::tune::params::ParamSpec::values(vec![ParamValue::Int(1), ParamValue::Int(2)]),
+        );
+        assert_eq!(sample_pool(&space, 0, &[], 5).len(), 2);
+        assert_eq!(sample_pool(&space, 0, &[], 1).len(), 1);
+    }
+}
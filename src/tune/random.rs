@@ -0,0 +1,113 @@
+use super::params::{Assignment, Domain, ParamSpace};
+use super::Sampler;
+use super::Trial;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+pub(crate) struct RandomSampler {
+    seed: u64,
+}
+
+impl RandomSampler {
+    pub(crate) fn new(seed: u64) -> Self {
+        RandomSampler { seed }
+    }
+}
+
+impl Sampler for RandomSampler {
+    fn propose(
+        &mut self,
+        space: &ParamSpace,
+        history: &[Trial],
+        running: &[Assignment],
+    ) -> Option<Assignment> {
+        let trial_count = history.len() + running.len();
+        sample_uniformly(space, &mut proposal_rng(self.seed, trial_count))
+    }
+}
+
+/// Seeds a generator from the sampler seed and the number of trials so far, so a resumed
+/// study proposes exactly what an uninterrupted one would have.
+pub(crate) fn proposal_rng(seed: u64, trial_count: usize) -> StdRng {
+    StdRng::seed_from_u64(seed ^ (trial_count as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+pub(crate) fn sample_uniformly<R: Rng>(space: &ParamSpace, rng: &mut R) -> Option<Assignment> {
+    let assignment = space
+        .iter()
+        .map(|(name, spec)| {
+            let value = match &spec.domain {
+                Domain::Values(values) => values[rng.random_range(0..values.len())].clone(),
+                Domain::Range(range) => range.sample(rng),
+            };
+            (name.clone(), value)
+        })
+        .collect();
+    Some(assignment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tune::params::{ParamSpec, ParamType, ParamValue, RangeSpec, Scale};
+
+    #[test]
+    fn proposals_stay_in_range() {
+        let mut space = ParamSpace::new();
+        space.insert(
+            "temp".to_string(),
+            ParamSpec::range(RangeSpec {
+                low: 1.0,
+                high: 2.0,
+                kind: ParamType::Float,
+                scale: Scale::Linear,
+            }),
+        );
+        space.insert(
+            "neighbors".to_string(),
+            ParamSpec::values(vec![ParamValue::Int(2), ParamValue::Int(3)]),
+        );
+        for seed in 0..100 {
+            let assignment = RandomSampler::new(seed).propose(&space, &[], &[]).unwrap();
+            let ParamValue::Float(value) = assignment["temp"] else {
+                panic!("unexpected value type");
+            };
+            assert!((1.0..=2.0).contains(&value));
+            assert!(matches!(
+                assignment["neighbors"],
+                ParamValue::Int(2) | ParamValue::Int(3)
+            ));
+        }
+    }
+
+    #[test]
+    fn proposals_depend_only_on_seed_and_history_length() {
+        let mut space = ParamSpace::new();
+        space.insert(
+            "temp".to_string(),
+            ParamSpec::range(RangeSpec {
+                low: 0.0,
+                high: 1.0,
+                kind: ParamType::Float,
+                scale: Scale::Linear,
+            }),
+        );
+        let mut sampler = RandomSampler::new(7);
+        let first = sampler.propose(&space, &[], &[]).unwrap();
+        assert_eq!(sampler.propose(&space, &[], &[]).unwrap(), first);
+        assert_eq!(
+            RandomSampler::new(7).propose(&space, &[], &[]).unwrap(),
+            first
+        );
+        assert_ne!(
+            RandomSampler::new(8).propose(&space, &[], &[]).unwrap(),
+            first
+        );
+        assert_ne!(
+            sampler
+                .propose(&space, &[], std::slice::from_ref(&first))
+                .unwrap(),
+            first
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use super::params::ParamValue;
+use super::{Objective, Trial};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+fn param_names(trials: &[Trial]) -> Vec<String> {
+    let mut names = trials
+        .iter()
+        .flat_map(|trial| trial.params.keys().cloned())
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn failed_cases(trial: &Trial) -> usize {
+    trial
+        .cases
+        .iter()
+        .filter(|case| !case.error_message.is_empty())
+        .count()
+}
+
+fn has_classes(trials: &[Trial]) -> bool {
+    trials.iter().any(|trial| trial.class.is_some())
+}
+
+fn has_pruned(trials: &[Trial]) -> bool {
+    trials.iter().any(|trial| trial.pruned)
+}
+
+fn rows(trials: &[Trial], names: &[String]) -> Vec<Vec<String>> {
+    let has_classes = has_classes(trials);
+    let has_pruned = has_pruned(trials);
+    trials
+        .iter()
+        .map(|trial| {
+            let mut row = vec![trial.id.to_string()];
+            if has_classes {
+                row.push(trial.class.map(|c| c.to_string()).unwrap_or_default());
+            }
+            row.extend(names.iter().map(|name| {
+                trial
+                    .params
+                    .get(name)
+                    .map(ParamValue::to_string)
+                    .unwrap_or_default()
+            }));
+            row.push(format!("{:.2}", trial.objective));
+            row.push(failed_cases(trial).to_string());
+            if has_pruned {
+                row.push(if trial.pruned { "yes" } else { "" }.to_string());
+            }
+            row
+        })
+        .collect()
+}
+
+fn header(trials: &[Trial], names: &[String]) -> Vec<String> {
+    let mut header = vec!["id".to_string()];
+    if has_classes(trials) {
+        header.push("class".to_string());
+    }
+    header.extend(names.iter().cloned());
+    header.push("objective".to_string());
+    header.push("failed".to_string());
+    if has_pruned(trials) {
+        header.push("pruned".to_string());
+    }
+    header
+}
+
+/// Formats the trials as an aligned table, best trial first.
+pub(crate) fn format_table(trials: &[Trial], objective: Objective) -> String {
+    let mut sorted = trials.to_vec();
+    sorted.sort_by(|a, b| {
+        let ordering = a.objective.total_cmp(&b.objective);
+        match objective {
+            Objective::Max => ordering.reverse(),
+            Objective::Min => ordering,
+        }
+    });
+
+    let names = param_names(&sorted);
+    let header = header(&sorted, &names);
+    let rows = rows(&sorted, &names);
+    let widths = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].len())
+                .chain(std::iter::once(header[i].len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let format_row = |row: &[String]| {
+        row.iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:>width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    let mut output = format_row(&header);
+    output.push('\n');
+    for row in &rows {
+        output.push_str(&format_row(row));
+        output.push('\n');
+    }
+    output
+}
+
+/// Formats the latest `limit` trials as a Markdown table in the order they ran, with the change
+/// each made to the best objective of its class.
+pub(crate) fn format_markdown(trials: &[Trial], objective: Objective, limit: usize) -> String {
+    let names = param_names(trials);
+    let mut best = BTreeMap::new();
+    let deltas = trials
+        .iter()
+        .map(|trial| {
+            if trial.pruned {
+                return String::new();
+            }
+            let previous = best.get(&trial.class).copied();
+            if previous.is_none_or(|previous| objective.is_better(trial.objective, previous)) {
+                best.insert(trial.class, trial.objective);
+            }
+            match previous {
+                Some(previous) if objective.is_better(trial.objective, previous) => {
+                    format!("{:+.2}", trial.objective - previous)
+                }
+                _ => String::new(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut header = header(trials, &names);
+    header.push("Δ best".to_string());
+    let skipped = trials.len().saturating_sub(limit);
+    let rows = rows(&trials[skipped..], &names)
+        .into_iter()
+        .zip(&deltas[skipped..])
+        .map(|(mut row, delta)| {
+            row.push(delta.clone());
+            row
+        });
+
+    let format_row = |row: &[String]| {
+        let cells = row
+            .iter()
+            .map(|cell| cell.replace('|', "\\|"))
+            .collect::<Vec<_>>();
+        format!("| {} |\n", cells.join(" | "))
+    };
+    let mut output = format_row(&header);
+    output.push_str(&format_row(&vec!["---".to_string(); header.len()]));
+    for row in rows {
+        output.push_str(&format_row(&row));
+    }
+    if skipped > 0 {
+        output.push_str(&format!("\n{} earlier trial(s) not shown\n", skipped));
+    }
+    output
+}
+
+pub(crate) fn format_csv(trials: &[Trial]) -> String {
+    let names = param_names(trials);
+    let escape = |cell: &String| {
+        if cell.contains([',', '"', '\n']) {
+            format!("\"{}\"", cell.replace('"', "\"\""))
+        } else {
+            cell.clone()
+        }
+    };
+
+    let mut output = String::new();
+    for row in std::iter::once(header(trials, &names)).chain(rows(trials, &names)) {
+        output.push_str(&row.iter().map(escape).collect::<Vec<_>>().join(","));
+        output.push('\n');
+    }
+    output
+}
+
+pub(crate) fn write_csv(path: &Path, trials: &[Trial]) -> Result<()> {
+    std::fs::write(path, format_csv(trials))
+        .context(format!("Failed to write trials to: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tune::params::{Assignment, ParamSpace};
+
+    fn trials() -> Vec<Trial> {
+        [(0, 1, "a,b", 10.0), (1, 20, "c", 30.0)]
+            .into_iter()
+            .map(|(id, neighbors, name, objective)| {
+                let mut params = Assignment::new();
+                params.insert("neighbors".to_string(), ParamValue::Int(neighbors));
+                params.insert("mode".to_string(), ParamValue::Str(name.to_string()));
+                Trial {
+                    id,
+                    pruned: false,
+                    class: None,
+                    space: ParamSpace::new(),
+                    params,
+                    cases: vec![],
+                    objective,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn table_is_sorted_best_first() {
+        let table = format_table(&trials(), Objective::Max);
+        assert_eq!(
+            table,
+            "id  mode  neighbors  objective  failed\n\
+             \x201     c         20      30.00       0\n\
+             \x200   a,b          1      10.00       0\n"
+        );
+    }
+
+    #[test]
+    fn markdown_shows_latest_trials_with_deltas() {
+        let mut trials = trials();
+        trials.push(Trial {
+            id: 2,
+            objective: 35.5,
+            ..trials[1].clone()
+        });
+        assert_eq!(
+            format_markdown(&trials, Objective::Max, 2),
+            "| id | mode | neighbors | objective | failed | Δ best |\n\
+             | --- | --- | --- | --- | --- | --- |\n\
+             | 1 | c | 20 | 30.00 | 0 | +20.00 |\n\
+             | 2 | c | 20 | 35.50 | 0 | +5.50 |\n\
+             \n1 earlier trial(s) not shown\n"
+        );
+    }
+
+    #[test]
+    fn csv_includes_class_and_pruned_columns() {
+        let mut trials = trials();
+        trials[1].class = Some(2);
+        trials[0].pruned = true;
+        assert_eq!(
+            format_csv(&trials),
+            "id,class,mode,neighbors,objective,failed,pruned\n0,,\"a,b\",1,10.00,0,yes\n1,2,c,20,30.00,0,\n"
+        );
+    }
+
+    #[test]
+    fn csv_escapes_cells() {
+        assert_eq!(
+            format_csv(&trials()),
+            "id,mode,neighbors,objective,failed\n0,\"a,b\",1,10.00,0\n1,c,20,30.00,0\n"
+        );
+    }
+}
@@ -0,0 +1,186 @@
+/// Mean and sample standard deviation.
+pub(crate) fn mean_and_sd(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    if values.len() < 2 {
+        return (mean, 0.0);
+    }
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance.sqrt())
+}
+
+/// One-sided p-value of a paired t-test that the differences have a negative mean.
+pub(crate) fn paired_t_test_less(differences: &[f64]) -> f64 {
+    let (mean, sd) = mean_and_sd(differences);
+    if differences.len() < 2 || sd == 0.0 {
+        return if mean < 0.0 { 0.0 } else { 1.0 };
+    }
+    let t = mean / (sd / (differences.len() as f64).sqrt());
+    student_t_cdf(t, (differences.len() - 1) as f64)
+}
+
+/// Two-sided p-value of a Wilcoxon signed-rank test that the differences are centered on 0, by
+/// the normal approximation with the variance corrected for ties. Zero differences are dropped.
+pub(crate) fn wilcoxon_signed_rank(differences: &[f64]) -> f64 {
+    let mut nonzero = differences
+        .iter()
+        .copied()
+        .filter(|difference| *difference != 0.0)
+        .collect::<Vec<_>>();
+    if nonzero.is_empty() {
+        return 1.0;
+    }
+    nonzero.sort_by(|a, b| a.abs().total_cmp(&b.abs()));
+    let (mut positive_ranks, mut ties) = (0.0, 0.0);
+    let mut start = 0;
+    while start < nonzero.len() {
+        let end = start
+            + nonzero[start..]
+                .iter()
+                .take_while(|difference| difference.abs() == nonzero[start].abs())
+                .count();
+        // Tied differences share the mean of their ranks
+        let rank = (start + end + 1) as f64 / 2.0;
+        let tied = (end - start) as f64;
+        ties += tied.powi(3) - tied;
+        positive_ranks += rank * nonzero[start..end].iter().filter(|d| **d > 0.0).count() as f64;
+        start = end;
+    }
+    let n = nonzero.len() as f64;
+    let mean = n * (n + 1.0) / 4.0;
+    let variance = n * (n + 1.0) * (2.0 * n + 1.0) / 24.0 - ties / 48.0;
+    if variance <= 0.0 {
+        return 1.0;
+    }
+    let z = ((positive_ranks - mean).abs() - 0.5).max(0.0) / variance.sqrt();
+    (2.0 * (1.0 - normal_cdf(z))).min(1.0)
+}
+
+/// Standard normal CDF, by the approximation of erf in Abramowitz and Stegun 7.1.26.
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - polynomial * (-x * x).exp();
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+pub(crate) fn student_t_cdf(t: f64, df: f64) -> f64 {
+    let tail = 0.5 * incomplete_beta(df / 2.0, 0.5, df / (df + t * t));
+    if t > 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+/// Regularized incomplete beta function I_x(a, b).
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // The continued fraction converges quickly only below the mean of the distribution
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut result = d;
+    for m in 1..200 {
+        let m = m as f64;
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = 1.0 + numerator / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            result *= d * c;
+        }
+        if (d * c - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    result
+}
+
+/// Lanczos approximation of ln Γ(x) for x > 0.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .fold(1.000000000190015, |sum, (i, c)| {
+            sum + c / (x + 1.0 + i as f64)
+        });
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_distribution_matches_tables() {
+        // Two-sided 95% critical values
+        assert!((student_t_cdf(2.262, 9.0) - 0.975).abs() < 1e-3);
+        assert!((student_t_cdf(1.96, 1e6) - 0.975).abs() < 1e-3);
+        assert!((student_t_cdf(0.0, 4.0) - 0.5).abs() < 1e-9);
+        assert!((student_t_cdf(-12.706, 1.0) - 0.025).abs() < 1e-3);
+    }
+
+    #[test]
+    fn paired_t_test_detects_consistent_losses() {
+        assert!(paired_t_test_less(&[-3.0, -2.0, -4.0, -3.5, -2.5]) < 0.01);
+        assert!(paired_t_test_less(&[-1.0, 1.0, -2.0, 2.0]) > 0.3);
+        assert_eq!(paired_t_test_less(&[-1.0, -1.0]), 0.0);
+    }
+
+    #[test]
+    fn wilcoxon_signed_rank_separates_shifts_from_noise() {
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-4);
+        assert!((normal_cdf(-1.0) - 0.1587).abs() < 1e-4);
+
+        let shifted = (1..=10).map(f64::from).collect::<Vec<_>>();
+        // The exact p-value is 2/1024, the normal approximation 0.0059
+        assert!((wilcoxon_signed_rank(&shifted) - 0.0059).abs() < 1e-3);
+        assert!(wilcoxon_signed_rank(&[-1.0, 1.0, -2.0, 2.0, 0.0]) > 0.9);
+        assert_eq!(wilcoxon_signed_rank(&[0.0, 0.0]), 1.0);
+        assert_eq!(wilcoxon_signed_rank(&[3.0]), wilcoxon_signed_rank(&[-3.0]));
+    }
+}
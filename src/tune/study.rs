@@ -0,0 +1,277 @@
+use super::evaluate::CaseResult;
+use super::params::{Assignment, ParamSpace};
+use super::{Objective, Strategy, Trial};
+use crate::messages::msg;
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+const TUNE_DIR: &str = ".ahc/tune";
+const TRIALS_FILE_NAME: &str = "trials.jsonl";
+const TRIALS_CSV_FILE_NAME: &str = "trials.csv";
+const DECISION_TABLE_FILE_NAME: &str = "decision_table.json";
+const STATE_FILE_NAME: &str = "state.json";
+const CHECKPOINT_FILE_NAME: &str = "checkpoint.json";
+const PLOTS_FILE_NAME: &str = "plots.html";
+const VALIDATION_FILE_NAME: &str = "validation.json";
+
+/// The plan of the latest run of a study, kept so `ahc tune --resume` can finish it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct StudyState {
+    pub(crate) strategy: Strategy,
+    pub(crate) objective: Objective,
+    pub(crate) sampler_seed: u64,
+    pub(crate) start_seed: u64,
+    pub(crate) end_seed: u64,
+    pub(crate) space: ParamSpace,
+    /// Trials to run per class, `None` when the run lasts until the time budget is spent
+    pub(crate) trials: Option<usize>,
+    #[serde(default)]
+    pub(crate) time_budget: Option<String>,
+    /// Id of the first trial of the run
+    pub(crate) first_trial: usize,
+    /// Time spent so far, which a resumed run deducts from the time budget
+    #[serde(default)]
+    pub(crate) elapsed_secs: f64,
+}
+
+/// The cases finished so far by a running trial, so an interrupted study can pick it up again.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Checkpoint {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) class: Option<usize>,
+    pub(crate) params: Assignment,
+    pub(crate) cases: Vec<CaseResult>,
+}
+
+pub(crate) struct StudyStore {
+    dir: PathBuf,
+}
+
+impl StudyStore {
+    pub(crate) fn new(study: &str) -> Self {
+        StudyStore {
+            dir: Path::new(TUNE_DIR).join(study),
+        }
+    }
+
+    /// Names of the studies recorded in the project.
+    pub(crate) fn list() -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(TUNE_DIR) else {
+            return vec![];
+        };
+        let mut names = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    fn trials_path(&self) -> PathBuf {
+        self.dir.join(TRIALS_FILE_NAME)
+    }
+
+    pub(crate) fn csv_path(&self) -> PathBuf {
+        self.dir.join(TRIALS_CSV_FILE_NAME)
+    }
+
+    pub(crate) fn decision_table_path(&self) -> PathBuf {
+        self.dir.join(DECISION_TABLE_FILE_NAME)
+    }
+
+    pub(crate) fn plots_path(&self) -> PathBuf {
+        self.dir.join(PLOTS_FILE_NAME)
+    }
+
+    pub(crate) fn validation_path(&self) -> PathBuf {
+        self.dir.join(VALIDATION_FILE_NAME)
+    }
+
+    /// Loads the recorded trials. A last line cut off by an interruption is dropped from the
+    /// file so that later trials are appended after a complete line.
+    pub(crate) fn load(&self) -> Result<Vec<Trial>> {
+        let path = self.trials_path();
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let content = std::fs::read_to_string(&path)
+            .context(format!("Failed to read trials file: {}", path.display()))?;
+        let complete = content.rfind('\n').map_or(0, |i| i + 1);
+        if complete < content.len() {
+            warn!("{}", msg!("tune.truncated_trial", path.display()));
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .and_then(|file| file.set_len(complete as u64))
+                .context(format!("Failed to repair trials file: {}", path.display()))?;
+        }
+
+        let mut trials = vec![];
+        for (i, line) in content[..complete].lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let trial = serde_json::from_str(line).context(format!(
+                "Failed to parse trial on line {} of {}",
+                i + 1,
+                path.display()
+            ))?;
+            trials.push(trial);
+        }
+        Ok(trials)
+    }
+
+    pub(crate) fn append(&self, trial: &Trial) -> Result<()> {
+        self.create_dir()?;
+        let path = self.trials_path();
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context(format!("Failed to open trials file: {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(trial)?)?;
+        Ok(())
+    }
+
+    pub(crate) fn load_state(&self) -> Result<Option<StudyState>> {
+        self.read_json(STATE_FILE_NAME)
+    }
+
+    pub(crate) fn save_state(&self, state: &StudyState) -> Result<()> {
+        self.write_json(STATE_FILE_NAME, state)
+    }
+
+    pub(crate) fn load_checkpoints(&self) -> Result<Vec<Checkpoint>> {
+        Ok(self.read_json(CHECKPOINT_FILE_NAME)?.unwrap_or_default())
+    }
+
+    /// Records the running trials, removing the checkpoint file once none are left.
+    pub(crate) fn save_checkpoints(&self, checkpoints: &[Checkpoint]) -> Result<()> {
+        if !checkpoints.is_empty() {
+            return self.write_json(CHECKPOINT_FILE_NAME, &checkpoints);
+        }
+        let path = self.dir.join(CHECKPOINT_FILE_NAME);
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .context(format!("Failed to remove checkpoint: {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    fn create_dir(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).context(format!(
+            "Failed to create directory: {}",
+            self.dir.display()
+        ))
+    }
+
+    fn read_json<T: DeserializeOwned>(&self, file_name: &str) -> Result<Option<T>> {
+        let path = self.dir.join(file_name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .context(format!("Failed to read file: {}", path.display()))?;
+        let value = serde_json::from_str(&content)
+            .context(format!("Failed to parse file: {}", path.display()))?;
+        Ok(Some(value))
+    }
+
+    // Written to a temporary file first so an interruption never leaves a partial file behind
+    fn write_json<T: Serialize>(&self, file_name: &str, value: &T) -> Result<()> {
+        self.create_dir()?;
+        let path = self.dir.join(file_name);
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_string_pretty(value)?)
+            .context(format!("Failed to write file: {}", temp_path.display()))?;
+        std::fs::rename(&temp_path, &path)
+            .context(format!("Failed to write file: {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tune::params::ParamValue;
+
+    fn store(dir: &Path) -> StudyStore {
+        StudyStore {
+            dir: dir.join("study"),
+        }
+    }
+
+    fn trial(id: usize, objective: f64) -> Trial {
+        let mut params = Assignment::new();
+        params.insert("temp".to_string(), ParamValue::Float(id as f64));
+        Trial {
+            id,
+            pruned: false,
+            class: None,
+            space: ParamSpace::new(),
+            params,
+            cases: vec![],
+            objective,
+        }
+    }
+
+    #[test]
+    fn trials_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path());
+        assert!(store.load().unwrap().is_empty());
+
+        store.append(&trial(0, 1.0)).unwrap();
+        store.append(&trial(1, 2.0)).unwrap();
+
+        let trials = store.load().unwrap();
+        assert_eq!(trials.len(), 2);
+        assert_eq!(trials[1].objective, 2.0);
+        assert_eq!(trials[1].params["temp"], ParamValue::Float(1.0));
+    }
+
+    #[test]
+    fn truncated_trial_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path());
+        store.append(&trial(0, 1.0)).unwrap();
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(store.trials_path())
+            .unwrap();
+        write!(file, "{{\"id\":1,\"par").unwrap();
+
+        assert_eq!(store.load().unwrap().len(), 1);
+        store.append(&trial(1, 2.0)).unwrap();
+        assert_eq!(store.load().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn checkpoints_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path());
+        assert!(store.load_checkpoints().unwrap().is_empty());
+
+        let checkpoints = vec![Checkpoint {
+            class: Some(1),
+            params: trial(0, 0.0).params,
+            cases: vec![CaseResult {
+                seed: 0,
+                score: 10,
+                execution_time: 0.5,
+                error_message: String::new(),
+                curve: None,
+            }],
+        }];
+        store.save_checkpoints(&checkpoints).unwrap();
+        assert_eq!(store.load_checkpoints().unwrap(), checkpoints);
+
+        store.save_checkpoints(&[]).unwrap();
+        assert!(store.load_checkpoints().unwrap().is_empty());
+        assert!(!store.dir.join(CHECKPOINT_FILE_NAME).exists());
+    }
+}
@@ -0,0 +1,299 @@
+use super::params::{Assignment, Domain, ParamSpace, ParamValue, RangeSpec, Scale};
+use super::random::proposal_rng;
+use super::{Objective, Sampler, Trial};
+use rand::rngs::StdRng;
+use rand::Rng;
+
+const STARTUP_TRIALS: usize = 10;
+const CANDIDATES: usize = 24;
+const GAMMA: f64 = 0.25;
+
+/// Tree-structured Parzen Estimator: models the density of good and bad trials per parameter
+/// and proposes the candidate which maximizes their ratio.
+pub(crate) struct TpeSampler {
+    seed: u64,
+    rng: StdRng,
+    objective: Objective,
+}
+
+impl TpeSampler {
+    pub(crate) fn new(seed: u64, objective: Objective) -> Self {
+        TpeSampler {
+            seed,
+            rng: proposal_rng(seed, 0),
+            objective,
+        }
+    }
+
+    fn split<'a>(&self, history: &'a [Trial]) -> (Vec<&'a Assignment>, Vec<&'a Assignment>) {
+        let mut sorted = history.iter().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| {
+            let ordering = a.objective.total_cmp(&b.objective);
+            match self.objective {
+                Objective::Max => ordering.reverse(),
+                Objective::Min => ordering,
+            }
+        });
+        let good_count = ((sorted.len() as f64 * GAMMA).ceil() as usize).max(1);
+        let bad = sorted.split_off(good_count);
+        let params =
+            |trials: Vec<&'a Trial>| trials.into_iter().map(|trial| &trial.params).collect();
+        (params(sorted), params(bad))
+    }
+
+    fn propose_range(
+        &mut self,
+        name: &str,
+        range: &RangeSpec,
+        good: &[&Assignment],
+        bad: &[&Assignment],
+    ) -> ParamValue {
+        let (low, high) = to_internal(range, range.low, range.high);
+        let observations = |trials: &[&Assignment]| {
+            trials
+                .iter()
+                .filter_map(|params| params.get(name).and_then(as_f64))
+                .map(|value| to_internal(range, value, value).0)
+                .collect::<Vec<_>>()
+        };
+        let good = Parzen::new(observations(good), low, high);
+        let bad = Parzen::new(observations(bad), low, high);
+
+        let mut best = (f64::NEG_INFINITY, low);
+        for _ in 0..CANDIDATES {
+            let candidate = good.sample(&mut self.rng);
+            let score = good.log_density(candidate) - bad.log_density(candidate);
+            if score > best.0 {
+                best = (score, candidate);
+            }
+        }
+        let value = match range.scale {
+            Scale::Linear => best.1,
+            Scale::Log => best.1.exp(),
+        };
+        range.to_value(value.clamp(range.low, range.high))
+    }
+
+    fn propose_value(
+        &mut self,
+        name: &str,
+        values: &[ParamValue],
+        good: &[&Assignment],
+        bad: &[&Assignment],
+    ) -> ParamValue {
+        // Laplace smoothing keeps every value reachable
+        let weights = |trials: &[&Assignment]| {
+            let mut weights = vec![1.0; values.len()];
+            for params in trials {
+                if let Some(i) = values.iter().position(|v| params.get(name) == Some(v)) {
+                    weights[i] += 1.0;
+                }
+            }
+            let total = weights.iter().sum::<f64>();
+            weights.into_iter().map(|w| w / total).collect::<Vec<_>>()
+        };
+        let good = weights(good);
+        let bad = weights(bad);
+
+        let mut best = (f64::NEG_INFINITY, 0);
+        for _ in 0..CANDIDATES {
+            let mut r = self.rng.random::<f64>();
+            let mut candidate = good.len() - 1;
+            for (i, weight) in good.iter().enumerate() {
+                if r < *weight {
+                    candidate = i;
+                    break;
+                }
+                r -= weight;
+            }
+            let score = good[candidate].ln() - bad[candidate].ln();
+            if score > best.0 {
+                best = (score, candidate);
+            }
+        }
+        values[best.1].clone()
+    }
+}
+
+impl Sampler for TpeSampler {
+    fn propose(
+        &mut self,
+        space: &ParamSpace,
+        history: &[Trial],
+        running: &[Assignment],
+    ) -> Option<Assignment> {
+        self.rng = proposal_rng(self.seed, history.len() + running.len());
+        if history.len() < STARTUP_TRIALS {
+            return super::random::sample_uniformly(space, &mut self.rng);
+        }
+
+        // Running trials count as bad ones, which steers concurrent proposals apart
+        let (good, mut bad) = self.split(history);
+        bad.extend(running);
+        let assignment = space
+            .iter()
+            .map(|(name, spec)| {
+                let value = match &spec.domain {
+                    Domain::Values(values) => self.propose_value(name, values, &good, &bad),
+                    Domain::Range(range) => self.propose_range(name, range, &good, &bad),
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        Some(assignment)
+    }
+}
+
+fn as_f64(value: &ParamValue) -> Option<f64> {
+    match value {
+        ParamValue::Int(value) => Some(*value as f64),
+        ParamValue::Float(value) => Some(*value),
+        ParamValue::Str(_) => None,
+    }
+}
+
+fn to_internal(range: &RangeSpec, low: f64, high: f64) -> (f64, f64) {
+    match range.scale {
+        Scale::Linear => (low, high),
+        Scale::Log => (low.ln(), high.ln()),
+    }
+}
+
+/// Mixture of gaussians centered on the observations plus a wide prior over the whole range.
+struct Parzen {
+    centers: Vec<f64>,
+    bandwidth: f64,
+    prior_center: f64,
+    prior_bandwidth: f64,
+    low: f64,
+    high: f64,
+}
+
+impl Parzen {
+    fn new(centers: Vec<f64>, low: f64, high: f64) -> Self {
+        let width = (high - low).max(f64::EPSILON);
+        let n = centers.len().max(1) as f64;
+        let mean = centers.iter().sum::<f64>() / n;
+        let std = (centers.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / n).sqrt();
+        // Scott's rule, bounded so a tight cluster keeps exploring around itself
+        let bandwidth = (1.06 * std * n.powf(-0.2)).clamp(width / 100.0, width);
+        Parzen {
+            centers,
+            bandwidth,
+            prior_center: (low + high) / 2.0,
+            prior_bandwidth: width,
+            low,
+            high,
+        }
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        let i = rng.random_range(0..=self.centers.len());
+        let (center, bandwidth) = match self.centers.get(i) {
+            Some(center) => (*center, self.bandwidth),
+            None => (self.prior_center, self.prior_bandwidth),
+        };
+        for _ in 0..16 {
+            let value = center + bandwidth * standard_normal(rng);
+            if (self.low..=self.high).contains(&value) {
+                return value;
+            }
+        }
+        center.clamp(self.low, self.high)
+    }
+
+    fn log_density(&self, x: f64) -> f64 {
+        let gaussian = |center: f64, bandwidth: f64| {
+            let z = (x - center) / bandwidth;
+            (-0.5 * z * z).exp() / bandwidth
+        };
+        let density = (self
+            .centers
+            .iter()
+            .map(|center| gaussian(*center, self.bandwidth))
+            .sum::<f64>()
+            + gaussian(self.prior_center, self.prior_bandwidth))
+            / (self.centers.len() + 1) as f64;
+        density.max(f64::MIN_POSITIVE).ln()
+    }
+}
+
+fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    // Box-Muller transform
+    let u1 = rng.random::<f64>().max(f64::MIN_POSITIVE);
+    let u2 = rng.random::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tune::params::{ParamSpec, ParamType};
+
+    fn space() -> ParamSpace {
+        let mut space = ParamSpace::new();
+        space.insert(
+            "x".to_string(),
+            ParamSpec::range(RangeSpec {
+                low: 0.0,
+                high: 10.0,
+                kind: ParamType::Float,
+                scale: Scale::Linear,
+            }),
+        );
+        space.insert(
+            "mode".to_string(),
+            ParamSpec::values(vec![
+                ParamValue::Str("a".to_string()),
+                ParamValue::Str("b".to_string()),
+            ]),
+        );
+        space
+    }
+
+    fn evaluate(assignment: &Assignment) -> f64 {
+        let ParamValue::Float(x) = assignment["x"] else {
+            panic!("x should be a float");
+        };
+        let bonus = if assignment["mode"] == ParamValue::Str("b".to_string()) {
+            5.0
+        } else {
+            0.0
+        };
+        -(x - 7.0).powi(2) + bonus
+    }
+
+    #[test]
+    fn converges_towards_optimum() {
+        let space = space();
+        let mut sampler = TpeSampler::new(1, Objective::Max);
+        let mut history = vec![];
+        for id in 0..60 {
+            let params = sampler.propose(&space, &history, &[]).unwrap();
+            history.push(Trial {
+                id,
+                pruned: false,
+                class: None,
+                space: ParamSpace::new(),
+                objective: evaluate(&params),
+                params,
+                cases: vec![],
+            });
+        }
+
+        let late = &history[40..];
+        let near_optimum = late.iter().filter(|trial| trial.objective > 3.0).count();
+        assert!(
+            near_optimum > late.len() / 2,
+            "{} of {}",
+            near_optimum,
+            late.len()
+        );
+    }
+
+    #[test]
+    fn parzen_density_peaks_at_observations() {
+        let parzen = Parzen::new(vec![2.0, 2.1, 1.9], 0.0, 10.0);
+        assert!(parzen.log_density(2.0) > parzen.log_density(9.0));
+    }
+}
@@ -0,0 +1,225 @@
+use super::evaluate::CommandEvaluator;
+use super::objective::Scoring;
+use super::{Objective, Trial};
+use crate::messages::msg;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// Settings for holding out seeds from tuning to check the best trials on.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct ValidationConfig {
+    /// Share of the seeds of each class held out from tuning
+    #[serde(default = "default_fraction")]
+    pub(crate) fraction: f64,
+    /// Number of the best trials evaluated on the held-out seeds
+    #[serde(default = "default_top")]
+    pub(crate) top: usize,
+    /// Relative shortfall of the held-out objective behind the tuning objective that is warned about
+    #[serde(default = "default_max_gap")]
+    pub(crate) max_gap: f64,
+}
+
+fn default_fraction() -> f64 {
+    0.2
+}
+
+fn default_top() -> usize {
+    3
+}
+
+fn default_max_gap() -> f64 {
+    0.02
+}
+
+impl ValidationConfig {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if !(self.fraction > 0.0 && self.fraction < 1.0) {
+            return Err(anyhow!(msg!("tune.validation_fraction")));
+        }
+        if self.top == 0 {
+            return Err(anyhow!(msg!("tune.validation_top")));
+        }
+        if self.max_gap < 0.0 {
+            return Err(anyhow!(msg!("tune.validation_max_gap")));
+        }
+        Ok(())
+    }
+
+    /// Splits seeds into tuning and held-out seeds. The split only depends on the seeds and
+    /// `salt`, so a resumed study holds out the same seeds; both parts keep at least one seed
+    /// where there are two.
+    pub(crate) fn split(&self, seeds: &[u64], salt: u64) -> (Vec<u64>, Vec<u64>) {
+        if seeds.len() < 2 {
+            return (seeds.to_vec(), vec![]);
+        }
+        let mut tuning = seeds.to_vec();
+        tuning.sort_by_key(|seed| (mix(seed ^ salt), *seed));
+        let held_out =
+            ((seeds.len() as f64 * self.fraction).round() as usize).clamp(1, seeds.len() - 1);
+        let mut validation = tuning.split_off(seeds.len() - held_out);
+        tuning.sort();
+        validation.sort();
+        (tuning, validation)
+    }
+}
+
+/// SplitMix64 finalizer.
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct ValidationRow {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) class: Option<usize>,
+    pub(crate) trial: usize,
+    /// Objective on the tuning seeds
+    pub(crate) tuning: f64,
+    /// Objective on the held-out seeds
+    pub(crate) validation: f64,
+}
+
+/// Evaluates the best trials of a class on the held-out seeds, best tuning objective first.
+pub(crate) fn validate(
+    config: &ValidationConfig,
+    evaluator: &CommandEvaluator,
+    scoring: &Scoring,
+    jobs: usize,
+    trials: &[Trial],
+    seeds: &[u64],
+) -> Vec<ValidationRow> {
+    let mut best = trials
+        .iter()
+        .filter(|trial| !trial.pruned)
+        .collect::<Vec<_>>();
+    best.sort_by(|a, b| {
+        let ordering = a.objective.total_cmp(&b.objective);
+        match scoring.objective {
+            Objective::Max => ordering.reverse(),
+            Objective::Min => ordering,
+        }
+    });
+    best.truncate(config.top);
+
+    let work = best
+        .iter()
+        .flat_map(|trial| seeds.iter().map(|seed| (&trial.params, *seed)))
+        .collect::<Vec<_>>();
+    let cases = evaluator.evaluate_all(&work, jobs);
+    best.iter()
+        .zip(cases.chunks(seeds.len().max(1)))
+        .map(|(trial, cases)| ValidationRow {
+            class: trial.class,
+            trial: trial.id,
+            tuning: trial.objective,
+            validation: scoring.objective(cases),
+        })
+        .collect()
+}
+
+pub(crate) fn format_validation(rows: &[ValidationRow]) -> String {
+    let mut output = format!("{:>6}  {:>12}  {:>12}\n", "trial", "tuning", "validation");
+    for row in rows {
+        output.push_str(&format!(
+            "{:>6}  {:>12.2}  {:>12.2}\n",
+            format!("#{}", row.trial),
+            row.tuning,
+            row.validation
+        ));
+    }
+    output
+}
+
+/// Signs of overfitting in the validation of one class: the chosen trial, the first row, doing
+/// much worse on the held-out seeds, or another trial beating it there.
+pub(crate) fn overfitting_warnings(
+    rows: &[ValidationRow],
+    objective: Objective,
+    max_gap: f64,
+) -> Vec<String> {
+    let Some(chosen) = rows.first() else {
+        return vec![];
+    };
+    let mut warnings = vec![];
+    let shortfall = match objective {
+        Objective::Max => chosen.tuning - chosen.validation,
+        Objective::Min => chosen.validation - chosen.tuning,
+    };
+    let gap = shortfall / chosen.tuning.abs().max(f64::EPSILON);
+    if gap > max_gap {
+        warnings.push(format!(
+            "Trial #{} is {:.1}% worse on the held-out seeds than on the tuning seeds, the tuned parameters may overfit",
+            chosen.trial,
+            gap * 100.0
+        ));
+    }
+    let better = rows[1..]
+        .iter()
+        .fold(None, |best: Option<&ValidationRow>, row| {
+            let current = best.map_or(chosen.validation, |best| best.validation);
+            if objective.is_better(row.validation, current) {
+                Some(row)
+            } else {
+                best
+            }
+        });
+    if let Some(better) = better {
+        warnings.push(format!(
+            "Trial #{} beats the chosen trial #{} on the held-out seeds",
+            better.trial, chosen.trial
+        ));
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(fraction: f64) -> ValidationConfig {
+        ValidationConfig {
+            fraction,
+            top: 3,
+            max_gap: 0.02,
+        }
+    }
+
+    #[test]
+    fn split_is_deterministic_and_keeps_both_parts() {
+        let seeds = (0..10).collect::<Vec<_>>();
+        let (tuning, validation) = config(0.2).split(&seeds, 0);
+        assert_eq!(tuning.len(), 8);
+        assert_eq!(validation.len(), 2);
+        assert!(validation.iter().all(|seed| !tuning.contains(seed)));
+        assert_eq!(config(0.2).split(&seeds, 0), (tuning, validation.clone()));
+        assert_ne!(config(0.2).split(&seeds, 1).1, validation);
+
+        assert_eq!(config(0.01).split(&[3, 4], 0).1.len(), 1);
+        assert_eq!(config(0.9).split(&[3, 4], 0).0.len(), 1);
+        assert_eq!(config(0.5).split(&[3], 0), (vec![3], vec![]));
+    }
+
+    #[test]
+    fn warns_about_gaps_and_rank_changes() {
+        let row = |trial, tuning, validation| ValidationRow {
+            class: None,
+            trial,
+            tuning,
+            validation,
+        };
+        let rows = [row(0, 100.0, 99.0), row(1, 98.0, 98.5)];
+        assert!(overfitting_warnings(&rows, Objective::Max, 0.02).is_empty());
+
+        let rows = [row(0, 100.0, 90.0), row(1, 98.0, 95.0)];
+        let warnings = overfitting_warnings(&rows, Objective::Max, 0.02);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("10.0% worse"));
+        assert!(warnings[1].contains("Trial #1 beats"));
+
+        let rows = [row(0, 100.0, 101.0)];
+        assert_eq!(overfitting_warnings(&rows, Objective::Min, 0.02).len(), 0);
+    }
+}
@@ -0,0 +1,88 @@
+//! `ahc undo`, which takes back the last commit `ahc commit` made: the branch moves back to its
+//! parent with the changes left staged, and its tag is deleted.
+
+use crate::journal::{self, Journal, Operation};
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use git2::{Oid, Repository, ResetType};
+use serde::Serialize;
+use tracing::info;
+
+#[derive(Args)]
+pub(crate) struct UndoArgs {}
+
+/// What `ahc undo --output json` prints.
+#[derive(Serialize, Debug)]
+struct UndoSummary {
+    commit: String,
+    message: String,
+    head: String,
+    tag: Option<String>,
+}
+
+pub(crate) fn undo(_args: UndoArgs, output: Output) -> Result<()> {
+    let journal = Journal::open();
+    let entries = journal.load()?;
+    let Some(entry) = journal::last_undoable(&entries) else {
+        return Err(anyhow!(msg!("undo.nothing")));
+    };
+    let Operation::Commit {
+        commit,
+        parent,
+        message,
+        tag,
+        ..
+    } = &entry.operation
+    else {
+        unreachable!("only commits can be undone");
+    };
+
+    let repo = Repository::open_from_env().context("Failed to open git repository")?;
+    let head = repo.head()?.peel_to_commit()?.id();
+    // Anything committed on top would be lost from the branch, so only the tip is undone
+    if head.to_string() != *commit {
+        return Err(anyhow!(msg!(
+            "undo.head_moved",
+            short(commit),
+            short(&head.to_string())
+        )));
+    }
+
+    if let Some(tag) = tag {
+        let reference = format!("refs/tags/{}", tag);
+        let tagged = repo
+            .find_reference(&reference)
+            .ok()
+            .and_then(|reference| reference.peel_to_commit().ok())
+            .map(|tagged| tagged.id());
+        if tagged == Some(head) {
+            repo.tag_delete(tag)
+                .context(format!("Failed to delete tag {}", tag))?;
+            info!("{}", msg!("undo.tag_deleted", tag));
+        }
+    }
+    let parent_commit = repo.find_commit(Oid::from_str(parent)?)?;
+    // A soft reset keeps the index, so the changes stay staged for another commit
+    repo.reset(parent_commit.as_object(), ResetType::Soft, None)
+        .context(format!("Failed to reset to {}", parent))?;
+    journal.append(Operation::Undo {
+        commit: commit.clone(),
+    })?;
+
+    info!("{}", msg!("undo.done", short(commit), message));
+    if output.is_json() {
+        print_json(&UndoSummary {
+            commit: commit.clone(),
+            message: message.clone(),
+            head: parent.clone(),
+            tag: tag.clone(),
+        })?;
+    }
+    Ok(())
+}
+
+fn short(id: &str) -> &str {
+    &id[..7.min(id.len())]
+}
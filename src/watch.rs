@@ -0,0 +1,211 @@
+//! `ahc watch`, which runs a few seeds as `ahc test` does whenever the source changes, and prints
+//! how the average score moved since the previous run.
+
+use crate::config::Config;
+use crate::error::ErrorKind;
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::runner::{self, RunOptions, TestRun};
+use anyhow::Result;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+#[derive(Args)]
+pub(crate) struct WatchArgs {
+    #[command(flatten)]
+    options: RunOptions,
+}
+
+/// `[watch]`, what `ahc watch` watches and runs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct WatchConfig {
+    /// Files and directories whose changes start a run
+    #[serde(default = "default_paths")]
+    pub(crate) paths: Vec<PathBuf>,
+    /// Seeds of each run unless `--seeds`, `--seeds-file` or `--sample` is given
+    #[serde(default = "default_seeds")]
+    pub(crate) seeds: String,
+    /// How often the files are checked for changes
+    #[serde(default = "default_interval_ms")]
+    pub(crate) interval_ms: u64,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        WatchConfig {
+            paths: default_paths(),
+            seeds: default_seeds(),
+            interval_ms: default_interval_ms(),
+        }
+    }
+}
+
+fn default_paths() -> Vec<PathBuf> {
+    vec![PathBuf::from("src")]
+}
+
+fn default_seeds() -> String {
+    "0..10".to_string()
+}
+
+fn default_interval_ms() -> u64 {
+    500
+}
+
+/// What `ahc watch --output json` prints after each run.
+#[derive(Serialize, Debug)]
+struct WatchRun {
+    result_file: PathBuf,
+    average_score: f64,
+    /// Change of the average score since the previous run of the watch
+    delta: Option<f64>,
+}
+
+pub(crate) fn watch(args: WatchArgs, config: Config, output: Output) -> Result<()> {
+    let watch_config = config.watch.clone().unwrap_or_default();
+    let mut options = args.options;
+    options.default_seeds(&watch_config.seeds);
+    let interval = Duration::from_millis(watch_config.interval_ms.max(1));
+    let paths = watch_config
+        .paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>();
+    info!("{}", msg!("watch.start", paths.join(", ")));
+
+    let mut files = snapshot(&watch_config.paths);
+    let mut previous = None;
+    loop {
+        match runner::run_test(&options, TestRun::default(), &config, output) {
+            Ok(summary) => {
+                let delta = previous.map(|previous| summary.average_score - previous);
+                info!("{}", format_delta(summary.average_score, previous));
+                if output.is_json() {
+                    print_json(&WatchRun {
+                        result_file: summary.result_file,
+                        average_score: summary.average_score,
+                        delta,
+                    })?;
+                }
+                previous = Some(summary.average_score);
+            }
+            // The configuration does not get fixed by editing the source
+            Err(e) if ErrorKind::of(&e) == Some(ErrorKind::Config) => return Err(e),
+            Err(e) => warn!("{:#}", e),
+        }
+        info!("{}", msg!("watch.waiting"));
+        files = wait_for_change(&watch_config.paths, files, interval);
+    }
+}
+
+/// Waits until the files under `paths` differ from `files`, then until they stay the same for an
+/// interval so that saving several files runs once, returning them as they are then.
+fn wait_for_change(
+    paths: &[PathBuf],
+    mut files: BTreeMap<PathBuf, SystemTime>,
+    interval: Duration,
+) -> BTreeMap<PathBuf, SystemTime> {
+    let mut changed = false;
+    loop {
+        std::thread::sleep(interval);
+        let current = snapshot(paths);
+        if current != files {
+            changed = true;
+            files = current;
+        } else if changed {
+            return files;
+        }
+    }
+}
+
+/// The modification times of the files under `paths`, leaving out hidden files and `target`
+/// directories, which builds write to.
+fn snapshot(paths: &[PathBuf]) -> BTreeMap<PathBuf, SystemTime> {
+    let mut files = BTreeMap::new();
+    for path in paths {
+        add_files(path, &mut files);
+    }
+    files
+}
+
+fn add_files(path: &Path, files: &mut BTreeMap<PathBuf, SystemTime>) {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return;
+    };
+    if metadata.is_file() {
+        if let Ok(modified) = metadata.modified() {
+            files.insert(path.to_path_buf(), modified);
+        }
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || name == "target" {
+            continue;
+        }
+        add_files(&entry.path(), files);
+    }
+}
+
+fn format_delta(average_score: f64, previous: Option<f64>) -> String {
+    match previous {
+        Some(previous) => {
+            let delta = average_score - previous;
+            let percent = if previous != 0.0 {
+                format!(", {:+.2}%", delta / previous * 100.0)
+            } else {
+                String::new()
+            };
+            msg!(
+                "watch.delta",
+                format!("{:.2}", average_score),
+                format!("{:+.2}{}", delta, percent)
+            )
+        }
+        None => msg!("watch.first", format!("{:.2}", average_score)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_source_files_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        for path in ["main.rs", "a/b.rs", ".hidden.swp", "target/release/bin"] {
+            let path = src.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let missing = dir.path().join("missing");
+
+        let files = snapshot(&[src.clone(), missing]);
+        assert_eq!(
+            files.keys().cloned().collect::<Vec<_>>(),
+            vec![src.join("a/b.rs"), src.join("main.rs")]
+        );
+    }
+
+    #[test]
+    fn formats_the_change_since_the_previous_run() {
+        assert_eq!(format_delta(110.0, None), "Average score 110.00");
+        assert_eq!(
+            format_delta(110.0, Some(100.0)),
+            "Average score 110.00 (+10.00, +10.00%) since the previous run"
+        );
+        assert_eq!(
+            format_delta(5.0, Some(0.0)),
+            "Average score 5.00 (+5.00) since the previous run"
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! `ahc workspace`, for repositories holding several contests: every directory with a project
+//! config or a cargo-compete package below the workspace root is a contest, and other commands
+//! run in the one chosen with `ahc workspace switch` or `--contest`.
+
+use crate::compete;
+use crate::config::Config;
+use crate::messages::msg;
+use crate::output::{print_json, Output};
+use crate::overlay::parse_time;
+use crate::pahcer;
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, Subcommand};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::info;
+
+/// File under the workspace root naming the contest chosen with `ahc workspace switch`
+const CURRENT_PATH: &str = ".ahc/current_contest";
+/// How deep below the root contests are looked for
+const MAX_DEPTH: usize = 3;
+
+#[derive(Args)]
+pub(crate) struct WorkspaceArgs {
+    #[command(subcommand)]
+    command: WorkspaceCommands,
+}
+
+#[derive(Subcommand)]
+enum WorkspaceCommands {
+    /// List the contests of the workspace with their latest score and deadline
+    List,
+    /// Make later commands run in a contest when invoked outside of any
+    Switch { name: String },
+}
+
+/// A contest project of the workspace.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+struct Project {
+    name: String,
+    dir: PathBuf,
+    /// Average score of the newest pahcer result
+    latest_score: Option<f64>,
+    /// End of the contest, `[overlay] end_time`
+    deadline: Option<String>,
+    current: bool,
+}
+
+pub(crate) fn workspace(args: WorkspaceArgs, config_file_name: &str, output: Output) -> Result<()> {
+    let root = root(&std::env::current_dir()?);
+    match args.command {
+        WorkspaceCommands::List => {
+            let projects = discover(&root, config_file_name)?;
+            if output.is_json() {
+                print_json(&projects)?;
+            } else {
+                print!("{}", format_projects(&projects, &root));
+            }
+        }
+        WorkspaceCommands::Switch { name } => {
+            let project = find(&root, config_file_name, &name)?;
+            let path = root.join(CURRENT_PATH);
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)
+                    .context(format!("Failed to create directory: {}", dir.display()))?;
+            }
+            std::fs::write(&path, format!("{}\n", project.name))
+                .context(format!("Failed to write file: {}", path.display()))?;
+            info!("{}", msg!("workspace.switched", project.name));
+            if output.is_json() {
+                print_json(&project)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The name and directory of the contest to run commands in: the one named by `--contest`, or
+/// the one chosen with `ahc workspace switch` when `dir` is not a project itself.
+pub(crate) fn contest_dir(
+    dir: &Path,
+    config_file_name: &str,
+    contest: Option<&str>,
+) -> Result<Option<(String, PathBuf)>> {
+    let root = root(dir);
+    let name = match contest {
+        Some(name) => name.to_string(),
+        None if dir.join(config_file_name).exists() => return Ok(None),
+        None => match current(&root) {
+            Some(name) => name,
+            None => return Ok(None),
+        },
+    };
+    let project = find(&root, config_file_name, &name)?;
+    Ok(Some((project.name, project.dir)))
+}
+
+/// Names of the contests, for shell completion.
+pub(crate) fn names(config_file_name: &str) -> Vec<String> {
+    let Ok(dir) = std::env::current_dir() else {
+        return vec![];
+    };
+    discover(&root(&dir), config_file_name)
+        .map(|projects| projects.into_iter().map(|project| project.name).collect())
+        .unwrap_or_default()
+}
+
+/// The cargo-compete workspace or git repository containing `dir`, or `dir` itself.
+fn root(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|dir| dir.join("compete.toml").exists() || dir.join(".git").exists())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+fn current(root: &Path) -> Option<String> {
+    let name = std::fs::read_to_string(root.join(CURRENT_PATH)).ok()?;
+    Some(name.trim().to_string()).filter(|name| !name.is_empty())
+}
+
+fn find(root: &Path, config_file_name: &str, name: &str) -> Result<Project> {
+    discover(root, config_file_name)?
+        .into_iter()
+        .find(|project| project.name == name)
+        .ok_or_else(|| anyhow!(msg!("workspace.unknown", name, root.display())))
+}
+
+fn discover(root: &Path, config_file_name: &str) -> Result<Vec<Project>> {
+    let current = current(root);
+    let mut projects = vec![];
+    let mut dirs = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = dirs.pop() {
+        if let Some(mut project) = read_project(&dir, config_file_name)? {
+            project.current = current.as_deref() == Some(project.name.as_str());
+            projects.push(project);
+        }
+        if depth == MAX_DEPTH {
+            continue;
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if entry.path().is_dir()
+                && !name.starts_with('.')
+                && name != "target"
+                && name != "node_modules"
+            {
+                dirs.push((entry.path(), depth + 1));
+            }
+        }
+    }
+    projects.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.dir.cmp(&b.dir)));
+    Ok(projects)
+}
+
+/// The contest of `dir`, from its project config or else its cargo-compete package.
+fn read_project(dir: &Path, config_file_name: &str) -> Result<Option<Project>> {
+    let config_path = dir.join(config_file_name);
+    let (name, config) = if config_path.exists() {
+        let content = std::fs::read_to_string(&config_path).context(format!(
+            "Failed to read config file: {}",
+            config_path.display()
+        ))?;
+        let config: Config = toml::from_str(&content).context(format!(
+            "Failed to parse config file: {}",
+            config_path.display()
+        ))?;
+        (config.general.name.clone(), Some(config))
+    } else if let Some(package) = compete::read_package(dir)? {
+        (package.name, None)
+    } else {
+        return Ok(None);
+    };
+
+    let results_dir = config.as_ref().map_or_else(
+        || PathBuf::from("pahcer/json"),
+        |c| c.paths.results_dir.clone(),
+    );
+    let latest_score = pahcer::find_latest_result(&dir.join(results_dir))?
+        .map(|path| pahcer::read_result(&path))
+        .transpose()?
+        .map(|result| result.average_score());
+    let deadline = config
+        .as_ref()
+        .and_then(|config| config.overlay.as_ref())
+        .and_then(|overlay| overlay.end_time.clone());
+    Ok(Some(Project {
+        name,
+        dir: dir.to_path_buf(),
+        latest_score,
+        deadline,
+        current: false,
+    }))
+}
+
+fn format_projects(projects: &[Project], root: &Path) -> String {
+    let now = SystemTime::now();
+    let mut output = String::new();
+    for project in projects {
+        let dir = project.dir.strip_prefix(root).unwrap_or(&project.dir);
+        let dir = if dir.as_os_str().is_empty() {
+            ".".to_string()
+        } else {
+            dir.display().to_string()
+        };
+        let score = project
+            .latest_score
+            .map_or("-".to_string(), |score| format!("{:.2}", score));
+        let deadline = match project.deadline.as_deref() {
+            Some(deadline) => match parse_time(deadline) {
+                Ok(end) => match end.duration_since(now) {
+                    Ok(left) => format!("{} ({} left)", deadline, format_duration(left.as_secs())),
+                    Err(_) => format!("{} (ended)", deadline),
+                },
+                Err(_) => deadline.to_string(),
+            },
+            None => "-".to_string(),
+        };
+        output.push_str(&format!(
+            "{} {:<12} {:<20} {:>12}  {}\n",
+            if project.current { "*" } else { " " },
+            project.name,
+            dir,
+            score,
+            deadline
+        ));
+    }
+    output
+}
+
+/// Formats a time span coarsely, e.g. `2d 3h` or `45m`.
+pub(crate) fn format_duration(secs: u64) -> String {
+    match (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60) {
+        (0, 0, minutes) => format!("{}m", minutes),
+        (0, hours, minutes) => format!("{}h {}m", hours, minutes),
+        (days, hours, _) => format!("{}d {}h", days, hours),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write_project(root: &Path, dir: &str, name: &str) {
+        let dir = root.join(dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("ahc_tools.toml"),
+            format!(
+                "[general]\nname = \"{}\"\nproblem_url = \"https://example.net\"\n",
+                name
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn discovers_projects_and_the_current_one() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        fs::create_dir(root.join(".git")).unwrap();
+        write_project(root, "2024/ahc030", "ahc030");
+        write_project(root, "ahc031", "ahc031");
+        write_project(root, "ahc031/target/copy", "ahc099");
+        fs::create_dir_all(root.join("ahc031/pahcer/json")).unwrap();
+        fs::write(
+            root.join("ahc031/pahcer/json/result_20240101_000000.json"),
+            r#"{"case_count": 2, "total_score": 30}"#,
+        )
+        .unwrap();
+
+        let projects = discover(root, "ahc_tools.toml").unwrap();
+        let names = projects.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["ahc030", "ahc031"]);
+        assert_eq!(projects[1].latest_score, Some(15.0));
+        assert_eq!(
+            contest_dir(&root.join("2024"), "ahc_tools.toml", None).unwrap(),
+            None
+        );
+
+        fs::create_dir_all(root.join(".ahc")).unwrap();
+        fs::write(root.join(CURRENT_PATH), "ahc030\n").unwrap();
+        assert_eq!(
+            contest_dir(&root.join("2024"), "ahc_tools.toml", None).unwrap(),
+            Some(("ahc030".to_string(), root.join("2024/ahc030")))
+        );
+        // A project directory stays itself, unless a contest is named explicitly
+        let ahc031 = root.join("ahc031");
+        assert_eq!(contest_dir(&ahc031, "ahc_tools.toml", None).unwrap(), None);
+        assert_eq!(
+            contest_dir(&ahc031, "ahc_tools.toml", Some("ahc030")).unwrap(),
+            Some(("ahc030".to_string(), root.join("2024/ahc030")))
+        );
+        assert!(contest_dir(root, "ahc_tools.toml", Some("ahc000")).is_err());
+    }
+}
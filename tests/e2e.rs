@@ -23,6 +23,38 @@ fn init() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn init_in_cargo_compete_package() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let package_dir = temp_dir.path().join("ahc030");
+    fs::create_dir_all(package_dir.join("src/bin"))?;
+    fs::write(temp_dir.path().join("compete.toml"), "")?;
+    fs::write(
+        package_dir.join("Cargo.toml"),
+        r#"
+        [package]
+        name = "ahc030"
+
+        [package.metadata.cargo-compete.bin]
+        ahc030-a = { alias = "a", problem = "https://atcoder.jp/contests/ahc030/tasks/ahc030_a" }
+    "#,
+    )?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("init")
+        .arg("--lang")
+        .arg("en")
+        .current_dir(package_dir.join("src/bin"))
+        .assert()
+        .success();
+
+    let content = fs::read_to_string(package_dir.join("ahc_tools.toml"))?;
+    assert!(content.contains("name = \"ahc030\""));
+    assert!(content.contains("https://atcoder.jp/contests/ahc030/tasks/ahc030_a?lang=en"));
+
+    Ok(())
+}
+
 #[test]
 fn download() -> Result<()> {
     let mut server = mockito::Server::new();
@@ -39,6 +71,7 @@ fn download() -> Result<()> {
         .mock("GET", "/tools.zip")
         .with_status(200)
         .with_header("content-type", "application/zip")
+        .with_header("etag", "\"v1\"")
         .with_body_from_file("src/tests/fixtures/test_archive.zip")
         .create();
 
@@ -65,7 +98,57 @@ fn download() -> Result<()> {
 
     html_mock.assert();
     zip_mock.assert();
-    
+
+    // The checksums tell the tools edited since
+    assert!(temp_dir.path().join(".ahc/tools_manifest.json").exists());
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["download", "--verify"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    fs::write(&file_path, "edited\n")?;
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let assert = cmd
+        .args(["download", "--verify"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("mock.txt"));
+
+    // The same archive is not extracted again
+    let not_modified_mock = server
+        .mock("GET", "/tools.zip")
+        .match_header("if-none-match", "\"v1\"")
+        .with_status(304)
+        .create();
+    fs::remove_file(&file_path)?;
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["download", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["unchanged"], true);
+    assert!(!file_path.exists());
+    not_modified_mock.assert();
+
+    // The archive kept in the cache is extracted without connecting
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["download", "--offline"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&file_path)?, "content\n");
+    let other_dir = tempfile::tempdir()?;
+    fs::copy(&config_file_path, other_dir.path().join("ahc_tools.toml"))?;
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["download", "--offline"])
+        .current_dir(other_dir.path())
+        .assert()
+        .failure();
+
     Ok(())
 }
 
@@ -144,20 +227,1612 @@ fn commit() -> Result<()> {
     Ok(())
 }
 
-fn copy_file_dir(dir: fs::ReadDir, dest: &std::path::Path) -> Result<()> {
-    for entry in dir {
-        let entry = entry?;
-        if entry.file_type()?.is_dir() {
-            let dir_name = entry.file_name();
-            let new_dest = dest.join(dir_name);
-            fs::create_dir(&new_dest)?;
-            let new_dir = fs::read_dir(entry.path())?;
-            copy_file_dir(new_dir, &new_dest)?;
-        } else {
-            let file_name = entry.file_name();
-            let new_dest = dest.join(file_name);
-            fs::copy(entry.path(), new_dest)?;
-        }
+#[test]
+fn commit_adds_the_max_time_under_a_time_limit() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [test]
+        time_limit_ms = 2000
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    copy_file_dir(fs::read_dir("tests/fixtures/e2e")?, temp_dir.path())?;
+    for args in [
+        vec!["init"],
+        vec!["config", "user.name", "test_user"],
+        vec!["config", "user.email", "test@example.com"],
+        vec!["add", "clean.sh"],
+        vec!["commit", "-m", "Initial commit"],
+        vec!["add", "."],
+    ] {
+        Command::new("git").args(args).current_dir(temp_dir.path()).assert().success();
+    }
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["commit", "test message"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    let output = Command::new("git")
+        .args(["log", "-1", "--pretty=%B"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    let output = String::from_utf8(output.stdout)?;
+    assert_eq!(output.trim(), "(50890.50, 0.218s) test message");
+    Ok(())
+}
+
+#[test]
+fn commit_nothing_exits_with_its_own_code() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    for args in [
+        vec!["init"],
+        vec!["add", "ahc_tools.toml"],
+        vec!["-c", "user.name=test_user", "-c", "user.email=test@example.com", "commit", "-m", "Initial commit"],
+    ] {
+        Command::new("git").args(args).current_dir(temp_dir.path()).assert().success();
+    }
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let assert = cmd
+        .arg("commit")
+        .arg("test message")
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(5);
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("Nothing to commit"));
+
+    Ok(())
+}
+
+#[test]
+fn undo_takes_back_the_last_commit() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    fs::write(temp_dir.path().join(".gitignore"), ".ahc/\n")?;
+    for args in [
+        vec!["init"],
+        vec!["config", "user.name", "test_user"],
+        vec!["config", "user.email", "test@example.com"],
+        vec!["add", "."],
+        vec!["commit", "-m", "Initial commit"],
+    ] {
+        Command::new("git").args(args).current_dir(temp_dir.path()).assert().success();
+    }
+    fs::write(temp_dir.path().join("main.rs"), "fn main() {}\n")?;
+    Command::new("git").args(["add", "main.rs"]).current_dir(temp_dir.path()).assert().success();
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["commit", "test message", "--tag", "best"])
+        .write_stdin("y\n")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    let git = |args: &[&str]| -> Result<String> {
+        let output = Command::new("git").args(args).current_dir(temp_dir.path()).output()?;
+        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+    };
+    assert_eq!(git(&["log", "-1", "--pretty=%B"])?, "test message");
+    assert_eq!(git(&["tag"])?, "best");
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("undo").current_dir(temp_dir.path()).assert().success();
+    assert_eq!(git(&["log", "-1", "--pretty=%B"])?, "Initial commit");
+    assert_eq!(git(&["tag"])?, "");
+    assert_eq!(git(&["diff", "--cached", "--name-only"])?, "main.rs");
+
+    // The initial commit was not made by ahc, so there is nothing left to undo
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("undo").current_dir(temp_dir.path()).assert().failure();
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn case_and_source_copy_to_the_clipboard() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let clipboard = temp_dir.path().join("clipboard.txt");
+    let config = format!(r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [clipboard]
+        command = ["sh", "-c", "cat > {}"]
+    "#, clipboard.display());
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    fs::create_dir_all(temp_dir.path().join("tools/out"))?;
+    fs::write(temp_dir.path().join("tools/out/0003.txt"), "3 1 4\n")?;
+    fs::create_dir_all(temp_dir.path().join("src"))?;
+    fs::write(temp_dir.path().join("src/main.rs"), "fn main() {}\n")?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["case", "3", "output", "--copy"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&clipboard)?, "3 1 4\n");
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["source", "--copy"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&clipboard)?, "fn main() {}\n");
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let assert = cmd.args(["case", "3", "output"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert_eq!(String::from_utf8(assert.get_output().stdout.clone())?, "3 1 4\n");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn run_commits_the_result() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    // A stand-in for pahcer, writing a result with an average score of 12.5
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [run]
+        command = ["sh", "-c", "mkdir -p pahcer/json && echo '{\"case_count\": 2, \"total_score\": 25}' > pahcer/json/result_20240101_000000.json"]
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    fs::write(temp_dir.path().join(".gitignore"), ".ahc/\n")?;
+    fs::write(temp_dir.path().join("main.rs"), "fn main() {}\n")?;
+    for args in [
+        vec!["init"],
+        vec!["config", "user.name", "test_user"],
+        vec!["config", "user.email", "test@example.com"],
+        vec!["add", "."],
+        vec!["commit", "-m", "Initial commit"],
+    ] {
+        Command::new("git").args(args).current_dir(temp_dir.path()).assert().success();
+    }
+    fs::write(temp_dir.path().join("main.rs"), "fn main() { solve() }\n")?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["run", "--commit", "try greedy", "--all", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["average_score"], 12.5);
+    assert_eq!(summary["commit"]["message"], "(12.50) try greedy");
+
+    let output = Command::new("git")
+        .args(["show", "--name-only", "--pretty=%B"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    let shown = String::from_utf8(output.stdout)?;
+    assert!(shown.starts_with("(12.50) try greedy"));
+    assert!(shown.contains("main.rs"));
+    assert!(shown.contains("pahcer/json/result_20240101_000000.json"));
+    Ok(())
+}
+
+/// A project with `config`, the input `inputs[seed]` of each seed, and a stand-in for the official
+/// visualizer scoring the output as is.
+#[cfg(unix)]
+fn scored_project(config: &str, inputs: &[&str]) -> Result<tempfile::TempDir> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempfile::tempdir()?;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    fs::create_dir_all(temp_dir.path().join("tools/in"))?;
+    for (seed, input) in inputs.iter().enumerate() {
+        fs::write(temp_dir.path().join(format!("tools/in/{:04}.txt", seed)), input)?;
+    }
+    let vis = temp_dir.path().join("tools/target/release/vis");
+    fs::create_dir_all(vis.parent().unwrap())?;
+    fs::write(&vis, "#!/bin/sh\necho \"Score = $(cat \"$2\")\"\n")?;
+    fs::set_permissions(&vis, fs::Permissions::from_mode(0o755))?;
+    Ok(temp_dir)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_runs_the_solver_without_pahcer() -> Result<()> {
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [test]
+        command = ["sh", "-c", "read n; echo $((n + 1))"]
+        jobs = 2
+
+        [solver.double]
+        command = ["sh", "-c", "read n; echo $((n * 2))"]
+    "#;
+    let temp_dir = scored_project(config, &["0\n", "1\n", "2\n", "3\n"])?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["test", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["average_score"], 2.5);
+    assert!(summary["max_memory_kb"].as_u64().is_some());
+    let output_dir = temp_dir.path().join(summary["output_dir"].as_str().unwrap());
+    assert_eq!(fs::read_to_string(output_dir.join("0003.txt"))?, "4\n");
+    assert!(output_dir.join("manifest.json").exists());
+    let run_id = summary["run_id"].as_str().unwrap();
+    for args in [vec!["case", "3", "output"], vec!["case", "3", "output", "--run", run_id]] {
+        let mut cmd = Command::cargo_bin(PRG)?;
+        cmd.args(args)
+            .current_dir(temp_dir.path())
+            .assert()
+            .success()
+            .stdout("4\n");
     }
+
+    // The result reads like one of pahcer's
+    let result_file = temp_dir.path().join(summary["result_file"].as_str().unwrap());
+    let result = ahc_tools::pahcer::read_result(&result_file)?;
+    assert_eq!(result.case_count, 4);
+    assert_eq!(result.total_score, 10);
+    assert_eq!(result.solver, None);
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["test", "--solver", "double", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["solver"], "double");
+    assert_eq!(summary["average_score"], 3.0);
+    let result_file = temp_dir.path().join(summary["result_file"].as_str().unwrap());
+    let result = ahc_tools::pahcer::read_result(&result_file)?;
+    assert_eq!(result.solver.as_deref(), Some("double"));
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["test", "--solver", "beam"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(3);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_builds_the_solver_with_a_profile() -> Result<()> {
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [test]
+        command = ["sh", "-c", "read n; echo $((n + 1))"]
+        build = ["sh", "-c", "echo \"$RUSTFLAGS\" > rustflags.txt"]
+    "#;
+    let temp_dir = scored_project(config, &["0\n"])?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["test", "--profile", "native", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["profile"], "native");
+    let rustflags = fs::read_to_string(temp_dir.path().join("rustflags.txt"))?;
+    assert!(rustflags.contains("-C target-cpu=native"));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_lists_regressions_by_the_objective_of_the_problem() -> Result<()> {
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [test]
+        command = ["sh", "-c", "read n; echo $((n + 1))"]
+
+        [solver.worse]
+        command = ["sh", "-c", "read n; echo $((n + 5))"]
+
+        [problem]
+        objective = "min"
+    "#;
+    let temp_dir = scored_project(config, &["0\n", "1\n", "2\n"])?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("test").current_dir(temp_dir.path()).assert().success();
+    // Higher scores are worse for a min problem, even without [tune]
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["test", "--solver", "worse", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["regressions"].as_array().map(Vec::len), Some(3));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_builds_and_runs_the_solver_in_a_container() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // The config is written below, once it can name the engine inside the project
+    let temp_dir = scored_project("", &["1\n"])?;
+    // A stand-in for docker, running the commands of `exec` here with IN_CONTAINER set
+    let engine = temp_dir.path().join("engine.sh");
+    fs::write(
+        &engine,
+        r#"#!/bin/sh
+echo "$1" >> "$(dirname "$0")/engine.log"
+case "$1" in
+    run) echo c0ffee ;;
+    exec)
+        shift
+        while [ "$1" != c0ffee ]; do
+            case "$1" in
+                -w) cd "$2"; shift 2 ;;
+                -e) export "$2"; shift 2 ;;
+                *) shift ;;
+            esac
+        done
+        shift
+        IN_CONTAINER=1 exec "$@"
+        ;;
+esac
+"#,
+    )?;
+    fs::set_permissions(&engine, fs::Permissions::from_mode(0o755))?;
+    let config = format!(
+        r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [test]
+        command = ["sh", "-c", "read n; echo $((n + ${{IN_CONTAINER:-0}} * K))"]
+        build = ["sh", "-c", "echo $IN_CONTAINER > built.txt"]
+
+        [test.container]
+        image = "judge:2024"
+        engine = "{}"
+    "#,
+        engine.display()
+    );
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["test", "--container", "judge:2025", "--param", "K=10", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["container"], "judge:2025");
+    assert_eq!(summary["average_score"], 11.0);
+    assert_eq!(fs::read_to_string(temp_dir.path().join("built.txt"))?, "1\n");
+    let log = fs::read_to_string(temp_dir.path().join("engine.log"))?;
+    assert_eq!(log.lines().collect::<Vec<_>>(), ["run", "exec", "exec", "rm"]);
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(unix)]
+#[test]
+fn test_notifies_slack_and_updates_the_overlay() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let finished_mock = server
+        .mock("POST", "/hook")
+        .match_body(mockito::Matcher::Regex(
+            r"finished\*\\n2 case\(s\), average score".to_string(),
+        ))
+        .with_status(200)
+        .expect(2)
+        .create();
+    let best_mock = server
+        .mock("POST", "/hook")
+        .match_body(mockito::Matcher::Regex(
+            r"Average score 11.50 \(\+10.00 vs 1.50\)".to_string(),
+        ))
+        .with_status(200)
+        .expect(1)
+        .create();
+
+    let config = format!(
+        r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [test]
+        command = ["sh", "-c", "read n; echo $((n + 1))"]
+
+        [solver.better]
+        command = ["sh", "-c", "read n; echo $((n + 11))"]
+
+        [notify.slack]
+        webhook_url = "{}/hook"
+
+        [overlay]
+        path = "overlay.json"
+    "#,
+        server.url()
+    );
+    let temp_dir = scored_project(&config, &["0\n", "1\n"])?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("test").current_dir(temp_dir.path()).assert().success();
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["test", "--solver", "better"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    finished_mock.assert();
+    best_mock.assert();
+    let overlay: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp_dir.path().join("overlay.json"))?)?;
+    assert_eq!(overlay["score"], 11.5);
+    assert_eq!(overlay["last_delta"], 10.0);
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_kills_the_solvers_when_interrupted() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, Instant};
+
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [test]
+        command = ["sh", "-c", "echo $$ >> pids.txt; sleep 30"]
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    fs::create_dir_all(temp_dir.path().join("tools/in"))?;
+    fs::write(temp_dir.path().join("tools/in/0000.txt"), "1\n")?;
+    let vis = temp_dir.path().join("tools/target/release/vis");
+    fs::create_dir_all(vis.parent().unwrap())?;
+    fs::write(&vis, "#!/bin/sh\necho \"Score = 1\"\n")?;
+    fs::set_permissions(&vis, fs::Permissions::from_mode(0o755))?;
+
+    let mut ahc = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .arg("test")
+        .current_dir(temp_dir.path())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    let pids = temp_dir.path().join("pids.txt");
+    let start = Instant::now();
+    while !fs::read_to_string(&pids).is_ok_and(|pids| pids.ends_with('\n')) {
+        assert!(start.elapsed() < Duration::from_secs(10), "the solver never started");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    unsafe { libc::kill(ahc.id() as libc::pid_t, libc::SIGINT) };
+    assert!(!ahc.wait()?.success());
+
+    // Gone, or a zombie waiting to be reaped
+    let alive = |pid: &str| {
+        fs::read_to_string(format!("/proc/{}/stat", pid))
+            .is_ok_and(|stat| stat.rsplit(')').next().is_some_and(|rest| !rest.starts_with(" Z")))
+    };
+    let pids = fs::read_to_string(&pids)?;
+    let start = Instant::now();
+    while pids.lines().any(alive) {
+        assert!(start.elapsed() < Duration::from_secs(5), "the solver outlived ahc");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_resumes_an_interrupted_run() -> Result<()> {
+    // The solver logs the seeds it ran
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [test]
+        command = ["sh", "-c", "read n; echo $n >> ran.txt; echo $((n + 1))"]
+    "#;
+    let temp_dir = scored_project(config, &["0\n", "1\n", "2\n", "3\n"])?;
+    // A run killed after seeds 0 and 2, while writing the case of seed 3
+    let run_dir = temp_dir.path().join("tools/out/20240101_000000");
+    fs::create_dir_all(&run_dir)?;
+    fs::write(
+        run_dir.join("progress.jsonl"),
+        r#"{"seed": 0, "score": 100, "execution_time": 0.1, "error_message": ""}
+{"seed": 2, "score": 300, "execution_time": 0.1, "error_message": ""}
+{"seed": 3, "sc"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["test", "--resume", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["run_id"], "20240101_000000");
+    assert_eq!(summary["case_count"], 4);
+    assert_eq!(summary["average_score"], 101.5);
+    let mut ran = fs::read_to_string(temp_dir.path().join("ran.txt"))?
+        .lines()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    ran.sort();
+    assert_eq!(ran, ["1", "3"]);
+    assert!(run_dir.join("manifest.json").exists());
+    assert!(!run_dir.join("progress.jsonl").exists());
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["test", "--resume"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_repeats_each_seed() -> Result<()> {
+    // The solver scores 10 more on each run
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [test]
+        command = ["sh", "-c", "read n; c=$(cat runs.txt 2>/dev/null || echo 0); echo $((c + 1)) > runs.txt; echo $((n + c * 10))"]
+        jobs = 1
+        aggregate = "median"
+    "#;
+    let temp_dir = scored_project(config, &["100\n"])?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["test", "--repeats", "3", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["repeats"], 3);
+    assert_eq!(summary["average_score"], 110.0);
+    let result: serde_json::Value = serde_json::from_str(&fs::read_to_string(
+        temp_dir.path().join(summary["result_file"].as_str().unwrap()),
+    )?)?;
+    assert_eq!(result["cases"][0]["repeats"]["scores"], serde_json::json!([100, 110, 120]));
+    assert_eq!(result["cases"][0]["repeats"]["std"], 10.0);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reuses_the_solver_built_at_a_clean_commit() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // The build counts how many times it ran
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [test]
+        command = ["target/release/solver"]
+        build = ["sh", "-c", "mkdir -p target/release && cp solver.sh target/release/solver && echo built >> builds.txt"]
+    "#;
+    let temp_dir = scored_project(config, &["7\n"])?;
+    fs::write(temp_dir.path().join(".gitignore"), ".ahc/
+target/
+builds.txt
+")?;
+    let solver = temp_dir.path().join("solver.sh");
+    fs::write(&solver, "#!/bin/sh\nread n; echo $n\n")?;
+    fs::set_permissions(&solver, fs::Permissions::from_mode(0o755))?;
+    for args in [
+        vec!["init"],
+        vec!["config", "user.name", "test_user"],
+        vec!["config", "user.email", "test@example.com"],
+        vec!["add", "ahc_tools.toml", ".gitignore", "solver.sh"],
+        vec!["commit", "-m", "Initial commit"],
+    ] {
+        Command::new("git").args(args).current_dir(temp_dir.path()).assert().success();
+    }
+
+    let builds = || -> Result<usize> {
+        let mut cmd = Command::cargo_bin(PRG)?;
+        cmd.arg("test").current_dir(temp_dir.path()).assert().success();
+        Ok(fs::read_to_string(temp_dir.path().join("builds.txt"))?.lines().count())
+    };
+    assert_eq!(builds()?, 1);
+    assert_eq!(builds()?, 1);
+    assert_eq!(fs::read_dir(temp_dir.path().join(".ahc/cache"))?.count(), 1);
+
+    // A change of the source is built
+    fs::write(&solver, "#!/bin/sh\nread n; echo $((n + 1))\n")?;
+    assert_eq!(builds()?, 2);
+    assert_eq!(builds()?, 3);
+
+    // The committed solver is built in a checkout, leaving the changed one alone
+    let head = Command::new("git").args(["rev-parse", "HEAD"]).current_dir(temp_dir.path()).output()?;
+    let head = String::from_utf8(head.stdout)?.trim().to_string();
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["test", "--at", "HEAD", "--no-cache", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["average_score"], 7.0);
+    assert_eq!(summary["commit"], head);
+    assert!(!temp_dir.path().join(".ahc/worktree").join(&head).exists());
+    assert_eq!(fs::read_to_string(&solver)?, "#!/bin/sh\nread n; echo $((n + 1))\n");
+    Command::new("git").args(["diff", "--cached", "--quiet"]).current_dir(temp_dir.path()).assert().success();
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["ab", "HEAD", "HEAD", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let comparison: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(comparison["ties"], 1);
+    assert_eq!(comparison["cases"][0]["score_b"], 7);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn sweep_runs_every_combination() -> Result<()> {
+    // K reaches the solver as an environment variable and OFFSET as an argument
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [test]
+        command = ["sh", "-c", "read n; echo $((n * K + $0))"]
+
+        [sweep.params]
+        K = [1, 3]
+        offset = { choices = [0, 10], arg = "{value}" }
+
+        [solver.file]
+        command = ["sh", "-c", "read n; echo $((n * $(grep '^K ' params.txt | cut -d ' ' -f 2)))"]
+        params_via = "file"
+    "#;
+    let temp_dir = scored_project(config, &["1\n", "2\n"])?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["sweep", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let averages = summary["combinations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|combination| combination["average_score"].as_f64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(averages, vec![1.5, 11.5, 4.5, 14.5]);
+    assert_eq!(summary["best"], serde_json::json!({"K": 3, "offset": 10}));
+
+    // A parameter given by hand reaches the solver through params.txt
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["test", "--solver", "file", "--param", "K=2", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["average_score"], 3.0);
+    assert_eq!(fs::read_to_string(temp_dir.path().join("params.txt"))?, "K 2\n");
+    Ok(())
+}
+
+#[test]
+fn batch_merges_the_results_of_its_workers() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    // Each worker scores its seeds by ten times the seed
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [batch]
+        workers = 2
+        command = ["sh", "-c", '''
+            cases=""
+            for seed in $(echo {seeds} | tr , ' '); do
+                cases="$cases${cases:+,}{\"seed\": $seed, \"score\": $((seed * 10)), \"execution_time\": 0.{worker}}"
+            done
+            echo "{\"case_count\": 0, \"total_score\": 0, \"cases\": [$cases]}" > {result}
+        ''']
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    fs::create_dir_all(temp_dir.path().join("tools/in"))?;
+    for seed in 0..5 {
+        fs::write(temp_dir.path().join(format!("tools/in/{:04}.txt", seed)), "")?;
+    }
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["batch", "--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["workers"], 2);
+    assert_eq!(summary["case_count"], 5);
+    assert_eq!(summary["average_score"], 20.0);
+    let result: serde_json::Value = serde_json::from_str(&fs::read_to_string(
+        temp_dir.path().join(summary["result_file"].as_str().unwrap()),
+    )?)?;
+    assert_eq!(result["total_score"], 100);
+    assert_eq!(result["max_execution_time"], 0.1);
+    assert_eq!(result["partition"], serde_json::json!({"strategy": "round-robin", "count": 2}));
+    assert_eq!(result["cases"][1]["seed"], 1);
+    Ok(())
+}
+
+#[test]
+fn results_merge_rejects_overlapping_seeds() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    for (name, cases) in [
+        ("a.json", r#"[{"seed": 0, "score": 10}, {"seed": 2, "score": 30}]"#),
+        ("b.json", r#"[{"seed": 1, "score": 20}]"#),
+        ("c.json", r#"[{"seed": 2, "score": 40}]"#),
+    ] {
+        let result = format!(
+            r#"{{"case_count": 0, "total_score": 0, "cases": {}}}"#,
+            cases
+        );
+        fs::write(temp_dir.path().join(name), result)?;
+    }
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .args(["results", "merge", "a.json", "b.json", "-o", "merged/all.json"])
+        .args(["--output", "json"])
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["case_count"], 3);
+    assert_eq!(summary["average_score"], 20.0);
+    let result: serde_json::Value = serde_json::from_str(&fs::read_to_string(
+        temp_dir.path().join("merged/all.json"),
+    )?)?;
+    assert_eq!(result["total_score"], 60);
+    assert_eq!(result["cases"][1]["seed"], 1);
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let assert = cmd
+        .args(["results", "merge", "a.json", "c.json"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("Seed 2 is in more than one result"));
+    assert!(!temp_dir.path().join("pahcer/json").exists());
+    Ok(())
+}
+
+#[test]
+fn archive_and_restore() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let project = temp_dir.path().join("project");
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+    "#;
+    for (path, content) in [
+        ("ahc_tools.toml", config),
+        ("src/main.rs", "fn main() {}\n"),
+        ("tools/in/0000.txt", "1 2 3\n"),
+        ("tools/out/0000.txt", "3\n"),
+        ("pahcer/json/result_20240101_000000.json", "{}"),
+        (".ahc/journal.jsonl", ""),
+        (".env", "SECRET=1\n"),
+    ] {
+        let path = project.join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, content)?;
+    }
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["archive", "--strip-inputs"])
+        .current_dir(&project)
+        .assert()
+        .success();
+    let archive = project.join("test_contest.tar.gz");
+    assert!(archive.exists());
+
+    let restored = temp_dir.path().join("restored");
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["restore", "-C"])
+        .arg(&restored)
+        .arg(&archive)
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(restored.join("tools/out/0000.txt"))?, "3\n");
+    assert!(restored.join("pahcer/json/result_20240101_000000.json").exists());
+    assert!(restored.join(".ahc/journal.jsonl").exists());
+    assert!(restored.join("src/main.rs").exists());
+    assert!(!restored.join("tools/in/0000.txt").exists());
+    assert!(!restored.join(".env").exists());
+
+    // Restoring again would overwrite the files
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["restore", "-C"])
+        .arg(&restored)
+        .arg(&archive)
+        .assert()
+        .failure();
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.args(["restore", "--force", "-C"])
+        .arg(&restored)
+        .arg(&archive)
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[test]
+fn config_show_effective() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .arg("config")
+        .arg("show")
+        .arg("--effective")
+        .env("AHC_CONFIG_HOME", temp_dir.path().join("global"))
+        .env("AHC_GENERAL__PROBLEM_URL", "https://example.com")
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+
+    let output = String::from_utf8(output.stdout)?;
+    assert!(output.contains("name = \"test_contest\"  # project (ahc_tools.toml)"));
+    assert!(output.contains("problem_url = \"https://example.com\"  # env (AHC_GENERAL__PROBLEM_URL)"));
+
+    Ok(())
+}
+
+#[test]
+fn config_show_effective_with_dotenv() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    fs::write(
+        temp_dir.path().join(".env"),
+        "AHC_GENERAL__PROBLEM_URL=https://example.org\n",
+    )?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .arg("config")
+        .arg("show")
+        .arg("--effective")
+        .env("AHC_CONFIG_HOME", temp_dir.path().join("global"))
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+
+    let output = String::from_utf8(output.stdout)?;
+    assert!(output.contains("problem_url = \"https://example.org\"  # .env (AHC_GENERAL__PROBLEM_URL)"));
+
+    Ok(())
+}
+
+#[test]
+fn config_edit_creates_global_config() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let global_dir = temp_dir.path().join("global");
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("config")
+        .arg("edit")
+        .arg("--global")
+        .env("AHC_CONFIG_HOME", &global_dir)
+        .env("VISUAL", "true")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    let content = fs::read_to_string(global_dir.join("config.toml"))?;
+    assert!(content.contains("# tools_dir = \"tools\""));
+
+    Ok(())
+}
+
+#[test]
+fn config_edit_reports_invalid_config() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), "[general]\n")?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("config")
+        .arg("edit")
+        .env("AHC_CONFIG_HOME", temp_dir.path().join("global"))
+        .env("VISUAL", "true")
+        .current_dir(temp_dir.path())
+        .assert()
+        .code(3);
+
+    Ok(())
+}
+
+#[test]
+fn tune() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "echo \"Score = {seed}\""]
+        start_seed = 0
+        end_seed = 3
+        trials = 2
+
+        [tune.params.temp]
+        low = 1.0
+        high = 2.0
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    let trials = fs::read_to_string(temp_dir.path().join(".ahc/tune/default/trials.jsonl"))?;
+    assert_eq!(trials.lines().count(), 2);
+    assert!(trials.contains("\"objective\":1.0"));
+    assert!(temp_dir.path().join(".ahc/tune/default/trials.csv").exists());
+
+    Ok(())
+}
+
+#[test]
+fn tune_output_json() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "echo \"Score = {seed}\""]
+        start_seed = 0
+        end_seed = 3
+        trials = 2
+
+        [tune.params.temp]
+        low = 1.0
+        high = 2.0
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .arg("--output")
+        .arg("json")
+        .arg("tune")
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(summary["study"], "default");
+    assert_eq!(summary["trials"].as_array().unwrap().len(), 2);
+    assert_eq!(summary["best"]["objective"], 1.0);
+
+    Ok(())
+}
+
+#[test]
+fn tune_per_class() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "echo \"Score = {k}\""]
+        start_seed = 0
+        end_seed = 3
+        strategy = "grid"
+
+        [tune.params]
+        k = [1, 2]
+
+        [tune.classes]
+        feature = "N"
+        bounds = [50]
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    let inputs_dir = temp_dir.path().join("tools/in");
+    fs::create_dir_all(&inputs_dir)?;
+    for (seed, n) in [(0, 10), (1, 100), (2, 20)] {
+        fs::write(inputs_dir.join(format!("{:04}.txt", seed)), format!("{}\n", n))?;
+    }
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    let trials = fs::read_to_string(temp_dir.path().join(".ahc/tune/default/trials.jsonl"))?;
+    assert_eq!(trials.lines().count(), 4);
+    let table = fs::read_to_string(temp_dir.path().join(".ahc/tune/default/decision_table.json"))?;
+    assert!(table.contains("\"label\": \"N < 50\""));
+    assert!(table.contains("\"label\": \"N >= 50\""));
+
+    Ok(())
+}
+
+#[test]
+fn tune_resume_after_interruption() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    // The first time seed 1 runs, the solver kills the tuner like a reboot would, after giving it
+    // a moment to checkpoint seed 0
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "if [ {seed} = 1 ] && [ ! -e killed ]; then touch killed; sleep 0.5; kill -9 $PPID; sleep 1; fi; echo \"Score = 5\""]
+        start_seed = 0
+        end_seed = 3
+        trials = 2
+
+        [tune.params.temp]
+        low = 1.0
+        high = 2.0
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    let study_dir = temp_dir.path().join(".ahc/tune/default");
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure();
+    assert!(!study_dir.join("trials.jsonl").exists());
+    assert!(study_dir.join("checkpoint.json").exists());
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let assert = cmd
+        .arg("tune")
+        .arg("--resume")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("Resuming trial after 1 seed(s)"));
+
+    let trials = fs::read_to_string(study_dir.join("trials.jsonl"))?;
+    assert_eq!(trials.lines().count(), 2);
+    assert!(trials
+        .lines()
+        .all(|line| line.contains("\"objective\":5.0")));
+    assert!(!study_dir.join("checkpoint.json").exists());
+
+    // Nothing is left to run once the planned trials are done
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .arg("--resume")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    let trials = fs::read_to_string(study_dir.join("trials.jsonl"))?;
+    assert_eq!(trials.lines().count(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn tune_parallel_jobs() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "echo \"Score = {k}\""]
+        start_seed = 0
+        end_seed = 2
+        strategy = "grid"
+
+        [tune.params]
+        k = [1, 2, 3, 4]
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .arg("--jobs")
+        .arg("3")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    let trials = fs::read_to_string(temp_dir.path().join(".ahc/tune/default/trials.jsonl"))?;
+    assert_eq!(trials.lines().count(), 4);
+    for k in 1..=4 {
+        assert!(trials.contains(&format!("\"params\":{{\"k\":{}}}", k)));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn tune_export_rust() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "echo \"Score = {k}\""]
+        start_seed = 0
+        end_seed = 2
+        strategy = "grid"
+
+        [tune.params]
+        k = [1, 3, 2]
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    fs::create_dir(temp_dir.path().join("src"))?;
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .arg("export")
+        .arg("--rust")
+        .arg("-o")
+        .arg("src/params.rs")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    let source = fs::read_to_string(temp_dir.path().join("src/params.rs"))?;
+    assert!(source.contains("pub const K: i64 = 3;"));
+
+    Ok(())
+}
+
+#[test]
+fn tune_time_penalty() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "if [ {k} = 3 ]; then sleep 0.5; fi; echo \"Score = {k}\""]
+        start_seed = 0
+        end_seed = 1
+        strategy = "grid"
+
+        [tune.params]
+        k = [1, 3, 2]
+
+        [tune.time_penalty]
+        time_limit = 0.4
+        penalty = 10
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let assert = cmd
+        .arg("tune")
+        .arg("export")
+        .arg("--rust")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    let source = String::from_utf8(assert.get_output().stdout.clone())?;
+    assert!(source.contains("pub const K: i64 = 2;"));
+
+    Ok(())
+}
+
+#[test]
+fn tune_plot() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "echo \"Score = {k}\""]
+        start_seed = 0
+        end_seed = 2
+        strategy = "grid"
+
+        [tune.params]
+        k = [1, 3, 2]
+        mode = ["a", "b"]
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .arg("plot")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    let html = fs::read_to_string(temp_dir.path().join(".ahc/tune/default/plots.html"))?;
+    assert_eq!(html.matches("<svg").count(), 3);
+    assert!(html.contains("Parameter importance"));
+
+    Ok(())
+}
+
+#[test]
+fn tune_annealing_stops_cases_behind_the_best_curve() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "echo \"t=0.5 score=$(($K * 10))\" >&2; if [ $K = 3 ]; then echo \"Score = 300 $COOLING\"; else sleep 5; echo \"Score = 100\"; fi"]
+        score_regex = '(?m)^Score = (?P<score>\d+)'
+        start_seed = 0
+        end_seed = 1
+        strategy = "grid"
+
+        [tune.params]
+        k = [3, 1]
+
+        [tune.annealing]
+        time_limit = 1.0
+        cutoffs = [0.5]
+        cooling = ["linear"]
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let start = std::time::Instant::now();
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let assert = cmd
+        .arg("tune")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    assert!(start.elapsed() < std::time::Duration::from_secs(4));
+
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("Trial #0: 300.00 (cooling=linear k=3)"));
+    assert!(stderr.contains("Trial #1: 100.00 (cooling=linear k=1)"));
+    assert!(stderr.contains("1 case(s) stopped early"));
+
+    Ok(())
+}
+
+#[test]
+fn tune_validation_holds_out_seeds() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "echo \"Score = {k}\"; echo {seed} >> seeds.txt"]
+        start_seed = 0
+        end_seed = 10
+        strategy = "grid"
+
+        [tune.params]
+        k = [1, 2]
+
+        [tune.validation]
+        fraction = 0.2
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let assert = cmd
+        .arg("tune")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("Holding out 2 seed(s) for validation"));
+    let stdout = String::from_utf8(assert.get_output().stdout.clone())?;
+    assert!(stdout.contains("    #1          2.00          2.00"));
+
+    // Both trials run on the 8 tuning seeds, then both are validated on the other 2
+    let seeds = fs::read_to_string(temp_dir.path().join("seeds.txt"))?;
+    assert_eq!(seeds.lines().count(), 2 * 8 + 2 * 2);
+    let rows: serde_json::Value = serde_json::from_str(&fs::read_to_string(
+        temp_dir.path().join(".ahc/tune/default/validation.json"),
+    )?)?;
+    assert_eq!(rows.as_array().unwrap().len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn tune_notifies_slack() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let best_mock = server
+        .mock("POST", "/hook")
+        .match_body(mockito::Matcher::Regex("New best in study default".to_string()))
+        .with_status(200)
+        .expect(1)
+        .create();
+    let finished_mock = server
+        .mock("POST", "/hook")
+        .match_body(mockito::Matcher::Regex(
+            r"Tuning study default finished\*\\n3 trial\(s\), best trial #1".to_string(),
+        ))
+        .with_status(200)
+        .expect(1)
+        .create();
+
+    let temp_dir = tempfile::tempdir()?;
+    let config = format!(
+        r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "echo \"Score = {{k}}\""]
+        start_seed = 0
+        end_seed = 1
+        strategy = "grid"
+
+        [tune.params]
+        k = [1, 3, 2]
+
+        [notify.slack]
+        webhook_url = "{}/hook"
+    "#,
+        server.url()
+    );
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    best_mock.assert();
+    finished_mock.assert();
+
+    Ok(())
+}
+
+#[test]
+fn tune_notifies_discord_with_chart() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let finished_mock = server
+        .mock("POST", "/hook")
+        .match_body(mockito::Matcher::AllOf(vec![
+            mockito::Matcher::Regex(r"Tuning study default finished\*\*\\n2 trial\(s\)".to_string()),
+            mockito::Matcher::Regex(r#"filename="chart.svg"[\s\S]*<svg"#.to_string()),
+        ]))
+        .with_status(200)
+        .expect(1)
+        .create();
+
+    let temp_dir = tempfile::tempdir()?;
+    let config = format!(
+        r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "echo \"Score = {{k}}\""]
+        start_seed = 0
+        end_seed = 1
+        strategy = "grid"
+
+        [tune.params]
+        k = [1, 2]
+
+        [notify.discord]
+        webhook_url = "{}/hook"
+        on_best = false
+        attach_chart = true
+    "#,
+        server.url()
+    );
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    finished_mock.assert();
+
+    Ok(())
+}
+
+#[test]
+fn tune_race() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+
+        [tune]
+        command = ["sh", "-c", "echo \"Score = {k}0{seed}\""]
+        start_seed = 1
+        end_seed = 10
+        strategy = "race"
+
+        [tune.params]
+        k = [1, 3, 2]
+
+        [tune.race]
+        first_test = 3
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("tune")
+        .current_dir(temp_dir.path())
+        .assert()
+        .success();
+
+    let trials = fs::read_to_string(temp_dir.path().join(".ahc/tune/default/trials.jsonl"))?;
+    assert_eq!(trials.lines().count(), 3);
+    assert_eq!(trials.matches("\"pruned\":true").count(), 2);
+    let survivor = trials.lines().last().unwrap();
+    assert!(survivor.contains("\"params\":{\"k\":3}"));
+    assert!(survivor.contains("\"seed\":3"));
+    assert!(!survivor.contains("\"seed\":4"));
+
+    Ok(())
+}
+
+fn copy_file_dir(dir: fs::ReadDir, dest: &std::path::Path) -> Result<()> {
+    for entry in dir {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            let dir_name = entry.file_name();
+            let new_dest = dest.join(dir_name);
+            fs::create_dir(&new_dest)?;
+            let new_dir = fs::read_dir(entry.path())?;
+            copy_file_dir(new_dir, &new_dest)?;
+        } else {
+            let file_name = entry.file_name();
+            let new_dest = dest.join(file_name);
+            fs::copy(entry.path(), new_dest)?;
+        }
+    }
+    Ok(())
+}
+#[cfg(unix)]
+#[test]
+fn runs_plugins_from_path() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempfile::tempdir()?;
+    let bin_dir = temp_dir.path().join("bin");
+    fs::create_dir(&bin_dir)?;
+    let plugin = bin_dir.join("ahc-hello");
+    fs::write(&plugin, "#!/bin/sh\necho \"$1 $AHC_CONTEST $(basename \"$AHC_CONFIG_FILE\")\"\n")?;
+    fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755))?;
+    let config = r#"
+        [general]
+        name = "test_contest"
+        problem_url = "https://example.net"
+    "#;
+    fs::write(temp_dir.path().join("ahc_tools.toml"), config)?;
+    let path = std::env::join_paths(
+        std::iter::once(bin_dir).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )?;
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    let output = cmd
+        .arg("hello")
+        .arg("world")
+        .env("PATH", &path)
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "world test_contest ahc_tools.toml\n");
+
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.arg("missing")
+        .env("PATH", &path)
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure();
+
+    Ok(())
+}